@@ -52,10 +52,7 @@ grpc::Status EnclaveApplicationImpl::OakSession(
   stream->SendInitialMetadata();
 
   absl::StatusOr<std::unique_ptr<ServerSession>> session =
-      ServerSession::Create(
-          session::SessionConfigBuilder(session::AttestationType::kUnattested,
-                                        session::HandshakeType::kNoiseNN)
-              .Build());
+      ServerSession::Create();
   if (!session.ok()) {
     return FromAbsl(
         Annotate(session.status(), "Failed to create server session"));
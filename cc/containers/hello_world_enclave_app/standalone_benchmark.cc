@@ -76,10 +76,7 @@ BENCHMARK_DEFINE_F(HelloWorldStandaloneBench, NoiseInvocation)
   std::string test_message = TestMessage(state.range(0));
 
   absl::StatusOr<std::unique_ptr<session::ClientSession>> session =
-      session::ClientSession::Create(
-          session::SessionConfigBuilder(session::AttestationType::kUnattested,
-                                        session::HandshakeType::kNoiseNN)
-              .Build());
+      session::ClientSession::Create();
 
   grpc::ClientContext context;
   std::unique_ptr<grpc::ClientReaderWriterInterface<
@@ -65,10 +65,7 @@ class HelloWorldStandaloneTest : public testing::Test {
 
 TEST_F(HelloWorldStandaloneTest, OakSessionReturnsResponse) {
   absl::StatusOr<std::unique_ptr<session::ClientSession>> session =
-      session::ClientSession::Create(
-          session::SessionConfigBuilder(session::AttestationType::kUnattested,
-                                        session::HandshakeType::kNoiseNN)
-              .Build());
+      session::ClientSession::Create();
 
   grpc::ClientContext context;
   std::unique_ptr<grpc::ClientReaderWriterInterface<
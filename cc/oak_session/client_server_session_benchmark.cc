@@ -34,7 +34,7 @@ class ClientServerSessionBench : public benchmark::Fixture {};
 SessionConfig* TestConfig() {
   return SessionConfigBuilder(AttestationType::kUnattested,
                               HandshakeType::kNoiseNN)
-      .Build();
+      .Build().value();
 }
 
 std::string TestMessage(int size) {
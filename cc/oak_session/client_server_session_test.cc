@@ -66,7 +66,7 @@ constexpr absl::string_view kServerKeyBytes =
 SessionConfig* TestConfigUnattestedNN() {
   return SessionConfigBuilder(AttestationType::kUnattested,
                               HandshakeType::kNoiseNN)
-      .Build();
+      .Build().value();
 }
 
 SessionConfig* TestConfigAttestedNNServer() {
@@ -103,7 +103,7 @@ SessionConfig* TestConfigAttestedNNServer() {
 
   bindings::free_signing_key(signing_key);
 
-  return builder.Build();
+  return builder.Build().value();
 }
 
 SessionConfig* TestConfigAttestedNNClient() {
@@ -114,14 +114,14 @@ SessionConfig* TestConfigAttestedNNClient() {
   return SessionConfigBuilder(AttestationType::kPeerUnidirectional,
                               HandshakeType::kNoiseNN)
       .AddPeerVerifier(kFakeAttesterId, verifier)
-      .Build();
+      .Build().value();
 }
 
 SessionConfig* TestConfigUnattestedNKClient(absl::string_view public_key) {
   return SessionConfigBuilder(AttestationType::kUnattested,
                               HandshakeType::kNoiseNK)
       .SetPeerStaticPublicKey(public_key)
-      .Build();
+      .Build().value();
 }
 
 SessionConfig* TestConfigUnattestedNKServer(
@@ -129,7 +129,7 @@ SessionConfig* TestConfigUnattestedNKServer(
   return SessionConfigBuilder(AttestationType::kUnattested,
                               HandshakeType::kNoiseNK)
       .SetSelfStaticPrivateKey(identity_key)
-      .Build();
+      .Build().value();
 }
 
 SessionConfig* TestConfigUnattestedKK(absl::string_view peer_public_key,
@@ -138,7 +138,7 @@ SessionConfig* TestConfigUnattestedKK(absl::string_view peer_public_key,
                               HandshakeType::kNoiseKK)
       .SetPeerStaticPublicKey(peer_public_key)
       .SetSelfStaticPrivateKey(self_private_key)
-      .Build();
+      .Build().value();
 }
 
 void DoHandshake(ClientSession& client_session, ServerSession& server_session) {
@@ -47,9 +47,14 @@ absl::StatusOr<std::unique_ptr<ClientSession>> ClientSession::Create(
 }
 
 absl::StatusOr<std::unique_ptr<ClientSession>> ClientSession::Create() {
-  return Create(SessionConfigBuilder(session::AttestationType::kUnattested,
-                                     session::HandshakeType::kNoiseNN)
-                    .Build());
+  absl::StatusOr<session::SessionConfig*> config =
+      SessionConfigBuilder(session::AttestationType::kUnattested,
+                           session::HandshakeType::kNoiseNN)
+          .Build();
+  if (!config.ok()) {
+    return config.status();
+  }
+  return Create(*config);
 }
 
 bool ClientSession::IsOpen() { return bindings::client_is_open(rust_session_); }
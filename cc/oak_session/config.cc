@@ -17,8 +17,10 @@
 #include "cc/oak_session/config.h"
 
 #include "absl/log/log.h"
+#include "absl/status/statusor.h"
 #include "absl/strings/str_cat.h"
 #include "cc/ffi/bytes_view.h"
+#include "cc/ffi/error_bindings.h"
 #include "cc/oak_session/oak_session_bindings.h"
 
 namespace oak::session {
@@ -83,8 +85,13 @@ SessionConfigBuilder SessionConfigBuilder::SetPeerStaticPublicKey(
   return *this;
 }
 
-session::SessionConfig* SessionConfigBuilder::Build() {
-  return bindings::session_config_builder_build(builder_);
+absl::StatusOr<session::SessionConfig*> SessionConfigBuilder::Build() {
+  const bindings::ErrorOrSessionConfig result =
+      bindings::session_config_builder_build(builder_);
+  if (result.error != nullptr) {
+    return ffi::bindings::ErrorIntoStatus(result.error);
+  }
+  return result.result;
 }
 
 }  // namespace oak::session
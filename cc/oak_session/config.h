@@ -17,6 +17,7 @@
 #include <optional>
 #include <string>
 
+#include "absl/status/statusor.h"
 #include "cc/oak_session/oak_session_bindings.h"
 
 #ifndef CC_OAK_SESSION_CONFIG_H_
@@ -51,7 +52,9 @@ class SessionConfigBuilder {
  public:
   SessionConfigBuilder(AttestationType attestation_type,
                        HandshakeType handshake_type);
-  SessionConfig* Build();
+  // Consumes the builder. Returns an error if the configured options are
+  // inconsistent, e.g. an attested session without any attesters.
+  absl::StatusOr<SessionConfig*> Build();
 
   SessionConfigBuilder AddSelfAttester(absl::string_view attester_id,
                                        bindings::FfiAttester attester);
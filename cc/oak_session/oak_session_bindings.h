@@ -102,6 +102,13 @@ struct ErrorOrSessionConfigBuilder {
   ffi::bindings::Error* error;
 };
 
+// Corresponds to ErrorOrSessionConfig struct in
+// oak_session/ffi/config.rs
+struct ErrorOrSessionConfig {
+  SessionConfig* result;
+  ffi::bindings::Error* error;
+};
+
 // Corresponds to ErrorOrFfiAttester struct in
 // oak_session/ffi/attestation.rs
 struct ErrorOrFfiAttester {
@@ -156,7 +163,8 @@ extern ErrorOrIdentityKey new_identity_key_from_bytes(ffi::bindings::BytesView);
 extern ffi::bindings::ErrorOrRustBytes identity_key_get_public_key(
     IdentityKey*);
 
-extern SessionConfig* session_config_builder_build(SessionConfigBuilder*);
+extern ErrorOrSessionConfig session_config_builder_build(
+    SessionConfigBuilder*);
 
 // Corresponds to functions in oak_session/ffi/client_session.rs
 extern ErrorOrClientSession new_client_session(SessionConfig*);
@@ -85,6 +85,16 @@ void DoHandshake(ServerSession* server_session, ClientSession* client_session) {
   ASSERT_TRUE(client_is_open(client_session));
 }
 
+SessionConfig* BuildSessionConfig(SessionConfigBuilder* builder) {
+  auto result = session_config_builder_build(builder);
+  if (result.error != nullptr) {
+    LOG(FATAL) << "Failed to build session config"
+               << static_cast<absl::string_view>(result.error->message);
+  }
+
+  return result.result;
+}
+
 SessionConfig* TestConfigUnattestedNN() {
   auto result = new_session_config_builder(ATTESTATION_TYPE_UNATTESTED,
                                            HANDSHAKE_TYPE_NOISE_NN);
@@ -93,7 +103,7 @@ SessionConfig* TestConfigUnattestedNN() {
                << static_cast<absl::string_view>(result.error->message);
   }
 
-  return session_config_builder_build(result.result);
+  return BuildSessionConfig(result.result);
 }
 
 SessionConfig* TestConfigAttestedNNServer() {
@@ -135,7 +145,7 @@ SessionConfig* TestConfigAttestedNNServer() {
   session_config_builder = session_config_builder_add_session_binder(
       session_config_builder, BytesView(attester_id), signing_key);
   free_signing_key(signing_key);
-  return session_config_builder_build(session_config_builder);
+  return BuildSessionConfig(session_config_builder);
 }
 
 SessionConfig* TestConfigAttestedNNClient() {
@@ -156,7 +166,7 @@ SessionConfig* TestConfigAttestedNNClient() {
   session_config_builder = session_config_builder_add_peer_verifier(
       session_config_builder, BytesView(attester_id), verifier);
 
-  return session_config_builder_build(session_config_builder);
+  return BuildSessionConfig(session_config_builder);
 }
 
 SessionConfig* TestConfigUnattestedNKClient(BytesView public_key) {
@@ -171,7 +181,7 @@ SessionConfig* TestConfigUnattestedNKClient(BytesView public_key) {
   session_config_builder = session_config_builder_set_peer_static_public_key(
       session_config_builder, public_key);
 
-  return session_config_builder_build(session_config_builder);
+  return BuildSessionConfig(session_config_builder);
 }
 
 SessionConfig* TestConfigUnattestedNKServer(IdentityKey* identity_key) {
@@ -186,7 +196,7 @@ SessionConfig* TestConfigUnattestedNKServer(IdentityKey* identity_key) {
   session_config_builder = session_config_builder_set_self_static_private_key(
       session_config_builder, identity_key);
 
-  return session_config_builder_build(session_config_builder);
+  return BuildSessionConfig(session_config_builder);
 }
 
 SessionConfig* TestConfigUnattestedKK(BytesView peer_public_key,
@@ -205,7 +215,7 @@ SessionConfig* TestConfigUnattestedKK(BytesView peer_public_key,
   session_config_builder = session_config_builder_set_peer_static_public_key(
       session_config_builder, peer_public_key);
 
-  return session_config_builder_build(session_config_builder);
+  return BuildSessionConfig(session_config_builder);
 }
 
 TEST(OakSessionBindingsTest, TestNNHandshake) {
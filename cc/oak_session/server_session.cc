@@ -45,9 +45,14 @@ absl::StatusOr<std::unique_ptr<ServerSession>> ServerSession::Create(
 }
 
 absl::StatusOr<std::unique_ptr<ServerSession>> ServerSession::Create() {
-  return Create(SessionConfigBuilder(session::AttestationType::kUnattested,
-                                     session::HandshakeType::kNoiseNN)
-                    .Build());
+  absl::StatusOr<session::SessionConfig*> config =
+      SessionConfigBuilder(session::AttestationType::kUnattested,
+                           session::HandshakeType::kNoiseNN)
+          .Build();
+  if (!config.ok()) {
+    return config.status();
+  }
+  return Create(*config);
 }
 
 bool ServerSession::IsOpen() { return bindings::server_is_open(rust_session_); }
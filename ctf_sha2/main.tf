@@ -56,10 +56,13 @@ resource "google_compute_instance" "ctf_sha2_instance" {
   }
 
   # Metadata required by Confidential Space to launch the container.
-  metadata = {
-    tee-image-reference        = var.image_digest
-    tee-container-log-redirect = "true"
-  }
+  metadata = merge(
+    {
+      tee-image-reference        = var.image_digest
+      tee-container-log-redirect = "true"
+    },
+    var.audience == null ? {} : { tee-env-OAK_CTF_SHA2_AUDIENCE = var.audience },
+  )
 
   # Allow Terraform to delete the instance.
   allow_stopping_for_update = true
@@ -12,45 +12,125 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::{env::VarError, process::ExitCode};
+
+use clap::Parser;
 use rand::{rngs::StdRng, CryptoRng, RngCore, SeedableRng};
 use sha2::{Digest, Sha256};
 
 fn assert_crypto_rng<T: CryptoRng>(_rng: &T) {}
 
+// Fixed seed of the RNG in the `deterministic_seed` test build, so that tests
+// can assert the flag digest. Never enable that feature in a deployed binary.
+#[cfg(feature = "deterministic_seed")]
+const TEST_SEED: u64 = 0x0a4c_7f5e_ed00_c7f0;
+
 // Unique audience for this binary, to prevent confused deputy attacks.
 // Randomly generated with
 // printf "z%020lu\n" "0x$(openssl rand -hex 8)"
 const OAK_CTF_SHA2_AUDIENCE: &str = "z08381475938604996746";
 
-fn main() {
-    // Initialize an empty byte array which will be filled with the secret flag.
-    let mut flag = [0; 64];
+// Environment variable that overrides the audience, so that the same binary can
+// be deployed in separate CTF instances. Confidential Space only passes it to
+// the container because the launch policy of the image allows it.
+const AUDIENCE_ENV_VAR: &str = "OAK_CTF_SHA2_AUDIENCE";
+
+fn audience() -> String {
+    match std::env::var(AUDIENCE_ENV_VAR) {
+        Ok(audience) => audience,
+        Err(VarError::NotPresent) => OAK_CTF_SHA2_AUDIENCE.to_string(),
+        Err(err) => panic!("invalid {AUDIENCE_ENV_VAR}: {err}"),
+    }
+}
 
+#[derive(Parser, Debug)]
+#[command(about = "Generates a random flag and attests to its SHA2-256 digest")]
+struct Flags {
+    /// Only print the flag digest, without requesting an attestation token.
+    /// For running the binary outside of Confidential Space, e.g. during
+    /// development.
+    #[arg(long)]
+    no_attestation: bool,
+}
+
+/// Creates the RNG that the flag is drawn from.
+#[cfg(not(feature = "deterministic_seed"))]
+fn create_rng() -> StdRng {
     // We must use a cryptographically secure RNG.
     // See <https://rust-random.github.io/book/guide-gen.html#cryptographically-secure-pseudo-random-number-generator>.
-    let mut rng = StdRng::from_entropy();
+    StdRng::from_entropy()
+}
+
+#[cfg(feature = "deterministic_seed")]
+fn create_rng() -> StdRng {
+    eprintln!("WARNING: the flag is generated from a fixed seed, for testing only");
+    StdRng::seed_from_u64(TEST_SEED)
+}
+
+/// Generates a secret flag with `rng`, and returns the hex-encoded SHA2-256
+/// digest of it. The flag itself is discarded.
+fn flag_digest(rng: &mut StdRng) -> String {
+    // Initialize an empty byte array which will be filled with the secret flag.
+    let mut flag = [0; 64];
+
     // Assert the RNG implements the required marker trait, to make sure it is not
     // accidentally replaced with a non-cryptographically secure RNG.
-    assert_crypto_rng(&rng);
+    assert_crypto_rng(rng);
     rng.fill_bytes(&mut flag);
 
     let mut hasher = Sha256::new();
     hasher.update(flag);
     let flag_digest = hasher.finalize();
 
-    let flag_digest_string = format!("{flag_digest:x}");
+    format!("{flag_digest:x}")
+}
+
+fn main() -> ExitCode {
+    let flags = Flags::parse();
+    let audience = audience();
+    assert!(!audience.is_empty(), "the audience must not be empty");
+
+    let flag_digest_string = flag_digest(&mut create_rng());
 
     eprintln!("flag_digest");
     eprintln!("{flag_digest_string}");
 
     eprintln!();
 
-    let attestation_token = oak_attestation_gcp::attestation::request_attestation_token(
-        OAK_CTF_SHA2_AUDIENCE,
+    if flags.no_attestation {
+        eprintln!("not requesting an attestation token (--no-attestation)");
+        return ExitCode::SUCCESS;
+    }
+
+    let attestation_token = match oak_attestation_gcp::attestation::request_attestation_token(
+        &audience,
         &flag_digest_string,
-    )
-    .expect("could not request attestation token");
+    ) {
+        Ok(attestation_token) => attestation_token,
+        Err(err) => {
+            eprintln!("could not request an attestation token: {err}");
+            eprintln!(
+                "the Confidential Space attestation agent is only available in Confidential \
+                 Space; use --no-attestation to run without it"
+            );
+            return ExitCode::FAILURE;
+        }
+    };
 
     eprintln!("attestation token");
     eprintln!("{attestation_token}");
+    ExitCode::SUCCESS
+}
+
+#[cfg(all(test, feature = "deterministic_seed"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_flag_digest() {
+        assert_eq!(
+            flag_digest(&mut create_rng()),
+            "a459456b4506b18a45b167078f411116b1e46fa9e7c6174484650c0c1e13f362"
+        );
+    }
 }
@@ -25,3 +25,9 @@ variable "image_digest" {
   type        = string
   description = "The full digest of the container image to run, in the format 'IMAGE_URL@sha256:DIGEST'."
 }
+
+variable "audience" {
+  type        = string
+  description = "Overrides the audience of the attestation token, so that separate CTF instances can run the same image. Defaults to the audience compiled into the binary."
+  default     = null
+}
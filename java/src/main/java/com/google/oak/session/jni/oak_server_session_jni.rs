@@ -29,7 +29,15 @@ extern "system" fn Java_com_google_oak_session_OakServerSession_nativeCreateServ
     // Safety: OakServerSession.java will only pass valid pointers.
     let config_builder: Box<SessionConfigBuilder> =
         unsafe { Box::from_raw(config_builder_ptr as *mut SessionConfigBuilder) };
-    match ServerSession::create(config_builder.build()) {
+    let config = match config_builder.try_build() {
+        Ok(config) => config,
+        Err(err) => {
+            oak_exception(env, "Invalid session config", err);
+            return 0;
+        }
+    };
+
+    match ServerSession::create(config) {
         Ok(session) => Box::into_raw(Box::new(session)) as jlong,
         Err(err) => {
             oak_exception(env, "Couldn't create a native session", err);
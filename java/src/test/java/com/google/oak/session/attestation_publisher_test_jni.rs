@@ -18,14 +18,17 @@
 
 use std::sync::Arc;
 
+use anyhow::ensure;
 use jni::{
     objects::{JClass, JObject, JValue},
     sys::{jlong, jobject},
     JNIEnv,
 };
+use oak_attestation_verification_types::verifier::AttestationVerifier;
+use oak_crypto::verifier::Verifier;
 use oak_jni_attestation_publisher::JNIAttestationPublisher;
 use oak_proto_rust::oak::{
-    attestation::v1::{Endorsements, EventLog, Evidence},
+    attestation::v1::{attestation_results, AttestationResults, Endorsements, EventLog, Evidence},
     session::v1::{Assertion, SessionBinding},
     Variant,
 };
@@ -35,6 +38,7 @@ use oak_session::{
     config::{SessionConfig, SessionConfigBuilder},
     generator::{AssertionGenerationError, AssertionGenerator, BindableAssertion},
     handshake::HandshakeType,
+    key_extractor::KeyExtractor,
     session::AttestationPublisher,
     session_binding::SessionBinder,
 };
@@ -89,6 +93,36 @@ impl SessionBinder for FakeSessionBinder {
     }
 }
 
+/// Accepts any evidence, so that the client publishes what the server sent.
+struct FakeAttestationVerifier {}
+
+impl AttestationVerifier for FakeAttestationVerifier {
+    fn verify(&self, _: &Evidence, _: &Endorsements) -> anyhow::Result<AttestationResults> {
+        Ok(AttestationResults {
+            status: attestation_results::Status::Success.into(),
+            ..Default::default()
+        })
+    }
+}
+
+/// Checks the bindings made by [`FakeSessionBinder`].
+struct FakeBindingVerifier {}
+
+impl Verifier for FakeBindingVerifier {
+    fn verify(&self, message: &[u8], signature: &[u8]) -> anyhow::Result<()> {
+        ensure!(message == signature, "binding doesn't match the bound data");
+        Ok(())
+    }
+}
+
+struct FakeKeyExtractor {}
+
+impl KeyExtractor for FakeKeyExtractor {
+    fn extract_verifying_key(&self, _: &AttestationResults) -> anyhow::Result<Box<dyn Verifier>> {
+        Ok(Box::new(FakeBindingVerifier {}))
+    }
+}
+
 #[no_mangle]
 extern "system" fn Java_com_google_oak_session_AttestationPublisherTest_nativeCreateServerConfigBuilder(
     mut env: JNIEnv,
@@ -131,6 +165,11 @@ extern "system" fn Java_com_google_oak_session_AttestationPublisherTest_nativeCr
     new_java_session_config_builder(
         &mut env,
         SessionConfig::builder(AttestationType::PeerUnidirectional, HandshakeType::NoiseNN)
+            .add_peer_verifier_with_key_extractor(
+                "test id".to_string(),
+                Box::new(FakeAttestationVerifier {}),
+                Box::new(FakeKeyExtractor {}),
+            )
             .add_attestation_publisher(&publisher),
     )
 }
@@ -14,7 +14,7 @@
 // limitations under the License.
 //
 
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc};
 
 use anyhow::Context;
 use axum::Router;
@@ -90,6 +90,7 @@ impl WeatherService {
                 AttestationType::PeerUnidirectional
             },
             clock.clone(),
+            BTreeMap::new(),
         )
         .await
         .context("couldn't connect to server")?;
@@ -806,7 +806,7 @@ pub fn serialize_confidential_space_reference_values(
 }
 
 pub fn serialize_cosign_reference_values(instance: &CosignReferenceValues) -> serde_json::Value {
-    let CosignReferenceValues { developer_public_key, rekor_public_key } = instance;
+    let CosignReferenceValues { developer_public_key, rekor_public_key, rekor_policy } = instance;
     let mut result = json!({});
     if let Some(developer_public_key) = developer_public_key {
         result["developer_public_key"] = serialize_verifying_key(developer_public_key);
@@ -814,6 +814,7 @@ pub fn serialize_cosign_reference_values(instance: &CosignReferenceValues) -> se
     if let Some(rekor_public_key) = rekor_public_key {
         result["rekor_public_key"] = serialize_verifying_key(rekor_public_key);
     }
+    result["rekor_policy"] = json!(rekor_policy);
     result
 }
 
@@ -37,7 +37,7 @@ use serde_json::Value;
 #[command(author, version, about, long_about = None)]
 struct Args {
     #[arg(long, value_parser = parse_key_at)]
-    signing_key: SigningKey<Sha256>,
+    signing_key: JwtSigningKey,
     #[arg(long, value_parser = parse_cert_at)]
     // note: we must use the fully qualified Vec or clap will fail at runtime
     signing_cert: std::vec::Vec<u8>,
@@ -46,9 +46,40 @@ struct Args {
     root_ca_cert: std::vec::Vec<u8>,
 }
 
-fn parse_key_at(path: &str) -> anyhow::Result<SigningKey<Sha256>> {
+/// The key used to sign the token, which determines its `alg` header.
+#[derive(Clone, Debug)]
+enum JwtSigningKey {
+    Rs256(Box<SigningKey<Sha256>>),
+    Es256(p256::ecdsa::SigningKey),
+}
+
+impl JwtSigningKey {
+    fn algorithm(&self) -> &'static str {
+        match self {
+            JwtSigningKey::Rs256(_) => "RS256",
+            JwtSigningKey::Es256(_) => "ES256",
+        }
+    }
+
+    fn sign(&self, message: &[u8]) -> std::vec::Vec<u8> {
+        match self {
+            JwtSigningKey::Rs256(key) => key.sign(message).to_vec(),
+            // JWS encodes ECDSA signatures as the concatenation of r and s.
+            JwtSigningKey::Es256(key) => {
+                let signature: p256::ecdsa::Signature = key.sign(message);
+                signature.to_bytes().to_vec()
+            }
+        }
+    }
+}
+
+fn parse_key_at(path: &str) -> anyhow::Result<JwtSigningKey> {
     let data = &fs::read_to_string(path).context(format!("failed to read signing key: {path}"))?;
-    SigningKey::<Sha256>::from_pkcs8_pem(data)
+    if let Ok(key) = SigningKey::<Sha256>::from_pkcs8_pem(data) {
+        return Ok(JwtSigningKey::Rs256(Box::new(key)));
+    }
+    p256::ecdsa::SigningKey::from_pkcs8_pem(data)
+        .map(JwtSigningKey::Es256)
         .map_err(|_| anyhow::anyhow!("failed to parse signing key: {}", path))
 }
 
@@ -64,7 +95,7 @@ fn main() -> anyhow::Result<()> {
     let x5c = vec![STANDARD.encode(&args.signing_cert), STANDARD.encode(&args.root_ca_cert)];
 
     let header = serde_json::json!({
-        "alg": "RS256",
+        "alg": args.signing_key.algorithm(),
         "typ": "JWT",
         "x5c": x5c,
     });
@@ -79,7 +110,7 @@ fn main() -> anyhow::Result<()> {
     let message = format!("{}.{}", header_b64, claims_b64);
 
     let signature = args.signing_key.sign(message.as_bytes());
-    let signature_b64 = URL_SAFE_NO_PAD.encode(signature.to_vec());
+    let signature_b64 = URL_SAFE_NO_PAD.encode(signature);
 
     print!("{}.{}.{}", header_b64, claims_b64, signature_b64);
 
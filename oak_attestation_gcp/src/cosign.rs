@@ -15,9 +15,10 @@
 //
 
 use endorsement::intoto::EndorsementStatement;
+use oak_attestation_verification_types::verdict::{Check, Outcome, Verdict};
 use oak_proto_rust::oak::attestation::v1::{
-    CosignReferenceValues as ProtoCosignReferenceValues, KeyType, SignedEndorsement,
-    VerifyingKey as ProtoVerifyingKey,
+    CosignReferenceValues as ProtoCosignReferenceValues, KeyType, RekorPolicy as ProtoRekorPolicy,
+    SignedEndorsement, VerifyingKey as ProtoVerifyingKey,
 };
 use oak_proto_rust_lib::parse_p256_ecdsa_verifying_key;
 use oak_time::Instant;
@@ -50,6 +51,12 @@ pub enum CosignVerificationError {
     RekorError(&'static str, sigstore::error::Error),
     #[error("rekor payload deserialization error: {0}")]
     RekorPayloadParseError(serde_json::Error),
+    #[error("missing rekor log entry")]
+    MissingRekorLogEntry,
+    #[error("missing rekor public key for rekor policy {0:?}")]
+    MissingRekorPublicKey(ProtoRekorPolicy),
+    #[error("invalid rekor policy: {0}")]
+    InvalidRekorPolicy(i32),
     #[error("Invalid verifying key: {0}")]
     InvalidVerifyingKey(&'static str),
     #[error("VerifyingKey parsing error: {0}")]
@@ -105,34 +112,61 @@ impl CosignEndorsement {
     }
 }
 
+/// Determines how the Rekor log entry of an endorsement is verified. The
+/// developer signature of the statement is verified regardless.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RekorPolicy {
+    /// The log entry must be present and verify with the Rekor public key.
+    Required(VerifyingKey),
+    /// The log entry is verified with the Rekor public key if present.
+    Optional(VerifyingKey),
+    /// The log entry is not verified, e.g. because Rekor can't be reached.
+    Disabled,
+}
+
 pub struct CosignReferenceValues {
     developer_public_key: VerifyingKey,
-    rekor_public_key: Option<VerifyingKey>,
+    rekor_policy: RekorPolicy,
 }
 
 impl CosignReferenceValues {
+    pub fn new(developer_public_key: VerifyingKey, rekor_policy: RekorPolicy) -> Self {
+        Self { developer_public_key, rekor_policy }
+    }
+
     pub fn partial(developer_public_key: VerifyingKey) -> Self {
-        Self { developer_public_key, rekor_public_key: None }
+        Self::new(developer_public_key, RekorPolicy::Disabled)
     }
 
     pub fn full(developer_public_key: VerifyingKey, rekor_public_key: VerifyingKey) -> Self {
-        Self { developer_public_key, rekor_public_key: Some(rekor_public_key) }
+        Self::new(developer_public_key, RekorPolicy::Required(rekor_public_key))
     }
 
     pub fn from_proto(proto: &ProtoCosignReferenceValues) -> Result<Self, CosignVerificationError> {
-        match &proto.developer_public_key {
-            None => Err(CosignVerificationError::MissingEndorsement),
-            Some(developer_public_key) => {
-                let developer_public_key = parse_verifying_key(developer_public_key.clone())?;
-                match &proto.rekor_public_key {
-                    None => Ok(Self::partial(developer_public_key)),
-                    Some(rekor_public_key) => {
-                        let rekor_public_key = parse_verifying_key(rekor_public_key.clone())?;
-                        Ok(Self::full(developer_public_key, rekor_public_key))
-                    }
-                }
+        let developer_public_key = match &proto.developer_public_key {
+            None => return Err(CosignVerificationError::MissingEndorsement),
+            Some(developer_public_key) => parse_verifying_key(developer_public_key.clone())?,
+        };
+        let rekor_public_key =
+            proto.rekor_public_key.clone().map(parse_verifying_key).transpose()?;
+        let proto_rekor_policy = ProtoRekorPolicy::try_from(proto.rekor_policy)
+            .map_err(|_| CosignVerificationError::InvalidRekorPolicy(proto.rekor_policy))?;
+        let rekor_policy = match (proto_rekor_policy, rekor_public_key) {
+            (ProtoRekorPolicy::Unspecified, None) | (ProtoRekorPolicy::Disabled, _) => {
+                RekorPolicy::Disabled
             }
-        }
+            (
+                ProtoRekorPolicy::Unspecified | ProtoRekorPolicy::Required,
+                Some(rekor_public_key),
+            ) => RekorPolicy::Required(rekor_public_key),
+            (ProtoRekorPolicy::Optional, Some(rekor_public_key)) => {
+                RekorPolicy::Optional(rekor_public_key)
+            }
+            (ProtoRekorPolicy::Required | ProtoRekorPolicy::Optional, None) => {
+                return Err(CosignVerificationError::MissingRekorPublicKey(proto_rekor_policy))
+            }
+        };
+        Ok(Self::new(developer_public_key, rekor_policy))
     }
 }
 
@@ -173,6 +207,29 @@ impl CosignVerificationReport {
             }
         }
     }
+
+    /// Returns the outcome of each verification step, without consuming the
+    /// report.
+    pub fn verdict(&self) -> Verdict {
+        match &self.statement_verification {
+            Err(err) => Verdict::failed(Check::Statement, err),
+            Ok(StatementReport { statement_validation, rekor_verification }) => {
+                Verdict::with_details(
+                    Check::Statement,
+                    vec![
+                        Verdict::from_result(Check::Validity, statement_validation),
+                        match rekor_verification {
+                            None => Verdict::new(Check::TransparencyLogEntry, Outcome::Skipped),
+                            Some(rekor_verification) => Verdict::from_result(
+                                Check::TransparencyLogEntry,
+                                rekor_verification,
+                            ),
+                        },
+                    ],
+                )
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -204,28 +261,31 @@ pub fn report_endorsement(
                 .map_err(|err| CosignVerificationError::StatementValidationError(err.to_string()))?
         };
 
-        let rekor_verification = ref_values.rekor_public_key.as_ref().map(|rekor_public_key| {
-            if let Some(rekor) = endorsement.rekor {
-                try {
-                    let rekor = rekor.verify(rekor_public_key).map_err(|err| {
-                        CosignVerificationError::RekorError("verifying rekor bundle", err)
-                    })?;
-                    let rekor: RekorPayload = serde_json::from_slice(rekor.message())
-                        .map_err(CosignVerificationError::RekorPayloadParseError)?;
-                    let hashed_rekord: HashedRekord<hashedrekord::Unverified> =
-                        rekor.payload_body().map_err(|err| {
-                            CosignVerificationError::RekorError("parsing hashedrekord payload", err)
-                        })?;
-                    hashed_rekord
-                        .verify(&ref_values.developer_public_key, statement.message())
-                        .map_err(|err| {
-                            CosignVerificationError::RekorError("verifying rekor payload", err)
-                        })?;
-                }
-            } else {
-                Err(CosignVerificationError::MissingEndorsement)
+        let rekor_verification = match (&ref_values.rekor_policy, endorsement.rekor) {
+            (RekorPolicy::Disabled, _) | (RekorPolicy::Optional(_), None) => None,
+            (RekorPolicy::Required(_), None) => {
+                Some(Err(CosignVerificationError::MissingRekorLogEntry))
             }
-        });
+            (
+                RekorPolicy::Required(rekor_public_key) | RekorPolicy::Optional(rekor_public_key),
+                Some(rekor),
+            ) => Some(try {
+                let rekor = rekor.verify(rekor_public_key).map_err(|err| {
+                    CosignVerificationError::RekorError("verifying rekor bundle", err)
+                })?;
+                let rekor: RekorPayload = serde_json::from_slice(rekor.message())
+                    .map_err(CosignVerificationError::RekorPayloadParseError)?;
+                let hashed_rekord: HashedRekord<hashedrekord::Unverified> =
+                    rekor.payload_body().map_err(|err| {
+                        CosignVerificationError::RekorError("parsing hashedrekord payload", err)
+                    })?;
+                hashed_rekord
+                    .verify(&ref_values.developer_public_key, statement.message())
+                    .map_err(|err| {
+                        CosignVerificationError::RekorError("verifying rekor payload", err)
+                    })?;
+            }),
+        };
 
         StatementReport { statement_validation, rekor_verification }
     };
@@ -238,6 +298,7 @@ mod tests {
     use core::assert_matches::assert_matches;
 
     use oak_file_utils::{read_testdata, read_testdata_string};
+    use oak_proto_rust_lib::p256_ecdsa_verifying_key_to_proto;
     use oak_time::Instant;
     use p256::pkcs8::DecodePublicKey;
 
@@ -330,4 +391,181 @@ mod tests {
             }
         );
     }
+
+    /// Reports the test endorsement, optionally accompanied by an (invalid)
+    /// Rekor log entry, under the given Rekor policy.
+    fn report_with_rekor_policy(
+        rekor_policy: RekorPolicy,
+        with_rekor_log_entry: bool,
+    ) -> CosignVerificationReport {
+        let verification_time = Instant::from_unix_seconds(1740000000);
+        let image_reference: Reference =
+            "europe-west2-docker.pkg.dev/oak-ci/example-enclave-apps/echo_enclave_app@sha256:313b8a83d3c8bfc9abcffee4f538424473e2705383a7e46f16d159faf0e5ef34"
+                .try_into()
+                .unwrap();
+        let statement = SignedMessage::unverified(
+            read_testdata!("endorsement.json"),
+            read_testdata!("endorsement_signature.sig"),
+        );
+        let endorsement = if with_rekor_log_entry {
+            CosignEndorsement::full(
+                statement,
+                SignedMessage::unverified(b"rekor payload".to_vec(), b"signature".to_vec()),
+            )
+        } else {
+            CosignEndorsement::partial(statement)
+        };
+        let developer_public_key =
+            VerifyingKey::from_public_key_pem(&read_testdata_string!("developer_key.pub.pem"))
+                .unwrap();
+
+        report_endorsement(
+            endorsement,
+            &image_reference,
+            &CosignReferenceValues::new(developer_public_key, rekor_policy),
+            verification_time,
+        )
+    }
+
+    fn rekor_public_key() -> VerifyingKey {
+        VerifyingKey::from_public_key_pem(&read_testdata_string!("other_developer_key.pub.pem"))
+            .unwrap()
+    }
+
+    #[test]
+    fn report_endorsement_rekor_required() {
+        let result = report_with_rekor_policy(RekorPolicy::Required(rekor_public_key()), false);
+        assert_matches!(
+            result,
+            CosignVerificationReport {
+                statement_verification: Ok(StatementReport {
+                    statement_validation: Ok(()),
+                    rekor_verification: Some(Err(CosignVerificationError::MissingRekorLogEntry))
+                })
+            }
+        );
+        assert_matches!(result.into_checked(), Err(_));
+
+        let result = report_with_rekor_policy(RekorPolicy::Required(rekor_public_key()), true);
+        assert_matches!(
+            result,
+            CosignVerificationReport {
+                statement_verification: Ok(StatementReport {
+                    rekor_verification: Some(Err(CosignVerificationError::RekorError(..))),
+                    ..
+                })
+            }
+        );
+    }
+
+    #[test]
+    fn report_endorsement_rekor_optional() {
+        let result = report_with_rekor_policy(RekorPolicy::Optional(rekor_public_key()), false);
+        assert_matches!(
+            result,
+            CosignVerificationReport {
+                statement_verification: Ok(StatementReport {
+                    statement_validation: Ok(()),
+                    rekor_verification: None
+                })
+            }
+        );
+        assert_matches!(result.into_checked(), Ok(()));
+
+        // A log entry that is present must still be valid.
+        let result = report_with_rekor_policy(RekorPolicy::Optional(rekor_public_key()), true);
+        assert_matches!(
+            result,
+            CosignVerificationReport {
+                statement_verification: Ok(StatementReport {
+                    rekor_verification: Some(Err(CosignVerificationError::RekorError(..))),
+                    ..
+                })
+            }
+        );
+    }
+
+    #[test]
+    fn report_endorsement_rekor_disabled() {
+        for with_rekor_log_entry in [false, true] {
+            let result = report_with_rekor_policy(RekorPolicy::Disabled, with_rekor_log_entry);
+            assert_matches!(
+                result,
+                CosignVerificationReport {
+                    statement_verification: Ok(StatementReport {
+                        statement_validation: Ok(()),
+                        rekor_verification: None
+                    })
+                }
+            );
+            assert_matches!(result.into_checked(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn report_endorsement_rekor_disabled_invalid_signature() {
+        let endorsement = CosignEndorsement::from_bytes_partial(
+            read_testdata!("endorsement.json"),
+            read_testdata!("other_endorsement_signature.sig"),
+        );
+        let developer_public_key =
+            VerifyingKey::from_public_key_pem(&read_testdata_string!("developer_key.pub.pem"))
+                .unwrap();
+
+        let result = report_endorsement(
+            endorsement,
+            &"europe-west2-docker.pkg.dev/oak-ci/example-enclave-apps/echo_enclave_app@sha256:313b8a83d3c8bfc9abcffee4f538424473e2705383a7e46f16d159faf0e5ef34"
+                .try_into()
+                .unwrap(),
+            &CosignReferenceValues::new(developer_public_key, RekorPolicy::Disabled),
+            Instant::from_unix_seconds(1740000000),
+        );
+        assert_matches!(result, CosignVerificationReport { statement_verification: Err(_) });
+    }
+
+    #[test]
+    fn rekor_policy_from_proto() {
+        let developer_public_key = Some(p256_ecdsa_verifying_key_to_proto(
+            &VerifyingKey::from_public_key_pem(&read_testdata_string!("developer_key.pub.pem"))
+                .unwrap(),
+        ));
+        let rekor_public_key = rekor_public_key();
+        let rekor_policy = |rekor_policy: ProtoRekorPolicy, with_rekor_public_key: bool| {
+            CosignReferenceValues::from_proto(&ProtoCosignReferenceValues {
+                developer_public_key: developer_public_key.clone(),
+                rekor_public_key: with_rekor_public_key
+                    .then(|| p256_ecdsa_verifying_key_to_proto(&rekor_public_key)),
+                rekor_policy: rekor_policy.into(),
+            })
+            .map(|reference_values| reference_values.rekor_policy)
+        };
+
+        // An unspecified policy depends on whether a Rekor public key is given.
+        assert_matches!(
+            rekor_policy(ProtoRekorPolicy::Unspecified, false),
+            Ok(RekorPolicy::Disabled)
+        );
+        assert_matches!(
+            rekor_policy(ProtoRekorPolicy::Unspecified, true),
+            Ok(RekorPolicy::Required(key)) if key == rekor_public_key
+        );
+        assert_matches!(
+            rekor_policy(ProtoRekorPolicy::Required, true),
+            Ok(RekorPolicy::Required(key)) if key == rekor_public_key
+        );
+        assert_matches!(
+            rekor_policy(ProtoRekorPolicy::Optional, true),
+            Ok(RekorPolicy::Optional(key)) if key == rekor_public_key
+        );
+        assert_matches!(rekor_policy(ProtoRekorPolicy::Disabled, true), Ok(RekorPolicy::Disabled));
+        assert_matches!(rekor_policy(ProtoRekorPolicy::Disabled, false), Ok(RekorPolicy::Disabled));
+        assert_matches!(
+            rekor_policy(ProtoRekorPolicy::Required, false),
+            Err(CosignVerificationError::MissingRekorPublicKey(ProtoRekorPolicy::Required))
+        );
+        assert_matches!(
+            rekor_policy(ProtoRekorPolicy::Optional, false),
+            Err(CosignVerificationError::MissingRekorPublicKey(ProtoRekorPolicy::Optional))
+        );
+    }
 }
@@ -0,0 +1,195 @@
+//
+// Copyright 2025 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Helpers for explaining why a Confidential Space token doesn't match the
+//! reference values of a policy.
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use endorsement::intoto::EndorsementStatement;
+use x509_cert::{der::Decode, Certificate};
+
+use crate::jwt::{
+    verification::{
+        CONFIDENTIAL_SPACE_SOFTWARE_NAME, PRODUCTION_DEBUG_STATUS, REQUIRED_SUPPORT_ATTRIBUTE,
+    },
+    Claims, Header,
+};
+
+/// A value observed in the token (or in the certificate chain of the token)
+/// that doesn't match any of the values expected by the policy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClaimMismatch {
+    /// Name of the mismatching claim.
+    pub claim: &'static str,
+    /// Values that the policy would have accepted.
+    pub expected: Vec<String>,
+    /// Value that was actually observed.
+    pub observed: String,
+}
+
+impl fmt::Display for ClaimMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: expected {:?}, observed {:?}", self.claim, self.expected, self.observed)
+    }
+}
+
+/// Compares the token claims against the values expected by the policy and
+/// returns all the claims that differ.
+///
+/// `endorsement_statement` is the workload endorsement statement, if the
+/// policy has workload reference values. It is only used to explain a
+/// mismatch, so it may come from an endorsement whose signature hasn't been
+/// verified.
+pub(crate) fn diff_claims(
+    claims: &Claims,
+    expected_nonce: &str,
+    endorsement_statement: Option<&EndorsementStatement>,
+) -> Vec<ClaimMismatch> {
+    let support_attributes = &claims.submods.confidential_space.support_attributes;
+    let endorsed_digests = endorsement_statement.map(|statement| {
+        statement
+            .subject()
+            .digest
+            .iter()
+            .map(|(algorithm, digest)| format!("{algorithm}:{digest}"))
+            .collect()
+    });
+    [
+        mismatch("eat_nonce", vec![expected_nonce.to_string()], &claims.eat_nonce),
+        mismatch("dbgstat", vec![PRODUCTION_DEBUG_STATUS.to_string()], &claims.debug_status),
+        mismatch(
+            "swname",
+            vec![CONFIDENTIAL_SPACE_SOFTWARE_NAME.to_string()],
+            &claims.software_name,
+        ),
+        (!support_attributes.iter().any(|attribute| attribute == REQUIRED_SUPPORT_ATTRIBUTE)).then(
+            || ClaimMismatch {
+                claim: "support_attributes",
+                expected: vec![REQUIRED_SUPPORT_ATTRIBUTE.to_string()],
+                observed: support_attributes.join(","),
+            },
+        ),
+        endorsed_digests.and_then(|endorsed_digests| {
+            mismatch("image_digest", endorsed_digests, &claims.submods.container.image_digest)
+        }),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+fn mismatch(claim: &'static str, expected: Vec<String>, observed: &str) -> Option<ClaimMismatch> {
+    (!expected.iter().any(|expected| expected == observed)).then(|| ClaimMismatch {
+        claim,
+        expected,
+        observed: observed.to_string(),
+    })
+}
+
+/// Compares the issuer of the last certificate in the token's x5c chain with
+/// the subjects of the trusted root certificates.
+///
+/// Returns [`None`] if the chain is empty or its last certificate can't be
+/// decoded, as the token report already explains those failures.
+pub(crate) fn diff_root(header: &Header, roots: &[Certificate]) -> Option<ClaimMismatch> {
+    let last_certificate =
+        Certificate::from_der(&STANDARD.decode(header.x509_chain.last()?).ok()?).ok()?;
+    Some(ClaimMismatch {
+        claim: "root_certificate",
+        expected: roots.iter().map(|root| root.tbs_certificate.subject.to_string()).collect(),
+        observed: last_certificate.tbs_certificate.issuer.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use oak_file_utils::read_testdata;
+
+    use super::*;
+    use crate::jwt::{ConfidentialSpaceClaims, ContainerClaims, Submods};
+
+    const DIGEST: &str = "sha256:313b8a83d3c8bfc9abcffee4f538424473e2705383a7e46f16d159faf0e5ef34";
+
+    fn production_claims(image_digest: &str) -> Claims {
+        Claims {
+            eat_nonce: "nonce".to_string(),
+            debug_status: PRODUCTION_DEBUG_STATUS.to_string(),
+            software_name: CONFIDENTIAL_SPACE_SOFTWARE_NAME.to_string(),
+            submods: Submods {
+                confidential_space: ConfidentialSpaceClaims {
+                    support_attributes: vec![REQUIRED_SUPPORT_ATTRIBUTE.to_string()],
+                },
+                container: ContainerClaims {
+                    image_digest: image_digest.to_string(),
+                    ..Default::default()
+                },
+            },
+            ..Default::default()
+        }
+    }
+
+    fn endorsement_statement() -> EndorsementStatement {
+        EndorsementStatement::try_from(read_testdata!("endorsement.json").as_slice()).unwrap()
+    }
+
+    #[test]
+    fn diff_claims_matching() {
+        let statement = endorsement_statement();
+
+        assert_eq!(diff_claims(&production_claims(DIGEST), "nonce", Some(&statement)), vec![]);
+    }
+
+    #[test]
+    fn diff_claims_reports_every_mismatch() {
+        let statement = endorsement_statement();
+        let claims =
+            Claims { debug_status: "enabled".to_string(), ..production_claims("sha256:0000") };
+
+        assert_eq!(
+            diff_claims(&claims, "other_nonce", Some(&statement)),
+            vec![
+                ClaimMismatch {
+                    claim: "eat_nonce",
+                    expected: vec!["other_nonce".to_string()],
+                    observed: "nonce".to_string(),
+                },
+                ClaimMismatch {
+                    claim: "dbgstat",
+                    expected: vec![PRODUCTION_DEBUG_STATUS.to_string()],
+                    observed: "enabled".to_string(),
+                },
+                ClaimMismatch {
+                    claim: "image_digest",
+                    expected: vec![DIGEST.to_string()],
+                    observed: "sha256:0000".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_claims_ignores_image_digest_without_statement() {
+        assert_eq!(diff_claims(&production_claims("sha256:0000"), "nonce", None), vec![]);
+    }
+}
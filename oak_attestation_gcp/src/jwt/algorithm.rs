@@ -14,12 +14,24 @@
 // limitations under the License.
 //
 
-use jwt::{algorithm::VerifyingAlgorithm, AlgorithmType};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jwt::{
+    algorithm::{SigningAlgorithm, VerifyingAlgorithm},
+    AlgorithmType,
+};
+use rsa::{
+    pkcs1v15::{Signature as RsaSignature, SigningKey, VerifyingKey as RsaVerifyingKey},
+    sha2::Sha256,
+    signature::{SignatureEncoding, Signer, Verifier},
+    RsaPrivateKey, RsaPublicKey,
+};
 use x509_cert::Certificate;
 use x509_verify::{Signature, VerifyInfo, VerifyingKey};
 
 /// An implementation of [`VerifyingAlgorithm`] to verify the signature of a JWT
 /// that uses an X509 certificate to verify the signature  of a JWT token.
+///
+/// RS256 and ES256 signatures are supported.
 pub(crate) struct CertificateAlgorithm {
     verifying_key: VerifyingKey,
     /// The algorithm used to sign the JWT. [`AlgorithmIdentifier`] is not
@@ -43,6 +55,15 @@ impl CertificateAlgorithm {
         let verifying_key = VerifyingKey::try_from(certificate)?;
         Ok(Self { verifying_key, algorithm, algorithm_type: AlgorithmType::Rs256 })
     }
+
+    pub(crate) fn es256(certificate: &Certificate) -> Result<Self, x509_verify::Error> {
+        let algorithm = x509_cert::spki::AlgorithmIdentifierOwned {
+            oid: const_oid::db::rfc5912::ECDSA_WITH_SHA_256,
+            parameters: None,
+        };
+        let verifying_key = VerifyingKey::try_from(certificate)?;
+        Ok(Self { verifying_key, algorithm, algorithm_type: AlgorithmType::Es256 })
+    }
 }
 
 impl VerifyingAlgorithm for CertificateAlgorithm {
@@ -71,6 +92,19 @@ impl VerifyingAlgorithm for CertificateAlgorithm {
         // the original signature format for verification.
         // https://datatracker.ietf.org/doc/html/rfc7515#section-5.1
         let signed_data = format!("{header_base64}.{claims_base64}");
+        // JWS encodes ECDSA signatures as the concatenation of r and s, whereas
+        // X.509 signatures are DER-encoded.
+        // https://datatracker.ietf.org/doc/html/rfc7518#section-3.4
+        let der_signature;
+        let signature = match self.algorithm_type {
+            AlgorithmType::Es256 => {
+                der_signature = p256::ecdsa::Signature::from_slice(signature)
+                    .map_err(|_| jwt::error::Error::InvalidSignature)?
+                    .to_der();
+                der_signature.as_bytes()
+            }
+            _ => signature,
+        };
         let info = VerifyInfo::new(
             signed_data.as_bytes().into(),
             Signature::new(&self.algorithm, signature),
@@ -79,3 +113,168 @@ impl VerifyingAlgorithm for CertificateAlgorithm {
         Ok(true)
     }
 }
+
+/// A [`SigningAlgorithm`] that signs JWTs with RS256 (RSASSA-PKCS1-v1_5 using
+/// SHA-256).
+///
+/// The algorithm type is fixed by construction rather than derived from the
+/// OpenSSL key ID, as `jwt::PKeyWithDigest` does, which does not reliably
+/// identify RSA keys.
+pub struct Rs256SigningKey {
+    signing_key: SigningKey<Sha256>,
+}
+
+impl Rs256SigningKey {
+    /// Returns the key that verifies the signatures produced by this key.
+    pub fn verifying_key(&self) -> Rs256VerifyingKey {
+        self.signing_key.as_ref().to_public_key().into()
+    }
+}
+
+impl From<RsaPrivateKey> for Rs256SigningKey {
+    fn from(private_key: RsaPrivateKey) -> Self {
+        Self { signing_key: SigningKey::new(private_key) }
+    }
+}
+
+impl SigningAlgorithm for Rs256SigningKey {
+    fn algorithm_type(&self) -> AlgorithmType {
+        AlgorithmType::Rs256
+    }
+
+    fn sign(&self, header_base64: &str, claims_base64: &str) -> Result<String, jwt::error::Error> {
+        let signed_data = format!("{header_base64}.{claims_base64}");
+        let signature: RsaSignature = self
+            .signing_key
+            .try_sign(signed_data.as_bytes())
+            .map_err(|_| jwt::error::Error::InvalidSignature)?;
+        Ok(URL_SAFE_NO_PAD.encode(signature.to_bytes()))
+    }
+}
+
+/// A [`VerifyingAlgorithm`] that verifies RS256 JWT signatures with a known
+/// RSA public key.
+pub struct Rs256VerifyingKey {
+    verifying_key: RsaVerifyingKey<Sha256>,
+}
+
+impl From<RsaPublicKey> for Rs256VerifyingKey {
+    fn from(public_key: RsaPublicKey) -> Self {
+        Self { verifying_key: RsaVerifyingKey::new(public_key) }
+    }
+}
+
+impl VerifyingAlgorithm for Rs256VerifyingKey {
+    fn algorithm_type(&self) -> AlgorithmType {
+        AlgorithmType::Rs256
+    }
+
+    fn verify_bytes(
+        &self,
+        header_base64: &str,
+        claims_base64: &str,
+        signature: &[u8],
+    ) -> Result<bool, jwt::error::Error> {
+        let signed_data = format!("{header_base64}.{claims_base64}");
+        let signature =
+            RsaSignature::try_from(signature).map_err(|_| jwt::error::Error::InvalidSignature)?;
+        self.verifying_key
+            .verify(signed_data.as_bytes(), &signature)
+            .map_err(|_| jwt::error::Error::InvalidSignature)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use googletest::prelude::*;
+    use jwt::{SignWithKey, Token, Unverified, VerifyWithKey};
+    use oak_file_utils::data_path;
+    use rsa::pkcs8::DecodePrivateKey;
+
+    use super::*;
+    use crate::jwt::{Claims, Header};
+
+    fn signing_key() -> Rs256SigningKey {
+        let pem =
+            fs::read_to_string(data_path("oak_attestation_gcp/testdata/signing.key.pem")).unwrap();
+        RsaPrivateKey::from_pkcs8_pem(&pem).expect("couldn't parse signing key").into()
+    }
+
+    fn claims() -> Claims {
+        Claims { audience: "test".to_string(), ..Default::default() }
+    }
+
+    #[test]
+    fn sign_then_verify() -> Result<()> {
+        let signing_key = signing_key();
+        let header = Header { algorithm: AlgorithmType::Rs256, x509_chain: vec![] };
+
+        let signed_token = Token::new(header, claims()).sign_with_key(&signing_key)?;
+        let unverified_token: Token<Header, Claims, Unverified> =
+            Token::parse_unverified(signed_token.as_str())?;
+        let verified_token = unverified_token.verify_with_key(&signing_key.verifying_key())?;
+
+        assert_that!(verified_token.claims(), eq(&claims()));
+        Ok(())
+    }
+
+    #[test]
+    fn verify_token_signed_by_jwtgen() -> Result<()> {
+        let token_str =
+            fs::read_to_string(data_path("oak_attestation_gcp/testdata/valid_token.jwt"))?;
+        let unverified_token: Token<Header, Claims, Unverified> =
+            Token::parse_unverified(&token_str)?;
+
+        assert_that!(
+            unverified_token.verify_with_key(&signing_key().verifying_key()).map(|_| ()),
+            ok(anything())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn verify_rejects_tampered_claims() -> Result<()> {
+        let signing_key = signing_key();
+        let header = Header { algorithm: AlgorithmType::Rs256, x509_chain: vec![] };
+        let signed_token = Token::new(header, claims()).sign_with_key(&signing_key)?;
+        let (header_base64, _) = signed_token.as_str().split_once('.').unwrap();
+        let (_, signature_base64) = signed_token.as_str().rsplit_once('.').unwrap();
+        let other_claims = Claims { audience: "other".to_string(), ..Default::default() };
+        let tampered_token = format!(
+            "{header_base64}.{}.{signature_base64}",
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(&other_claims)?)
+        );
+
+        let unverified_token: Token<Header, Claims, Unverified> =
+            Token::parse_unverified(&tampered_token)?;
+
+        assert_that!(
+            unverified_token.verify_with_key(&signing_key.verifying_key()).map(|_| ()),
+            err(matches_pattern!(jwt::error::Error::InvalidSignature))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn verify_rejects_other_algorithm() -> Result<()> {
+        let signing_key = signing_key();
+        let header = Header { algorithm: AlgorithmType::Rs256, x509_chain: vec![] };
+        let signed_token = Token::new(header, claims()).sign_with_key(&signing_key)?;
+        let (_, rest) = signed_token.as_str().split_once('.').unwrap();
+        let es256_header = Header { algorithm: AlgorithmType::Es256, x509_chain: vec![] };
+        let es256_token =
+            format!("{}.{rest}", URL_SAFE_NO_PAD.encode(serde_json::to_vec(&es256_header)?));
+
+        let unverified_token: Token<Header, Claims, Unverified> =
+            Token::parse_unverified(&es256_token)?;
+
+        assert_that!(
+            unverified_token.verify_with_key(&signing_key.verifying_key()).map(|_| ()),
+            err(matches_pattern!(jwt::error::Error::AlgorithmMismatch(_, _)))
+        );
+        Ok(())
+    }
+}
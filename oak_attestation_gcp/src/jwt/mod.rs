@@ -24,6 +24,8 @@ use serde::{Deserialize, Serialize};
 pub(crate) mod algorithm;
 pub mod verification;
 
+pub use algorithm::{Rs256SigningKey, Rs256VerifyingKey};
+
 /// Partial view of a JWT header with the fields interesting for the validation
 /// of the PKI flavour of Confidential Space JWT tokens.
 ///
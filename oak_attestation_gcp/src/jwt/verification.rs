@@ -15,15 +15,35 @@
 //
 
 use alloc::fmt;
+use core::slice;
 
 use base64::{engine::general_purpose::STANDARD, Engine};
-use jwt::{Token, Unverified, Verified, VerifyWithKey};
+use jwt::{AlgorithmType, Token, Unverified, Verified, VerifyWithKey};
+use oak_attestation_verification_types::verdict::{Check, Outcome, Verdict};
 use oak_time::Instant;
 use x509_cert::{der::Decode, Certificate};
 use x509_verify::VerifyingKey;
 
 use crate::jwt::{algorithm::CertificateAlgorithm, Claims, Header};
 
+/// Debug status of a production Confidential Space image. See 'dbgstat' in
+/// https://cloud.google.com/confidential-computing/confidential-space/docs/reference/token-claims#top-level_claims.
+pub const PRODUCTION_DEBUG_STATUS: &str = "disabled-since-boot";
+
+/// Software name of Confidential Space images. See 'swname' in
+/// https://cloud.google.com/confidential-computing/confidential-space/docs/reference/token-claims#top-level_claims.
+pub const CONFIDENTIAL_SPACE_SOFTWARE_NAME: &str = "CONFIDENTIAL_SPACE";
+
+/// Support attribute that a production Confidential Space image must have. See
+/// 'support_attributes' in
+/// https://cloud.google.com/confidential-computing/confidential-space/docs/reference/token-claims#submods-claims.
+pub const REQUIRED_SUPPORT_ATTRIBUTE: &str = "STABLE";
+
+/// Signing algorithms accepted for attestation tokens. Tokens declaring any
+/// other algorithm in their header (in particular `none`) are rejected before
+/// their signature is checked, to prevent algorithm confusion.
+pub const ALLOWED_ALGORITHMS: &[AlgorithmType] = &[AlgorithmType::Rs256, AlgorithmType::Es256];
+
 #[derive(thiserror::Error, Debug)]
 pub enum AttestationVerificationError {
     #[error("Failed to verify JWT: {0}")]
@@ -44,8 +64,12 @@ pub enum AttestationVerificationError {
     JWTValidityNotBefore { nbf: Instant, current_time: Instant },
     #[error("Token validity exp: {exp} < {current_time}")]
     JWTValidityExpiration { exp: Instant, current_time: Instant },
+    #[error("Token signing algorithm {0:?} is not allowed")]
+    UnsupportedAlgorithm(AlgorithmType),
     #[error("Empty X509 certificate chain")]
     EmptyX509Chain,
+    #[error("No trusted root certificates provided")]
+    NoTrustedRoots,
     #[error("Invalid debug status: want {want}, got {got}")]
     InvalidDebugStatus { want: &'static str, got: String },
     #[error("Invalid software name: want {want}, got {got}")]
@@ -158,6 +182,60 @@ impl AttestationTokenVerificationReport {
             }
         }
     }
+
+    /// Returns the outcome of each verification step, without consuming the
+    /// report.
+    pub fn verdict(&self) -> Verdict {
+        let AttestationTokenVerificationReport {
+            production_image,
+            validity,
+            verification,
+            issuer_report,
+        } = self;
+        Verdict::with_details(
+            Check::Token,
+            vec![
+                Verdict::from_result(Check::ProductionImage, production_image),
+                Verdict::from_result(Check::Validity, validity),
+                Verdict::from_result(Check::Signature, &verification.as_ref().map(|_| ())),
+                certificate_chain_verdict(issuer_report),
+            ],
+        )
+    }
+}
+
+/// Flattens the recursive certificate chain report into a list of
+/// certificates, ending with the root certificate if the chain is valid up to
+/// it.
+fn certificate_chain_verdict(
+    report: &Result<CertificateReport, AttestationVerificationError>,
+) -> Verdict {
+    let mut report = match report {
+        Ok(report) => report,
+        Err(err) => return Verdict::failed(Check::CertificateChain, err),
+    };
+    let mut details = Vec::new();
+    loop {
+        details.push(Verdict::with_details(
+            Check::ChainCertificate,
+            vec![
+                Verdict::from_result(Check::Validity, &report.validity),
+                Verdict::from_result(Check::Signature, &report.verification),
+            ],
+        ));
+        match report.issuer_report.as_ref() {
+            IssuerReport::Root => {
+                details.push(Verdict::new(Check::RootCertificate, Outcome::Passed));
+                break;
+            }
+            IssuerReport::OtherCertificate(Err(err)) => {
+                details.push(Verdict::failed(Check::ChainCertificate, err));
+                break;
+            }
+            IssuerReport::OtherCertificate(Ok(issuer_report)) => report = issuer_report,
+        }
+    }
+    Verdict::with_details(Check::CertificateChain, details)
 }
 
 #[derive(Debug)]
@@ -191,6 +269,24 @@ pub fn report_attestation_token(
     root: &Certificate,
     current_time: &oak_time::Instant,
 ) -> AttestationTokenVerificationReport {
+    let (_, report) =
+        report_attestation_token_with_roots(token, slice::from_ref(root), current_time);
+    report
+}
+
+/// Returns a full report on the success/failure status of verifying the JWT
+/// attestation token from Confidential Space against a set of trusted root
+/// certificates, together with the index in `roots` of the root that the
+/// token's certificate chain terminates at.
+///
+/// The chain is accepted if its last certificate is issued by any of the
+/// roots. If none of them issued it, the returned index is [`None`] and the
+/// report contains the error from verifying against the first root.
+pub fn report_attestation_token_with_roots(
+    token: Token<Header, Claims, Unverified>,
+    roots: &[Certificate],
+    current_time: &oak_time::Instant,
+) -> (Option<usize>, AttestationTokenVerificationReport) {
     // Construct a chain of certificate verification reports, going
     // through all certificates in the chain.
     // See https://cloud.google.com/confidential-computing/confidential-space/docs/reference/token-claims and https://datatracker.ietf.org/doc/html/rfc7515#section-4.1.6
@@ -198,62 +294,103 @@ pub fn report_attestation_token(
     // token is the first in the chain, followed by the certificate used to sign
     // that certificate, and so on until the last certificate, which is signed
     // by the root.)
-    let mut issuer = Box::new(root.clone());
+    let mut root_index = None;
+    let mut issuer: Option<Box<Certificate>> = None;
     let mut issuer_report = None;
     for base64_der in token.header().x509_chain.iter().rev() {
-        issuer_report = Some(
-            try {
-                let certificate = Box::new(Certificate::from_der(&STANDARD.decode(base64_der)?)?);
-                let validity = verify_certificate_validity(certificate.as_ref(), current_time);
-                let verification = verify_certificate(issuer.as_ref(), certificate.as_ref());
-                issuer = certificate;
-                CertificateReport {
-                    validity,
-                    verification,
-                    issuer_report: Box::new(match issuer_report {
-                        Some(issuer_report) => IssuerReport::OtherCertificate(issuer_report),
-                        None => IssuerReport::Root,
-                    }),
+        issuer_report = Some(try {
+            let certificate = Box::new(Certificate::from_der(&STANDARD.decode(base64_der)?)?);
+            let validity = verify_certificate_validity(certificate.as_ref(), current_time);
+            let verification = match issuer.as_deref() {
+                Some(issuer) => verify_certificate(issuer, certificate.as_ref()),
+                None => {
+                    let (index, verification) = find_root(roots, certificate.as_ref());
+                    root_index = index;
+                    verification
                 }
-            },
-        );
+            };
+            issuer = Some(certificate);
+            CertificateReport {
+                validity,
+                verification,
+                issuer_report: Box::new(match issuer_report {
+                    Some(issuer_report) => IssuerReport::OtherCertificate(issuer_report),
+                    None => IssuerReport::Root,
+                }),
+            }
+        });
     }
     let issuer_report = issuer_report.unwrap_or(Err(AttestationVerificationError::EmptyX509Chain));
 
-    AttestationTokenVerificationReport {
+    let report = AttestationTokenVerificationReport {
         production_image: verify_production_image(token.claims()),
         validity: verify_token_validity(&token, current_time),
         verification: try {
+            let algorithm = verify_algorithm(token.header())?;
+            let issuer = issuer.ok_or(AttestationVerificationError::EmptyX509Chain)?;
             // See https://cloud.google.com/confidential-computing/confidential-vm/docs/token-claims#token_items:
-            // "Confidential VM supports the RS256 algorithm".
-            token.verify_with_key(&CertificateAlgorithm::rs256(issuer.as_ref())?)?
+            // "Confidential VM supports the RS256 algorithm". ES256 is also
+            // accepted for tokens issued by other configurations.
+            let algorithm = match algorithm {
+                AlgorithmType::Rs256 => CertificateAlgorithm::rs256(issuer.as_ref())?,
+                AlgorithmType::Es256 => CertificateAlgorithm::es256(issuer.as_ref())?,
+                algorithm => Err(AttestationVerificationError::UnsupportedAlgorithm(algorithm))?,
+            };
+            token.verify_with_key(&algorithm)?
         },
         issuer_report,
+    };
+    (root_index, report)
+}
+
+/// Finds the first root in `roots` that issued `certificate`.
+fn find_root(
+    roots: &[Certificate],
+    certificate: &Certificate,
+) -> (Option<usize>, Result<(), AttestationVerificationError>) {
+    let mut first_error = None;
+    for (index, root) in roots.iter().enumerate() {
+        match verify_certificate(root, certificate) {
+            Ok(()) => return (Some(index), Ok(())),
+            Err(err) => {
+                first_error.get_or_insert(err);
+            }
+        }
+    }
+    (None, Err(first_error.unwrap_or(AttestationVerificationError::NoTrustedRoots)))
+}
+
+/// Checks that the token header declares one of the [`ALLOWED_ALGORITHMS`].
+fn verify_algorithm(header: &Header) -> Result<AlgorithmType, AttestationVerificationError> {
+    if ALLOWED_ALGORITHMS.contains(&header.algorithm) {
+        Ok(header.algorithm)
+    } else {
+        Err(AttestationVerificationError::UnsupportedAlgorithm(header.algorithm))
     }
 }
 
 fn verify_production_image(claims: &Claims) -> Result<(), AttestationVerificationError> {
-    // See 'dbgstat' in
-    // https://cloud.google.com/confidential-computing/confidential-space/docs/reference/token-claims#top-level_claims.
-    if claims.debug_status != "disabled-since-boot" {
+    if claims.debug_status != PRODUCTION_DEBUG_STATUS {
         return Err(AttestationVerificationError::InvalidDebugStatus {
-            want: "disabled-since-boot",
+            want: PRODUCTION_DEBUG_STATUS,
             got: claims.debug_status.clone(),
         });
     }
-    // See 'swname' in
-    // https://cloud.google.com/confidential-computing/confidential-space/docs/reference/token-claims#top-level_claims.
-    if claims.software_name != "CONFIDENTIAL_SPACE" {
+    if claims.software_name != CONFIDENTIAL_SPACE_SOFTWARE_NAME {
         return Err(AttestationVerificationError::InvalidSoftwareName {
-            want: "CONFIDENTIAL_SPACE",
+            want: CONFIDENTIAL_SPACE_SOFTWARE_NAME,
             got: claims.software_name.clone(),
         });
     }
-    // See 'support_attributes' in
-    // https://cloud.google.com/confidential-computing/confidential-space/docs/reference/token-claims#submods-claims.
-    if !claims.submods.confidential_space.support_attributes.contains(&"STABLE".to_string()) {
+    if !claims
+        .submods
+        .confidential_space
+        .support_attributes
+        .iter()
+        .any(|attribute| attribute == REQUIRED_SUPPORT_ATTRIBUTE)
+    {
         return Err(AttestationVerificationError::MissingRequiredSupportAttribute {
-            want: "STABLE",
+            want: REQUIRED_SUPPORT_ATTRIBUTE,
             got: claims.submods.confidential_space.support_attributes.clone(),
         });
     }
@@ -326,8 +463,9 @@ mod tests {
     use core::assert_matches::assert_matches;
     use std::fs;
 
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
     use googletest::prelude::*;
-    use jwt::{Token, Unverified};
+    use jwt::{AlgorithmType, Token, Unverified};
     use oak_file_utils::data_path;
     use oak_time::{make_instant, Duration, Instant};
     use x509_cert::{der::DecodePem, Certificate};
@@ -409,6 +547,95 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn validate_es256_token_ok() -> Result<()> {
+        let token_str = read_testdata("es256_token.jwt");
+        let root = Certificate::from_pem(read_testdata("root_ca_cert.pem"))
+            .expect("Failed to parse root certificate");
+
+        let unverified_token: Token<Header, Claims, Unverified> =
+            Token::parse_unverified(&token_str)?;
+        assert_eq!(unverified_token.header().algorithm, AlgorithmType::Es256);
+
+        verify_attestation_token(unverified_token, &root, &current_time())?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate_token_unsupported_algorithm() -> Result<()> {
+        let token_str = with_header_algorithm(&read_testdata("valid_token.jwt"), "PS256");
+        let root = Certificate::from_pem(read_testdata("root_ca_cert.pem"))
+            .expect("Failed to parse root certificate");
+
+        let unverified_token: Token<Header, Claims, Unverified> =
+            Token::parse_unverified(&token_str)?;
+
+        assert_matches!(
+            verify_attestation_token(unverified_token, &root, &current_time()).map(|_| ()),
+            Err(AttestationVerificationError::UnsupportedAlgorithm(AlgorithmType::Ps256))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate_token_none_algorithm() -> Result<()> {
+        // An unsigned token, as produced by an attacker: `alg: none` with an
+        // empty signature.
+        let token_str = with_header_algorithm(&read_testdata("valid_token.jwt"), "none");
+        let (signed_data, _) = token_str.rsplit_once('.').unwrap();
+        let token_str = format!("{signed_data}.");
+        let root = Certificate::from_pem(read_testdata("root_ca_cert.pem"))
+            .expect("Failed to parse root certificate");
+
+        let unverified_token: Token<Header, Claims, Unverified> =
+            Token::parse_unverified(&token_str)?;
+
+        assert_matches!(
+            verify_attestation_token(unverified_token, &root, &current_time()).map(|_| ()),
+            Err(AttestationVerificationError::UnsupportedAlgorithm(AlgorithmType::None))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate_token_hmac_algorithm() -> Result<()> {
+        // HS256 would verify the signature with a shared secret, which an
+        // attacker could set to the (public) signing certificate.
+        let token_str = with_header_algorithm(&read_testdata("valid_token.jwt"), "HS256");
+        let root = Certificate::from_pem(read_testdata("root_ca_cert.pem"))
+            .expect("Failed to parse root certificate");
+
+        let unverified_token: Token<Header, Claims, Unverified> =
+            Token::parse_unverified(&token_str)?;
+
+        assert_matches!(
+            verify_attestation_token(unverified_token, &root, &current_time()).map(|_| ()),
+            Err(AttestationVerificationError::UnsupportedAlgorithm(AlgorithmType::Hs256))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate_es256_token_with_rs256_header() -> Result<()> {
+        let token_str = with_header_algorithm(&read_testdata("es256_token.jwt"), "RS256");
+        let root = Certificate::from_pem(read_testdata("root_ca_cert.pem"))
+            .expect("Failed to parse root certificate");
+
+        let unverified_token: Token<Header, Claims, Unverified> =
+            Token::parse_unverified(&token_str)?;
+
+        assert_matches!(
+            verify_attestation_token(unverified_token, &root, &current_time()).map(|_| ()),
+            Err(AttestationVerificationError::JWTError(jwt::Error::InvalidSignature))
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn report_token_invalid_signature() -> Result<()> {
         let token_str = read_testdata("invalid_signature_token.jwt");
@@ -589,6 +816,15 @@ mod tests {
         Ok(())
     }
 
+    /// Replaces the `alg` header of `token_str`, keeping its signature.
+    fn with_header_algorithm(token_str: &str, algorithm: &str) -> String {
+        let (header_base64, rest) = token_str.split_once('.').unwrap();
+        let mut header: serde_json::Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(header_base64).unwrap()).unwrap();
+        header["alg"] = algorithm.into();
+        format!("{}.{rest}", URL_SAFE_NO_PAD.encode(header.to_string()))
+    }
+
     fn read_testdata(file: &str) -> String {
         fs::read_to_string(data_path(format!("oak_attestation_gcp/testdata/{file}"))).unwrap()
     }
@@ -22,6 +22,7 @@ extern crate alloc;
 
 pub mod attestation;
 pub mod cosign;
+pub mod diff;
 pub mod jwt;
 pub mod policy;
 pub mod policy_generator;
@@ -16,9 +16,13 @@
 
 use alloc::{string::String, vec::Vec};
 
+use endorsement::intoto::EndorsementStatement;
 use jwt::Token;
 use oak_attestation_verification::{decode_event_proto, results::set_session_binding_public_key};
-use oak_attestation_verification_types::policy::Policy;
+use oak_attestation_verification_types::{
+    policy::Policy,
+    verdict::{Check, Outcome, Verdict},
+};
 use oak_proto_rust::oak::{
     attestation::v1::{
         ConfidentialSpaceEndorsement, EventAttestationResults, SessionBindingPublicKeyData,
@@ -34,9 +38,10 @@ use crate::{
         self, CosignEndorsement, CosignReferenceValues, CosignVerificationError,
         CosignVerificationReport,
     },
+    diff::{diff_claims, diff_root, ClaimMismatch},
     jwt::{
         verification::{
-            report_attestation_token, AttestationTokenVerificationReport,
+            report_attestation_token_with_roots, AttestationTokenVerificationReport,
             AttestationVerificationError,
         },
         Claims, Header,
@@ -50,6 +55,14 @@ pub struct ConfidentialSpaceVerificationReport {
     pub workload_endorsement_verification:
         Option<Result<CosignVerificationReport, CosignVerificationError>>,
     pub token_report: AttestationTokenVerificationReport,
+    /// Index of the trusted root certificate that the token's certificate
+    /// chain terminates at, or [`None`] if it doesn't terminate at any of
+    /// them.
+    pub root_certificate_index: Option<usize>,
+    /// Values in the token that differ from the ones expected by the policy.
+    /// Meant to explain a failed verification; some failures, such as an
+    /// invalid signature, don't result in any mismatch.
+    pub claim_mismatches: Vec<ClaimMismatch>,
 }
 
 impl ConfidentialSpaceVerificationReport {
@@ -62,6 +75,8 @@ impl ConfidentialSpaceVerificationReport {
                 public_key_verification: Ok(()),
                 workload_endorsement_verification,
                 token_report,
+                root_certificate_index: _,
+                claim_mismatches: _,
             } => {
                 if let Some(workload_endorsement_verification) = workload_endorsement_verification {
                     workload_endorsement_verification?.into_checked()?;
@@ -73,9 +88,37 @@ impl ConfidentialSpaceVerificationReport {
                 public_key_verification: Err(err),
                 workload_endorsement_verification: _,
                 token_report: _,
+                root_certificate_index: _,
+                claim_mismatches: _,
             } => Err(err),
         }
     }
+
+    /// Returns the outcome of each verification step, without consuming the
+    /// report.
+    pub fn verdict(&self) -> Vec<Verdict> {
+        let mut verdicts = vec![
+            Verdict::from_result(Check::PublicKey, &self.public_key_verification),
+            self.token_report.verdict(),
+            match &self.workload_endorsement_verification {
+                None => Verdict::new(Check::WorkloadEndorsement, Outcome::Skipped),
+                Some(Err(err)) => Verdict::failed(Check::WorkloadEndorsement, err),
+                Some(Ok(report)) => {
+                    Verdict::with_details(Check::WorkloadEndorsement, vec![report.verdict()])
+                }
+            },
+        ];
+        if !self.claim_mismatches.is_empty() {
+            verdicts.push(Verdict::with_details(
+                Check::ReferenceValueMismatches,
+                self.claim_mismatches
+                    .iter()
+                    .map(|mismatch| Verdict::failed(Check::ReferenceValue, mismatch))
+                    .collect(),
+            ));
+        }
+        verdicts
+    }
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -106,8 +149,12 @@ pub enum ConfidentialSpaceVerificationError {
 
 /// Attstation policy that verifies evidence for a container workload running in
 /// Google Cloud Confidential Space.
+///
+/// The token certificate chain is accepted if it terminates at any of the
+/// trusted root certificates, so that both the old and the new root can be
+/// trusted while the root is being rotated.
 pub struct ConfidentialSpacePolicy {
-    root_certificate: Certificate,
+    root_certificates: Vec<Certificate>,
     workload_reference_values: Option<CosignReferenceValues>,
 }
 
@@ -115,16 +162,16 @@ impl ConfidentialSpacePolicy {
     /// Creates a new policy with reference values for the platform and the
     /// workload.
     pub(crate) fn new(
-        root_certificate: Certificate,
+        root_certificates: Vec<Certificate>,
         workload_reference_values: CosignReferenceValues,
     ) -> Self {
-        Self { root_certificate, workload_reference_values: Some(workload_reference_values) }
+        Self { root_certificates, workload_reference_values: Some(workload_reference_values) }
     }
 
     /// Creates a new policy with reference values only for the platform
     /// certificate.
-    pub(crate) fn new_unendorsed(root_certificate: Certificate) -> Self {
-        Self { root_certificate, workload_reference_values: None }
+    pub(crate) fn new_unendorsed(root_certificates: Vec<Certificate>) -> Self {
+        Self { root_certificates, workload_reference_values: None }
     }
 
     /// Produce a full report of the provided evidence and endorsement.
@@ -144,8 +191,8 @@ impl ConfidentialSpacePolicy {
             .map_err(ConfidentialSpaceVerificationError::VariantDecodeError)?;
 
         let token: Token<Header, Claims, _> = Token::parse_unverified(&endorsement.jwt_token)?;
-        let public_key_verification =
-            verify_claims_public_key(token.claims(), &public_key_data.session_binding_public_key);
+        let expected_nonce = public_key_nonce(&public_key_data.session_binding_public_key);
+        let public_key_verification = verify_claims_public_key(token.claims(), &expected_nonce);
 
         let image_reference = token.claims().effective_reference()?;
         let workload_endorsement_verification =
@@ -161,14 +208,31 @@ impl ConfidentialSpacePolicy {
                 }
             });
 
-        let token_report =
-            report_attestation_token(token, &self.root_certificate, &verification_time);
+        let endorsement_statement = self
+            .workload_reference_values
+            .as_ref()
+            .and(endorsement.workload_endorsement.as_ref())
+            .and_then(|workload_endorsement| workload_endorsement.endorsement.as_ref())
+            .and_then(|statement| {
+                EndorsementStatement::try_from(statement.serialized.as_slice()).ok()
+            });
+        let mut claim_mismatches =
+            diff_claims(token.claims(), &expected_nonce, endorsement_statement.as_ref());
+        let root_mismatch = diff_root(token.header(), &self.root_certificates);
+
+        let (root_certificate_index, token_report) =
+            report_attestation_token_with_roots(token, &self.root_certificates, &verification_time);
+        if root_certificate_index.is_none() {
+            claim_mismatches.extend(root_mismatch);
+        }
 
         Ok(ConfidentialSpaceVerificationReport {
             session_binding_public_key: public_key_data.session_binding_public_key.clone(),
             public_key_verification,
             workload_endorsement_verification,
             token_report,
+            root_certificate_index,
+            claim_mismatches,
         })
     }
 }
@@ -190,14 +254,19 @@ impl Policy<[u8]> for ConfidentialSpacePolicy {
     }
 }
 
+/// Returns the "eat_nonce" claim expected for a token binding the given
+/// public key.
+fn public_key_nonce(public_key: &[u8]) -> String {
+    hex::encode(Sha256::digest(public_key))
+}
+
 fn verify_claims_public_key(
     claims: &Claims,
-    expected_public_key: &Vec<u8>,
+    expected_nonce: &str,
 ) -> Result<(), ConfidentialSpaceVerificationError> {
-    let public_key_hash = hex::encode(Sha256::digest(expected_public_key));
-    if claims.eat_nonce != public_key_hash {
+    if claims.eat_nonce != expected_nonce {
         return Err(ConfidentialSpaceVerificationError::TokenClaimPublicKeyMismatch {
-            expected: public_key_hash,
+            expected: expected_nonce.to_string(),
             actual: claims.eat_nonce.clone(),
         });
     }
@@ -208,6 +277,7 @@ fn verify_claims_public_key(
 mod tests {
     use core::assert_matches::assert_matches;
 
+    use oak_attestation_verification_types::verdict::all_passed;
     use oak_file_utils::{read_testdata, read_testdata_string};
     use oak_proto_rust::oak::attestation::v1::{
         endorsement::Format, CosignReferenceValues as CosignReferenceValuesProto, Endorsement,
@@ -273,7 +343,7 @@ mod tests {
         let cosign_reference_values =
             CosignReferenceValues::from_proto(&cosign_reference_values_proto).unwrap();
 
-        let policy = ConfidentialSpacePolicy::new(root_certificate, cosign_reference_values);
+        let policy = ConfidentialSpacePolicy::new(vec![root_certificate], cosign_reference_values);
 
         let result = policy.verify(current_time, &event.encode_to_vec(), &endorsement.into());
 
@@ -319,7 +389,7 @@ mod tests {
         let cosign_reference_values =
             CosignReferenceValues::from_proto(&cosign_reference_values_proto).unwrap();
 
-        let policy = ConfidentialSpacePolicy::new(root_certificate, cosign_reference_values);
+        let policy = ConfidentialSpacePolicy::new(vec![root_certificate], cosign_reference_values);
 
         let result = policy.report(current_time, &event.encode_to_vec(), &endorsement.into());
 
@@ -348,7 +418,9 @@ mod tests {
                         rekor_verification: None
                     })
                 })),
-            }) if *session_binding_public_key == BINDING_KEY_BYTES
+                root_certificate_index: Some(0),
+                ref claim_mismatches,
+            }) if *session_binding_public_key == BINDING_KEY_BYTES && claim_mismatches.is_empty()
         );
     }
 
@@ -369,7 +441,7 @@ mod tests {
 
         let root_certificate = Certificate::from_pem(&root_certificate_pem).unwrap();
 
-        let policy = ConfidentialSpacePolicy::new_unendorsed(root_certificate);
+        let policy = ConfidentialSpacePolicy::new_unendorsed(vec![root_certificate]);
 
         let result = policy.report(current_time, &event.encode_to_vec(), &endorsement.into());
 
@@ -393,7 +465,177 @@ mod tests {
                     }),
                 },
                 workload_endorsement_verification: None,
-            }) if *session_binding_public_key == BINDING_KEY_BYTES
+                root_certificate_index: Some(0),
+                ref claim_mismatches,
+            }) if *session_binding_public_key == BINDING_KEY_BYTES && claim_mismatches.is_empty()
+        );
+    }
+
+    #[test]
+    fn confidential_space_policy_verdict_succeeds_unendorsed() {
+        // The time has been set inside the validity interval of the test token and the
+        // root certificate.
+        let current_time = make_instant!("2025-07-01T17:31:32Z");
+
+        let event = create_public_key_event(&BINDING_KEY_BYTES);
+
+        let endorsement = ConfidentialSpaceEndorsement {
+            jwt_token: read_testdata_string!("valid_token.jwt"),
+            ..Default::default()
+        };
+
+        let root_certificate =
+            Certificate::from_pem(read_testdata_string!("root_ca_cert.pem")).unwrap();
+
+        let policy = ConfidentialSpacePolicy::new_unendorsed(vec![root_certificate]);
+
+        let verdict = policy
+            .report(current_time, &event.encode_to_vec(), &endorsement.into())
+            .unwrap()
+            .verdict();
+
+        let passed = |check| Verdict::new(check, Outcome::Passed);
+        let chain_certificate = || {
+            Verdict::with_details(
+                Check::ChainCertificate,
+                vec![passed(Check::Validity), passed(Check::Signature)],
+            )
+        };
+        assert_eq!(
+            verdict,
+            vec![
+                passed(Check::PublicKey),
+                Verdict::with_details(
+                    Check::Token,
+                    vec![
+                        passed(Check::ProductionImage),
+                        passed(Check::Validity),
+                        passed(Check::Signature),
+                        Verdict::with_details(
+                            Check::CertificateChain,
+                            vec![
+                                chain_certificate(),
+                                chain_certificate(),
+                                passed(Check::RootCertificate)
+                            ]
+                        ),
+                    ]
+                ),
+                Verdict::new(Check::WorkloadEndorsement, Outcome::Skipped),
+            ]
+        );
+        assert!(all_passed(&verdict));
+    }
+
+    #[test]
+    fn confidential_space_policy_report_matches_any_root() {
+        // The time has been set inside the validity interval of the test tokens and
+        // the root certificates.
+        let current_time = make_instant!("2025-07-01T17:31:32Z");
+
+        let event = create_public_key_event(&BINDING_KEY_BYTES);
+
+        let root_certificate =
+            Certificate::from_pem(read_testdata_string!("root_ca_cert.pem")).unwrap();
+        let rotated_root_certificate =
+            Certificate::from_pem(read_testdata_string!("rotated_root_ca_cert.pem")).unwrap();
+
+        let policy = ConfidentialSpacePolicy::new_unendorsed(vec![
+            root_certificate,
+            rotated_root_certificate,
+        ]);
+
+        for (token, expected_root_certificate_index) in
+            [("valid_token.jwt", 0), ("rotated_root_token.jwt", 1)]
+        {
+            let endorsement = ConfidentialSpaceEndorsement {
+                jwt_token: read_testdata_string!(token),
+                ..Default::default()
+            };
+
+            let report =
+                policy.report(current_time, &event.encode_to_vec(), &endorsement.into()).unwrap();
+
+            assert_eq!(report.root_certificate_index, Some(expected_root_certificate_index));
+            assert_matches!(report.into_session_binding_public_key(), Ok(key) if key == BINDING_KEY_BYTES);
+        }
+    }
+
+    #[test]
+    fn confidential_space_policy_report_fails_untrusted_root() {
+        // The time has been set inside the validity interval of the test token and the
+        // root certificates.
+        let current_time = make_instant!("2025-07-01T17:31:32Z");
+
+        let event = create_public_key_event(&BINDING_KEY_BYTES);
+
+        let endorsement = ConfidentialSpaceEndorsement {
+            jwt_token: read_testdata_string!("rotated_root_token.jwt"),
+            ..Default::default()
+        };
+
+        let root_certificate =
+            Certificate::from_pem(read_testdata_string!("root_ca_cert.pem")).unwrap();
+
+        let policy = ConfidentialSpacePolicy::new_unendorsed(vec![root_certificate]);
+
+        let report =
+            policy.report(current_time, &event.encode_to_vec(), &endorsement.into()).unwrap();
+
+        assert_eq!(report.root_certificate_index, None);
+        assert!(!all_passed(&report.verdict()));
+        assert_eq!(
+            report.claim_mismatches,
+            vec![ClaimMismatch {
+                claim: "root_certificate",
+                expected: vec!["CN=Test Root CA".to_string()],
+                observed: "CN=Test Rotated Root CA".to_string(),
+            }]
+        );
+        assert_matches!(
+            report.into_session_binding_public_key(),
+            Err(ConfidentialSpaceVerificationError::TokenVerificationError(
+                AttestationVerificationError::X509VerificationError(_)
+            ))
+        );
+    }
+
+    #[test]
+    fn confidential_space_policy_report_explains_mismatches() {
+        // The time has been set inside the validity interval of the test token and the
+        // root certificate.
+        let current_time = make_instant!("2025-07-01T17:31:32Z");
+
+        let other_binding_key = [0; 32];
+        let event = create_public_key_event(&other_binding_key);
+
+        let endorsement = ConfidentialSpaceEndorsement {
+            jwt_token: read_testdata_string!("debug_token.jwt"),
+            ..Default::default()
+        };
+
+        let root_certificate =
+            Certificate::from_pem(read_testdata_string!("root_ca_cert.pem")).unwrap();
+
+        let policy = ConfidentialSpacePolicy::new_unendorsed(vec![root_certificate]);
+
+        let report =
+            policy.report(current_time, &event.encode_to_vec(), &endorsement.into()).unwrap();
+
+        assert_eq!(
+            report.claim_mismatches,
+            vec![
+                ClaimMismatch {
+                    claim: "eat_nonce",
+                    expected: vec![public_key_nonce(&other_binding_key)],
+                    observed: public_key_nonce(&BINDING_KEY_BYTES),
+                },
+                ClaimMismatch {
+                    claim: "dbgstat",
+                    expected: vec!["disabled-since-boot".to_string()],
+                    observed: "enabled".to_string(),
+                },
+            ]
         );
     }
 
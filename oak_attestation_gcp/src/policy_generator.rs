@@ -36,7 +36,7 @@ pub fn confidential_space_policy_from_reference_values(
             let cosign_reference_values =
                 CosignReferenceValues::from_proto(cosign_reference_values)
                     .map_err(anyhow::Error::msg)?;
-            Ok(ConfidentialSpacePolicy::new(root_certificate, cosign_reference_values))
+            Ok(ConfidentialSpacePolicy::new(vec![root_certificate], cosign_reference_values))
         }
         Some(confidential_space_reference_values::ContainerImage::ContainerImageReference(
             _container_image_reference,
@@ -44,7 +44,7 @@ pub fn confidential_space_policy_from_reference_values(
             // TODO: b/439861326 - Generate policy based on container image reference.
             Err(anyhow::Error::msg("Container image reference not yet supported"))
         }
-        None => Ok(ConfidentialSpacePolicy::new_unendorsed(root_certificate)),
+        None => Ok(ConfidentialSpacePolicy::new_unendorsed(vec![root_certificate])),
     }
 }
 
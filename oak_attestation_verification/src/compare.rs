@@ -121,7 +121,8 @@ fn compare_oak_containers_measurement_digests(
         values.container_layer.as_ref().context("no container layer evidence values")?,
         expected.container_layer.as_ref().context("no system layer expected_values")?,
     )
-    .context("comparing container layer digests")
+    .context("comparing container layer digests")?;
+    Ok(())
 }
 
 /// Validates the values extracted from the evidence against the reference
@@ -293,20 +294,32 @@ pub(crate) fn compare_application_layer_measurement_digests(
     .context("comparing application config digests")
 }
 
+/// Indices of the expected digests that matched the container layer
+/// measurements.
+///
+/// An index is `None` if the verification of the corresponding measurement
+/// was skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ContainerLayerDigestMatches {
+    pub bundle: Option<usize>,
+    pub config: Option<usize>,
+}
+
 pub(crate) fn compare_container_layer_measurement_digests(
     values: &ContainerLayerData,
     expected: &ContainerLayerExpectedValues,
-) -> anyhow::Result<()> {
-    compare_measurement_digest(
+) -> anyhow::Result<ContainerLayerDigestMatches> {
+    let bundle = compare_measurement_digest_index(
         values.bundle.as_ref().context("no bundle evidence value")?,
         expected.bundle.as_ref().context("no expected bundle value")?,
     )
     .context("comparing container bundle digests")?;
-    compare_measurement_digest(
+    let config = compare_measurement_digest_index(
         values.config.as_ref().context("no config evidence value")?,
         expected.config.as_ref().context("no expected config value")?,
     )
-    .context("comparing container config digests")
+    .context("comparing container config digests")?;
+    Ok(ContainerLayerDigestMatches { bundle, config })
 }
 
 /// Verifies the measurement digest value against a reference value and
@@ -315,16 +328,27 @@ pub(crate) fn compare_measurement_digest(
     measurement: &RawDigest,
     expected: &ExpectedDigests,
 ) -> anyhow::Result<()> {
+    compare_measurement_digest_index(measurement, expected).map(|_| ())
+}
+
+/// Same as [`compare_measurement_digest`], but also returns the index of the
+/// first expected digest that matched the measurement, or `None` if the
+/// verification was skipped.
+pub(crate) fn compare_measurement_digest_index(
+    measurement: &RawDigest,
+    expected: &ExpectedDigests,
+) -> anyhow::Result<Option<usize>> {
     match expected.r#type.as_ref() {
-        Some(expected_digests::Type::Skipped(_)) => Ok(()),
+        Some(expected_digests::Type::Skipped(_)) => Ok(None),
         Some(expected_digests::Type::Digests(digests)) => digests
             .digests
             .iter()
-            .find(|expected| is_raw_digest_match(measurement, expected).is_ok())
-            .map(|_| ())
+            .position(|expected| is_raw_digest_match(measurement, expected).is_ok())
+            .map(Some)
             .ok_or(anyhow::anyhow!(
-                "measurement digest {:?} does not match any reference values",
-                measurement
+                "measurement digest {:?} does not match any of the expected digests {:?}",
+                measurement,
+                digests.digests
             )),
         None => Err(anyhow::anyhow!("empty expected value")),
     }
@@ -17,15 +17,21 @@
 //! Utilities for validating Intel provisioning certificates and attestation
 //! quotes.
 
+use alloc::vec::Vec;
+
 use anyhow::{anyhow, Context};
-use const_oid::db::rfc5912::ECDSA_WITH_SHA_256;
+use const_oid::{db::rfc5912::ECDSA_WITH_SHA_256, ObjectIdentifier};
 use oak_tdx_quote::{QeCertificationData, TdxQuoteWrapper};
 use p256::{
     ecdsa::{signature::Verifier, Signature, VerifyingKey},
     EncodedPoint,
 };
 use x509_cert::{
-    der::{referenced::OwnedToRef, DecodePem, Encode},
+    der::{
+        asn1::{Any, OctetStringRef},
+        referenced::OwnedToRef,
+        Decode, DecodePem, Encode, SliceReader,
+    },
     Certificate,
 };
 
@@ -33,11 +39,39 @@ use crate::util::hash_sha2_256;
 
 const PCK_ROOT: &str = include_str!("../data/Intel_SGX_Provisioning_Certification_RootCA.pem");
 
+// OIDs of the Intel SGX extensions in PCK certificates, taken from the Intel
+// SGX PCK Certificate and Certificate Revocation List Profile Specification.
+const SGX_EXTENSIONS_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113741.1.13.1");
+const FMSPC_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113741.1.13.1.4");
+
+/// The Family-Model-Stepping-Platform-CustomSKU value that identifies the
+/// platform model a PCK certificate was issued for.
+pub type Fmspc = [u8; 6];
+
+#[derive(thiserror::Error, Debug)]
+pub enum FmspcVerificationError {
+    #[error("PCK certificate does not contain an FMSPC")]
+    MissingFmspc,
+    #[error("invalid SGX extensions in PCK certificate: {0}")]
+    InvalidExtension(x509_cert::der::Error),
+    #[error("FMSPC has invalid length: {0}")]
+    InvalidFmspcLength(usize),
+    #[error("FMSPC {} is not in the allowlist", hex::encode(.0))]
+    DisallowedFmspc(Fmspc),
+}
+
 /// Verifies that the TDX Attestation Quote is correctly signed and that the
 /// entire chain of trust is valid all the way to the Provisioning Certification
 /// Key (PCK) root certificate.
+///
+/// If `allowed_fmspcs` is given, the FMSPC of the PCK leaf certificate must
+/// also be one of the listed values, which pins the quote to specific platform
+/// models.
 #[allow(unused)]
-pub fn verify_intel_tdx_quote_validity(quote: &TdxQuoteWrapper) -> anyhow::Result<()> {
+pub fn verify_intel_tdx_quote_validity(
+    quote: &TdxQuoteWrapper,
+    allowed_fmspcs: Option<&[Fmspc]>,
+) -> anyhow::Result<()> {
     let signature_data = quote.parse_signature_data().context("parsing signature data")?;
 
     let report_certification = match signature_data.certification_data {
@@ -51,6 +85,9 @@ pub fn verify_intel_tdx_quote_validity(quote: &TdxQuoteWrapper) -> anyhow::Resul
     let pck_leaf =
         verify_quote_cert_chain_and_extract_leaf(&report_certification.certification_data)
             .context("verifying quote cert chain")?;
+    if let Some(allowed_fmspcs) = allowed_fmspcs {
+        verify_fmspc(&pck_leaf, allowed_fmspcs)?;
+    }
 
     // Verify that the Quoting Enclave report is signed using the PCK leaf
     // certificate.
@@ -118,6 +155,47 @@ pub fn verify_quote_cert_chain_and_extract_leaf(
     Ok(leaf)
 }
 
+/// Verifies that the FMSPC of the PCK certificate is one of the allowed values.
+pub fn verify_fmspc(
+    pck_certificate: &Certificate,
+    allowed_fmspcs: &[Fmspc],
+) -> Result<(), FmspcVerificationError> {
+    let fmspc = extract_fmspc(pck_certificate)?;
+    if allowed_fmspcs.contains(&fmspc) {
+        Ok(())
+    } else {
+        Err(FmspcVerificationError::DisallowedFmspc(fmspc))
+    }
+}
+
+/// Extracts the FMSPC from the SGX extensions of the PCK certificate.
+pub fn extract_fmspc(pck_certificate: &Certificate) -> Result<Fmspc, FmspcVerificationError> {
+    let sgx_extensions = pck_certificate
+        .tbs_certificate
+        .extensions
+        .as_ref()
+        .and_then(|exts| exts.iter().find(|&ext| ext.extn_id == SGX_EXTENSIONS_OID))
+        .ok_or(FmspcVerificationError::MissingFmspc)?;
+    // The SGX extensions are a sequence of (OID, value) pairs.
+    let entries = Vec::<Any>::from_der(sgx_extensions.extn_value.as_bytes())
+        .map_err(FmspcVerificationError::InvalidExtension)?;
+    for entry in entries {
+        let mut reader =
+            SliceReader::new(entry.value()).map_err(FmspcVerificationError::InvalidExtension)?;
+        let oid = ObjectIdentifier::decode(&mut reader)
+            .map_err(FmspcVerificationError::InvalidExtension)?;
+        if oid == FMSPC_OID {
+            let value = OctetStringRef::decode(&mut reader)
+                .map_err(FmspcVerificationError::InvalidExtension)?;
+            return value
+                .as_bytes()
+                .try_into()
+                .map_err(|_| FmspcVerificationError::InvalidFmspcLength(value.as_bytes().len()));
+        }
+    }
+    Err(FmspcVerificationError::MissingFmspc)
+}
+
 fn verify_ecdsa_cert_signature(signer: &Certificate, signee: &Certificate) -> anyhow::Result<()> {
     anyhow::ensure!(
         signee.signature_algorithm.oid == ECDSA_WITH_SHA_256,
@@ -22,14 +22,27 @@ use x509_cert::der::DecodePem;
 
 use super::{
     verify_ecdsa_cert_signature, verify_intel_tdx_quote_validity,
-    verify_quote_cert_chain_and_extract_leaf, PCK_ROOT,
+    verify_quote_cert_chain_and_extract_leaf, Fmspc, FmspcVerificationError, PCK_ROOT,
 };
 
+// The FMSPC in the PCK leaf certificate of the evidence.
+const EVIDENCE_FMSPC: Fmspc = [0x00, 0x80, 0x6f, 0x05, 0x00, 0x00];
+
 fn get_evidence_quote_bytes() -> Vec<u8> {
     let d = AttestationData::load_tdx_oc();
     d.evidence.root_layer.expect("no root layer").remote_attestation_report
 }
 
+/// The number of bytes of the evidence quote that are covered by its
+/// structures. The rest of the buffer is padding.
+fn get_used_quote_length(quote_buffer: &[u8]) -> usize {
+    let wrapper = TdxQuoteWrapper::new(quote_buffer);
+    let quote_data = wrapper.get_quote_data_bytes().expect("couldn't get quote data bytes");
+    let signature_data =
+        wrapper.get_signature_data_bytes().expect("couldn't get signature data bytes");
+    quote_data.len() + core::mem::size_of::<u32>() + signature_data.len()
+}
+
 #[test]
 fn pck_root_signs_itself() {
     let pck_root = x509_cert::Certificate::from_pem(PCK_ROOT).expect("could not parse cert");
@@ -64,7 +77,7 @@ fn pck_chain_validation_passes() {
 fn valid_tdx_quote_validation_passes() {
     let quote_buffer = get_evidence_quote_bytes();
     let wrapper = TdxQuoteWrapper::new(quote_buffer.as_slice());
-    assert!(verify_intel_tdx_quote_validity(&wrapper).is_ok());
+    assert!(verify_intel_tdx_quote_validity(&wrapper, None).is_ok());
 }
 
 #[test]
@@ -72,10 +85,10 @@ fn tdx_quote_with_invalid_pck_chain_fails() {
     let mut quote_buffer = get_evidence_quote_bytes();
     // Change a character in the PEM-encoded PCK leaf cert
     // (`oak_tdx_quote::QeReportCertificationData::certification_data` which will be
-    // parsed from bytes 1258..4939 of the evidence).
+    // parsed from byte 1258 up to the end of the used part of the evidence).
     quote_buffer[1299] = b'v';
     let wrapper = TdxQuoteWrapper::new(quote_buffer.as_slice());
-    assert!(verify_intel_tdx_quote_validity(&wrapper).is_err());
+    assert!(verify_intel_tdx_quote_validity(&wrapper, None).is_err());
 }
 
 #[test]
@@ -86,7 +99,7 @@ fn tdx_quote_with_invalid_qe_report_signature_fails() {
     // parsed from bytes 1154..1218 of the evidence).
     quote_buffer[1210] = 0;
     let wrapper = TdxQuoteWrapper::new(quote_buffer.as_slice());
-    assert!(verify_intel_tdx_quote_validity(&wrapper).is_err());
+    assert!(verify_intel_tdx_quote_validity(&wrapper, None).is_err());
 }
 
 #[test]
@@ -98,7 +111,7 @@ fn tdx_quote_with_invalid_attestation_key_fails() {
 
     quote_buffer[701] = 0;
     let wrapper = TdxQuoteWrapper::new(quote_buffer.as_slice());
-    assert!(verify_intel_tdx_quote_validity(&wrapper).is_err());
+    assert!(verify_intel_tdx_quote_validity(&wrapper, None).is_err());
 }
 
 #[test]
@@ -109,5 +122,57 @@ fn tdx_quote_with_invalid_attestation_signature_fails() {
     // from bytes 636..700 of the evidence).
     quote_buffer[637] = 0;
     let wrapper = TdxQuoteWrapper::new(quote_buffer.as_slice());
-    assert!(verify_intel_tdx_quote_validity(&wrapper).is_err());
+    assert!(verify_intel_tdx_quote_validity(&wrapper, None).is_err());
+}
+
+#[test]
+fn truncated_tdx_quote_fails() {
+    let quote_buffer = get_evidence_quote_bytes();
+    let used_length = get_used_quote_length(quote_buffer.as_slice());
+    for length in 0..used_length {
+        let wrapper = TdxQuoteWrapper::new(&quote_buffer[..length]);
+        assert!(
+            verify_intel_tdx_quote_validity(&wrapper, None).is_err(),
+            "truncated to {length} bytes"
+        );
+    }
+}
+
+#[test]
+fn tdx_quote_with_oversized_authentication_data_fails() {
+    let mut quote_buffer = get_evidence_quote_bytes();
+    // Set the length of the authentication data
+    // (`oak_tdx_quote::QeReportCertificationData::authentication_data` which will
+    // be parsed from bytes 1218..1220 of the evidence) to its maximum.
+    quote_buffer[1218..1220].fill(0xff);
+    let wrapper = TdxQuoteWrapper::new(quote_buffer.as_slice());
+    assert!(verify_intel_tdx_quote_validity(&wrapper, None).is_err());
+}
+
+#[test]
+fn tdx_quote_with_allowed_fmspc_passes() {
+    let quote_buffer = get_evidence_quote_bytes();
+    let wrapper = TdxQuoteWrapper::new(quote_buffer.as_slice());
+    let allowed_fmspcs = [[0x00, 0x90, 0x6e, 0xd5, 0x00, 0x00], EVIDENCE_FMSPC];
+    assert!(verify_intel_tdx_quote_validity(&wrapper, Some(&allowed_fmspcs)).is_ok());
+}
+
+#[test]
+fn tdx_quote_with_disallowed_fmspc_fails() {
+    let quote_buffer = get_evidence_quote_bytes();
+    let wrapper = TdxQuoteWrapper::new(quote_buffer.as_slice());
+    let allowed_fmspcs = [[0x00, 0x90, 0x6e, 0xd5, 0x00, 0x00]];
+    let err = verify_intel_tdx_quote_validity(&wrapper, Some(&allowed_fmspcs))
+        .expect_err("verification should fail");
+    assert!(matches!(
+        err.downcast_ref::<FmspcVerificationError>(),
+        Some(FmspcVerificationError::DisallowedFmspc(fmspc)) if *fmspc == EVIDENCE_FMSPC
+    ));
+}
+
+#[test]
+fn tdx_quote_with_empty_fmspc_allowlist_fails() {
+    let quote_buffer = get_evidence_quote_bytes();
+    let wrapper = TdxQuoteWrapper::new(quote_buffer.as_slice());
+    assert!(verify_intel_tdx_quote_validity(&wrapper, Some(&[])).is_err());
 }
@@ -68,7 +68,7 @@ pub use util::{
 };
 pub use verifiers::{
     create_amd_verifier, create_insecure_verifier, AmdSevSnpDiceAttestationVerifier,
-    EventLogVerifier, InsecureAttestationVerifier,
+    EventLogVerifier, EventLogVerifierBuilder, InsecureAttestationVerifier,
 };
 
 /// Verifies a signed endorsement against a reference value.
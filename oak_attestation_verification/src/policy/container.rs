@@ -31,6 +31,7 @@ use crate::{
     compare::compare_container_layer_measurement_digests,
     expect::acquire_container_event_expected_values,
     results::{
+        set_container_bundle_digest_index, set_container_config_digest_index,
         set_hybrid_encryption_public_key, set_session_binding_public_key, set_signing_public_key,
     },
     util::decode_event_proto,
@@ -95,10 +96,16 @@ impl Policy<[u8]> for ContainerPolicy {
         )
         .context("acquiring container event expected values")?;
 
-        compare_container_layer_measurement_digests(&event, &expected_values)
+        let digest_matches = compare_container_layer_measurement_digests(&event, &expected_values)
             .context("comparing container layer digests")?;
 
         let mut results = EventAttestationResults { ..Default::default() };
+        if let Some(index) = digest_matches.bundle {
+            set_container_bundle_digest_index(&mut results, index);
+        }
+        if let Some(index) = digest_matches.config {
+            set_container_config_digest_index(&mut results, index);
+        }
         if !event.session_binding_public_key.is_empty() {
             set_session_binding_public_key(&mut results, &event.session_binding_public_key);
         }
@@ -116,9 +123,20 @@ impl Policy<[u8]> for ContainerPolicy {
 
 #[cfg(test)]
 mod tests {
+    use oak_proto_rust::oak::{
+        attestation::v1::{
+            expected_digests, ContainerLayerExpectedValues, ExpectedDigests, RawDigests,
+            VerificationSkipped,
+        },
+        RawDigest,
+    };
     use test_util::{get_oc_reference_values, AttestationData};
 
     use super::*;
+    use crate::{
+        compare::ContainerLayerDigestMatches,
+        results::{get_container_bundle_digest_index, get_container_config_digest_index},
+    };
 
     const CONTAINER_EVENT_INDEX: usize = 2;
 
@@ -135,6 +153,10 @@ mod tests {
 
         // TODO: b/356631062 - Verify detailed attestation results.
         assert!(result.is_ok(), "Failed: {:?}", result.err().unwrap());
+        // Skipped verification doesn't match any expected digest.
+        let results = result.unwrap();
+        assert_eq!(get_container_bundle_digest_index(&results), None);
+        assert_eq!(get_container_config_digest_index(&results), None);
     }
 
     #[test]
@@ -163,5 +185,63 @@ mod tests {
         let result =
             ContainerPolicy::new(&rv).verify(d.make_valid_time(), event, &Variant::default());
         assert!(result.is_ok(), "Failed: {:?}", result.err().unwrap());
+        let results = result.unwrap();
+        assert_eq!(get_container_bundle_digest_index(&results), Some(0));
+        assert_eq!(get_container_config_digest_index(&results), Some(0));
+    }
+
+    #[test]
+    fn compare_digests_returns_matched_index() {
+        let d = AttestationData::load_milan_oc_release();
+        let event = decode_event_proto::<ContainerLayerData>(
+            "type.googleapis.com/oak.attestation.v1.ContainerLayerData",
+            &d.evidence.event_log.as_ref().unwrap().encoded_events[CONTAINER_EVENT_INDEX],
+        )
+        .unwrap();
+        let other_digest = RawDigest { sha2_256: vec![0; 32], ..Default::default() };
+        let expected_values = ContainerLayerExpectedValues {
+            bundle: Some(ExpectedDigests {
+                r#type: Some(expected_digests::Type::Digests(RawDigests {
+                    digests: vec![other_digest.clone(), event.bundle.clone().unwrap()],
+                    valid: None,
+                })),
+            }),
+            config: Some(ExpectedDigests {
+                r#type: Some(expected_digests::Type::Skipped(VerificationSkipped {})),
+            }),
+        };
+
+        let result = compare_container_layer_measurement_digests(&event, &expected_values);
+
+        assert_eq!(
+            result.expect("comparing digests failed"),
+            ContainerLayerDigestMatches { bundle: Some(1), config: None }
+        );
+    }
+
+    #[test]
+    fn compare_digests_reports_expected_digests_on_mismatch() {
+        let d = AttestationData::load_milan_oc_release();
+        let event = decode_event_proto::<ContainerLayerData>(
+            "type.googleapis.com/oak.attestation.v1.ContainerLayerData",
+            &d.evidence.event_log.as_ref().unwrap().encoded_events[CONTAINER_EVENT_INDEX],
+        )
+        .unwrap();
+        let other_digest = RawDigest { sha2_256: vec![0; 32], ..Default::default() };
+        let expected_values = ContainerLayerExpectedValues {
+            bundle: Some(ExpectedDigests {
+                r#type: Some(expected_digests::Type::Digests(RawDigests {
+                    digests: vec![other_digest.clone()],
+                    valid: None,
+                })),
+            }),
+            config: None,
+        };
+
+        let result = compare_container_layer_measurement_digests(&event, &expected_values);
+
+        let message = format!("{:#}", result.expect_err("comparing digests succeeded"));
+        assert!(message.contains(&format!("{:?}", event.bundle.unwrap())), "{message}");
+        assert!(message.contains(&format!("{:?}", [other_digest])), "{message}");
     }
 }
@@ -14,9 +14,12 @@
 // limitations under the License.
 //
 
-use alloc::vec::Vec;
+use alloc::{vec, vec::Vec};
 
-use oak_attestation_verification_types::policy::Policy;
+use oak_attestation_verification_types::{
+    policy::Policy,
+    verdict::{Check, Verdict},
+};
 use oak_crypto::{
     certificate::certificate_verifier::{
         CertificateVerificationError, CertificateVerificationReport, CertificateVerifier,
@@ -44,7 +47,7 @@ pub struct SessionBindingPublicKeyVerificationReport {
 }
 
 impl SessionBindingPublicKeyVerificationReport {
-    fn into_session_binding_public_key(
+    pub fn into_session_binding_public_key(
         self,
     ) -> Result<Vec<u8>, SessionBindingPublicKeyVerificationError> {
         match self {
@@ -60,6 +63,27 @@ impl SessionBindingPublicKeyVerificationReport {
             } => Err(SessionBindingPublicKeyVerificationError::CertificateVerificationError(err)),
         }
     }
+
+    /// Returns the outcome of each verification step, without consuming the
+    /// report.
+    pub fn verdict(&self) -> Vec<Verdict> {
+        vec![match &self.endorsement {
+            Ok(report) => certificate_verdict(report),
+            Err(err) => Verdict::failed(Check::Certificate, err),
+        }]
+    }
+}
+
+fn certificate_verdict(report: &CertificateVerificationReport) -> Verdict {
+    let CertificateVerificationReport { validity, verification, freshness } = report;
+    let mut details = vec![
+        Verdict::from_result(Check::Validity, validity),
+        Verdict::from_result(Check::Signature, verification),
+    ];
+    details.extend(
+        freshness.as_ref().map(|freshness| Verdict::from_result(Check::Freshness, freshness)),
+    );
+    Verdict::with_details(Check::Certificate, details)
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -150,7 +174,10 @@ mod tests {
     use alloc::sync::Arc;
     use core::assert_matches::assert_matches;
 
-    use oak_attestation_verification_types::verifier::AttestationVerifier;
+    use oak_attestation_verification_types::{
+        verdict::{all_passed, Outcome},
+        verifier::AttestationVerifier,
+    };
     use oak_proto_rust::oak::{
         attestation::v1::{
             CertificateAuthorityEndorsement, Endorsements, Event, EventLog, Evidence,
@@ -538,6 +565,63 @@ mod tests {
         );
     }
 
+    #[test]
+    fn verdict_succeeds() {
+        let evidence = create_public_key_evidence(&TEST_PUBLIC_KEY);
+        let endorsements = create_public_key_endorsements(&TEST_SIGNATURE);
+        let event = &evidence.event_log.as_ref().unwrap().encoded_events[CERTIFICATE_EVENT_INDEX];
+        let endorsement = &endorsements.events[CERTIFICATE_EVENT_INDEX];
+        let certificate_verifier: CertificateVerifier<TestSignatureVerifier> =
+            CertificateVerifier::new(TestSignatureVerifier {
+                expected_signature: TEST_SIGNATURE.to_vec(),
+            });
+        let policy = SessionBindingPublicKeyPolicy::new(certificate_verifier);
+
+        let verdict = policy.report(TEST_TIME, event, endorsement).unwrap().verdict();
+
+        assert_eq!(
+            verdict,
+            vec![Verdict::with_details(
+                Check::Certificate,
+                vec![
+                    Verdict::new(Check::Validity, Outcome::Passed),
+                    Verdict::new(Check::Signature, Outcome::Passed),
+                ]
+            )]
+        );
+        assert!(all_passed(&verdict));
+    }
+
+    #[test]
+    fn verdict_fails_with_invalid_signature() {
+        let certificate_verifier: CertificateVerifier<TestSignatureVerifier> =
+            CertificateVerifier::new(TestSignatureVerifier {
+                expected_signature: TEST_SIGNATURE.to_vec(),
+            });
+        let policy = SessionBindingPublicKeyPolicy::new(certificate_verifier);
+        let event = create_public_key_event(&TEST_PUBLIC_KEY).encode_to_vec();
+        let invalid_signature: Variant =
+            create_public_key_endorsement(&TEST_WRONG_SIGNATURE).into();
+
+        let verdict = policy.report(TEST_TIME, &event, &invalid_signature).unwrap().verdict();
+
+        assert_matches!(
+            verdict.as_slice(),
+            [Verdict {
+                check: Check::Certificate,
+                outcome: Outcome::Passed,
+                details,
+            }] if matches!(
+                details.as_slice(),
+                [
+                    Verdict { check: Check::Validity, outcome: Outcome::Passed, .. },
+                    Verdict { check: Check::Signature, outcome: Outcome::Failed(_), .. },
+                ]
+            )
+        );
+        assert!(!all_passed(&verdict));
+    }
+
     #[test]
     fn event_log_verifier_success() {
         let clock = FixedClock::at_instant(TEST_TIME);
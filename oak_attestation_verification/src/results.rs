@@ -21,19 +21,27 @@ use alloc::{string::ToString, vec::Vec};
 use oak_proto_rust::oak::attestation::v1::{AttestationResults, EventAttestationResults};
 
 /// Key for the initial measurement of stage0.
-const INITIAL_MEASUREMENT_ID: &str = "initial-measurement";
+pub const INITIAL_MEASUREMENT_ID: &str = "initial-measurement";
 
 /// Denotes an artifact ID of a public key used to verify the Noise handshake
 /// transcript signature.
-const SESSION_BINDING_PUBLIC_KEY_ID: &str = "oak-session-binding-public-key:ecdsa-p256";
+pub const SESSION_BINDING_PUBLIC_KEY_ID: &str = "oak-session-binding-public-key:ecdsa-p256";
 
 /// Denotes an artifact ID of a key to encrypt a single message with hybrid
 /// encryption before sending it to the enclave.
-const HYBRID_ENCRYPTION_PUBLIC_KEY_ID: &str = "oak-hybrid-encryption-public-key:X25519";
+pub const HYBRID_ENCRYPTION_PUBLIC_KEY_ID: &str = "oak-hybrid-encryption-public-key:X25519";
 
 /// Denotes an artifact ID of a key used to verify artifacts generated and
 /// signed by the enclave.
-const SIGNING_PUBLIC_KEY_ID: &str = "oak-signing-public-key:ecdsa-p256";
+pub const SIGNING_PUBLIC_KEY_ID: &str = "oak-signing-public-key:ecdsa-p256";
+
+/// Denotes an artifact ID of the index of the expected container bundle digest
+/// that matched the measurement. Encoded as a little-endian u64.
+pub const CONTAINER_BUNDLE_DIGEST_INDEX_ID: &str = "container-bundle-digest-index";
+
+/// Denotes an artifact ID of the index of the expected container config digest
+/// that matched the measurement. Encoded as a little-endian u64.
+pub const CONTAINER_CONFIG_DIGEST_INDEX_ID: &str = "container-config-digest-index";
 
 pub fn get_initial_measurement(results: &EventAttestationResults) -> Option<&Vec<u8>> {
     results.artifacts.get(INITIAL_MEASUREMENT_ID)
@@ -67,6 +75,31 @@ pub fn set_signing_public_key(results: &mut EventAttestationResults, key: &[u8])
     results.artifacts.insert(SIGNING_PUBLIC_KEY_ID.to_string(), key.to_vec());
 }
 
+pub fn get_container_bundle_digest_index(results: &EventAttestationResults) -> Option<usize> {
+    get_index_artifact(results, CONTAINER_BUNDLE_DIGEST_INDEX_ID)
+}
+
+pub fn set_container_bundle_digest_index(results: &mut EventAttestationResults, index: usize) {
+    set_index_artifact(results, CONTAINER_BUNDLE_DIGEST_INDEX_ID, index);
+}
+
+pub fn get_container_config_digest_index(results: &EventAttestationResults) -> Option<usize> {
+    get_index_artifact(results, CONTAINER_CONFIG_DIGEST_INDEX_ID)
+}
+
+pub fn set_container_config_digest_index(results: &mut EventAttestationResults, index: usize) {
+    set_index_artifact(results, CONTAINER_CONFIG_DIGEST_INDEX_ID, index);
+}
+
+fn get_index_artifact(results: &EventAttestationResults, artifact_id: &str) -> Option<usize> {
+    let bytes: [u8; 8] = results.artifacts.get(artifact_id)?.as_slice().try_into().ok()?;
+    usize::try_from(u64::from_le_bytes(bytes)).ok()
+}
+
+fn set_index_artifact(results: &mut EventAttestationResults, artifact_id: &str, index: usize) {
+    results.artifacts.insert(artifact_id.to_string(), (index as u64).to_le_bytes().to_vec());
+}
+
 /// Returns a reference to the event artifact from `attestation_results` with a
 /// given `artifact_id`.
 pub fn get_event_artifact<'a>(
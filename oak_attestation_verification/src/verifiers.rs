@@ -205,6 +205,54 @@ impl EventLogVerifier {
     pub fn new(event_policies: Vec<Box<dyn EventPolicy>>, clock: Arc<dyn Clock>) -> Self {
         Self { event_policies, clock }
     }
+
+    /// Returns a builder for an [`EventLogVerifier`] that gets the verification
+    /// time from `clock`.
+    ///
+    /// The builder starts without any policies. They must be added in the
+    /// order of the events in the event log, as each policy verifies the
+    /// event at the same position, and verification fails unless there is
+    /// exactly one policy per event.
+    pub fn builder(clock: Arc<dyn Clock>) -> EventLogVerifierBuilder {
+        EventLogVerifierBuilder { event_policies: Vec::new(), clock }
+    }
+}
+
+/// Builder for an [`EventLogVerifier`] that selects the event policies based
+/// on reference values.
+///
+/// Policies are appended in the order in which they are added, which must
+/// match the order of the events in the DICE event log.
+pub struct EventLogVerifierBuilder {
+    event_policies: Vec<Box<dyn EventPolicy>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl EventLogVerifierBuilder {
+    /// Adds the policies for all the event log layers covered by the given
+    /// reference values.
+    ///
+    /// The root layer reference values (including stage0) are ignored, since
+    /// the root layer is not part of the event log. Confidential Space
+    /// policies are defined outside of this crate and have to be added with
+    /// [`EventLogVerifierBuilder::add_policy`].
+    pub fn add_reference_values(
+        mut self,
+        reference_values: &ReferenceValues,
+    ) -> anyhow::Result<Self> {
+        self.event_policies.extend(create_event_policies(reference_values)?);
+        Ok(self)
+    }
+
+    /// Adds a policy for the next event in the event log.
+    pub fn add_policy(mut self, policy: Box<dyn EventPolicy>) -> Self {
+        self.event_policies.push(policy);
+        self
+    }
+
+    pub fn build(self) -> EventLogVerifier {
+        EventLogVerifier::new(self.event_policies, self.clock)
+    }
 }
 
 // Verifies the EventLog in the evidence. Verification fails if any of
@@ -256,22 +304,11 @@ pub fn create_amd_verifier<T: Clock + 'static>(
             let platform_policy = AmdSevSnpPolicy::new(amd);
             let firmware_policy =
                 FirmwarePolicy::new(amd.stage0.as_ref().context("no stage0 reference value")?);
-            let kernel_policy = KernelPolicy::new(
-                rvs.kernel_layer.as_ref().context("no kernel layer reference values")?,
-            );
-            let system_policy = SystemPolicy::new(
-                rvs.system_layer.as_ref().context("no system layer reference values")?,
-            );
-            let container_policy = ContainerPolicy::new(
-                rvs.container_layer.as_ref().context("no container layer reference values")?,
-            );
-            let event_policies: Vec<Box<dyn Policy<[u8]>>> =
-                vec![Box::new(kernel_policy), Box::new(system_policy), Box::new(container_policy)];
 
             Ok(AmdSevSnpDiceAttestationVerifier::new(
                 platform_policy,
                 Box::new(firmware_policy),
-                event_policies,
+                create_event_policies(reference_values)?,
                 Arc::new(clock),
             ))
         }
@@ -281,20 +318,11 @@ pub fn create_amd_verifier<T: Clock + 'static>(
             let platform_policy = AmdSevSnpPolicy::new(amd);
             let firmware_policy =
                 FirmwarePolicy::new(amd.stage0.as_ref().context("no firmware reference value")?);
-            let kernel_policy = KernelPolicy::new(
-                rvs.kernel_layer.as_ref().context("no kernel layer reference values")?,
-            );
-            // TODO: b/382550581 - Application reference values currently skip verification.
-            let application_policy = ApplicationPolicy::new(
-                rvs.application_layer.as_ref().context("no application layer reference values")?,
-            );
-            let event_policies: Vec<Box<dyn Policy<[u8]>>> =
-                vec![Box::new(kernel_policy), Box::new(application_policy)];
 
             Ok(AmdSevSnpDiceAttestationVerifier::new(
                 platform_policy,
                 Box::new(firmware_policy),
-                event_policies,
+                create_event_policies(reference_values)?,
                 Arc::new(clock),
             ))
         }
@@ -312,6 +340,32 @@ pub fn create_insecure_verifier<T: Clock + 'static>(
         Some(reference_values::Type::OakContainers(rvs)) => {
             let root_rvs = rvs.root_layer.as_ref().context("no root layer reference values")?;
             anyhow::ensure!(root_rvs.insecure.is_some(), "insecure not allowed");
+
+            Ok(InsecureAttestationVerifier::new(
+                Arc::new(clock),
+                create_event_policies(reference_values)?,
+            ))
+        }
+        Some(reference_values::Type::OakRestrictedKernel(rvs)) => {
+            let root_rvs = rvs.root_layer.as_ref().context("no root layer reference values")?;
+            anyhow::ensure!(root_rvs.insecure.is_some(), "insecure not allowed");
+
+            Ok(InsecureAttestationVerifier::new(
+                Arc::new(clock),
+                create_event_policies(reference_values)?,
+            ))
+        }
+        _ => anyhow::bail!("malformed reference values"),
+    }
+}
+
+// Creates the policies for the event log layers described by the reference
+// values, in the same order as the events in the DICE event log.
+fn create_event_policies(
+    reference_values: &ReferenceValues,
+) -> anyhow::Result<Vec<Box<dyn EventPolicy>>> {
+    match reference_values.r#type.as_ref() {
+        Some(reference_values::Type::OakContainers(rvs)) => {
             let kernel_policy = KernelPolicy::new(
                 rvs.kernel_layer.as_ref().context("no kernel layer reference values")?,
             );
@@ -321,24 +375,20 @@ pub fn create_insecure_verifier<T: Clock + 'static>(
             let container_policy = ContainerPolicy::new(
                 rvs.container_layer.as_ref().context("no container layer reference values")?,
             );
-            let event_policies: Vec<Box<dyn Policy<[u8]>>> =
-                vec![Box::new(kernel_policy), Box::new(system_policy), Box::new(container_policy)];
-
-            Ok(InsecureAttestationVerifier::new(Arc::new(clock), event_policies))
+            Ok(vec![Box::new(kernel_policy), Box::new(system_policy), Box::new(container_policy)])
         }
         Some(reference_values::Type::OakRestrictedKernel(rvs)) => {
-            let root_rvs = rvs.root_layer.as_ref().context("no root layer reference values")?;
-            anyhow::ensure!(root_rvs.insecure.is_some(), "insecure not allowed");
             let kernel_policy = KernelPolicy::new(
                 rvs.kernel_layer.as_ref().context("no kernel layer reference values")?,
             );
+            // TODO: b/382550581 - Application reference values currently skip verification.
             let application_policy = ApplicationPolicy::new(
                 rvs.application_layer.as_ref().context("no application layer reference values")?,
             );
-            let event_policies: Vec<Box<dyn Policy<[u8]>>> =
-                vec![Box::new(kernel_policy), Box::new(application_policy)];
-
-            Ok(InsecureAttestationVerifier::new(Arc::new(clock), event_policies))
+            Ok(vec![Box::new(kernel_policy), Box::new(application_policy)])
+        }
+        Some(reference_values::Type::ConfidentialSpace(_)) => {
+            anyhow::bail!("Confidential Space policies have to be added explicitly")
         }
         _ => anyhow::bail!("malformed reference values"),
     }
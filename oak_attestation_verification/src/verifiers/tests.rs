@@ -19,11 +19,52 @@
 // endorsements are created and signed on the fly. For other tests (in
 // particular negative ones) see verifier_tests.rs.
 
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, sync::Arc};
 
-use oak_proto_rust::oak::attestation::v1::EventAttestationResults;
+use oak_attestation_verification_types::verifier::AttestationVerifier;
+use oak_proto_rust::oak::attestation::v1::{
+    reference_values, ConfidentialSpaceReferenceValues, EventAttestationResults, ReferenceValues,
+};
+use oak_time::clock::FixedClock;
+use test_util::AttestationData;
 
-use crate::verifiers::verify_event_artifacts_uniqueness;
+use crate::{
+    results::get_session_binding_public_key,
+    verifiers::{verify_event_artifacts_uniqueness, EventLogVerifier},
+};
+
+#[test]
+fn test_event_log_verifier_builder_oc_release_succeeds() {
+    let d = AttestationData::load_milan_oc_release();
+    let clock = FixedClock::at_instant(d.make_valid_time());
+
+    let verifier = EventLogVerifier::builder(Arc::new(clock))
+        .add_reference_values(&d.reference_values)
+        .expect("couldn't add reference values")
+        .build();
+    let result = verifier.verify(&d.evidence, &d.endorsements);
+
+    assert!(result.is_ok(), "Failed: {:?}", result.err().unwrap());
+    let results = result.unwrap();
+    // Kernel, system and container layers.
+    assert_eq!(results.event_attestation_results.len(), 3);
+    assert!(get_session_binding_public_key(&results).is_some());
+}
+
+#[test]
+fn test_event_log_verifier_builder_rejects_confidential_space_reference_values() {
+    let d = AttestationData::load_milan_oc_release();
+    let reference_values = ReferenceValues {
+        r#type: Some(reference_values::Type::ConfidentialSpace(
+            ConfidentialSpaceReferenceValues::default(),
+        )),
+    };
+
+    let result = EventLogVerifier::builder(Arc::new(FixedClock::at_instant(d.make_valid_time())))
+        .add_reference_values(&reference_values);
+
+    assert!(result.is_err());
+}
 
 #[test]
 fn test_verify_event_artifacts_uniqueness_succeeds() {
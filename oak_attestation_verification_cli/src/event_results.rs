@@ -0,0 +1,199 @@
+//
+// Copyright 2025 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::fmt::Write;
+
+use oak_attestation_verification::results::{
+    HYBRID_ENCRYPTION_PUBLIC_KEY_ID, INITIAL_MEASUREMENT_ID, SESSION_BINDING_PUBLIC_KEY_ID,
+    SIGNING_PUBLIC_KEY_ID,
+};
+use oak_proto_rust::oak::attestation::v1::{AttestationResults, EventAttestationResults};
+
+use crate::print::print_indented;
+
+/// Names of the layers for which an Oak Containers verifier reports results,
+/// in the order of [`AttestationResults::event_attestation_results`].
+pub const OAK_CONTAINERS_LAYERS: &[&str] =
+    &["Platform", "Firmware", "Kernel", "System", "Container"];
+
+/// The artifacts that a single layer of an event log verification extracted.
+pub struct LayerReport {
+    pub name: String,
+    pub artifacts: Vec<(String, Vec<u8>)>,
+}
+
+/// Per-layer view of the [`AttestationResults`] of an event log verification.
+pub struct EventLogReport {
+    pub layers: Vec<LayerReport>,
+}
+
+impl EventLogReport {
+    /// Builds a report from [results], naming each layer after the entry at the
+    /// same position in [layer_names]. Layers beyond the provided names are
+    /// named after their position.
+    pub fn new(results: &AttestationResults, layer_names: &[&str]) -> EventLogReport {
+        let layers = results
+            .event_attestation_results
+            .iter()
+            .enumerate()
+            .map(|(index, event_results)| {
+                let name = layer_names
+                    .get(index)
+                    .map(|name| name.to_string())
+                    .unwrap_or_else(|| format!("Event {}", index));
+                LayerReport::new(name, event_results)
+            })
+            .collect();
+        EventLogReport { layers }
+    }
+
+    pub fn print(&self, writer: &mut impl Write, indent: usize) -> std::fmt::Result {
+        print_indented!(writer, indent, "📚 Event log:")?;
+        if self.layers.is_empty() {
+            return print_indented!(writer, indent + 1, "🤷 no events verified");
+        }
+        self.layers.iter().try_for_each(|layer| layer.print(writer, indent + 1))
+    }
+}
+
+impl LayerReport {
+    pub fn new(name: String, results: &EventAttestationResults) -> LayerReport {
+        let artifacts =
+            results.artifacts.iter().map(|(id, value)| (id.clone(), value.clone())).collect();
+        LayerReport { name, artifacts }
+    }
+
+    pub fn print(&self, writer: &mut impl Write, indent: usize) -> std::fmt::Result {
+        print_indented!(writer, indent, "🧩 {}:", self.name)?;
+        if self.artifacts.is_empty() {
+            return print_indented!(writer, indent + 1, "🤷 no artifacts extracted");
+        }
+        for (id, value) in &self.artifacts {
+            print_indented!(writer, indent + 1, "{}:", artifact_name(id))?;
+            print_indented!(writer, indent + 2, "{}", hex::encode(value))?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns a human-readable name for the artifact ID, falling back to the ID
+/// itself for artifacts this tool doesn't know about.
+fn artifact_name(id: &str) -> String {
+    match id {
+        INITIAL_MEASUREMENT_ID => "📏 Initial measurement".to_string(),
+        SESSION_BINDING_PUBLIC_KEY_ID => "🔑 Session binding public key".to_string(),
+        HYBRID_ENCRYPTION_PUBLIC_KEY_ID => "🔑 Hybrid encryption public key".to_string(),
+        SIGNING_PUBLIC_KEY_ID => "🔑 Signing public key".to_string(),
+        _ => format!("🏷️ {}", id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use oak_attestation_verification::{
+        create_amd_verifier,
+        results::{
+            get_hybrid_encryption_public_key, get_initial_measurement,
+            get_session_binding_public_key, get_signing_public_key,
+        },
+    };
+    use oak_attestation_verification_types::verifier::AttestationVerifier;
+    use oak_time::clock::FixedClock;
+    use test_util::AttestationData;
+
+    use super::*;
+    use crate::print::assert_eq_trimmed_lines;
+
+    #[test]
+    fn test_print_event_log_report_milan_oc_release() {
+        let d = AttestationData::load_milan_oc_release();
+        let verifier =
+            create_amd_verifier(FixedClock::at_instant(d.make_valid_time()), &d.reference_values)
+                .expect("couldn't create verifier");
+        let results = verifier.verify(&d.evidence, &d.endorsements).expect("verification failed");
+
+        let mut buffer = String::new();
+        EventLogReport::new(&results, OAK_CONTAINERS_LAYERS).print(&mut buffer, 0).unwrap();
+
+        let initial_measurement =
+            hex::encode(get_initial_measurement(&results.event_attestation_results[0]).unwrap());
+        let session_binding_public_key =
+            hex::encode(get_session_binding_public_key(&results).unwrap());
+        let hybrid_encryption_public_key =
+            hex::encode(get_hybrid_encryption_public_key(&results).unwrap());
+        let signing_public_key = hex::encode(get_signing_public_key(&results).unwrap());
+        assert_eq_trimmed_lines(
+            &buffer,
+            &[
+                "📚 Event log:",
+                "🧩 Platform:",
+                "📏 Initial measurement:",
+                &initial_measurement,
+                "🧩 Firmware:",
+                "🤷 no artifacts extracted",
+                "🧩 Kernel:",
+                "🤷 no artifacts extracted",
+                "🧩 System:",
+                "🤷 no artifacts extracted",
+                "🧩 Container:",
+                "🔑 Hybrid encryption public key:",
+                &hybrid_encryption_public_key,
+                "🔑 Session binding public key:",
+                &session_binding_public_key,
+                "🔑 Signing public key:",
+                &signing_public_key,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_print_event_log_report_unnamed_layers_and_unknown_artifacts() {
+        let results = AttestationResults {
+            event_attestation_results: vec![
+                EventAttestationResults::default(),
+                EventAttestationResults {
+                    artifacts: [("custom-artifact".to_string(), vec![0xde, 0xad])].into(),
+                },
+            ],
+            ..Default::default()
+        };
+
+        let mut buffer = String::new();
+        EventLogReport::new(&results, &["Platform"]).print(&mut buffer, 0).unwrap();
+
+        assert_eq_trimmed_lines(
+            &buffer,
+            &[
+                "📚 Event log:",
+                "🧩 Platform:",
+                "🤷 no artifacts extracted",
+                "🧩 Event 1:",
+                "🏷️ custom-artifact:",
+                "dead",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_print_event_log_report_no_events() {
+        let mut buffer = String::new();
+        EventLogReport::new(&AttestationResults::default(), OAK_CONTAINERS_LAYERS)
+            .print(&mut buffer, 0)
+            .unwrap();
+
+        assert_eq_trimmed_lines(&buffer, &["📚 Event log:", "🤷 no events verified"]);
+    }
+}
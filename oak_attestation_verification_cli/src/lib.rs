@@ -0,0 +1,499 @@
+//
+// Copyright 2025 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Verification of the attestations collected by Oak session clients, and
+//! human-readable reports of the outcome, as printed by the
+//! `oak_attestation_verification_cli` tool.
+
+mod event_results;
+mod print;
+pub mod report;
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Context};
+use oak_proto_rust::{
+    attestation::{CERTIFICATE_BASED_ATTESTATION_ID, CONFIDENTIAL_SPACE_ATTESTATION_ID},
+    oak::{
+        attestation::v1::{
+            reference_values, CollectedAttestation, ReferenceValues, ReferenceValuesCollection,
+        },
+        session::v1::EndorsedEvidence,
+        Variant,
+    },
+};
+use oak_time::Instant;
+use prost::Message;
+
+use crate::{
+    print::print_indented,
+    report::{check_handshake_hash, VerificationReport},
+};
+
+/// Deserializes a [`CollectedAttestation`] (as captured by a client) and
+/// verifies all the evidence, endorsements and session bindings it contains
+/// against the provided reference values.
+///
+/// A human-readable report of every verification step is written to [writer].
+/// Returns whether all of the steps succeeded.
+pub fn verify_collected_attestation(
+    writer: &mut impl Write,
+    serialized_attestation: &[u8],
+    reference_values: &ReferenceValuesCollection,
+) -> anyhow::Result<bool> {
+    let attestation = CollectedAttestation::decode(serialized_attestation)
+        .context("couldn't decode collected attestation")?;
+    let indent = 0;
+
+    let attestation_timestamp = get_timestamp(&attestation);
+    print_timestamp_report(writer, indent, &attestation_timestamp)?;
+    let mut verified =
+        matches!(attestation_timestamp, Ok(timestamp) if timestamp != Instant::UNIX_EPOCH);
+
+    let handshake_hash = attestation.handshake_hash.clone();
+    print_handshake_hash_report(writer, indent, &handshake_hash)?;
+    verified &= check_handshake_hash(&handshake_hash).is_ok();
+
+    if attestation.endorsed_evidence.is_empty() {
+        print_indented!(writer, indent, "❌ No attestation evidence found")?;
+        verified = false;
+    }
+
+    for (attestation_type_id, report) in verification_reports(&attestation, reference_values) {
+        let session_binding = attestation.session_bindings.get(&attestation_type_id);
+        match report {
+            Ok(report) => {
+                report.print(writer, indent, &handshake_hash, session_binding)?;
+                verified &= report.into_checked(&handshake_hash, session_binding).is_ok();
+            }
+            Err(ref err) => {
+                print_indented!(writer, indent, "❌ Provided attestation is invalid: {}", err)?;
+                verified = false;
+            }
+        }
+    }
+    Ok(verified)
+}
+
+/// Re-evaluates the evidence and endorsements stored in [attestation] against
+/// [reference_values], independently of the session during which they were
+/// captured, e.g. to check old captures against updated reference values.
+///
+/// Time-dependent checks use the request time recorded in [attestation], so
+/// that they give the same results as when the attestation was captured.
+/// Returns a report, or the reason why none could be produced, for each
+/// attestation ID. The session bindings are not checked; see
+/// [`VerificationReport::into_checked`].
+pub fn verification_reports(
+    attestation: &CollectedAttestation,
+    reference_values: &ReferenceValuesCollection,
+) -> BTreeMap<String, anyhow::Result<VerificationReport>> {
+    let attestation_timestamp = get_timestamp(attestation).unwrap_or(Instant::UNIX_EPOCH);
+    attestation
+        .endorsed_evidence
+        .iter()
+        .map(|(attestation_type_id, endorsed_evidence)| {
+            let report = process_attestation(
+                attestation_type_id.clone(),
+                endorsed_evidence,
+                attestation_timestamp,
+                reference_values.reference_values.get(attestation_type_id),
+            );
+            (attestation_type_id.clone(), report)
+        })
+        .collect()
+}
+
+// TODO: b/419209669 - add tests for process_attestation (or perhaps more
+// correctly the VerificationReport constructors).
+fn process_attestation(
+    attestation_type_id: String,
+    endorsed_evidence: &EndorsedEvidence,
+    attestation_timestamp: Instant,
+    reference_values: Option<&ReferenceValues>,
+) -> anyhow::Result<VerificationReport> {
+    match attestation_type_id.as_str() {
+        CONFIDENTIAL_SPACE_ATTESTATION_ID => match reference_values {
+            Some(ReferenceValues {
+                r#type:
+                    Some(reference_values::Type::ConfidentialSpace(
+                        ref confidential_space_reference_values,
+                    )),
+            }) => VerificationReport::confidential_space(
+                confidential_space_reference_values,
+                attestation_timestamp,
+                &find_single_event(endorsed_evidence)?,
+                &find_single_endorsement(endorsed_evidence)?,
+            ),
+            _ => Err(anyhow!("Found no reference values")),
+        },
+        CERTIFICATE_BASED_ATTESTATION_ID => match reference_values {
+            Some(ReferenceValues {
+                r#type:
+                    Some(reference_values::Type::CertificateBased(
+                        ref certificate_based_reference_values,
+                    )),
+            }) => VerificationReport::certificate_based(
+                certificate_based_reference_values,
+                attestation_timestamp,
+                &find_single_event(endorsed_evidence)?,
+                &find_single_endorsement(endorsed_evidence)?,
+            ),
+            _ => Err(anyhow!("Found no reference values")),
+        },
+        // Other attestation IDs are only recognized by their reference values.
+        _ => match reference_values {
+            Some(
+                reference_values @ ReferenceValues {
+                    r#type: Some(reference_values::Type::OakContainers(_)),
+                },
+            ) => VerificationReport::oak_containers(
+                reference_values,
+                attestation_timestamp,
+                endorsed_evidence,
+            ),
+            _ => Err(anyhow!("Unrecognized attestation type ID: {}", attestation_type_id)),
+        },
+    }
+}
+
+fn get_timestamp(attestation: &CollectedAttestation) -> anyhow::Result<Instant> {
+    let request_time =
+        attestation.request_metadata.clone().unwrap_or_default().request_time.unwrap_or_default();
+    let system_time = SystemTime::try_from(request_time)?;
+    let duration_since_epoch = system_time.duration_since(UNIX_EPOCH)?;
+    Ok(Instant::from_unix_millis(duration_since_epoch.as_millis().try_into()?))
+}
+
+/// Prints out a report for the provided timestamp
+fn print_timestamp_report(
+    writer: &mut impl Write,
+    indent: usize,
+    timestamp: &anyhow::Result<Instant>,
+) -> std::fmt::Result {
+    print_indented!(writer, indent, "🕠 Recorded timestamp:")?;
+    match timestamp {
+        Err(err) => {
+            let indent = indent + 1;
+            print_indented!(writer, indent, "❌ is invalid: {:?}", err)?;
+        }
+        Ok(timestamp) => {
+            let indent = indent + 1;
+            if *timestamp != Instant::UNIX_EPOCH {
+                print_indented!(writer, indent, "✅ is valid: {}", *timestamp)?;
+            } else {
+                print_indented!(writer, indent, "❌ is unset")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints out the overall outcome of the verification.
+pub fn print_verdict(writer: &mut impl Write, indent: usize, verified: bool) -> std::fmt::Result {
+    print_indented!(writer, indent, "🏁 Verdict:")?;
+    let indent = indent + 1;
+    if verified {
+        print_indented!(writer, indent, "✅ attestation verified successfully")
+    } else {
+        print_indented!(writer, indent, "❌ attestation failed to verify")
+    }
+}
+
+/// Prints out whether the handshake hash is present and well-formed, which is
+/// a precondition for verifying any of the session bindings.
+fn print_handshake_hash_report(
+    writer: &mut impl Write,
+    indent: usize,
+    handshake_hash: &[u8],
+) -> std::fmt::Result {
+    print_indented!(writer, indent, "🤝 Session handshake:")?;
+    let indent = indent + 1;
+    match check_handshake_hash(handshake_hash) {
+        Ok(()) => print_indented!(writer, indent, "✅ is present with the expected length")?,
+        Err(err) => print_indented!(writer, indent, "❌ {}", err)?,
+    }
+    Ok(())
+}
+
+fn find_single_event(endorsed_evidence: &EndorsedEvidence) -> anyhow::Result<Vec<u8>> {
+    let evidence = &endorsed_evidence.evidence.clone().ok_or(anyhow!("missing evidence"))?;
+    let event_log = &evidence.event_log.clone().ok_or(anyhow!("missing event log"))?;
+    let encoded_events = &event_log.encoded_events;
+    if encoded_events.len() > 1 {
+        Err(anyhow!("too many ({}) events (expected: 1)", encoded_events.len()))?;
+    }
+    Ok(encoded_events.iter().next().ok_or(anyhow!("missing event"))?.clone())
+}
+
+fn find_single_endorsement(endorsed_evidence: &EndorsedEvidence) -> anyhow::Result<Variant> {
+    let endorsements =
+        &endorsed_evidence.endorsements.clone().ok_or(anyhow!("missing endorsements"))?;
+    let events = &endorsements.events;
+    if events.len() > 1 {
+        Err(anyhow!("too many ({}) endorsements (expected: 1)", events.len()))?;
+    }
+    Ok(events.iter().next().ok_or(anyhow!("missing endorsement"))?.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use oak_attestation_verification_types::verdict::all_passed;
+    use oak_file_utils::data_path;
+    use oak_proto_rust::oak::attestation::v1::{
+        collected_attestation::RequestMetadata, ConfidentialSpaceEndorsement,
+        ConfidentialSpaceReferenceValues, Endorsements, Event, EventLog, Evidence,
+        SessionBindingPublicKeyData,
+    };
+    use test_util::AttestationData;
+
+    use super::*;
+    use crate::print::assert_eq_trimmed_lines;
+
+    // Matches the "eat_nonce" claim of the Confidential Space test token.
+    const BINDING_KEY_BYTES: [u8; 32] = [
+        0xad, 0x57, 0x5f, 0x38, 0x17, 0x7e, 0x11, 0x4a, 0x48, 0x2d, 0x5a, 0x24, 0x71, 0x28, 0x73,
+        0x64, 0x27, 0x41, 0x53, 0x48, 0x51, 0x5b, 0x76, 0x78, 0x47, 0x11, 0x12, 0x43, 0x01, 0x61,
+        0x64, 0x66,
+    ];
+
+    fn read_gcp_testdata(file: &str) -> String {
+        fs::read_to_string(data_path(format!("oak_attestation_gcp/testdata/{file}"))).unwrap()
+    }
+
+    /// Returns a capture of the Confidential Space test token binding
+    /// [`BINDING_KEY_BYTES`], requested while the token was valid.
+    fn confidential_space_attestation() -> CollectedAttestation {
+        let event = Event {
+            tag: "session_binding_key".to_string(),
+            event: Some(prost_types::Any {
+                type_url: "type.googleapis.com/oak.attestation.v1.SessionBindingPublicKeyData"
+                    .to_string(),
+                value: SessionBindingPublicKeyData {
+                    session_binding_public_key: BINDING_KEY_BYTES.to_vec(),
+                }
+                .encode_to_vec(),
+            }),
+        };
+        let endorsement = ConfidentialSpaceEndorsement {
+            jwt_token: read_gcp_testdata("valid_token.jwt"),
+            workload_endorsement: None,
+        };
+        CollectedAttestation {
+            request_metadata: Some(RequestMetadata {
+                uri: "http://test".to_string(),
+                // 2025-07-01T18:00:00Z
+                request_time: Some(prost_types::Timestamp { seconds: 1751392800, nanos: 0 }),
+            }),
+            endorsed_evidence: [(
+                CONFIDENTIAL_SPACE_ATTESTATION_ID.to_string(),
+                EndorsedEvidence {
+                    evidence: Some(Evidence {
+                        event_log: Some(EventLog { encoded_events: vec![event.encode_to_vec()] }),
+                        ..Default::default()
+                    }),
+                    endorsements: Some(Endorsements {
+                        events: vec![endorsement.into()],
+                        ..Default::default()
+                    }),
+                },
+            )]
+            .into(),
+            ..Default::default()
+        }
+    }
+
+    fn confidential_space_reference_values(
+        root_certificate_file: &str,
+    ) -> ReferenceValuesCollection {
+        ReferenceValuesCollection {
+            reference_values: [(
+                CONFIDENTIAL_SPACE_ATTESTATION_ID.to_string(),
+                ReferenceValues {
+                    r#type: Some(reference_values::Type::ConfidentialSpace(
+                        ConfidentialSpaceReferenceValues {
+                            root_certificate_pem: read_gcp_testdata(root_certificate_file),
+                            container_image: None,
+                        },
+                    )),
+                },
+            )]
+            .into(),
+        }
+    }
+
+    #[test]
+    fn test_verification_reports_passing_reference_values() {
+        let reports = verification_reports(
+            &confidential_space_attestation(),
+            &confidential_space_reference_values("root_ca_cert.pem"),
+        );
+
+        assert_eq!(reports.keys().collect::<Vec<_>>(), [CONFIDENTIAL_SPACE_ATTESTATION_ID]);
+        let report = reports[CONFIDENTIAL_SPACE_ATTESTATION_ID].as_ref().unwrap();
+        assert!(all_passed(&report.verdict()));
+    }
+
+    #[test]
+    fn test_verification_reports_failing_reference_values() {
+        // The token doesn't chain to the rotated root certificate.
+        let reports = verification_reports(
+            &confidential_space_attestation(),
+            &confidential_space_reference_values("rotated_root_ca_cert.pem"),
+        );
+
+        let report = reports[CONFIDENTIAL_SPACE_ATTESTATION_ID].as_ref().unwrap();
+        assert!(!all_passed(&report.verdict()));
+    }
+
+    #[test]
+    fn test_verification_reports_missing_reference_values() {
+        let reports = verification_reports(
+            &confidential_space_attestation(),
+            &ReferenceValuesCollection::default(),
+        );
+
+        assert!(reports[CONFIDENTIAL_SPACE_ATTESTATION_ID].is_err());
+    }
+
+    #[test]
+    fn test_verification_reports_oak_containers() {
+        let d = AttestationData::load_milan_oc_release();
+        let attestation_type_id = "oak-containers";
+        let attestation = CollectedAttestation {
+            request_metadata: Some(RequestMetadata {
+                uri: "http://test".to_string(),
+                request_time: Some(d.make_valid_time().into_timestamp()),
+            }),
+            endorsed_evidence: [(
+                attestation_type_id.to_string(),
+                EndorsedEvidence {
+                    evidence: Some(d.evidence.clone()),
+                    endorsements: Some(d.endorsements.clone()),
+                },
+            )]
+            .into(),
+            ..Default::default()
+        };
+        let reference_values = ReferenceValuesCollection {
+            reference_values: [(attestation_type_id.to_string(), d.reference_values.clone())]
+                .into(),
+        };
+
+        let reports = verification_reports(&attestation, &reference_values);
+
+        let report = reports[attestation_type_id].as_ref().unwrap();
+        assert!(matches!(report, VerificationReport::OakContainers(_)));
+        let mut buffer = String::new();
+        report.print(&mut buffer, 0, &[], None).unwrap();
+        assert!(buffer.starts_with("📚 Event log:"));
+        assert!(buffer.contains("❌ No session binding found"));
+    }
+
+    #[test]
+    fn test_verify_collected_attestation_invalid_encoding() {
+        let mut writer = String::new();
+        assert!(verify_collected_attestation(
+            &mut writer,
+            b"not a proto",
+            &ReferenceValuesCollection::default()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_verify_collected_attestation_empty() {
+        let mut writer = String::new();
+        let serialized_attestation = CollectedAttestation::default().encode_to_vec();
+        let verified = verify_collected_attestation(
+            &mut writer,
+            &serialized_attestation,
+            &ReferenceValuesCollection::default(),
+        )
+        .unwrap();
+        assert!(!verified);
+        assert_eq_trimmed_lines(
+            &writer,
+            &[
+                "🕠 Recorded timestamp:",
+                "❌ is unset",
+                "🤝 Session handshake:",
+                "❌ is missing",
+                "❌ No attestation evidence found",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_verify_collected_attestation_unknown_attestation_type() {
+        let mut writer = String::new();
+        let serialized_attestation = CollectedAttestation {
+            endorsed_evidence: [("unknown".to_string(), EndorsedEvidence::default())].into(),
+            handshake_hash: [0xAB; 32].to_vec(),
+            ..Default::default()
+        }
+        .encode_to_vec();
+        let verified = verify_collected_attestation(
+            &mut writer,
+            &serialized_attestation,
+            &ReferenceValuesCollection::default(),
+        )
+        .unwrap();
+        assert!(!verified);
+        assert_eq_trimmed_lines(
+            &writer,
+            &[
+                "🕠 Recorded timestamp:",
+                "❌ is unset",
+                "🤝 Session handshake:",
+                "✅ is present with the expected length",
+                "❌ Provided attestation is invalid: Unrecognized attestation type ID: unknown",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_print_handshake_hash_report_malformed() {
+        let mut writer = String::new();
+        print_handshake_hash_report(&mut writer, 0, b"abc123def").unwrap();
+        assert_eq_trimmed_lines(
+            &writer,
+            &["🤝 Session handshake:", "❌ has length 9 (expected: 32)"],
+        );
+    }
+
+    #[test]
+    fn test_print_verdict() {
+        let mut writer = String::new();
+        print_verdict(&mut writer, 0, true).unwrap();
+        print_verdict(&mut writer, 0, false).unwrap();
+        assert_eq_trimmed_lines(
+            &writer,
+            &[
+                "🏁 Verdict:",
+                "✅ attestation verified successfully",
+                "🏁 Verdict:",
+                "❌ attestation failed to verify",
+            ],
+        );
+    }
+}
@@ -0,0 +1,315 @@
+//
+// Copyright 2025 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Loading and validation of the reference values passed to the CLI.
+
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, Context};
+use oak_proto_rust::{
+    attestation::{CERTIFICATE_BASED_ATTESTATION_ID, CONFIDENTIAL_SPACE_ATTESTATION_ID},
+    oak::attestation::v1::{
+        confidential_space_reference_values::ContainerImage, reference_values,
+        CertificateBasedReferenceValues, ConfidentialSpaceReferenceValues, ReferenceValues,
+        ReferenceValuesCollection,
+    },
+};
+use prost::Message;
+use serde::Deserialize;
+use x509_cert::{der::DecodePem, Certificate};
+
+/// JSON representation of the reference values accepted by the CLI.
+///
+/// Only the attestation types supported by the CLI are represented; each one
+/// is keyed by the camel-cased name of the corresponding proto message field.
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct JsonReferenceValues {
+    confidential_space: Option<ConfidentialSpaceReferenceValues>,
+    certificate_based: Option<CertificateBasedReferenceValues>,
+}
+
+impl From<JsonReferenceValues> for ReferenceValuesCollection {
+    fn from(value: JsonReferenceValues) -> Self {
+        let mut collection = ReferenceValuesCollection::default();
+        if let Some(confidential_space) = value.confidential_space {
+            collection.reference_values.insert(
+                CONFIDENTIAL_SPACE_ATTESTATION_ID.to_string(),
+                ReferenceValues {
+                    r#type: Some(reference_values::Type::ConfidentialSpace(confidential_space)),
+                },
+            );
+        }
+        if let Some(certificate_based) = value.certificate_based {
+            collection.reference_values.insert(
+                CERTIFICATE_BASED_ATTESTATION_ID.to_string(),
+                ReferenceValues {
+                    r#type: Some(reference_values::Type::CertificateBased(certificate_based)),
+                },
+            );
+        }
+        collection
+    }
+}
+
+/// Loads and validates the reference values stored in the [path] file.
+///
+/// Files with a `.json` extension are parsed as JSON (see the README for the
+/// expected layout), anything else is decoded as a binary
+/// `oak.attestation.v1.ReferenceValuesCollection` proto.
+pub fn load_reference_values(path: &Path) -> anyhow::Result<ReferenceValuesCollection> {
+    let contents = fs::read(path)
+        .with_context(|| format!("couldn't read reference values from {}", path.display()))?;
+    let reference_values = if path.extension().is_some_and(|extension| extension == "json") {
+        parse_json_reference_values(&contents)
+    } else {
+        ReferenceValuesCollection::decode(contents.as_slice())
+            .context("couldn't decode binary ReferenceValuesCollection proto")
+    }
+    .with_context(|| format!("invalid reference values in {}", path.display()))?;
+    validate_reference_values(&reference_values)
+        .with_context(|| format!("invalid reference values in {}", path.display()))?;
+    Ok(reference_values)
+}
+
+fn parse_json_reference_values(contents: &[u8]) -> anyhow::Result<ReferenceValuesCollection> {
+    let reference_values: JsonReferenceValues =
+        serde_json::from_slice(contents).context("couldn't parse JSON reference values")?;
+    Ok(reference_values.into())
+}
+
+/// Checks that the fields required by the supported verification flows are
+/// present, so that mistakes are reported before any evidence is processed.
+fn validate_reference_values(reference_values: &ReferenceValuesCollection) -> anyhow::Result<()> {
+    if reference_values.reference_values.is_empty() {
+        return Err(anyhow!(
+            "no reference values provided: expected entries for `{}` and/or `{}`",
+            CONFIDENTIAL_SPACE_ATTESTATION_ID,
+            CERTIFICATE_BASED_ATTESTATION_ID
+        ));
+    }
+    for (attestation_type_id, reference_values) in reference_values.reference_values.iter() {
+        match (attestation_type_id.as_str(), &reference_values.r#type) {
+            (
+                CONFIDENTIAL_SPACE_ATTESTATION_ID,
+                Some(reference_values::Type::ConfidentialSpace(reference_values)),
+            ) => validate_confidential_space(reference_values),
+            (
+                CERTIFICATE_BASED_ATTESTATION_ID,
+                Some(reference_values::Type::CertificateBased(reference_values)),
+            ) => validate_certificate_based(reference_values),
+            (CONFIDENTIAL_SPACE_ATTESTATION_ID | CERTIFICATE_BASED_ATTESTATION_ID, _) => {
+                Err(anyhow!("reference values don't match the attestation type"))
+            }
+            // Reference values for other attestation types are never looked up.
+            _ => Ok(()),
+        }
+        .with_context(|| format!("reference values for `{}`", attestation_type_id))?;
+    }
+    Ok(())
+}
+
+fn validate_confidential_space(
+    reference_values: &ConfidentialSpaceReferenceValues,
+) -> anyhow::Result<()> {
+    if reference_values.root_certificate_pem.is_empty() {
+        return Err(anyhow!(
+            "missing `rootCertificatePem`: set it to the PEM-encoded Confidential Space root \
+             certificate"
+        ));
+    }
+    Certificate::from_pem(&reference_values.root_certificate_pem).map_err(|err| {
+        anyhow!("`rootCertificatePem` is not a PEM-encoded X.509 certificate: {}", err)
+    })?;
+    if let Some(ContainerImage::CosignReferenceValues(cosign_reference_values)) =
+        &reference_values.container_image
+    {
+        if cosign_reference_values.developer_public_key.is_none() {
+            return Err(anyhow!(
+                "missing `cosignReferenceValues.developerPublicKey`: set it to the key used to \
+                 sign the workload endorsement, or remove `cosignReferenceValues` to skip \
+                 workload endorsement verification"
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn validate_certificate_based(
+    reference_values: &CertificateBasedReferenceValues,
+) -> anyhow::Result<()> {
+    if reference_values.ca.as_ref().is_none_or(|ca| ca.tink_proto_keyset.is_empty()) {
+        return Err(anyhow!(
+            "missing `ca.tinkProtoKeyset`: set it to the base64-encoded Tink keyset of the \
+             certificate authority"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use oak_attestation_gcp::CONFIDENTIAL_SPACE_ROOT_CERT_PEM;
+    use oak_proto_rust::oak::attestation::v1::{
+        CertificateAuthorityReferenceValue, CosignReferenceValues,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_parse_json_reference_values() {
+        let json = r#"{
+            "confidentialSpace": {
+                "rootCertificatePem": "root",
+                "containerImageReference": "example.com/image"
+            },
+            "certificateBased": {
+                "ca": { "tinkProtoKeyset": "AQID" }
+            }
+        }"#;
+
+        let reference_values = parse_json_reference_values(json.as_bytes()).unwrap();
+
+        assert_eq!(
+            reference_values.reference_values.get(CONFIDENTIAL_SPACE_ATTESTATION_ID),
+            Some(&ReferenceValues {
+                r#type: Some(reference_values::Type::ConfidentialSpace(
+                    ConfidentialSpaceReferenceValues {
+                        root_certificate_pem: "root".to_string(),
+                        container_image: Some(ContainerImage::ContainerImageReference(
+                            "example.com/image".to_string()
+                        )),
+                    }
+                )),
+            })
+        );
+        assert_eq!(
+            reference_values.reference_values.get(CERTIFICATE_BASED_ATTESTATION_ID),
+            Some(&ReferenceValues {
+                r#type: Some(reference_values::Type::CertificateBased(
+                    CertificateBasedReferenceValues {
+                        ca: Some(CertificateAuthorityReferenceValue {
+                            tink_proto_keyset: vec![1, 2, 3]
+                        }),
+                    }
+                )),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_json_reference_values_unknown_field() {
+        let json = r#"{ "confidentialSpaces": {} }"#;
+
+        assert!(parse_json_reference_values(json.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_validate_empty_reference_values() {
+        let result = validate_reference_values(&ReferenceValuesCollection::default());
+
+        assert!(result.unwrap_err().to_string().contains("no reference values provided"));
+    }
+
+    #[test]
+    fn test_validate_confidential_space_missing_root_certificate() {
+        let reference_values = JsonReferenceValues {
+            confidential_space: Some(ConfidentialSpaceReferenceValues::default()),
+            ..Default::default()
+        };
+
+        let result = validate_reference_values(&reference_values.into());
+
+        assert!(format!("{:#}", result.unwrap_err()).contains("missing `rootCertificatePem`"));
+    }
+
+    #[test]
+    fn test_validate_confidential_space_invalid_root_certificate() {
+        let reference_values = JsonReferenceValues {
+            confidential_space: Some(ConfidentialSpaceReferenceValues {
+                root_certificate_pem: "not a certificate".to_string(),
+                container_image: None,
+            }),
+            ..Default::default()
+        };
+
+        let result = validate_reference_values(&reference_values.into());
+
+        assert!(format!("{:#}", result.unwrap_err())
+            .contains("`rootCertificatePem` is not a PEM-encoded X.509 certificate"));
+    }
+
+    #[test]
+    fn test_validate_confidential_space_missing_developer_public_key() {
+        let reference_values = JsonReferenceValues {
+            confidential_space: Some(ConfidentialSpaceReferenceValues {
+                root_certificate_pem: CONFIDENTIAL_SPACE_ROOT_CERT_PEM.to_string(),
+                container_image: Some(ContainerImage::CosignReferenceValues(
+                    CosignReferenceValues::default(),
+                )),
+            }),
+            ..Default::default()
+        };
+
+        let result = validate_reference_values(&reference_values.into());
+
+        assert!(format!("{:#}", result.unwrap_err())
+            .contains("missing `cosignReferenceValues.developerPublicKey`"));
+    }
+
+    #[test]
+    fn test_validate_confidential_space_success() {
+        let reference_values = JsonReferenceValues {
+            confidential_space: Some(ConfidentialSpaceReferenceValues {
+                root_certificate_pem: CONFIDENTIAL_SPACE_ROOT_CERT_PEM.to_string(),
+                container_image: None,
+            }),
+            ..Default::default()
+        };
+
+        assert!(validate_reference_values(&reference_values.into()).is_ok());
+    }
+
+    #[test]
+    fn test_validate_certificate_based_missing_ca() {
+        let reference_values = JsonReferenceValues {
+            certificate_based: Some(CertificateBasedReferenceValues::default()),
+            ..Default::default()
+        };
+
+        let result = validate_reference_values(&reference_values.into());
+
+        assert!(format!("{:#}", result.unwrap_err()).contains("missing `ca.tinkProtoKeyset`"));
+    }
+
+    #[test]
+    fn test_validate_mismatched_reference_values() {
+        let mut reference_values = ReferenceValuesCollection::default();
+        reference_values.reference_values.insert(
+            CONFIDENTIAL_SPACE_ATTESTATION_ID.to_string(),
+            ReferenceValues {
+                r#type: Some(reference_values::Type::CertificateBased(
+                    CertificateBasedReferenceValues::default(),
+                )),
+            },
+        );
+
+        let result = validate_reference_values(&reference_values);
+
+        assert!(format!("{:#}", result.unwrap_err())
+            .contains("reference values don't match the attestation type"));
+    }
+}
@@ -24,3 +24,12 @@ macro_rules! print_indented {
 }
 
 pub(crate) use print_indented;
+
+/// Asserts that the (trimmed) lines in [actual] are equal to those in
+/// [expected].
+#[cfg(test)]
+pub(crate) fn assert_eq_trimmed_lines(actual: &str, expected: &[&str]) {
+    let lines: Vec<&str> =
+        actual.split("\n").map(|line| line.trim()).filter(|line| !line.is_empty()).collect();
+    assert_eq!(lines.as_slice(), expected);
+}
@@ -14,36 +14,77 @@
 // limitations under the License.
 //
 
-use std::fmt::Write;
+use std::fmt::{Display, Write};
 
-use anyhow::anyhow;
+use anyhow::{anyhow, Context};
 use oak_attestation_gcp::{
-    cosign::{CosignVerificationReport, StatementReport},
-    jwt::verification::{AttestationTokenVerificationReport, CertificateReport, IssuerReport},
     policy::ConfidentialSpaceVerificationReport,
     policy_generator::confidential_space_policy_from_reference_values,
 };
 use oak_attestation_verification::{
-    SessionBindingPublicKeyPolicy, SessionBindingPublicKeyVerificationReport,
+    create_amd_verifier, results::get_session_binding_public_key, SessionBindingPublicKeyPolicy,
+    SessionBindingPublicKeyVerificationReport,
 };
-use oak_crypto::certificate::certificate_verifier::{
-    CertificateVerificationReport, CertificateVerifier,
+use oak_attestation_verification_types::{
+    verdict::{Check, Outcome, Verdict},
+    verifier::AttestationVerifier,
+};
+use oak_crypto::{
+    certificate::certificate_verifier::CertificateVerifier, noise_handshake::SHA256_OUTPUT_LEN,
 };
 use oak_crypto_tink::signature_verifier::SignatureVerifier;
 use oak_proto_rust::oak::{
-    attestation::v1::{CertificateBasedReferenceValues, ConfidentialSpaceReferenceValues},
-    session::v1::SessionBinding,
+    attestation::v1::{
+        AttestationResults, CertificateBasedReferenceValues, ConfidentialSpaceReferenceValues,
+        ReferenceValues,
+    },
+    session::v1::{EndorsedEvidence, SessionBinding},
     Variant,
 };
 use oak_session::session_binding::{SessionBindingVerifier, SignatureBindingVerifierBuilder};
-use oak_time::Instant;
+use oak_time::{clock::FixedClock, Instant};
 use p256::ecdsa::VerifyingKey;
 
-use crate::print::print_indented;
+use crate::{
+    event_results::{EventLogReport, OAK_CONTAINERS_LAYERS},
+    print::print_indented,
+};
+
+/// Why a captured handshake hash cannot be the hash of a Noise handshake.
+#[derive(Debug, PartialEq)]
+pub enum HandshakeHashError {
+    Missing,
+    WrongLength(usize),
+}
+
+impl Display for HandshakeHashError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HandshakeHashError::Missing => write!(f, "is missing"),
+            HandshakeHashError::WrongLength(len) => {
+                write!(f, "has length {} (expected: {})", len, SHA256_OUTPUT_LEN)
+            }
+        }
+    }
+}
+
+/// Checks that the handshake hash is present and has the length of a SHA-256
+/// digest, so that a missing or truncated hash is reported as such instead of
+/// as a session binding that fails to verify.
+pub fn check_handshake_hash(handshake_hash: &[u8]) -> Result<(), HandshakeHashError> {
+    match handshake_hash.len() {
+        0 => Err(HandshakeHashError::Missing),
+        SHA256_OUTPUT_LEN => Ok(()),
+        len => Err(HandshakeHashError::WrongLength(len)),
+    }
+}
 
 pub enum VerificationReport {
     CertificateBased(SessionBindingPublicKeyVerificationReport),
     ConfidentialSpace(ConfidentialSpaceVerificationReport),
+    /// The results of successfully verifying the event log of an Oak
+    /// Containers attestation, with the artifacts extracted from each layer.
+    OakContainers(AttestationResults),
 }
 
 impl VerificationReport {
@@ -77,6 +118,25 @@ impl VerificationReport {
         Ok(VerificationReport::ConfidentialSpace(report))
     }
 
+    /// Verifies the full DICE evidence of an Oak Containers attestation.
+    ///
+    /// Unlike the other attestation types, the verification doesn't report
+    /// the outcome of the individual checks, so any failure is returned as an
+    /// error.
+    pub fn oak_containers(
+        reference_values: &ReferenceValues,
+        attestation_timestamp: Instant,
+        endorsed_evidence: &EndorsedEvidence,
+    ) -> anyhow::Result<VerificationReport> {
+        let verifier =
+            create_amd_verifier(FixedClock::at_instant(attestation_timestamp), reference_values)?;
+        let results = verifier.verify(
+            endorsed_evidence.evidence.as_ref().context("missing evidence")?,
+            endorsed_evidence.endorsements.as_ref().context("missing endorsements")?,
+        )?;
+        Ok(VerificationReport::OakContainers(results))
+    }
+
     pub fn print(
         &self,
         writer: &mut impl Write,
@@ -84,13 +144,11 @@ impl VerificationReport {
         handshake_hash: &[u8],
         session_binding: Option<&SessionBinding>,
     ) -> std::fmt::Result {
-        match self {
-            VerificationReport::ConfidentialSpace(report) => {
-                print_confidential_space_attestation_report(writer, indent, report)?;
-            }
-            VerificationReport::CertificateBased(report) => {
-                print_certificate_based_attestation_report(writer, indent, report)?;
-            }
+        if let VerificationReport::OakContainers(results) = self {
+            EventLogReport::new(results, OAK_CONTAINERS_LAYERS).print(writer, indent)?;
+        }
+        for verdict in self.verdict() {
+            print_step(writer, indent, &verdict)?;
         }
 
         let indent = indent + 1;
@@ -99,6 +157,14 @@ impl VerificationReport {
             Some(session_binding) => {
                 print_indented!(writer, indent, "🔐 Session binding:")?;
                 let indent = indent + 1;
+                if let Err(err) = check_handshake_hash(handshake_hash) {
+                    return print_indented!(
+                        writer,
+                        indent,
+                        "❌ not verified: handshake hash {}",
+                        err
+                    );
+                }
                 match verify_session_binding(
                     &self.session_binding_public_key(),
                     handshake_hash,
@@ -112,168 +178,189 @@ impl VerificationReport {
         Ok(())
     }
 
-    fn session_binding_public_key(&self) -> Vec<u8> {
-        match self {
+    /// Consumes the report, returning an error if any of the verification
+    /// steps failed or if the session binding does not bind the attested
+    /// public key to the handshake.
+    pub fn into_checked(
+        self,
+        handshake_hash: &[u8],
+        session_binding: Option<&SessionBinding>,
+    ) -> anyhow::Result<()> {
+        let session_binding_public_key = match self {
             VerificationReport::ConfidentialSpace(report) => {
-                report.session_binding_public_key.clone()
+                report.into_session_binding_public_key()?
             }
             VerificationReport::CertificateBased(report) => {
-                report.session_binding_public_key.clone()
+                report.into_session_binding_public_key()?
             }
+            VerificationReport::OakContainers(results) => get_session_binding_public_key(&results)
+                .context("no session binding public key in the attestation results")?
+                .clone(),
+        };
+        let session_binding = session_binding.ok_or(anyhow!("no session binding found"))?;
+        check_handshake_hash(handshake_hash).map_err(|err| anyhow!("handshake hash {}", err))?;
+        verify_session_binding(
+            &session_binding_public_key,
+            handshake_hash,
+            &session_binding.binding,
+        )
+    }
+
+    /// Returns the outcome of each verification step, excluding the session
+    /// binding. Oak Containers reports have none, as they are only created
+    /// once all the steps succeeded.
+    pub fn verdict(&self) -> Vec<Verdict> {
+        match self {
+            VerificationReport::ConfidentialSpace(report) => report.verdict(),
+            VerificationReport::CertificateBased(report) => report.verdict(),
+            VerificationReport::OakContainers(_) => Vec::new(),
         }
     }
-}
 
-fn print_certificate_based_attestation_report(
-    writer: &mut impl Write,
-    indent: usize,
-    report: &SessionBindingPublicKeyVerificationReport,
-) -> std::fmt::Result {
-    match &report.endorsement {
-        Err(err) => print_indented!(writer, indent, "❌ is invalid: {}", err),
-        Ok(certificate_verification_report) => {
-            print_certificate_verification_report(writer, indent, certificate_verification_report)
+    /// Returns the report of the attestation type, through which the attested
+    /// session binding public key can be retrieved.
+    fn as_session_binding_key_report(&self) -> &dyn HasSessionBindingKey {
+        match self {
+            VerificationReport::ConfidentialSpace(report) => report,
+            VerificationReport::CertificateBased(report) => report,
+            VerificationReport::OakContainers(results) => results,
         }
     }
 }
 
-fn print_certificate_verification_report(
-    writer: &mut impl Write,
-    indent: usize,
-    report: &CertificateVerificationReport,
-) -> std::fmt::Result {
-    print_indented!(writer, indent, "📜 Certificate:")?;
-    let indent = indent + 1;
-    let CertificateVerificationReport { validity, verification, freshness: freshness_option } =
-        report;
-    match validity {
-        Err(err) => print_indented!(writer, indent, "❌ is invalid: {}", err)?,
-        Ok(()) => print_indented!(writer, indent, "✅ is valid")?,
-    }
-    match verification {
-        Err(err) => print_indented!(writer, indent, "❌ failed to verify: {}", err)?,
-        Ok(()) => print_indented!(writer, indent, "✅ verified successfully")?,
-    }
-    if let Some(freshness) = freshness_option {
-        match freshness {
-            Err(err) => {
-                print_indented!(writer, indent, "❌ proof of freshness failed to verify: {}", err)?
-            }
-            Ok(()) => print_indented!(writer, indent, "✅ is fresh")?,
-        }
+/// Implemented by the report of each attestation type that attests a session
+/// binding public key.
+pub trait HasSessionBindingKey {
+    /// Returns the attested public key, regardless of whether its verification
+    /// succeeded.
+    fn session_binding_public_key(&self) -> Vec<u8>;
+}
+
+impl HasSessionBindingKey for VerificationReport {
+    fn session_binding_public_key(&self) -> Vec<u8> {
+        self.as_session_binding_key_report().session_binding_public_key()
     }
-    Ok(())
 }
 
-fn print_confidential_space_attestation_report(
-    writer: &mut impl Write,
-    indent: usize,
-    report: &ConfidentialSpaceVerificationReport,
-) -> std::fmt::Result {
-    print_indented!(writer, indent, "🔑 Public key:")?;
-    {
-        let indent = indent + 1;
-        match &report.public_key_verification {
-            Err(err) => print_indented!(writer, indent, "❌ failed to verify: {}", err)?,
-            Ok(()) => print_indented!(writer, indent, "✅ verified successfully")?,
-        }
+impl HasSessionBindingKey for ConfidentialSpaceVerificationReport {
+    fn session_binding_public_key(&self) -> Vec<u8> {
+        self.session_binding_public_key.clone()
     }
-    print_token_report(writer, indent, &report.token_report)?;
-    print_indented!(writer, indent, "📦 Workload endorsement:")?;
-    {
-        let indent = indent + 1;
-        match &report.workload_endorsement_verification {
-            None => print_indented!(writer, indent, "🤷 not present")?,
-            Some(Err(err)) => print_indented!(writer, indent, "❌ failed to verify: {}", err)?,
-            Some(Ok(CosignVerificationReport { statement_verification })) => {
-                print_indented!(writer, indent, " Statement")?;
-                let indent = indent + 1;
-                match statement_verification {
-                    Err(err) => print_indented!(writer, indent, "❌ failed to verify: {}", err)?,
-                    Ok(StatementReport { statement_validation, rekor_verification }) => {
-                        match statement_validation {
-                            Err(err) => print_indented!(writer, indent, "❌ is invalid: {}", err)?,
-                            Ok(()) => print_indented!(writer, indent, "✅ is valid")?,
-                        }
-                        match rekor_verification {
-                            None => print_indented!(writer, indent, "🤷 not verified")?,
-                            Some(Err(err)) => {
-                                print_indented!(writer, indent, "❌ failed to verify: {}", err)?
-                            }
-                            Some(Ok(())) => {
-                                print_indented!(writer, indent, "✅ verified successfully")?
-                            }
-                        }
-                    }
-                }
-            }
-        }
+}
+
+impl HasSessionBindingKey for SessionBindingPublicKeyVerificationReport {
+    fn session_binding_public_key(&self) -> Vec<u8> {
+        self.session_binding_public_key.clone()
     }
-    Ok(())
 }
 
-fn print_token_report(
-    writer: &mut impl Write,
-    indent: usize,
-    report: &AttestationTokenVerificationReport,
-) -> std::fmt::Result {
-    print_indented!(writer, indent, "🪙 Token verification:")?;
-    let indent = indent + 1;
-    let AttestationTokenVerificationReport {
-        production_image,
-        validity,
-        verification,
-        issuer_report,
-    } = report;
-    match production_image {
-        Err(err) => print_indented!(writer, indent, "❌ obtained from a debug image: {}", err)?,
-        Ok(()) => print_indented!(writer, indent, "✅ obtained from a production image")?,
-    }
-    match validity {
-        Err(err) => print_indented!(writer, indent, "❌ is invalid: {}", err)?,
-        Ok(()) => print_indented!(writer, indent, "✅ is valid")?,
-    }
-    match verification {
-        Err(err) => print_indented!(writer, indent, "❌ failed to verify: {}", err)?,
-        Ok(_) => print_indented!(writer, indent, "✅ verified successfully")?,
-    }
-    print_indented!(writer, indent, "📜 Certificate chain:")?;
-    print_certificate_chain(writer, indent + 1, issuer_report)
+impl HasSessionBindingKey for AttestationResults {
+    fn session_binding_public_key(&self) -> Vec<u8> {
+        get_session_binding_public_key(self).cloned().unwrap_or_default()
+    }
 }
 
-fn print_certificate_chain(
-    writer: &mut impl Write,
-    indent: usize,
-    report: &Result<
-        CertificateReport,
-        oak_attestation_gcp::jwt::verification::AttestationVerificationError,
-    >,
-) -> std::fmt::Result {
-    match report {
-        Err(err) => print_indented!(writer, indent, "❌ invalid: {}", err),
-        Ok(report) => {
+/// Renders the outcome of a verification step, followed by its details.
+fn print_step(writer: &mut impl Write, indent: usize, verdict: &Verdict) -> std::fmt::Result {
+    let Verdict { check, outcome, details } = verdict;
+    match (check, outcome) {
+        (Check::Certificate, Outcome::Failed(err)) => {
+            print_indented!(writer, indent, "❌ is invalid: {}", err)
+        }
+        (Check::ChainCertificate, Outcome::Failed(err)) => {
+            print_indented!(writer, indent, "❌ invalid: {}", err)
+        }
+        (Check::Certificate | Check::ChainCertificate, _) => {
             print_indented!(writer, indent, "📜 Certificate:")?;
-            {
-                let indent = indent + 1;
-                match &report.validity {
-                    Err(err) => print_indented!(writer, indent, "❌ is invalid: {}", err)?,
-                    Ok(()) => print_indented!(writer, indent, "✅ is valid")?,
-                }
-                match &report.verification {
-                    Err(err) => print_indented!(writer, indent, "❌ failed to verify: {}", err)?,
-                    Ok(()) => print_indented!(writer, indent, "✅ verified successfully")?,
-                }
+            let indent = indent + 1;
+            print_details(writer, indent, details)?;
+            if *check == Check::ChainCertificate {
                 print_indented!(writer, indent, "✍️ issued by:")?;
             }
-            match report.issuer_report.as_ref() {
-                IssuerReport::OtherCertificate(report) => {
-                    print_certificate_chain(writer, indent, report)
-                }
-                IssuerReport::Root => {
-                    print_indented!(writer, indent, "🛡️ Confidential Space root certificate")
-                }
+            Ok(())
+        }
+        (Check::RootCertificate, _) => {
+            print_indented!(writer, indent, "🛡️ Confidential Space root certificate")
+        }
+        (Check::CertificateChain, _) => {
+            print_indented!(writer, indent, "📜 Certificate chain:")?;
+            let indent = indent + 1;
+            match outcome {
+                Outcome::Failed(err) => print_indented!(writer, indent, "❌ invalid: {}", err),
+                _ => print_details(writer, indent, details),
             }
         }
+        (Check::Validity, Outcome::Passed) => print_indented!(writer, indent, "✅ is valid"),
+        (Check::Validity, Outcome::Failed(err)) => {
+            print_indented!(writer, indent, "❌ is invalid: {}", err)
+        }
+        (Check::Freshness, Outcome::Passed) => print_indented!(writer, indent, "✅ is fresh"),
+        (Check::Freshness, Outcome::Failed(err)) => {
+            print_indented!(writer, indent, "❌ proof of freshness failed to verify: {}", err)
+        }
+        (Check::ProductionImage, Outcome::Passed) => {
+            print_indented!(writer, indent, "✅ obtained from a production image")
+        }
+        (Check::ProductionImage, Outcome::Failed(err)) => {
+            print_indented!(writer, indent, "❌ obtained from a debug image: {}", err)
+        }
+        (Check::ReferenceValue, _) => match outcome {
+            Outcome::Failed(err) => print_indented!(writer, indent, "❌ {}", err),
+            _ => Ok(()),
+        },
+        (Check::PublicKey, _) => {
+            print_indented!(writer, indent, "🔑 Public key:")?;
+            print_outcome(writer, indent + 1, outcome)
+        }
+        (Check::Token, _) => {
+            print_indented!(writer, indent, "🪙 Token verification:")?;
+            print_group(writer, indent + 1, outcome, details)
+        }
+        (Check::WorkloadEndorsement, Outcome::Skipped) => {
+            print_indented!(writer, indent, "📦 Workload endorsement:")?;
+            print_indented!(writer, indent + 1, "🤷 not present")
+        }
+        (Check::WorkloadEndorsement, _) => {
+            print_indented!(writer, indent, "📦 Workload endorsement:")?;
+            print_group(writer, indent + 1, outcome, details)
+        }
+        (Check::Statement, _) => {
+            print_indented!(writer, indent, " Statement")?;
+            print_group(writer, indent + 1, outcome, details)
+        }
+        (Check::ReferenceValueMismatches, _) => {
+            print_indented!(writer, indent, "🔍 Reference value mismatches:")?;
+            print_group(writer, indent + 1, outcome, details)
+        }
+        (Check::Validity | Check::Freshness | Check::ProductionImage, Outcome::Skipped)
+        | (Check::Signature | Check::TransparencyLogEntry, _) => {
+            print_outcome(writer, indent, outcome)
+        }
+    }
+}
+
+fn print_details(writer: &mut impl Write, indent: usize, details: &[Verdict]) -> std::fmt::Result {
+    details.iter().try_for_each(|detail| print_step(writer, indent, detail))
+}
+
+/// Renders the details of a step, or why the step itself failed.
+fn print_group(
+    writer: &mut impl Write,
+    indent: usize,
+    outcome: &Outcome,
+    details: &[Verdict],
+) -> std::fmt::Result {
+    match outcome {
+        Outcome::Failed(err) => print_indented!(writer, indent, "❌ failed to verify: {}", err),
+        _ => print_details(writer, indent, details),
+    }
+}
+
+fn print_outcome(writer: &mut impl Write, indent: usize, outcome: &Outcome) -> std::fmt::Result {
+    match outcome {
+        Outcome::Passed => print_indented!(writer, indent, "✅ verified successfully"),
+        Outcome::Failed(err) => print_indented!(writer, indent, "❌ failed to verify: {}", err),
+        Outcome::Skipped => print_indented!(writer, indent, "🤷 not verified"),
     }
 }
 
@@ -295,18 +382,16 @@ fn verify_session_binding(
 mod tests {
     use core::str::FromStr;
 
-    use jwt::{
-        algorithm::{openssl::PKeyWithDigest, AlgorithmType},
-        SignWithKey, SigningAlgorithm, Token, Verified, VerifyWithKey, VerifyingAlgorithm,
-    };
+    use jwt::{algorithm::AlgorithmType, SignWithKey, Token, Verified, VerifyWithKey};
     use oak_attestation_gcp::{
         cosign::{CosignVerificationError, CosignVerificationReport, StatementReport},
+        diff::ClaimMismatch,
         jwt::{
             verification::{
                 AttestationTokenVerificationReport, AttestationVerificationError,
                 CertificateReport, IssuerReport,
             },
-            Claims, Header,
+            Claims, Header, Rs256SigningKey,
         },
         policy::{ConfidentialSpaceVerificationError, ConfidentialSpaceVerificationReport},
     };
@@ -314,10 +399,13 @@ mod tests {
     use oak_crypto::certificate::certificate_verifier::{
         CertificateVerificationError, CertificateVerificationReport,
     };
-    use openssl::{hash::MessageDigest, pkey::PKey, rsa::Rsa};
+    use openssl::rsa::Rsa;
     use p256::ecdsa::{signature::SignerMut, Signature, SigningKey};
+    use rsa::{pkcs1::DecodeRsaPrivateKey, RsaPrivateKey};
+    use test_util::AttestationData;
 
     use super::*;
+    use crate::print::assert_eq_trimmed_lines;
 
     const INDENT: usize = 0;
 
@@ -329,11 +417,63 @@ tY7X8XNBXwBH9yNp9Nza8ymFRbmhRANCAAShmAYmC7YQ2SHOzTaugBQDSVQrjwnh
 Nj98VHCkMOChdP0NoY0+ASi3S9WesDHql/SS3TeVKIW0W7VRIYDz51rU
 -----END PRIVATE KEY-----
 ";
-    const HANDSHAKE_HASH: &[u8] = b"abc123def";
+    const HANDSHAKE_HASH: &[u8] = b"abc123def456ghi789jkl012mno345pq";
 
     // TODO: b/419209669 - Add test cases for the VerificationReport constructor
     // methods.
 
+    #[test]
+    fn test_session_binding_public_key_certificate_based() {
+        let signing_key = SigningKey::from_str(SIGNING_KEY).unwrap();
+        let expected = signing_key.verifying_key().to_sec1_bytes().to_vec();
+
+        let report = valid_certificate_based_report(&signing_key);
+
+        assert_eq!(report.session_binding_public_key(), expected);
+    }
+
+    #[test]
+    fn test_session_binding_public_key_confidential_space() {
+        let signing_key = SigningKey::from_str(SIGNING_KEY).unwrap();
+        let expected = signing_key.verifying_key().to_sec1_bytes().to_vec();
+
+        let report = valid_confidential_space_report(&signing_key);
+
+        assert_eq!(report.session_binding_public_key(), expected);
+    }
+
+    #[test]
+    fn test_session_binding_public_key_of_failed_report() {
+        let report =
+            VerificationReport::CertificateBased(SessionBindingPublicKeyVerificationReport {
+                endorsement: Err(CertificateVerificationError::UnknownError("failed")),
+                session_binding_public_key: b"not a key".to_vec(),
+            });
+
+        assert_eq!(report.session_binding_public_key(), b"not a key".to_vec());
+    }
+
+    #[test]
+    fn test_session_binding_public_key_oak_containers() {
+        let d = AttestationData::load_milan_oc_release();
+        let verifier =
+            create_amd_verifier(FixedClock::at_instant(d.make_valid_time()), &d.reference_values)
+                .expect("couldn't create verifier");
+        let results = verifier.verify(&d.evidence, &d.endorsements).expect("verification failed");
+        let expected = get_session_binding_public_key(&results).unwrap().clone();
+
+        let report = VerificationReport::OakContainers(results);
+
+        assert_eq!(report.session_binding_public_key(), expected);
+    }
+
+    #[test]
+    fn test_session_binding_public_key_oak_containers_missing_key() {
+        let report = VerificationReport::OakContainers(AttestationResults::default());
+
+        assert_eq!(report.session_binding_public_key(), Vec::<u8>::new());
+    }
+
     #[test]
     fn test_print_certificate_based_report_success() {
         let mut signing_key = SigningKey::from_str(SIGNING_KEY).unwrap();
@@ -424,6 +564,77 @@ Nj98VHCkMOChdP0NoY0+ASi3S9WesDHql/SS3TeVKIW0W7VRIYDz51rU
         );
     }
 
+    #[test]
+    fn test_check_handshake_hash() {
+        assert_eq!(check_handshake_hash(HANDSHAKE_HASH), Ok(()));
+        assert_eq!(check_handshake_hash(&[]), Err(HandshakeHashError::Missing));
+        assert_eq!(check_handshake_hash(b"abc123def"), Err(HandshakeHashError::WrongLength(9)));
+    }
+
+    #[test]
+    fn test_print_certificate_based_report_missing_handshake_hash() {
+        let mut signing_key = SigningKey::from_str(SIGNING_KEY).unwrap();
+        let handshake_signature: Signature = signing_key.sign(&[]);
+        let binding = session_binding(&handshake_signature.to_bytes());
+
+        let report = valid_certificate_based_report(&signing_key);
+        let mut writer = String::new();
+        report.print(&mut writer, INDENT, &[], Option::Some(&binding)).unwrap();
+
+        assert_eq_trimmed_lines(
+            &writer,
+            &[
+                "📜 Certificate:",
+                "✅ is valid",
+                "✅ verified successfully",
+                "✅ is fresh",
+                "🔐 Session binding:",
+                "❌ not verified: handshake hash is missing",
+            ],
+        );
+        assert!(report.into_checked(&[], Option::Some(&binding)).is_err());
+    }
+
+    #[test]
+    fn test_print_certificate_based_report_malformed_handshake_hash() {
+        let mut signing_key = SigningKey::from_str(SIGNING_KEY).unwrap();
+        let handshake_hash = &HANDSHAKE_HASH[..9];
+        let handshake_signature: Signature = signing_key.sign(handshake_hash);
+        let binding = session_binding(&handshake_signature.to_bytes());
+
+        let report = valid_certificate_based_report(&signing_key);
+        let mut writer = String::new();
+        report.print(&mut writer, INDENT, handshake_hash, Option::Some(&binding)).unwrap();
+
+        assert_eq_trimmed_lines(
+            &writer,
+            &[
+                "📜 Certificate:",
+                "✅ is valid",
+                "✅ verified successfully",
+                "✅ is fresh",
+                "🔐 Session binding:",
+                "❌ not verified: handshake hash has length 9 (expected: 32)",
+            ],
+        );
+        assert!(report.into_checked(handshake_hash, Option::Some(&binding)).is_err());
+    }
+
+    #[test]
+    fn test_certificate_based_report_into_checked_valid_handshake_hash() {
+        let mut signing_key = SigningKey::from_str(SIGNING_KEY).unwrap();
+        let handshake_signature: Signature = signing_key.sign(HANDSHAKE_HASH);
+
+        let report = valid_certificate_based_report(&signing_key);
+
+        assert!(report
+            .into_checked(
+                HANDSHAKE_HASH,
+                Option::Some(&session_binding(&handshake_signature.to_bytes()))
+            )
+            .is_ok());
+    }
+
     #[test]
     fn test_print_confidential_space_report_success() {
         let mut signing_key = SigningKey::from_str(SIGNING_KEY).unwrap();
@@ -448,6 +659,8 @@ Nj98VHCkMOChdP0NoY0+ASi3S9WesDHql/SS3TeVKIW0W7VRIYDz51rU
                 }),
             })),
             session_binding_public_key: signing_key.verifying_key().to_sec1_bytes().to_vec(),
+            root_certificate_index: Some(0),
+            claim_mismatches: vec![],
         });
 
         let mut writer = String::new();
@@ -500,6 +713,8 @@ Nj98VHCkMOChdP0NoY0+ASi3S9WesDHql/SS3TeVKIW0W7VRIYDz51rU
             },
             workload_endorsement_verification: None,
             session_binding_public_key: vec![],
+            root_certificate_index: Some(0),
+            claim_mismatches: vec![],
         });
 
         let mut writer = String::new();
@@ -546,6 +761,12 @@ Nj98VHCkMOChdP0NoY0+ASi3S9WesDHql/SS3TeVKIW0W7VRIYDz51rU
                 ),
             )),
             session_binding_public_key: signing_key.verifying_key().to_sec1_bytes().to_vec(),
+            root_certificate_index: None,
+            claim_mismatches: vec![ClaimMismatch {
+                claim: "dbgstat",
+                expected: vec!["disabled-since-boot".to_string()],
+                observed: "enabled".to_string(),
+            }],
         });
 
         let mut writer = String::new();
@@ -570,6 +791,8 @@ Nj98VHCkMOChdP0NoY0+ASi3S9WesDHql/SS3TeVKIW0W7VRIYDz51rU
                 "❌ invalid: Unknown error: issuer error",
                 "📦 Workload endorsement:",
                 "❌ failed to verify: endorsement validation error: workload endorsement error",
+                "🔍 Reference value mismatches:",
+                "❌ dbgstat: expected [\"disabled-since-boot\"], observed \"enabled\"",
                 "🔐 Session binding:",
                 "❌ failed to verify: could not parse signature",
             ],
@@ -604,6 +827,8 @@ Nj98VHCkMOChdP0NoY0+ASi3S9WesDHql/SS3TeVKIW0W7VRIYDz51rU
                 }),
             })),
             session_binding_public_key: signing_key.verifying_key().to_sec1_bytes().to_vec(),
+            root_certificate_index: Some(0),
+            claim_mismatches: vec![],
         });
 
         let mut writer = String::new();
@@ -640,15 +865,39 @@ Nj98VHCkMOChdP0NoY0+ASi3S9WesDHql/SS3TeVKIW0W7VRIYDz51rU
         );
     }
 
-    /// Asserts that the (trimmed) lines in [actual] are equal to those in
-    /// [expected].
-    fn assert_eq_trimmed_lines(actual: &str, expected: &[&str]) {
-        let lines: Vec<&str> = actual
-            .split("\n")
-            .map(|line| line.trim())
-            .filter(|line| !line.trim().is_empty())
-            .collect();
-        assert_eq!(lines.as_slice(), expected);
+    /// Returns a certificate-based report in which all endorsement checks
+    /// passed, attesting the public key of `signing_key`.
+    fn valid_certificate_based_report(signing_key: &SigningKey) -> VerificationReport {
+        VerificationReport::CertificateBased(SessionBindingPublicKeyVerificationReport {
+            endorsement: Ok(CertificateVerificationReport {
+                validity: Ok(()),
+                verification: Ok(()),
+                freshness: Some(Ok(())),
+            }),
+            session_binding_public_key: signing_key.verifying_key().to_sec1_bytes().to_vec(),
+        })
+    }
+
+    /// Returns a Confidential Space report in which all checks passed,
+    /// attesting the public key of `signing_key`.
+    fn valid_confidential_space_report(signing_key: &SigningKey) -> VerificationReport {
+        VerificationReport::ConfidentialSpace(ConfidentialSpaceVerificationReport {
+            public_key_verification: Ok(()),
+            token_report: AttestationTokenVerificationReport {
+                production_image: Ok(()),
+                validity: Ok(()),
+                verification: Ok(generate_verified_token().unwrap()),
+                issuer_report: Ok(CertificateReport {
+                    validity: Ok(()),
+                    verification: Ok(()),
+                    issuer_report: Box::new(IssuerReport::Root),
+                }),
+            },
+            workload_endorsement_verification: None,
+            session_binding_public_key: signing_key.verifying_key().to_sec1_bytes().to_vec(),
+            root_certificate_index: Some(0),
+            claim_mismatches: vec![],
+        })
     }
 
     fn session_binding(session_binding: &[u8]) -> SessionBinding {
@@ -656,54 +905,14 @@ Nj98VHCkMOChdP0NoY0+ASi3S9WesDHql/SS3TeVKIW0W7VRIYDz51rU
     }
 
     fn generate_verified_token() -> anyhow::Result<Token<Header, Claims, Verified>> {
-        let key: PKey<openssl::pkey::Private> = PKey::from_rsa(Rsa::generate(2048)?)?;
-        let private_key = PKeyWithDigest { digest: MessageDigest::sha256(), key: key.clone() };
-        let public_key = PKeyWithDigest {
-            digest: MessageDigest::sha256(),
-            key: PKey::public_key_from_pem(key.public_key_to_pem()?.as_slice())?,
-        };
+        let private_key = Rsa::generate(2048)?.private_key_to_der()?;
+        let signing_key: Rs256SigningKey =
+            RsaPrivateKey::from_pkcs1_der(&private_key).map_err(anyhow::Error::msg)?.into();
         let header = Header { algorithm: AlgorithmType::Rs256, x509_chain: vec![] };
         let claims = Claims { ..Default::default() };
-        let signed_token = Token::new(header, claims)
-            .sign_with_key(&Rs256PKeyWithDigest { delegate: private_key })?;
+        let signed_token = Token::new(header, claims).sign_with_key(&signing_key)?;
         let unverified_token: Token<Header, Claims, _> =
             Token::parse_unverified(signed_token.as_str())?;
-        Ok(unverified_token.verify_with_key(&Rs256PKeyWithDigest { delegate: public_key })?)
-    }
-
-    // This is a hack, and _shouldn't_ be necessary.
-    // https://github.com/mikkyang/rust-jwt/blob/47e8fbb/src/token/verified.rs#L171-L194
-    // shows an example of the jwt crate doing the same as the code above
-    // (generating a token, signing it, and verifying it), but I cannot get this
-    // to work. No matter how I generate a key, the error at
-    // https://github.com/mikkyang/rust-jwt/blob/47e8fbb/src/algorithm/openssl.rs#L44
-    // is thrown, apparently because the key ID never gets set correctly.
-    // So instead, we have this hack to completely override the algorithm_type()
-    // function.
-    struct Rs256PKeyWithDigest<T> {
-        delegate: PKeyWithDigest<T>,
-    }
-
-    impl SigningAlgorithm for Rs256PKeyWithDigest<openssl::pkey::Private> {
-        fn algorithm_type(&self) -> AlgorithmType {
-            AlgorithmType::Rs256
-        }
-        fn sign(&self, header: &str, claims: &str) -> Result<String, jwt::error::Error> {
-            self.delegate.sign(header, claims)
-        }
-    }
-
-    impl VerifyingAlgorithm for Rs256PKeyWithDigest<openssl::pkey::Public> {
-        fn algorithm_type(&self) -> AlgorithmType {
-            AlgorithmType::Rs256
-        }
-        fn verify_bytes(
-            &self,
-            header: &str,
-            claims: &str,
-            signature: &[u8],
-        ) -> Result<bool, jwt::error::Error> {
-            self.delegate.verify_bytes(header, claims, signature)
-        }
+        Ok(unverified_token.verify_with_key(&signing_key.verifying_key())?)
     }
 }
@@ -0,0 +1,208 @@
+//
+// Copyright 2025 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Generation of reference value skeletons from observed attestations.
+//!
+//! The values are copied from the attestation without verifying it, so the
+//! output is only a starting point for an operator writing a policy: every
+//! value must be reviewed before it is pinned.
+
+use std::fmt::Write;
+
+use anyhow::{anyhow, Context};
+use jwt::{Token, Unverified};
+use oak_attestation_gcp::jwt::{Claims, Header};
+use oak_proto_rust::{
+    attestation::CONFIDENTIAL_SPACE_ATTESTATION_ID,
+    oak::attestation::v1::{CollectedAttestation, ConfidentialSpaceEndorsement},
+};
+
+/// Placeholder for the root certificate, which must come from a trusted source
+/// rather than from the attestation.
+const ROOT_CERTIFICATE_PLACEHOLDER: &str =
+    "<REPLACE WITH THE PEM-ENCODED CONFIDENTIAL SPACE ROOT CERTIFICATE>";
+
+/// Returns an `oak.attestation.v1.ConfidentialSpaceReferenceValues` textproto
+/// prefilled with the values observed in the Confidential Space attestation
+/// of `attestation`.
+pub fn confidential_space_reference_values_skeleton(
+    attestation: &CollectedAttestation,
+) -> anyhow::Result<String> {
+    let endorsed_evidence = attestation
+        .endorsed_evidence
+        .get(CONFIDENTIAL_SPACE_ATTESTATION_ID)
+        .ok_or_else(|| anyhow!("no Confidential Space attestation found"))?;
+    let endorsement = endorsed_evidence
+        .endorsements
+        .as_ref()
+        .and_then(|endorsements| endorsements.events.first())
+        .ok_or_else(|| anyhow!("missing endorsement"))?;
+    let endorsement =
+        ConfidentialSpaceEndorsement::try_from(endorsement).map_err(anyhow::Error::msg)?;
+    let token: Token<Header, Claims, Unverified> =
+        Token::parse_unverified(&endorsement.jwt_token).context("couldn't parse token")?;
+    let claims = token.claims();
+    let image_reference = claims
+        .effective_reference()
+        .map_err(|err| anyhow!("couldn't parse the observed image reference: {}", err))?;
+
+    let mut skeleton = String::new();
+    writeln!(skeleton, "# proto-message: oak.attestation.v1.ConfidentialSpaceReferenceValues")?;
+    writeln!(skeleton, "#")?;
+    writeln!(skeleton, "# OBSERVED, UNVERIFIED: the values below were copied from an attestation")?;
+    writeln!(skeleton, "# that has NOT been verified. Review each of them before pinning it.")?;
+    writeln!(skeleton, "#")?;
+    writeln!(skeleton, "# Observed debug status: {}", claims.debug_status)?;
+    writeln!(
+        skeleton,
+        "# Observed image support attributes: {}",
+        claims.submods.confidential_space.support_attributes.join(", ")
+    )?;
+    writeln!(skeleton)?;
+    writeln!(skeleton, "# Not taken from the attestation: set to the root certificate you trust.")?;
+    writeln!(skeleton, "root_certificate_pem: {}", textproto_string(ROOT_CERTIFICATE_PLACEHOLDER))?;
+    writeln!(skeleton)?;
+    writeln!(skeleton, "# Observed workload image, pinned by its digest.")?;
+    writeln!(
+        skeleton,
+        "container_image_reference: {}",
+        textproto_string(&image_reference.whole())
+    )?;
+    if let Some(signature) = endorsement
+        .workload_endorsement
+        .as_ref()
+        .and_then(|endorsement| endorsement.signature.as_ref())
+    {
+        writeln!(skeleton)?;
+        writeln!(
+            skeleton,
+            "# The workload image is endorsed (observed signing key ID: {}). To verify the",
+            signature.key_id
+        )?;
+        writeln!(skeleton, "# endorsement instead of pinning the image, replace")?;
+        writeln!(skeleton, "# container_image_reference with the key of the signer you trust:")?;
+        writeln!(skeleton, "# cosign_reference_values {{")?;
+        writeln!(skeleton, "#   developer_public_key {{ ... }}")?;
+        writeln!(skeleton, "# }}")?;
+    }
+    Ok(skeleton)
+}
+
+/// Encodes `value` as a textproto string literal.
+fn textproto_string(value: &str) -> String {
+    let mut literal = String::from("\"");
+    for character in value.chars() {
+        match character {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            character => literal.push(character),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+#[cfg(test)]
+mod tests {
+    use jwt::ToBase64;
+    use oak_attestation_gcp::jwt::{ContainerClaims, Submods};
+    use oak_proto_rust::oak::{
+        attestation::v1::{Endorsements, Signature, SignedEndorsement},
+        session::v1::EndorsedEvidence,
+    };
+
+    use super::*;
+
+    const IMAGE_REFERENCE: &str = "europe-west2-docker.pkg.dev/oak-ci/example/echo:latest";
+    const IMAGE_DIGEST: &str =
+        "sha256:0b8cd3bc5bc3b6b3d2ee3b16d2be0f5e0b5d3c2f1e7a6b9c8d7e6f5a4b3c2d1e";
+
+    fn attestation(workload_endorsement: Option<SignedEndorsement>) -> CollectedAttestation {
+        let header = Header { algorithm: jwt::AlgorithmType::Rs256, x509_chain: vec![] };
+        let claims = Claims {
+            debug_status: "disabled-since-boot".to_string(),
+            submods: Submods {
+                container: ContainerClaims {
+                    image_reference: IMAGE_REFERENCE.to_string(),
+                    image_digest: IMAGE_DIGEST.to_string(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let jwt_token =
+            format!("{}.{}.signature", header.to_base64().unwrap(), claims.to_base64().unwrap());
+        let endorsement = ConfidentialSpaceEndorsement { jwt_token, workload_endorsement };
+        CollectedAttestation {
+            endorsed_evidence: [(
+                CONFIDENTIAL_SPACE_ATTESTATION_ID.to_string(),
+                EndorsedEvidence {
+                    evidence: None,
+                    endorsements: Some(Endorsements {
+                        events: vec![endorsement.into()],
+                        ..Default::default()
+                    }),
+                },
+            )]
+            .into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_skeleton_contains_observed_values() {
+        let skeleton = confidential_space_reference_values_skeleton(&attestation(None)).unwrap();
+
+        assert!(skeleton.contains("OBSERVED, UNVERIFIED"));
+        assert!(skeleton.contains("# Observed debug status: disabled-since-boot"));
+        assert!(skeleton
+            .contains(&format!("root_certificate_pem: \"{}\"", ROOT_CERTIFICATE_PLACEHOLDER)));
+        assert!(skeleton.contains(&format!(
+            "container_image_reference: \"{}@{}\"",
+            IMAGE_REFERENCE, IMAGE_DIGEST
+        )));
+        assert!(!skeleton.contains("cosign_reference_values"));
+    }
+
+    #[test]
+    fn test_skeleton_mentions_workload_endorsement_signer() {
+        let workload_endorsement = SignedEndorsement {
+            signature: Some(Signature { key_id: 7, ..Default::default() }),
+            ..Default::default()
+        };
+
+        let skeleton =
+            confidential_space_reference_values_skeleton(&attestation(Some(workload_endorsement)))
+                .unwrap();
+
+        assert!(skeleton.contains("observed signing key ID: 7"));
+        assert!(skeleton.contains("# cosign_reference_values {"));
+    }
+
+    #[test]
+    fn test_skeleton_without_confidential_space_attestation() {
+        let result = confidential_space_reference_values_skeleton(&CollectedAttestation::default());
+
+        assert!(result.unwrap_err().to_string().contains("no Confidential Space attestation"));
+    }
+
+    #[test]
+    fn test_textproto_string_escapes() {
+        assert_eq!(textproto_string("a\"b\\c\nd"), "\"a\\\"b\\\\c\\nd\"");
+    }
+}
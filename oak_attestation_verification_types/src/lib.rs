@@ -19,6 +19,10 @@
 
 extern crate alloc;
 
+#[cfg(test)]
+extern crate std;
+
 pub mod policy;
 pub mod util;
+pub mod verdict;
 pub mod verifier;
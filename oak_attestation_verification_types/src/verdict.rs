@@ -0,0 +1,184 @@
+//
+// Copyright 2025 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Structured outcome of verifying an attestation, independent of how it is
+//! presented to a user.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::Display;
+
+/// A check performed while verifying an attestation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Check {
+    /// A certificate. Its details are the checks performed on it.
+    Certificate,
+    /// A certificate chain. Its details are the certificates in the chain,
+    /// starting with the one that signs the attested object and ending with
+    /// the root certificate.
+    CertificateChain,
+    /// A certificate in a certificate chain, which is issued by the next
+    /// certificate in the chain.
+    ChainCertificate,
+    /// The trusted root certificate a certificate chain terminates at.
+    RootCertificate,
+    /// The validity period of a certificate, token or statement.
+    Validity,
+    /// The signature of a certificate, token or statement.
+    Signature,
+    /// The proof of freshness of a certificate.
+    Freshness,
+    /// The binding between the token and the session binding public key.
+    PublicKey,
+    /// An attestation token.
+    Token,
+    /// Whether the token was obtained from a production image.
+    ProductionImage,
+    /// The endorsement of the workload.
+    WorkloadEndorsement,
+    /// An endorsement statement.
+    Statement,
+    /// The transparency log entry of a statement.
+    TransparencyLogEntry,
+    /// Values that differ from the reference values. Its details are the
+    /// individual mismatches.
+    ReferenceValueMismatches,
+    /// A single value that differs from the reference values.
+    ReferenceValue,
+}
+
+/// The result of a single check, not taking its details into account.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    Failed(String),
+    /// The check was not performed, e.g. because the policy doesn't require
+    /// it.
+    Skipped,
+}
+
+/// The outcome of a check, together with the outcomes of the checks it
+/// consists of.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Verdict {
+    pub check: Check,
+    pub outcome: Outcome,
+    pub details: Vec<Verdict>,
+}
+
+impl Verdict {
+    pub fn new(check: Check, outcome: Outcome) -> Self {
+        Self { check, outcome, details: Vec::new() }
+    }
+
+    /// Creates a verdict for a check that passes if all its details pass.
+    pub fn with_details(check: Check, details: Vec<Verdict>) -> Self {
+        Self { check, outcome: Outcome::Passed, details }
+    }
+
+    pub fn failed(check: Check, err: &impl Display) -> Self {
+        Self::new(check, Outcome::Failed(err.to_string()))
+    }
+
+    pub fn from_result<E: Display>(check: Check, result: &Result<(), E>) -> Self {
+        match result {
+            Ok(()) => Self::new(check, Outcome::Passed),
+            Err(err) => Self::failed(check, err),
+        }
+    }
+
+    /// Returns whether neither this check nor any of its details failed.
+    /// Skipped checks don't cause the verdict to fail.
+    pub fn passed(&self) -> bool {
+        !matches!(self.outcome, Outcome::Failed(_)) && all_passed(&self.details)
+    }
+}
+
+/// Returns whether none of the given checks failed.
+pub fn all_passed(verdicts: &[Verdict]) -> bool {
+    verdicts.iter().all(Verdict::passed)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::ToString, vec};
+
+    use super::*;
+
+    #[test]
+    fn from_result_maps_ok_to_passed() {
+        let result: Result<(), &str> = Ok(());
+        assert_eq!(
+            Verdict::from_result(Check::Signature, &result),
+            Verdict::new(Check::Signature, Outcome::Passed)
+        );
+    }
+
+    #[test]
+    fn from_result_maps_err_to_failed_with_message() {
+        let result: Result<(), &str> = Err("bad signature");
+        assert_eq!(
+            Verdict::from_result(Check::Signature, &result),
+            Verdict::new(Check::Signature, Outcome::Failed("bad signature".to_string()))
+        );
+    }
+
+    #[test]
+    fn passed_ignores_skipped_checks() {
+        let verdict = Verdict::with_details(
+            Check::Token,
+            vec![
+                Verdict::new(Check::Validity, Outcome::Passed),
+                Verdict::new(Check::Freshness, Outcome::Skipped),
+            ],
+        );
+        assert!(verdict.passed());
+    }
+
+    #[test]
+    fn passed_fails_on_nested_failure() {
+        let chain = Verdict::with_details(
+            Check::CertificateChain,
+            vec![Verdict::with_details(
+                Check::ChainCertificate,
+                vec![Verdict::failed(Check::Signature, &"bad signature")],
+            )],
+        );
+        assert!(!chain.passed());
+        assert!(!Verdict::with_details(Check::Token, vec![chain]).passed());
+    }
+
+    #[test]
+    fn passed_fails_on_own_failure() {
+        let verdict = Verdict {
+            check: Check::Token,
+            outcome: Outcome::Failed("expired".to_string()),
+            details: vec![Verdict::new(Check::Validity, Outcome::Passed)],
+        };
+        assert!(!verdict.passed());
+    }
+
+    #[test]
+    fn all_passed_checks_every_verdict() {
+        let passed = Verdict::new(Check::Statement, Outcome::Passed);
+        let failed = Verdict::failed(Check::TransparencyLogEntry, &"missing");
+        assert!(all_passed(&[]));
+        assert!(all_passed(&[passed.clone(), Verdict::new(Check::Validity, Outcome::Skipped)]));
+        assert!(!all_passed(&[passed, failed]));
+    }
+}
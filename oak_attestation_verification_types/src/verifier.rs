@@ -14,6 +14,8 @@
 // limitations under the License.
 //
 
+#[cfg(test)]
+use mockall::automock;
 use oak_proto_rust::oak::attestation::v1::{AttestationResults, Endorsements, Evidence};
 
 /// Trait that provides the functionality for appraising the attestation
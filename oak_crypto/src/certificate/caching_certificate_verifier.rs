@@ -0,0 +1,138 @@
+//
+// Copyright 2025 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use core::cmp::min;
+
+use hashbrown::HashMap;
+use oak_proto_rust::oak::crypto::v1::{Certificate, CertificatePayload};
+use oak_time::{Duration, Instant};
+use prost::Message;
+use sha2::{Digest, Sha256};
+use spinning_top::Spinlock;
+
+use crate::{
+    certificate::certificate_verifier::{CertificateVerificationError, CertificateVerifier},
+    verifier::Verifier,
+};
+
+/// Default maximum number of successful verifications kept in the cache.
+pub const DEFAULT_MAX_CACHE_ENTRIES: usize = 1024;
+
+type CacheKey = [u8; 32];
+
+/// Time interval during which a successful verification can be reused.
+#[derive(Clone, Copy)]
+struct CacheEntry {
+    verified_at: Instant,
+    expires_at: Instant,
+}
+
+impl CacheEntry {
+    fn is_valid_at(&self, current_time: Instant) -> bool {
+        self.verified_at <= current_time && current_time <= self.expires_at
+    }
+}
+
+/// Wrapper around [`CertificateVerifier`] that memoizes successful
+/// verifications, so that certificates that are presented repeatedly (such as
+/// intermediate certificates) are not re-verified for every attestation.
+///
+/// Cached results are keyed by a hash of the certificate together with the
+/// expected subject public key and purpose ID. A cached result is reused for at
+/// most `ttl` after the verification and never after the end of the
+/// certificate validity period, or for a time before the verification, so
+/// expiry is still enforced by the wrapped verifier. Failed verifications are
+/// not cached.
+pub struct CachingCertificateVerifier<V: Verifier> {
+    certificate_verifier: CertificateVerifier<V>,
+    ttl: Duration,
+    max_cache_entries: usize,
+    cache: Spinlock<HashMap<CacheKey, CacheEntry>>,
+}
+
+impl<V: Verifier> CachingCertificateVerifier<V> {
+    /// Creates a new instance of [`CachingCertificateVerifier`] that reuses
+    /// successful verifications for at most `ttl`.
+    pub fn new(certificate_verifier: CertificateVerifier<V>, ttl: Duration) -> Self {
+        Self {
+            certificate_verifier,
+            ttl,
+            max_cache_entries: DEFAULT_MAX_CACHE_ENTRIES,
+            cache: Spinlock::new(HashMap::new()),
+        }
+    }
+
+    /// Sets the maximum number of successful verifications kept in the cache.
+    pub fn set_max_cache_entries(&mut self, max_cache_entries: usize) {
+        self.max_cache_entries = max_cache_entries;
+    }
+
+    /// Verifies the [`Certificate`] proto in the same way as
+    /// [`CertificateVerifier::verify`], reusing a previous successful
+    /// verification if it is still valid at `current_time`.
+    pub fn verify(
+        &self,
+        current_time: Instant,
+        subject_public_key: &[u8],
+        purpose_id: &[u8],
+        certificate: &Certificate,
+    ) -> Result<(), CertificateVerificationError> {
+        let key = cache_key(subject_public_key, purpose_id, certificate);
+        if self.cache.lock().get(&key).is_some_and(|entry| entry.is_valid_at(current_time)) {
+            return Ok(());
+        }
+
+        self.certificate_verifier.verify(
+            current_time,
+            subject_public_key,
+            purpose_id,
+            certificate,
+        )?;
+
+        // The validity period has just been checked by the verifier, so it's
+        // present in the payload.
+        let not_after: Instant =
+            CertificatePayload::decode(certificate.serialized_payload.as_ref())?
+                .validity
+                .and_then(|validity| validity.not_after)
+                .ok_or(CertificateVerificationError::MissingField("Validity.not_after"))?
+                .into();
+        let entry = CacheEntry {
+            verified_at: current_time,
+            expires_at: min(current_time + self.ttl, not_after),
+        };
+
+        let mut cache = self.cache.lock();
+        if cache.len() >= self.max_cache_entries {
+            cache.retain(|_, entry| entry.expires_at >= current_time);
+        }
+        if cache.len() < self.max_cache_entries || cache.contains_key(&key) {
+            cache.insert(key, entry);
+        }
+        Ok(())
+    }
+}
+
+fn cache_key(subject_public_key: &[u8], purpose_id: &[u8], certificate: &Certificate) -> CacheKey {
+    let mut hasher = Sha256::new();
+    // Prefix each field with its length so that different field boundaries
+    // result in different keys.
+    for field in [subject_public_key, purpose_id, &certificate.encode_to_vec()] {
+        hasher.update((field.len() as u64).to_le_bytes());
+        hasher.update(field);
+    }
+    hasher.finalize().into()
+}
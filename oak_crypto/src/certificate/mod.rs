@@ -14,6 +14,7 @@
 // limitations under the License.
 //
 
+pub mod caching_certificate_verifier;
 pub mod certificate_authority;
 pub mod certificate_verifier;
 #[cfg(test)]
@@ -14,8 +14,11 @@
 // limitations under the License.
 //
 
-use alloc::{vec, vec::Vec};
-use core::assert_matches::assert_matches;
+use alloc::{sync::Arc, vec, vec::Vec};
+use core::{
+    assert_matches::assert_matches,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 use oak_proto_rust::oak::crypto::v1::{
     Certificate, CertificatePayload, ProofOfFreshness, SignatureInfo, SubjectPublicKeyInfo,
@@ -25,9 +28,12 @@ use oak_time::{Duration, Instant};
 use prost::Message;
 
 use crate::{
-    certificate::certificate_verifier::{
-        CertificateVerificationError, CertificateVerificationReport, CertificateVerifier,
-        ProofOfFreshnessVerification,
+    certificate::{
+        caching_certificate_verifier::CachingCertificateVerifier,
+        certificate_verifier::{
+            CertificateVerificationError, CertificateVerificationReport, CertificateVerifier,
+            ProofOfFreshnessVerification,
+        },
     },
     verifier::Verifier,
 };
@@ -53,6 +59,20 @@ impl Verifier for MockVerifier {
     }
 }
 
+/// Verifier that counts how many signatures it has verified.
+#[derive(Clone, Default)]
+struct CountingVerifier {
+    verification_count: Arc<AtomicUsize>,
+}
+
+impl Verifier for CountingVerifier {
+    fn verify(&self, _message: &[u8], signature: &[u8]) -> anyhow::Result<()> {
+        self.verification_count.fetch_add(1, Ordering::SeqCst);
+        anyhow::ensure!(signature == TEST_SIGNATURE, "couldn't verify signature");
+        Ok(())
+    }
+}
+
 fn create_test_certificate(
     not_before: Instant,
     not_after: Instant,
@@ -645,3 +665,97 @@ fn test_report_certificate_freshness_unimplemented() {
         })
     );
 }
+
+#[test]
+fn test_caching_verifier_cache_hit_skips_verification() {
+    let certificate = create_test_certificate(
+        TEST_CURRENT_TIME - Duration::from_seconds(10),
+        TEST_CURRENT_TIME + Duration::from_seconds(10),
+        TEST_PUBLIC_KEY,
+        TEST_PURPOSE_ID,
+        TEST_SIGNATURE,
+        None,
+    );
+    let signature_verifier = CountingVerifier::default();
+    let verifier = CachingCertificateVerifier::new(
+        CertificateVerifier::new(signature_verifier.clone()),
+        Duration::from_seconds(5),
+    );
+
+    for elapsed in [0, 1, 5] {
+        let current_time = TEST_CURRENT_TIME + Duration::from_seconds(elapsed);
+        let result = verifier.verify(current_time, TEST_PUBLIC_KEY, TEST_PURPOSE_ID, &certificate);
+        assert!(result.is_ok(), "Expected verification to succeed, but got error: {:?}", result);
+    }
+    assert_eq!(signature_verifier.verification_count.load(Ordering::SeqCst), 1);
+
+    // A different subject public key is not served from the cache.
+    let result =
+        verifier.verify(TEST_CURRENT_TIME, TEST_BAD_PUBLIC_KEY, TEST_PURPOSE_ID, &certificate);
+    assert_matches!(result, Err(CertificateVerificationError::SubjectPublicKeyMismatch { .. }));
+    assert_eq!(signature_verifier.verification_count.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn test_caching_verifier_time_advancement_reverifies() {
+    let certificate = create_test_certificate(
+        TEST_CURRENT_TIME - Duration::from_seconds(10),
+        TEST_CURRENT_TIME + Duration::from_seconds(10),
+        TEST_PUBLIC_KEY,
+        TEST_PURPOSE_ID,
+        TEST_SIGNATURE,
+        None,
+    );
+    let signature_verifier = CountingVerifier::default();
+    let verifier = CachingCertificateVerifier::new(
+        CertificateVerifier::new(signature_verifier.clone()),
+        Duration::from_seconds(5),
+    );
+
+    let result = verifier.verify(TEST_CURRENT_TIME, TEST_PUBLIC_KEY, TEST_PURPOSE_ID, &certificate);
+    assert!(result.is_ok(), "Expected verification to succeed, but got error: {:?}", result);
+
+    // Past the TTL, the certificate is verified again.
+    let result = verifier.verify(
+        TEST_CURRENT_TIME + Duration::from_seconds(6),
+        TEST_PUBLIC_KEY,
+        TEST_PURPOSE_ID,
+        &certificate,
+    );
+    assert!(result.is_ok(), "Expected verification to succeed, but got error: {:?}", result);
+    assert_eq!(signature_verifier.verification_count.load(Ordering::SeqCst), 2);
+
+    // The cached result doesn't outlive the certificate validity period.
+    let result = verifier.verify(
+        TEST_CURRENT_TIME + Duration::from_seconds(11),
+        TEST_PUBLIC_KEY,
+        TEST_PURPOSE_ID,
+        &certificate,
+    );
+    assert_matches!(result, Err(CertificateVerificationError::ValidityPeriodExpired { .. }));
+    assert_eq!(signature_verifier.verification_count.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn test_caching_verifier_does_not_cache_failures() {
+    let certificate = create_test_certificate(
+        TEST_CURRENT_TIME - Duration::from_seconds(10),
+        TEST_CURRENT_TIME + Duration::from_seconds(10),
+        TEST_PUBLIC_KEY,
+        TEST_PURPOSE_ID,
+        TEST_BAD_SIGNATURE,
+        None,
+    );
+    let signature_verifier = CountingVerifier::default();
+    let verifier = CachingCertificateVerifier::new(
+        CertificateVerifier::new(signature_verifier.clone()),
+        Duration::from_seconds(5),
+    );
+
+    for _ in 0..2 {
+        let result =
+            verifier.verify(TEST_CURRENT_TIME, TEST_PUBLIC_KEY, TEST_PURPOSE_ID, &certificate);
+        assert_matches!(result, Err(CertificateVerificationError::SignatureVerificationError(_)));
+    }
+    assert_eq!(signature_verifier.verification_count.load(Ordering::SeqCst), 2);
+}
@@ -14,24 +14,38 @@
 // limitations under the License.
 //
 
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    path::Path,
+    sync::Arc,
+};
 
-use anyhow::{anyhow, Context, Result};
-use futures::channel::mpsc::{self, Sender};
+use anyhow::{anyhow, ensure, Context, Result};
+use futures::{
+    channel::mpsc::{self, Sender},
+    future::{self, BoxFuture},
+    FutureExt, Sink, SinkExt, Stream, StreamExt,
+};
 use oak_attestation_gcp::{
+    jwt::{Claims, Header},
     policy_generator::confidential_space_policy_from_reference_values,
     CONFIDENTIAL_SPACE_ROOT_CERT_PEM,
 };
 use oak_attestation_verification::EventLogVerifier;
+use oak_attestation_verification_types::verifier::AttestationVerifier;
 use oak_grpc::oak::functions::standalone::oak_functions_session_client::OakFunctionsSessionClient;
 use oak_proto_rust::{
     attestation::CONFIDENTIAL_SPACE_ATTESTATION_ID,
     oak::{
         attestation::v1::{
-            collected_attestation::RequestMetadata, CollectedAttestation,
-            ConfidentialSpaceReferenceValues,
+            collected_attestation::RequestMetadata, AttestationResults, CollectedAttestation,
+            ConfidentialSpaceEndorsement, ConfidentialSpaceReferenceValues, Endorsements, Evidence,
         },
         functions::standalone::{OakSessionRequest, OakSessionResponse},
+        session::v1::{
+            session_response::Response, EndorsedEvidence, SessionRequest, SessionResponse,
+        },
     },
 };
 use oak_session::{
@@ -39,128 +53,1540 @@ use oak_session::{
     channel::{SessionChannel, SessionInitializer},
     config::SessionConfig,
     handshake::HandshakeType,
-    key_extractor::DefaultBindingKeyExtractor,
+    key_extractor::{DefaultBindingKeyExtractor, KeyExtractor},
+    session::AttestationEvidence,
     ClientSession, Session,
 };
-use oak_time::Clock;
+use oak_time::{clock::FixedClock, Clock, Duration, Instant};
+use prost::Message;
 use tonic::transport::{Channel, Uri};
+use x509_cert::{der::DecodePem, Certificate};
+
+/// Period before the expiry of the pinned Confidential Space root certificate
+/// during which a warning is printed when a client is created.
+const ROOT_CERTIFICATE_EXPIRY_WARNING_PERIOD: Duration = Duration::from_seconds(30 * 24 * 60 * 60);
+
+/// Distance of the verification time from the validity window of the server's
+/// attestation token beyond which a failed verification is blamed on the
+/// client's clock rather than on the token.
+const CLOCK_SKEW_DIAGNOSTIC_THRESHOLD: Duration = Duration::from_seconds(60 * 60);
+
+/// Default capacity of the channel carrying requests to the server.
+pub const DEFAULT_REQUEST_CHANNEL_CAPACITY: usize = 10;
+
+/// Prefix of the keys of the client's own evidence in a
+/// [`CollectedAttestation`] of a bidirectional session.
+pub const SELF_ATTESTATION_KEY_PREFIX: &str = "self/";
+
+/// Prefix of the keys of the server's evidence in a [`CollectedAttestation`] of
+/// a bidirectional session.
+pub const PEER_ATTESTATION_KEY_PREFIX: &str = "peer/";
+
+/// Parameters negotiated for an open session, e.g. for recording in audit
+/// logs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SessionInfo {
+    /// The Noise handshake pattern with which the session was established.
+    pub handshake_type: HandshakeType,
+    /// The full Noise protocol name, which also names the DH function, cipher
+    /// and hash function protecting the session, e.g.
+    /// `Noise_NN_P256_AESGCM_SHA256`.
+    pub noise_protocol_name: &'static str,
+}
+
+impl SessionInfo {
+    /// Returns the parameters of the open `client_session`.
+    fn of(client_session: &ClientSession) -> Result<Self> {
+        let handshake_type = client_session.handshake_type()?;
+        Ok(Self { handshake_type, noise_protocol_name: handshake_type.noise_protocol_name() })
+    }
+}
+
+/// The evidence the server sent during a handshake that failed afterwards,
+/// e.g. because the evidence didn't verify.
+///
+/// [`OakFunctionsClient::create`] attaches this as context to its error, so
+/// that the evidence can be retrieved with
+/// `err.downcast_ref::<UnverifiedPeerEvidence>()` and inspected offline.
+#[derive(Debug)]
+pub struct UnverifiedPeerEvidence(pub CollectedAttestation);
+
+impl UnverifiedPeerEvidence {
+    /// Writes the evidence to `path`; see
+    /// [`OakFunctionsClient::dump_peer_evidence`].
+    pub fn dump(&self, path: impl AsRef<Path>) -> Result<()> {
+        write_collected_attestation(path.as_ref(), &self.0)
+    }
+}
+
+impl fmt::Display for UnverifiedPeerEvidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "handshake failed after receiving evidence for {:?}",
+            self.0.endorsed_evidence.keys().collect::<Vec<_>>()
+        )
+    }
+}
+
+/// Context of the errors after which a session can't be used anymore, because
+/// the server ended or reset it, or its responses can't be decrypted anymore.
+#[derive(Debug)]
+struct SessionLost(&'static str);
+
+impl fmt::Display for SessionLost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+/// Returns whether `err` means that the session it occurred in was lost.
+fn is_session_lost(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<SessionLost>().is_some()
+}
 
 /// A client for streaming requests to the Oak Functions Standalone server over
 /// an E2EE Noise Protocol session.
 pub struct OakFunctionsClient {
     client_session: ClientSession,
+    // Where and when the server's evidence was obtained.
+    request_metadata: RequestMetadata,
     response_stream: tonic::codec::Streaming<OakSessionResponse>,
     tx: Sender<OakSessionRequest>,
+    // Identifier of the next request sent over the open session. Handshake
+    // messages all use the default identifier 0.
+    next_request_id: u64,
+    // Maximum number of requests that `invoke_many` sends before waiting for
+    // the oldest outstanding response. Bounding this keeps the client from
+    // filling up the transport buffers while nobody is reading responses.
+    max_in_flight_requests: usize,
 }
 
 impl OakFunctionsClient {
+    /// Connects to the server at `url` and establishes a session of the given
+    /// `attestation_type`.
+    ///
+    /// The server's evidence is verified at the time of `clock`; see
+    /// [`Self::create_with_verification_time`] to verify it at a fixed time.
+    ///
+    /// `key_extractors` maps attestation IDs to the [`KeyExtractor`] used to
+    /// get the session binding key from the results of verifying the evidence
+    /// with that ID. Attestation IDs without an entry use
+    /// [`DefaultBindingKeyExtractor`].
+    ///
+    /// For peer-unidirectional sessions, the session binding of each verified
+    /// piece of evidence is checked against the handshake hash with the
+    /// extracted key before the session opens, so this fails if the attested
+    /// key isn't bound to the channel.
+    ///
+    /// If the handshake fails after the server sent its evidence, the error
+    /// carries the evidence as [`UnverifiedPeerEvidence`].
     pub async fn create<T: AsRef<str>>(
         url: T,
         attestation_type: AttestationType,
         clock: Arc<dyn Clock>,
+        key_extractors: BTreeMap<String, Box<dyn KeyExtractor>>,
+    ) -> Result<OakFunctionsClient> {
+        Self::create_with_channel_capacity(
+            url,
+            attestation_type,
+            clock,
+            key_extractors,
+            DEFAULT_REQUEST_CHANNEL_CAPACITY,
+        )
+        .await
+    }
+
+    /// Like [`Self::create`], but verifies the server's evidence as of
+    /// `verification_time` instead of the current time, e.g. to reproduce the
+    /// verification of a session at a captured moment.
+    ///
+    /// All time-dependent checks use `verification_time`, including the expiry
+    /// of the pinned root certificate.
+    pub async fn create_with_verification_time<T: AsRef<str>>(
+        url: T,
+        attestation_type: AttestationType,
+        key_extractors: BTreeMap<String, Box<dyn KeyExtractor>>,
+        verification_time: Instant,
+    ) -> Result<OakFunctionsClient> {
+        Self::connect_at(
+            url,
+            attestation_type,
+            key_extractors,
+            verification_time,
+            CONFIDENTIAL_SPACE_ROOT_CERT_PEM,
+        )
+        .await
+    }
+
+    /// Like [`Self::create_with_verification_time`], but verifies the
+    /// server's evidence against `root_certificate_pem`.
+    async fn connect_at<T: AsRef<str>>(
+        url: T,
+        attestation_type: AttestationType,
+        key_extractors: BTreeMap<String, Box<dyn KeyExtractor>>,
+        verification_time: Instant,
+        root_certificate_pem: &str,
+    ) -> Result<OakFunctionsClient> {
+        Self::connect(
+            url,
+            attestation_type,
+            Arc::new(FixedClock::at_instant(verification_time)),
+            key_extractors,
+            DEFAULT_REQUEST_CHANNEL_CAPACITY,
+            root_certificate_pem,
+        )
+        .await
+    }
+
+    /// Like [`Self::create`], but buffers up to `channel_capacity` requests
+    /// that have not been picked up by the transport yet.
+    ///
+    /// Sending a request while the buffer is full waits until there is room,
+    /// and [`Self::invoke_many`] keeps at most `channel_capacity` requests in
+    /// flight.
+    pub async fn create_with_channel_capacity<T: AsRef<str>>(
+        url: T,
+        attestation_type: AttestationType,
+        clock: Arc<dyn Clock>,
+        key_extractors: BTreeMap<String, Box<dyn KeyExtractor>>,
+        channel_capacity: usize,
     ) -> Result<OakFunctionsClient> {
+        Self::connect(
+            url,
+            attestation_type,
+            clock,
+            key_extractors,
+            channel_capacity,
+            CONFIDENTIAL_SPACE_ROOT_CERT_PEM,
+        )
+        .await
+    }
+
+    /// Like [`Self::create_with_channel_capacity`], but verifies the server's
+    /// evidence against `root_certificate_pem`.
+    async fn connect<T: AsRef<str>>(
+        url: T,
+        attestation_type: AttestationType,
+        clock: Arc<dyn Clock>,
+        key_extractors: BTreeMap<String, Box<dyn KeyExtractor>>,
+        channel_capacity: usize,
+        root_certificate_pem: &str,
+    ) -> Result<OakFunctionsClient> {
+        let (mut tx, rx) = request_channel(channel_capacity)?;
+
         let url = url.as_ref().to_owned();
+        let request_metadata = RequestMetadata {
+            uri: url.clone(),
+            request_time: Some(clock.get_time().into_timestamp()),
+        };
         let uri = Uri::from_maybe_shared(url).context("invalid URI")?;
         let channel =
             Channel::builder(uri).connect().await.context("couldn't connect via gRPC channel")?;
 
         let mut client = OakFunctionsSessionClient::new(channel);
 
-        let (mut tx, rx) = mpsc::channel(10);
-
         let mut response_stream =
             client.oak_session(rx).await.context("couldn't send stream request")?.into_inner();
 
-        let mut client_session = match attestation_type {
-            AttestationType::Unattested => {
-                println!("creating unattested client session");
-                ClientSession::create(
-                    SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN)
-                        .build(),
-                )
-                .context("failed to create unattested client session")?
-            }
+        let mut client_session = ClientSession::create(client_session_config(
+            attestation_type,
+            root_certificate_pem,
+            clock,
+            key_extractors,
+        )?)
+        .context("failed to create client session")?;
 
-            AttestationType::PeerUnidirectional => {
-                println!("creating peer unidirectional client session");
-                let reference_values = ConfidentialSpaceReferenceValues {
-                    root_certificate_pem: CONFIDENTIAL_SPACE_ROOT_CERT_PEM.to_owned(),
-                    r#container_image: None,
-                };
-                let policy = confidential_space_policy_from_reference_values(&reference_values)?;
-                let attestation_verifier =
-                    EventLogVerifier::new(vec![Box::new(policy)], clock.clone());
-
-                ClientSession::create(
-                    SessionConfig::builder(
-                        AttestationType::PeerUnidirectional,
-                        HandshakeType::NoiseNN,
-                    )
-                    .add_peer_verifier_with_key_extractor(
-                        CONFIDENTIAL_SPACE_ATTESTATION_ID.to_string(),
-                        Box::new(attestation_verifier),
-                        Box::new(DefaultBindingKeyExtractor {}),
-                    )
-                    .build(),
-                )
-                .context("Failed to create client session")?
-            }
-            AttestationType::SelfUnidirectional | AttestationType::Bidirectional => {
-                return Err(anyhow!("cannot generate client side attestation"));
-            }
+        let mut received_evidence = BTreeMap::new();
+        let handshake_result = {
+            let mut init_responses = (&mut response_stream).map(|response| {
+                let response = response
+                    .context("response was failure")?
+                    .response
+                    .context("no session response")?;
+                record_peer_evidence(&response, &mut received_evidence);
+                Ok(response)
+            });
+            let mut init_requests = (&mut tx).with(|request| {
+                future::ready(Ok::<_, mpsc::SendError>(OakSessionRequest {
+                    request: Some(request),
+                    ..Default::default()
+                }))
+            });
+            drive_client_handshake(&mut client_session, &mut init_responses, &mut init_requests)
+                .await
         };
-
-        while !client_session.is_open() {
-            let request =
-                client_session.next_init_message().context("expected client init message")?;
-            let oak_session_request = OakSessionRequest { request: Some(request) };
-            tx.try_send(oak_session_request).context("failed to send to server")?;
-            if !client_session.is_open() {
-                let response = response_stream
-                    .message()
-                    .await
-                    .context("expected a response")?
-                    .context("response was failure")?;
-                client_session
-                    .handle_init_message(response.response.context("no session response")?)
-                    .context("failed to handle init response")?;
+        if let Err(err) = handshake_result {
+            if received_evidence.is_empty() {
+                return Err(err);
             }
+            return Err(err.context(UnverifiedPeerEvidence(CollectedAttestation {
+                request_metadata: Some(request_metadata),
+                endorsed_evidence: received_evidence,
+                ..Default::default()
+            })));
         }
 
-        Ok(OakFunctionsClient { client_session, response_stream, tx })
+        Ok(OakFunctionsClient {
+            client_session,
+            request_metadata,
+            response_stream,
+            tx,
+            next_request_id: 1,
+            max_in_flight_requests: channel_capacity,
+        })
     }
 
     pub async fn invoke(&mut self, request: &[u8]) -> Result<Vec<u8>> {
-        let request = self.client_session.encrypt(request).context("failed to encrypt message")?;
-        let oak_session_request = OakSessionRequest { request: Some(request) };
+        let mut responses = self.invoke_many(&[request]).await?;
+        responses.pop().context("didn't get any response")
+    }
+
+    /// Sends all `requests` over the session without waiting for each response
+    /// before sending the next request, and returns the responses in the same
+    /// order as the requests.
+    ///
+    /// The server handles the requests of a session one at a time, in the
+    /// order in which they were sent, so responses arrive in request order as
+    /// well. Each response carries the identifier of its request, and an
+    /// out-of-order response is reported as an error, since the session
+    /// encryption state would no longer match between client and server.
+    pub async fn invoke_many<T: AsRef<[u8]>>(&mut self, requests: &[T]) -> Result<Vec<Vec<u8>>> {
+        let mut pending_request_ids = VecDeque::with_capacity(self.max_in_flight_requests);
+        let mut responses = Vec::with_capacity(requests.len());
+
+        for request in requests {
+            if pending_request_ids.len() == self.max_in_flight_requests {
+                let request_id = pending_request_ids.pop_front().expect("no pending request");
+                responses.push(self.receive_response(request_id).await?);
+            }
+            let request_id = self.send_request(request.as_ref()).await?;
+            pending_request_ids.push_back(request_id);
+        }
+        while let Some(request_id) = pending_request_ids.pop_front() {
+            responses.push(self.receive_response(request_id).await?);
+        }
+
+        Ok(responses)
+    }
 
-        self.tx.try_send(oak_session_request).context("couldn't send request to server")?;
+    /// Closes the session, consuming the client.
+    ///
+    /// Flushes any requests that are still buffered, signals the end of the
+    /// request stream to the server, and then waits for the server to end the
+    /// response stream in turn. Since the server only does so after it has
+    /// handled every request it received, a successful return means that all
+    /// requests sent over this client were processed. Any error status with
+    /// which the server ends the stream is returned, as is any response that
+    /// was never retrieved by a call to [`Self::invoke`] or
+    /// [`Self::invoke_many`].
+    pub async fn close(mut self) -> Result<()> {
+        self.tx.close().await.context("couldn't close request stream")?;
+        let unexpected_response = self
+            .response_stream
+            .message()
+            .await
+            .context("server ended the session with an error")?;
+        ensure!(
+            unexpected_response.is_none(),
+            "received unexpected response to request {} while closing the session",
+            unexpected_response.map(|response| response.request_id).unwrap_or_default()
+        );
+        Ok(())
+    }
 
+    async fn send_request(&mut self, request: &[u8]) -> Result<u64> {
+        let request = self.client_session.encrypt(request).context("failed to encrypt message")?;
+        let request_id = self.next_request_id;
+        self.next_request_id += 1;
+        self.tx
+            .send(OakSessionRequest { request: Some(request), request_id })
+            .await
+            .context(SessionLost("couldn't send request to server"))?;
+        Ok(request_id)
+    }
+
+    async fn receive_response(&mut self, expected_request_id: u64) -> Result<Vec<u8>> {
         let response = self
             .response_stream
             .message()
             .await
-            .context("error getting response")?
-            .context("didn't get any response")?;
+            .context(SessionLost("error getting response"))?
+            .context(SessionLost("didn't get any response"))?;
+        ensure!(
+            response.request_id == expected_request_id,
+            "expected response to request {expected_request_id}, got response to request {}",
+            response.request_id
+        );
 
         self.client_session
             .decrypt(response.response.context("no session response")?)
-            .context("failed to decrypt response")
+            .context(SessionLost("failed to decrypt response"))
     }
 
+    /// Collects the evidence received from the server into a
+    /// [`CollectedAttestation`].
+    ///
+    /// Returns `None` for unattested sessions, in which the server doesn't
+    /// send any evidence.
     pub fn fetch_attestation(
         &self,
         uri: String,
         clock: Arc<dyn Clock>,
+    ) -> Result<Option<CollectedAttestation>> {
+        let request_metadata =
+            RequestMetadata { uri, request_time: Some(clock.get_time().into_timestamp()) };
+        collect_peer_attestation(&self.client_session, request_metadata)
+    }
+
+    /// Writes the evidence received from the server to `path` as a binary
+    /// [`CollectedAttestation`], e.g. to check it with the attestation
+    /// verification CLI against different reference values.
+    ///
+    /// The request time recorded in the file is the time at which the session
+    /// was created. See [`UnverifiedPeerEvidence`] to get the evidence if
+    /// creating the session failed.
+    pub fn dump_peer_evidence(&self, path: impl AsRef<Path>) -> Result<()> {
+        let attestation =
+            collect_peer_attestation(&self.client_session, self.request_metadata.clone())?
+                .context("no attestation evidence: session is unattested")?;
+        write_collected_attestation(path.as_ref(), &attestation)
+    }
+
+    /// Collects the evidence exchanged in a bidirectional session, i.e.
+    /// `self_evidence` sent by this client and the evidence received from the
+    /// server, into a single [`CollectedAttestation`].
+    ///
+    /// See [`merge_bidirectional_attestation`] for the layout of the result.
+    pub fn fetch_bidirectional_attestation(
+        &self,
+        uri: String,
+        clock: Arc<dyn Clock>,
+        self_evidence: AttestationEvidence,
     ) -> Result<CollectedAttestation> {
-        let evidence = self.client_session.get_peer_attestation_evidence()?;
+        let peer_evidence = self.client_session.get_peer_attestation_evidence()?;
         let request_metadata =
             RequestMetadata { uri, request_time: Some(clock.get_time().into_timestamp()) };
-        Ok(CollectedAttestation {
-            request_metadata: Some(request_metadata),
+        merge_bidirectional_attestation(request_metadata, self_evidence, peer_evidence)
+    }
+
+    /// Returns the results of verifying the server's evidence during the
+    /// session handshake, keyed by attestation ID.
+    ///
+    /// Only successfully verified evidence is included, so the map is empty for
+    /// unattested sessions.
+    pub fn attestation_results(&self) -> Result<BTreeMap<String, AttestationResults>> {
+        self.client_session.get_peer_attestation_results()
+    }
+
+    /// Returns the handshake pattern and cipher suite that were negotiated
+    /// with the server when the session was opened.
+    pub fn session_info(&self) -> Result<SessionInfo> {
+        SessionInfo::of(&self.client_session)
+    }
+}
+
+/// The response to a request sent with [`ReattestingClient::invoke`].
+#[derive(Debug)]
+pub struct ReattestedResponse {
+    pub response: Vec<u8>,
+    /// The results of verifying the server's evidence again, keyed by
+    /// attestation ID, if the session was lost and had to be re-established
+    /// to send the request.
+    pub reattestation: Option<BTreeMap<String, AttestationResults>>,
+}
+
+type Connect = Box<dyn Fn() -> BoxFuture<'static, Result<OakFunctionsClient>> + Send + Sync>;
+
+/// An [`OakFunctionsClient`] for long-lived connections, which re-attests the
+/// server when the session is lost, e.g. because the server was restarted
+/// during a rollout.
+///
+/// When a request fails because the session was lost, the client connects
+/// again, verifying the server's evidence like [`OakFunctionsClient::create`],
+/// and sends the request once more over the new session. Since the server may
+/// have handled the request before the session was lost, only use this for
+/// idempotent requests.
+pub struct ReattestingClient {
+    client: OakFunctionsClient,
+    connect: Connect,
+}
+
+impl ReattestingClient {
+    /// Connects to the server at `url` like [`OakFunctionsClient::create`].
+    ///
+    /// `key_extractors` is called for every connection, as each session needs
+    /// its own extractors.
+    pub async fn create(
+        url: impl Into<String>,
+        attestation_type: AttestationType,
+        clock: Arc<dyn Clock>,
+        key_extractors: impl Fn() -> BTreeMap<String, Box<dyn KeyExtractor>> + Send + Sync + 'static,
+    ) -> Result<Self> {
+        let url = url.into();
+        let connect: Connect = Box::new(move || {
+            OakFunctionsClient::create(
+                url.clone(),
+                attestation_type,
+                clock.clone(),
+                key_extractors(),
+            )
+            .boxed()
+        });
+        let client = connect().await?;
+        Ok(Self { client, connect })
+    }
+
+    /// Sends `request` like [`OakFunctionsClient::invoke`], re-attesting the
+    /// server and retrying once if the session was lost.
+    pub async fn invoke(&mut self, request: &[u8]) -> Result<ReattestedResponse> {
+        match self.client.invoke(request).await {
+            Ok(response) => Ok(ReattestedResponse { response, reattestation: None }),
+            Err(err) if is_session_lost(&err) => {
+                println!("session lost ({err:#}), re-attesting the server");
+                self.client = (self.connect)()
+                    .await
+                    .context("couldn't re-attest the server after the session was lost")?;
+                let reattestation = self.client.attestation_results()?;
+                let response = self.client.invoke(request).await?;
+                Ok(ReattestedResponse { response, reattestation: Some(reattestation) })
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns the client of the current session.
+    pub fn client(&self) -> &OakFunctionsClient {
+        &self.client
+    }
+
+    /// Closes the current session; see [`OakFunctionsClient::close`].
+    pub async fn close(self) -> Result<()> {
+        self.client.close().await
+    }
+}
+
+/// Collects the server's evidence from the open `client_session`, or returns
+/// `None` if the session doesn't verify any server evidence.
+fn collect_peer_attestation(
+    client_session: &ClientSession,
+    request_metadata: RequestMetadata,
+) -> Result<Option<CollectedAttestation>> {
+    if client_session.expected_peer_attestation_ids().next().is_none() {
+        return Ok(None);
+    }
+    let evidence = client_session.get_peer_attestation_evidence()?;
+    Ok(Some(CollectedAttestation {
+        request_metadata: Some(request_metadata),
+        endorsed_evidence: evidence.evidence,
+        session_bindings: evidence.evidence_bindings,
+        handshake_hash: evidence.handshake_hash,
+    }))
+}
+
+/// Adds the evidence in `response` to `evidence`, if it is the server's
+/// attestation response, so that it is available even if verifying it fails.
+fn record_peer_evidence(
+    response: &SessionResponse,
+    evidence: &mut BTreeMap<String, EndorsedEvidence>,
+) {
+    if let Some(Response::AttestResponse(attest_response)) = &response.response {
+        evidence.extend(attest_response.endorsed_evidence.clone());
+    }
+}
+
+fn write_collected_attestation(path: &Path, attestation: &CollectedAttestation) -> Result<()> {
+    std::fs::write(path, attestation.encode_to_vec())
+        .with_context(|| format!("couldn't write evidence to {}", path.display()))
+}
+
+/// Returns the configuration of a client session of the given
+/// `attestation_type`.
+///
+/// Sessions with peer attestation verify the server's Confidential Space
+/// evidence against `root_certificate_pem`, at the time of
+/// `verification_clock`.
+fn client_session_config(
+    attestation_type: AttestationType,
+    root_certificate_pem: &str,
+    verification_clock: Arc<dyn Clock>,
+    key_extractors: BTreeMap<String, Box<dyn KeyExtractor>>,
+) -> Result<SessionConfig> {
+    match attestation_type {
+        AttestationType::Unattested => {
+            println!("creating unattested client session");
+            Ok(SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN).build())
+        }
+        AttestationType::PeerUnidirectional => {
+            println!("creating peer unidirectional client session");
+            let attestation_verifier =
+                confidential_space_verifier(root_certificate_pem, verification_clock)?;
+            let peer_verifiers: BTreeMap<String, Box<dyn AttestationVerifier>> =
+                BTreeMap::from([(
+                    CONFIDENTIAL_SPACE_ATTESTATION_ID.to_string(),
+                    Box::new(attestation_verifier) as Box<dyn AttestationVerifier>,
+                )]);
+            peer_unidirectional_config(peer_verifiers, key_extractors)
+        }
+        AttestationType::SelfUnidirectional | AttestationType::Bidirectional => {
+            Err(anyhow!("cannot generate client side attestation"))
+        }
+    }
+}
+
+/// Creates the channel carrying requests to the server, buffering up to
+/// `capacity` requests.
+fn request_channel(
+    capacity: usize,
+) -> Result<(Sender<OakSessionRequest>, mpsc::Receiver<OakSessionRequest>)> {
+    ensure!(capacity > 0, "request channel capacity must be positive");
+    Ok(mpsc::channel(capacity))
+}
+
+/// Advances `client_session` through its initialization until it is open,
+/// sending the client's init messages to `outgoing` and reading the server's
+/// init messages from `incoming`.
+///
+/// This is the client-side counterpart of
+/// `oak_functions_standalone::drive_server_handshake`.
+pub async fn drive_client_handshake<I, O>(
+    client_session: &mut ClientSession,
+    incoming: &mut I,
+    outgoing: &mut O,
+) -> Result<()>
+where
+    I: Stream<Item = Result<SessionResponse>> + Unpin,
+    O: Sink<SessionRequest> + Unpin,
+    O::Error: std::error::Error + Send + Sync + 'static,
+{
+    while !client_session.is_open() {
+        let request = client_session.next_init_message().context("expected client init message")?;
+        outgoing.send(request).await.context("failed to send to server")?;
+        if !client_session.is_open() {
+            let response = incoming.next().await.context("expected a response")??;
+            client_session
+                .handle_init_message(response)
+                .context("failed to handle init response")?;
+        }
+    }
+    Ok(())
+}
+
+/// Merges the evidence of both parties of a bidirectional session into a
+/// single [`CollectedAttestation`].
+///
+/// The fields of the result are populated as follows:
+/// - `endorsed_evidence` and `session_bindings` hold the entries of
+///   `self_evidence` keyed by [`SELF_ATTESTATION_KEY_PREFIX`] followed by the
+///   attestation ID, and the entries of `peer_evidence` keyed by
+///   [`PEER_ATTESTATION_KEY_PREFIX`] followed by the attestation ID, so that
+///   both parties can use the same attestation IDs;
+/// - `handshake_hash` is the hash of the handshake transcript, which both
+///   parties share, so both sides of the evidence must come from the same
+///   session.
+///
+/// The evidence of each party can be recovered with
+/// [`split_bidirectional_attestation`].
+pub fn merge_bidirectional_attestation(
+    request_metadata: RequestMetadata,
+    self_evidence: AttestationEvidence,
+    peer_evidence: AttestationEvidence,
+) -> Result<CollectedAttestation> {
+    ensure!(
+        self_evidence.handshake_hash == peer_evidence.handshake_hash,
+        "the self and peer evidence were collected from different sessions"
+    );
+    Ok(CollectedAttestation {
+        request_metadata: Some(request_metadata),
+        endorsed_evidence: prefix_keys(SELF_ATTESTATION_KEY_PREFIX, self_evidence.evidence)
+            .chain(prefix_keys(PEER_ATTESTATION_KEY_PREFIX, peer_evidence.evidence))
+            .collect(),
+        session_bindings: prefix_keys(SELF_ATTESTATION_KEY_PREFIX, self_evidence.evidence_bindings)
+            .chain(prefix_keys(PEER_ATTESTATION_KEY_PREFIX, peer_evidence.evidence_bindings))
+            .collect(),
+        handshake_hash: peer_evidence.handshake_hash,
+    })
+}
+
+/// Splits a [`CollectedAttestation`] created by
+/// [`merge_bidirectional_attestation`] back into the evidence of the client
+/// and the evidence of the server, in that order.
+pub fn split_bidirectional_attestation(
+    collected_attestation: &CollectedAttestation,
+) -> Result<(AttestationEvidence, AttestationEvidence)> {
+    let mut self_evidence = AttestationEvidence {
+        handshake_hash: collected_attestation.handshake_hash.clone(),
+        ..Default::default()
+    };
+    let mut peer_evidence = AttestationEvidence {
+        handshake_hash: collected_attestation.handshake_hash.clone(),
+        ..Default::default()
+    };
+    for (key, evidence) in &collected_attestation.endorsed_evidence {
+        let (is_self, attestation_id) = split_key(key)?;
+        let side = if is_self { &mut self_evidence } else { &mut peer_evidence };
+        side.evidence.insert(attestation_id.to_string(), evidence.clone());
+    }
+    for (key, binding) in &collected_attestation.session_bindings {
+        let (is_self, attestation_id) = split_key(key)?;
+        let side = if is_self { &mut self_evidence } else { &mut peer_evidence };
+        side.evidence_bindings.insert(attestation_id.to_string(), binding.clone());
+    }
+    Ok((self_evidence, peer_evidence))
+}
+
+fn prefix_keys<V>(
+    prefix: &'static str,
+    entries: BTreeMap<String, V>,
+) -> impl Iterator<Item = (String, V)> {
+    entries
+        .into_iter()
+        .map(move |(attestation_id, value)| (prefix.to_owned() + &attestation_id, value))
+}
+
+/// Returns whether `key` belongs to the client's own evidence, along with the
+/// attestation ID it refers to.
+fn split_key(key: &str) -> Result<(bool, &str)> {
+    if let Some(attestation_id) = key.strip_prefix(SELF_ATTESTATION_KEY_PREFIX) {
+        Ok((true, attestation_id))
+    } else if let Some(attestation_id) = key.strip_prefix(PEER_ATTESTATION_KEY_PREFIX) {
+        Ok((false, attestation_id))
+    } else {
+        Err(anyhow!("attestation key {key:?} doesn't belong to either party"))
+    }
+}
+
+/// Creates the configuration of a session that verifies the peer with each of
+/// `peer_verifiers`, keyed by attestation ID.
+///
+/// The binding key of each attestation is extracted with the matching entry of
+/// `key_extractors`, or with [`DefaultBindingKeyExtractor`] if there is none.
+fn peer_unidirectional_config(
+    peer_verifiers: BTreeMap<String, Box<dyn AttestationVerifier>>,
+    mut key_extractors: BTreeMap<String, Box<dyn KeyExtractor>>,
+) -> Result<SessionConfig> {
+    let mut builder =
+        SessionConfig::builder(AttestationType::PeerUnidirectional, HandshakeType::NoiseNN);
+    for (attestation_id, verifier) in peer_verifiers {
+        let key_extractor = key_extractors
+            .remove(&attestation_id)
+            .unwrap_or_else(|| Box::new(DefaultBindingKeyExtractor {}));
+        builder =
+            builder.add_peer_verifier_with_key_extractor(attestation_id, verifier, key_extractor);
+    }
+    ensure!(
+        key_extractors.is_empty(),
+        "key extractors given for unknown attestation IDs: {:?}",
+        key_extractors.keys().collect::<Vec<_>>()
+    );
+    Ok(builder.build())
+}
+
+/// Creates a verifier for Confidential Space evidence chaining to
+/// `root_certificate_pem`.
+///
+/// All time-dependent checks, including the root certificate expiry and the
+/// validity window of the attestation token, use `clock`, so that tests can
+/// pin the verification time.
+fn confidential_space_verifier(
+    root_certificate_pem: &str,
+    clock: Arc<dyn Clock>,
+) -> Result<ClockSkewDiagnosingVerifier> {
+    check_root_certificate_expiry(root_certificate_pem, clock.get_time())?;
+    let reference_values = ConfidentialSpaceReferenceValues {
+        root_certificate_pem: root_certificate_pem.to_owned(),
+        r#container_image: None,
+    };
+    let policy = confidential_space_policy_from_reference_values(&reference_values)?;
+    Ok(ClockSkewDiagnosingVerifier {
+        inner: EventLogVerifier::new(vec![Box::new(policy)], clock.clone()),
+        clock,
+    })
+}
+
+/// Verifies Confidential Space evidence with `inner`, and points out when a
+/// failure is likely caused by a misconfigured clock: a clock that is far off
+/// otherwise only shows up as an invalid attestation token.
+struct ClockSkewDiagnosingVerifier {
+    inner: EventLogVerifier,
+    clock: Arc<dyn Clock>,
+}
+
+impl AttestationVerifier for ClockSkewDiagnosingVerifier {
+    fn verify(
+        &self,
+        evidence: &Evidence,
+        endorsements: &Endorsements,
+    ) -> Result<AttestationResults> {
+        self.inner.verify(evidence, endorsements).map_err(|err| {
+            let now = self.clock.get_time();
+            let skew = endorsements
+                .events
+                .iter()
+                .filter_map(attestation_token_claims)
+                .filter_map(|claims| token_clock_skew(&claims, now))
+                .max();
+            match skew {
+                Some(skew) if skew > CLOCK_SKEW_DIAGNOSTIC_THRESHOLD => err.context(format!(
+                    "verifier clock appears skewed by {} hours: current time is {now}",
+                    skew.into_seconds() / (60 * 60)
+                )),
+                _ => err,
+            }
+        })
+    }
+}
+
+/// Returns the unverified claims of the attestation token in `endorsement`, or
+/// `None` if it isn't a Confidential Space endorsement.
+fn attestation_token_claims(endorsement: &oak_proto_rust::oak::Variant) -> Option<Claims> {
+    let endorsement = ConfidentialSpaceEndorsement::try_from(endorsement).ok()?;
+    let token = jwt::Token::<Header, Claims, _>::parse_unverified(&endorsement.jwt_token).ok()?;
+    let (_, claims): (Header, Claims) = token.into();
+    Some(claims)
+}
+
+/// Returns how far `now` lies outside the window between the issue time and
+/// the expiry of the token with `claims`, or `None` if it lies inside.
+fn token_clock_skew(claims: &Claims, now: Instant) -> Option<Duration> {
+    if now < claims.issued_at {
+        Some(claims.issued_at - now)
+    } else if now > claims.not_after {
+        Some(now - claims.not_after)
+    } else {
+        None
+    }
+}
+
+/// Checks that the pinned root certificate has not expired at [now], so that
+/// an outdated certificate is reported up front instead of surfacing as a
+/// handshake failure. Prints a warning if the certificate expires soon.
+fn check_root_certificate_expiry(root_certificate_pem: &str, now: Instant) -> Result<()> {
+    let root_certificate = Certificate::from_pem(root_certificate_pem)
+        .map_err(anyhow::Error::msg)
+        .context("couldn't parse Confidential Space root certificate")?;
+    let not_after = certificate_not_after(&root_certificate)?;
+
+    if now > not_after {
+        return Err(anyhow!(
+            "the pinned Confidential Space root certificate expired at {not_after} (current time: \
+             {now}); update CONFIDENTIAL_SPACE_ROOT_CERT_PEM"
+        ));
+    }
+    if now + ROOT_CERTIFICATE_EXPIRY_WARNING_PERIOD > not_after {
+        println!(
+            "warning: the pinned Confidential Space root certificate expires soon, at {not_after}"
+        );
+    }
+    Ok(())
+}
+
+fn certificate_not_after(certificate: &Certificate) -> Result<Instant> {
+    let not_after_nanos =
+        certificate.tbs_certificate.validity.not_after.to_unix_duration().as_nanos();
+    Ok(Instant::from_unix_nanos(
+        i128::try_from(not_after_nanos).context("certificate expiry out of range")?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::Ipv4Addr,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use futures::{stream::BoxStream, FutureExt};
+    use oak_attestation_types::{attester::Attester, endorser::Endorser};
+    use oak_crypto::verifier::Verifier;
+    use oak_file_utils::data_path;
+    use oak_grpc::oak::functions::standalone::oak_functions_session_server::{
+        OakFunctionsSession, OakFunctionsSessionServer,
+    };
+    use oak_proto_rust::oak::{
+        attestation::v1::{
+            AttestationResults, ConfidentialSpaceEndorsement, Endorsements, Event, EventLog,
+            Evidence, RootLayerEvidence, SessionBindingPublicKeyData,
+        },
+        session::v1::{AttestResponse, HandshakeResponse, SessionBinding},
+    };
+    use oak_session::{session_binding::SessionBinder, ServerSession};
+    use oak_time::make_instant;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+
+    use super::*;
+
+    struct PassingVerifier;
+
+    impl AttestationVerifier for PassingVerifier {
+        fn verify(&self, _: &Evidence, _: &Endorsements) -> Result<AttestationResults> {
+            Ok(AttestationResults::default())
+        }
+    }
+
+    /// Key extractor that counts how often it is used and never finds a key.
+    #[derive(Clone, Default)]
+    struct CountingKeyExtractor {
+        extraction_count: Arc<AtomicUsize>,
+    }
+
+    impl KeyExtractor for CountingKeyExtractor {
+        fn extract_verifying_key(&self, _: &AttestationResults) -> Result<Box<dyn Verifier>> {
+            self.extraction_count.fetch_add(1, Ordering::SeqCst);
+            Err(anyhow!("no key"))
+        }
+    }
+
+    fn create_binding_verifier(config: &SessionConfig, attestation_id: &str) -> Result<()> {
+        config.attestation_handler_config.peer_verifiers[attestation_id]
+            .binding_verifier_provider
+            .create_session_binding_verifier(&AttestationResults::default())
+            .map(|_| ())
+    }
+
+    /// Returns evidence for `attestation_id` that is distinguishable by
+    /// `report`.
+    fn attestation_evidence(attestation_id: &str, report: &[u8]) -> AttestationEvidence {
+        let endorsed_evidence = EndorsedEvidence {
+            evidence: Some(Evidence {
+                root_layer: Some(RootLayerEvidence {
+                    remote_attestation_report: report.to_vec(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            endorsements: Some(Endorsements::default()),
+        };
+        AttestationEvidence {
+            evidence: BTreeMap::from([(attestation_id.to_string(), endorsed_evidence)]),
+            evidence_bindings: BTreeMap::from([(
+                attestation_id.to_string(),
+                SessionBinding { binding: report.to_vec() },
+            )]),
+            handshake_hash: b"handshake hash".to_vec(),
+        }
+    }
+
+    // Matches the "eat_nonce" claim of the Confidential Space test token.
+    const BINDING_KEY_BYTES: [u8; 32] = [
+        0xad, 0x57, 0x5f, 0x38, 0x17, 0x7e, 0x11, 0x4a, 0x48, 0x2d, 0x5a, 0x24, 0x71, 0x28, 0x73,
+        0x64, 0x27, 0x41, 0x53, 0x48, 0x51, 0x5b, 0x76, 0x78, 0x47, 0x11, 0x12, 0x43, 0x01, 0x61,
+        0x64, 0x66,
+    ];
+
+    fn read_gcp_testdata(file: &str) -> String {
+        std::fs::read_to_string(data_path(format!("oak_attestation_gcp/testdata/{file}"))).unwrap()
+    }
+
+    /// Returns evidence and endorsements binding [`BINDING_KEY_BYTES`] with the
+    /// Confidential Space test token, which is valid from
+    /// 2025-07-01T17:31:32Z until 2025-07-01T18:31:32Z.
+    fn confidential_space_evidence() -> (Evidence, Endorsements) {
+        let event = Event {
+            tag: "session_binding_key".to_string(),
+            event: Some(prost_types::Any {
+                type_url: "type.googleapis.com/oak.attestation.v1.SessionBindingPublicKeyData"
+                    .to_string(),
+                value: SessionBindingPublicKeyData {
+                    session_binding_public_key: BINDING_KEY_BYTES.to_vec(),
+                }
+                .encode_to_vec(),
+            }),
+        };
+        let evidence = Evidence {
+            event_log: Some(EventLog { encoded_events: vec![event.encode_to_vec()] }),
+            ..Default::default()
+        };
+        let endorsement = ConfidentialSpaceEndorsement {
+            jwt_token: read_gcp_testdata("valid_token.jwt"),
+            workload_endorsement: None,
+        };
+        let endorsements = Endorsements { events: vec![endorsement.into()], ..Default::default() };
+        (evidence, endorsements)
+    }
+
+    fn verify_confidential_space_evidence_at(now: Instant) -> Result<AttestationResults> {
+        let verifier = confidential_space_verifier(
+            &read_gcp_testdata("root_ca_cert.pem"),
+            Arc::new(FixedClock::at_instant(now)),
+        )?;
+        let (evidence, endorsements) = confidential_space_evidence();
+        verifier.verify(&evidence, &endorsements)
+    }
+
+    #[test]
+    fn test_confidential_space_verifier_within_token_validity() {
+        let result = verify_confidential_space_evidence_at(make_instant!("2025-07-01T18:00:00Z"));
+
+        assert!(result.is_ok(), "verification failed: {:?}", result.err());
+    }
+
+    #[test]
+    fn test_confidential_space_verifier_before_token_validity() {
+        let result = verify_confidential_space_evidence_at(make_instant!("2025-07-01T17:00:00Z"));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_confidential_space_verifier_after_token_validity() {
+        let result = verify_confidential_space_evidence_at(make_instant!("2025-07-01T18:32:00Z"));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_confidential_space_verifier_reports_clock_skew() {
+        let result = verify_confidential_space_evidence_at(make_instant!("2025-07-03T18:00:00Z"));
+
+        let err = format!("{:#}", result.unwrap_err());
+        assert!(err.contains("verifier clock appears skewed by 47 hours"), "{err}");
+    }
+
+    #[test]
+    fn test_token_clock_skew() {
+        let claims = Claims {
+            issued_at: make_instant!("2025-07-01T17:30:00Z"),
+            not_after: make_instant!("2025-07-01T18:30:00Z"),
+            ..Default::default()
+        };
+
+        assert_eq!(token_clock_skew(&claims, make_instant!("2025-07-01T18:00:00Z")), None);
+        assert_eq!(
+            token_clock_skew(&claims, make_instant!("2025-07-01T14:30:00Z")),
+            Some(Duration::from_seconds(3 * 60 * 60))
+        );
+        assert_eq!(
+            token_clock_skew(&claims, make_instant!("2025-07-02T18:30:00Z")),
+            Some(Duration::from_seconds(24 * 60 * 60))
+        );
+    }
+
+    #[test]
+    fn test_confidential_space_verifier_rejects_expired_root_certificate() {
+        // The test root certificate is valid for ten years from 2025-01-01.
+        let result = verify_confidential_space_evidence_at(make_instant!("2035-01-02T00:00:00Z"));
+
+        assert!(result.unwrap_err().to_string().contains("root certificate expired"));
+    }
+
+    fn root_certificate_not_after() -> Instant {
+        certificate_not_after(&Certificate::from_pem(CONFIDENTIAL_SPACE_ROOT_CERT_PEM).unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_root_certificate_valid() {
+        let now = root_certificate_not_after()
+            - ROOT_CERTIFICATE_EXPIRY_WARNING_PERIOD
+            - Duration::from_seconds(60);
+
+        assert!(check_root_certificate_expiry(CONFIDENTIAL_SPACE_ROOT_CERT_PEM, now).is_ok());
+    }
+
+    #[test]
+    fn test_root_certificate_near_expiry() {
+        let now = root_certificate_not_after() - Duration::from_seconds(60);
+
+        assert!(check_root_certificate_expiry(CONFIDENTIAL_SPACE_ROOT_CERT_PEM, now).is_ok());
+    }
+
+    #[test]
+    fn test_root_certificate_expired() {
+        let now = root_certificate_not_after() + Duration::from_seconds(1);
+
+        let result = check_root_certificate_expiry(CONFIDENTIAL_SPACE_ROOT_CERT_PEM, now);
+
+        assert!(result.unwrap_err().to_string().contains("root certificate expired"));
+    }
+
+    #[test]
+    fn test_root_certificate_invalid() {
+        assert!(check_root_certificate_expiry("not a certificate", Instant::UNIX_EPOCH).is_err());
+    }
+
+    #[test]
+    fn test_peer_verifiers_use_key_extractor_for_attestation_id() {
+        let first_extractor = CountingKeyExtractor::default();
+        let second_extractor = CountingKeyExtractor::default();
+        let peer_verifiers: BTreeMap<String, Box<dyn AttestationVerifier>> = BTreeMap::from([
+            ("first".to_string(), Box::new(PassingVerifier) as Box<dyn AttestationVerifier>),
+            ("second".to_string(), Box::new(PassingVerifier) as Box<dyn AttestationVerifier>),
+            ("default".to_string(), Box::new(PassingVerifier) as Box<dyn AttestationVerifier>),
+        ]);
+        let key_extractors: BTreeMap<String, Box<dyn KeyExtractor>> = BTreeMap::from([
+            ("first".to_string(), Box::new(first_extractor.clone()) as Box<dyn KeyExtractor>),
+            ("second".to_string(), Box::new(second_extractor.clone()) as Box<dyn KeyExtractor>),
+        ]);
+
+        let config = peer_unidirectional_config(peer_verifiers, key_extractors).unwrap();
+
+        assert!(create_binding_verifier(&config, "first").is_err());
+        assert_eq!(first_extractor.extraction_count.load(Ordering::SeqCst), 1);
+        assert_eq!(second_extractor.extraction_count.load(Ordering::SeqCst), 0);
+
+        assert!(create_binding_verifier(&config, "second").is_err());
+        assert_eq!(first_extractor.extraction_count.load(Ordering::SeqCst), 1);
+        assert_eq!(second_extractor.extraction_count.load(Ordering::SeqCst), 1);
+
+        // The default extractor looks for the session binding public key.
+        let result = create_binding_verifier(&config, "default");
+        assert!(format!("{:#}", result.unwrap_err()).contains("session binding public key"));
+        assert_eq!(first_extractor.extraction_count.load(Ordering::SeqCst), 1);
+        assert_eq!(second_extractor.extraction_count.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_key_extractor_for_unknown_attestation_id() {
+        let peer_verifiers: BTreeMap<String, Box<dyn AttestationVerifier>> = BTreeMap::from([(
+            "known".to_string(),
+            Box::new(PassingVerifier) as Box<dyn AttestationVerifier>,
+        )]);
+        let key_extractors: BTreeMap<String, Box<dyn KeyExtractor>> = BTreeMap::from([(
+            "unknown".to_string(),
+            Box::new(CountingKeyExtractor::default()) as Box<dyn KeyExtractor>,
+        )]);
+
+        let result = peer_unidirectional_config(peer_verifiers, key_extractors);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_full_request_channel_applies_backpressure() {
+        let (mut tx, mut rx) = request_channel(1).expect("couldn't create request channel");
+        while tx.try_send(OakSessionRequest::default()).is_ok() {}
+
+        // Sending on the full channel waits instead of failing...
+        let mut context = std::task::Context::from_waker(futures::task::noop_waker_ref());
+        let mut send = tx.send(OakSessionRequest { request_id: 1, ..Default::default() });
+        assert!(send.poll_unpin(&mut context).is_pending());
+
+        // ...and completes once the transport picks up the buffered requests.
+        while rx.next().now_or_never().flatten().is_some_and(|request| request.request_id == 0) {}
+        assert!(matches!(send.poll_unpin(&mut context), std::task::Poll::Ready(Ok(()))));
+        assert_eq!(rx.next().now_or_never().flatten().map(|request| request.request_id), Some(1));
+    }
+
+    #[test]
+    fn test_request_channel_without_capacity() {
+        assert!(request_channel(0).is_err());
+    }
+
+    /// Opens a session between a client and a server in the same process,
+    /// returning the client side.
+    fn open_session(
+        client_config: SessionConfig,
+        server_config: SessionConfig,
+    ) -> Result<ClientSession> {
+        let mut client_session = ClientSession::create(client_config)?;
+        let mut server_session = ServerSession::create(server_config)?;
+
+        while !client_session.is_open() {
+            let request = client_session.next_init_message()?;
+            server_session.handle_init_message(request)?;
+            if !client_session.is_open() {
+                let response = server_session.next_init_message()?;
+                client_session.handle_init_message(response)?;
+            }
+        }
+        Ok(client_session)
+    }
+
+    /// Opens an unattested session between a client and a server in the same
+    /// process, returning the client side.
+    fn open_unattested_session() -> ClientSession {
+        open_session(
+            SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN).build(),
+            SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN).build(),
+        )
+        .expect("couldn't open session")
+    }
+
+    /// Attester that always returns the same evidence.
+    struct StaticAttester(Evidence);
+
+    impl Attester for StaticAttester {
+        fn extend(&mut self, _: &[u8]) -> Result<()> {
+            Err(anyhow!("static evidence cannot be extended"))
+        }
+
+        fn quote(&self) -> Result<Evidence> {
+            Ok(self.0.clone())
+        }
+    }
+
+    /// Endorser that always returns the same endorsements.
+    struct StaticEndorser(Endorsements);
+
+    impl Endorser for StaticEndorser {
+        fn endorse(&self, _: Option<&Evidence>) -> Result<Endorsements> {
+            Ok(self.0.clone())
+        }
+    }
+
+    /// Session binder and verifier for which every binding is valid, since the
+    /// private key of [`BINDING_KEY_BYTES`] isn't available to tests.
+    struct AcceptingBinding;
+
+    impl SessionBinder for AcceptingBinding {
+        fn bind(&self, _: &[u8]) -> Vec<u8> {
+            Vec::new()
+        }
+    }
+
+    impl Verifier for AcceptingBinding {
+        fn verify(&self, _: &[u8], _: &[u8]) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    impl KeyExtractor for AcceptingBinding {
+        fn extract_verifying_key(&self, _: &AttestationResults) -> Result<Box<dyn Verifier>> {
+            Ok(Box::new(AcceptingBinding))
+        }
+    }
+
+    /// Session binder and verifier that only accept a binding equal to the
+    /// given bytes, to simulate a binding made with a different key.
+    struct FixedBinding(&'static [u8]);
+
+    impl SessionBinder for FixedBinding {
+        fn bind(&self, _: &[u8]) -> Vec<u8> {
+            self.0.to_vec()
+        }
+    }
+
+    impl Verifier for FixedBinding {
+        fn verify(&self, _: &[u8], signature: &[u8]) -> Result<()> {
+            ensure!(signature == self.0, "binding doesn't match");
+            Ok(())
+        }
+    }
+
+    impl KeyExtractor for FixedBinding {
+        fn extract_verifying_key(&self, _: &AttestationResults) -> Result<Box<dyn Verifier>> {
+            Ok(Box::new(FixedBinding(self.0)))
+        }
+    }
+
+    /// Returns the config of a server presenting the Confidential Space test
+    /// evidence, which it binds to the session with `binder`.
+    fn confidential_space_server_config(binder: Box<dyn SessionBinder>) -> SessionConfig {
+        let (evidence, endorsements) = confidential_space_evidence();
+        SessionConfig::builder(AttestationType::SelfUnidirectional, HandshakeType::NoiseNN)
+            .add_self_attester(
+                CONFIDENTIAL_SPACE_ATTESTATION_ID.to_string(),
+                Box::new(StaticAttester(evidence)),
+            )
+            .add_self_endorser(
+                CONFIDENTIAL_SPACE_ATTESTATION_ID.to_string(),
+                Box::new(StaticEndorser(endorsements)),
+            )
+            .add_session_binder(CONFIDENTIAL_SPACE_ATTESTATION_ID.to_string(), binder)
+            .build()
+    }
+
+    /// Opens a session with a server presenting the Confidential Space test
+    /// evidence, which the client verifies as of `verification_time`.
+    fn open_confidential_space_session(verification_time: Instant) -> Result<ClientSession> {
+        open_confidential_space_session_with_binding(
+            verification_time,
+            Box::new(AcceptingBinding),
+            Box::new(AcceptingBinding),
+        )
+    }
+
+    /// Like [`open_confidential_space_session`], but the server binds its
+    /// evidence to the session with `binder`, and the client checks that
+    /// binding with the key from `key_extractor`.
+    fn open_confidential_space_session_with_binding(
+        verification_time: Instant,
+        binder: Box<dyn SessionBinder>,
+        key_extractor: Box<dyn KeyExtractor>,
+    ) -> Result<ClientSession> {
+        let server_config = confidential_space_server_config(binder);
+        let client_config = client_session_config(
+            AttestationType::PeerUnidirectional,
+            &read_gcp_testdata("root_ca_cert.pem"),
+            Arc::new(FixedClock::at_instant(verification_time)),
+            BTreeMap::from([(CONFIDENTIAL_SPACE_ATTESTATION_ID.to_string(), key_extractor)]),
+        )?;
+        open_session(client_config, server_config)
+    }
+
+    #[test]
+    fn test_session_verified_at_time_of_evidence() {
+        let client_session = open_confidential_space_session(make_instant!("2025-07-01T18:00:00Z"))
+            .expect("couldn't open session");
+
+        assert!(client_session
+            .get_peer_attestation_results()
+            .unwrap()
+            .contains_key(CONFIDENTIAL_SPACE_ATTESTATION_ID));
+    }
+
+    #[test]
+    fn test_session_verified_after_evidence_expired() {
+        // The test token expired long before the test root certificate does.
+        let result = open_confidential_space_session(make_instant!("2025-07-02T18:00:00Z"));
+
+        assert!(result.is_err());
+    }
+
+    /// gRPC service that opens sessions presenting the Confidential Space test
+    /// evidence, and then closes the response stream without handling any
+    /// requests.
+    struct ConfidentialSpaceHandshakeService;
+
+    #[tonic::async_trait]
+    impl OakFunctionsSession for ConfidentialSpaceHandshakeService {
+        type OakSessionStream = BoxStream<'static, Result<OakSessionResponse, tonic::Status>>;
+
+        async fn oak_session(
+            &self,
+            request: tonic::Request<tonic::Streaming<OakSessionRequest>>,
+        ) -> Result<tonic::Response<Self::OakSessionStream>, tonic::Status> {
+            let mut server_session =
+                ServerSession::create(confidential_space_server_config(Box::new(AcceptingBinding)))
+                    .map_err(|err| tonic::Status::internal(format!("{err:?}")))?;
+            let mut requests = request.into_inner();
+            let (mut tx, rx) = mpsc::channel(1);
+            tokio::spawn(async move {
+                while let Some(Ok(OakSessionRequest { request: Some(request), .. })) =
+                    requests.next().await
+                {
+                    if server_session.handle_init_message(request).is_err()
+                        || server_session.is_open()
+                    {
+                        break;
+                    }
+                    let response =
+                        server_session.next_init_message().expect("expected server init message");
+                    let response = OakSessionResponse { response: Some(response), request_id: 0 };
+                    if tx.send(Ok(response)).await.is_err() {
+                        break;
+                    }
+                }
+            });
+            Ok(tonic::Response::new(rx.boxed()))
+        }
+    }
+
+    /// Starts a [`ConfidentialSpaceHandshakeService`] on a new port, returning
+    /// its URL.
+    async fn start_confidential_space_server() -> String {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(OakFunctionsSessionServer::new(ConfidentialSpaceHandshakeService))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        url
+    }
+
+    #[tokio::test]
+    async fn test_create_with_verification_time() {
+        let url = start_confidential_space_server().await;
+        let root_certificate_pem = read_gcp_testdata("root_ca_cert.pem");
+        let create_at = |verification_time| {
+            OakFunctionsClient::connect_at(
+                url.clone(),
+                AttestationType::PeerUnidirectional,
+                BTreeMap::from([(
+                    CONFIDENTIAL_SPACE_ATTESTATION_ID.to_string(),
+                    Box::new(AcceptingBinding) as Box<dyn KeyExtractor>,
+                )]),
+                verification_time,
+                &root_certificate_pem,
+            )
+        };
+
+        // A day after the test token expired.
+        let err = create_at(make_instant!("2025-07-02T18:00:00Z")).await.err().unwrap();
+        assert!(err.downcast_ref::<UnverifiedPeerEvidence>().is_some());
+
+        let client = create_at(make_instant!("2025-07-01T18:00:00Z")).await.unwrap();
+        assert_eq!(
+            client.request_metadata.request_time,
+            Some(make_instant!("2025-07-01T18:00:00Z").into_timestamp())
+        );
+    }
+
+    #[test]
+    fn test_session_with_matching_binding() {
+        let result = open_confidential_space_session_with_binding(
+            make_instant!("2025-07-01T18:00:00Z"),
+            Box::new(FixedBinding(b"binding")),
+            Box::new(FixedBinding(b"binding")),
+        );
+
+        result.expect("couldn't open session");
+    }
+
+    #[test]
+    fn test_session_with_mismatched_binding() {
+        // The evidence verifies, but it's bound to the session with another key.
+        let result = open_confidential_space_session_with_binding(
+            make_instant!("2025-07-01T18:00:00Z"),
+            Box::new(FixedBinding(b"other binding")),
+            Box::new(FixedBinding(b"binding")),
+        );
+
+        let err = result.err().expect("opened a session with a mismatched binding");
+        assert!(format!("{:#}", err).contains("binding doesn't match"));
+    }
+
+    #[test]
+    fn test_session_info_before_session_is_open() {
+        let client_session = ClientSession::create(
+            SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN).build(),
+        )
+        .expect("couldn't create client session");
+
+        assert!(SessionInfo::of(&client_session).is_err());
+    }
+
+    #[test]
+    fn test_session_info_reports_negotiated_handshake() {
+        let client_session = open_unattested_session();
+
+        assert_eq!(
+            SessionInfo::of(&client_session).expect("couldn't get session info"),
+            SessionInfo {
+                handshake_type: HandshakeType::NoiseNN,
+                noise_protocol_name: "Noise_NN_P256_AESGCM_SHA256",
+            }
+        );
+    }
+
+    #[test]
+    fn test_collect_peer_attestation_unattested() {
+        let client_session = open_unattested_session();
+
+        let attestation = collect_peer_attestation(
+            &client_session,
+            RequestMetadata { uri: "http://test".to_string(), request_time: None },
+        )
+        .expect("couldn't collect attestation");
+
+        assert_eq!(attestation, None);
+    }
+
+    #[test]
+    fn test_is_session_lost() {
+        let lost: Result<()> =
+            Err(anyhow!("stream reset")).context(SessionLost("error getting response"));
+        let lost = lost.unwrap_err();
+
+        assert!(is_session_lost(&lost));
+        assert_eq!(lost.to_string(), "error getting response");
+        assert!(!is_session_lost(&anyhow!("failed to encrypt message")));
+    }
+
+    #[test]
+    fn test_record_peer_evidence() {
+        let endorsed_evidence = BTreeMap::from([(
+            "test".to_string(),
+            EndorsedEvidence { evidence: Some(Evidence::default()), endorsements: None },
+        )]);
+        let attest_response = SessionResponse {
+            response: Some(Response::AttestResponse(AttestResponse {
+                endorsed_evidence: endorsed_evidence.clone(),
+                ..Default::default()
+            })),
+        };
+        let handshake_response = SessionResponse {
+            response: Some(Response::HandshakeResponse(HandshakeResponse::default())),
+        };
+
+        let mut evidence = BTreeMap::new();
+        record_peer_evidence(&attest_response, &mut evidence);
+        record_peer_evidence(&handshake_response, &mut evidence);
+
+        assert_eq!(evidence, endorsed_evidence);
+    }
+
+    #[test]
+    fn test_dump_unverified_peer_evidence() {
+        let evidence = attestation_evidence("test", b"server");
+        let attestation = CollectedAttestation {
+            request_metadata: Some(RequestMetadata {
+                uri: "http://test".to_string(),
+                request_time: None,
+            }),
+            endorsed_evidence: evidence.evidence,
+            ..Default::default()
+        };
+        let err =
+            anyhow!("verification failed").context(UnverifiedPeerEvidence(attestation.clone()));
+        let path = tempfile::NamedTempFile::new().expect("couldn't create file").into_temp_path();
+
+        err.downcast_ref::<UnverifiedPeerEvidence>()
+            .expect("no evidence in error")
+            .dump(&path)
+            .expect("couldn't dump evidence");
+
+        let dumped = CollectedAttestation::decode(
+            std::fs::read(&path).expect("couldn't read evidence").as_slice(),
+        )
+        .expect("couldn't decode evidence");
+        assert_eq!(dumped, attestation);
+    }
+
+    #[test]
+    fn test_bidirectional_attestation_round_trip() {
+        let request_metadata =
+            RequestMetadata { uri: "http://test".to_string(), request_time: None };
+
+        // Both parties use the same attestation ID.
+        let collected_attestation = merge_bidirectional_attestation(
+            request_metadata.clone(),
+            attestation_evidence("test", b"client"),
+            attestation_evidence("test", b"server"),
+        )
+        .expect("couldn't merge evidence");
+        let collected_attestation =
+            CollectedAttestation::decode(collected_attestation.encode_to_vec().as_slice())
+                .expect("couldn't decode collected attestation");
+
+        assert_eq!(collected_attestation.request_metadata, Some(request_metadata));
+        assert_eq!(
+            collected_attestation.endorsed_evidence.keys().collect::<Vec<_>>(),
+            ["peer/test", "self/test"]
+        );
+        let (self_evidence, peer_evidence) =
+            split_bidirectional_attestation(&collected_attestation).expect("couldn't split");
+        assert_eq!(self_evidence, attestation_evidence("test", b"client"));
+        assert_eq!(peer_evidence, attestation_evidence("test", b"server"));
+    }
+
+    #[test]
+    fn test_bidirectional_attestation_from_different_sessions() {
+        let mut self_evidence = attestation_evidence("test", b"client");
+        self_evidence.handshake_hash = b"other handshake hash".to_vec();
+
+        let result = merge_bidirectional_attestation(
+            RequestMetadata::default(),
+            self_evidence,
+            attestation_evidence("test", b"server"),
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_split_unidirectional_attestation() {
+        let evidence = attestation_evidence("test", b"server");
+        let collected_attestation = CollectedAttestation {
+            request_metadata: None,
             endorsed_evidence: evidence.evidence,
             session_bindings: evidence.evidence_bindings,
             handshake_hash: evidence.handshake_hash,
-        })
+        };
+
+        assert!(split_bidirectional_attestation(&collected_attestation).is_err());
     }
 }
@@ -16,15 +16,14 @@
 
 //! Sends a string to the enclave app and prints the return.
 
-use std::{fs, sync::Arc};
+use std::{collections::BTreeMap, sync::Arc};
 
 use anyhow::Context;
 use clap::{Parser, ValueEnum};
-use oak_functions_standalone_client_lib::OakFunctionsClient;
+use oak_functions_standalone_client_lib::{OakFunctionsClient, UnverifiedPeerEvidence};
 use oak_session::attestation::AttestationType;
 use oak_time::Clock;
 use oak_time_std::clock::FrozenSystemTimeClock;
-use prost::Message;
 
 // Supported AttestationTypes for Google Cloud Platform, derived from
 // oak/oak_session/src/attestation.rs.
@@ -52,7 +51,7 @@ pub struct Opt {
 
     #[arg(
         long,
-        help = "Path to save the attestation evidence to. If not specified, the attestation is not saved."
+        help = "Path to save the attestation evidence to, even if it fails verification. If not specified, the attestation is not saved."
     )]
     attestation_evidence_path: Option<String>,
 }
@@ -69,14 +68,28 @@ async fn main() -> anyhow::Result<()> {
 
     let clock: Arc<dyn Clock> = Arc::new(FrozenSystemTimeClock::default());
 
-    let mut client = OakFunctionsClient::create(&opt.uri, attestation_type, clock.clone())
-        .await
-        .context("couldn't connect to server")?;
+    let mut client = match OakFunctionsClient::create(
+        &opt.uri,
+        attestation_type,
+        clock,
+        BTreeMap::new(),
+    )
+    .await
+    {
+        Ok(client) => client,
+        Err(err) => {
+            if let (Some(path), Some(evidence)) =
+                (&opt.attestation_evidence_path, err.downcast_ref::<UnverifiedPeerEvidence>())
+            {
+                evidence.dump(path)?;
+                eprintln!("Saved the unverified attestation evidence to {path}");
+            }
+            return Err(err.context("couldn't connect to server"));
+        }
+    };
 
     if let Some(path) = opt.attestation_evidence_path {
-        let attestation =
-            client.fetch_attestation(opt.uri, clock).context("unable to parse attestation")?;
-        fs::write(path, attestation.encode_to_vec())?;
+        client.dump_peer_evidence(path).context("unable to save attestation")?;
     }
 
     println!("Request: {}", opt.request);
@@ -86,5 +99,5 @@ async fn main() -> anyhow::Result<()> {
     .context("response is not valid UTF-8")?;
     println!("Response: {decrypted_response}");
 
-    Ok(())
+    client.close().await.context("couldn't close session")
 }
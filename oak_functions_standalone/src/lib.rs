@@ -19,6 +19,7 @@
 use std::{error::Error, pin::Pin, sync::Arc};
 
 use anyhow::Context;
+use futures::{channel::mpsc, future, Sink, SinkExt};
 use oak_attestation::public_key::{PublicKeyAttester, PublicKeyEndorser};
 use oak_attestation_types::{attester::Attester, endorser::Endorser};
 use oak_functions_service::{instance::OakFunctionsInstance, Handler};
@@ -35,6 +36,7 @@ use oak_proto_rust::{
             ExtendNextLookupDataRequest, FinishNextLookupDataRequest, InitializeRequest,
             LookupDataChunk, ReserveRequest,
         },
+        session::v1::{SessionRequest, SessionResponse},
     },
 };
 use oak_session::{
@@ -50,6 +52,9 @@ use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_stream::{Stream, StreamExt};
 use tonic::{codec::CompressionEncoding, transport::server::Connected};
 
+/// Capacity of the channel carrying responses to the client.
+const RESPONSE_CHANNEL_CAPACITY: usize = 10;
+
 // Arguements to start up the Oak Functions Session Service.
 // Note that lookup data and attestation are optional features for the service.
 // The AttestaionArgs help construct a SelfUnidirectional attestation type.
@@ -195,22 +200,15 @@ where
         &self,
         request: tonic::Request<tonic::Streaming<OakSessionRequest>>,
     ) -> Result<tonic::Response<Self::OakSessionStream>, tonic::Status> {
-        let mut server_session: ServerSession =
-            match self.attestation_generation.attestation_type {
-                AttestationType::Unattested => ServerSession::create(
-                    SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN)
-                        .build(),
-                )
-                .map_err(|e| {
-                    tonic::Status::internal(format!(
-                        "error creating Unattested server session: {e:?}"
-                    ))
-                }),
-                AttestationType::SelfUnidirectional => ServerSession::create(
-                    SessionConfig::builder(
-                        AttestationType::SelfUnidirectional,
-                        HandshakeType::NoiseNN,
-                    )
+        let server_session: ServerSession = match self.attestation_generation.attestation_type {
+            AttestationType::Unattested => ServerSession::create(
+                SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN).build(),
+            )
+            .map_err(|e| {
+                tonic::Status::internal(format!("error creating Unattested server session: {e:?}"))
+            }),
+            AttestationType::SelfUnidirectional => ServerSession::create(
+                SessionConfig::builder(AttestationType::SelfUnidirectional, HandshakeType::NoiseNN)
                     .add_self_attester_ref(
                         CONFIDENTIAL_SPACE_ATTESTATION_ID.to_owned(),
                         self.attestation_generation.attester.as_ref().expect("no attester"),
@@ -227,58 +225,122 @@ where
                             .expect("no session binder"),
                     )
                     .build(),
-                )
-                .map_err(|e| {
-                    tonic::Status::internal(format!(
-                        "error creating SelfUnidirectional server session: {e:?}"
-                    ))
-                }),
-                AttestationType::PeerUnidirectional => Err(tonic::Status::unimplemented(
-                    "no support for attestation type: PeerUnidirectional",
-                )),
-                AttestationType::Bidirectional => Err(tonic::Status::unimplemented(
-                    "no support for attestation type: Bidirectional",
-                )),
+            )
+            .map_err(|e| {
+                tonic::Status::internal(format!(
+                    "error creating SelfUnidirectional server session: {e:?}"
+                ))
+            }),
+            AttestationType::PeerUnidirectional => Err(tonic::Status::unimplemented(
+                "no support for attestation type: PeerUnidirectional",
+            )),
+            AttestationType::Bidirectional => {
+                Err(tonic::Status::unimplemented("no support for attestation type: Bidirectional"))
             }
-            .expect("server session failed");
+        }
+        .expect("server session failed");
 
         let instance: Arc<OakFunctionsInstance<H>> = self.get_instance();
 
-        let mut request_stream = request.into_inner();
-        let response_stream = async_stream::try_stream! {
-          while let Some(result_request) = request_stream.next().await {
-            let oak_session_request = result_request?;
-            let session_request = oak_session_request
-              .request
-              .ok_or(tonic::Status::invalid_argument("No request in OakSessionRequest"))?;
-            if server_session.is_open() {
-              let decrypted_request = server_session.decrypt(session_request).map_err(|e| tonic::Status::internal(format!("{e:?}")))?;
-              println!("Request received");
-              let invoke_response =
-                instance.handle_user_request(decrypted_request).map_err(map_status);
+        let (mut tx, rx) = mpsc::channel(RESPONSE_CHANNEL_CAPACITY);
+        let request_stream = request.into_inner();
+        tokio::spawn(async move {
+            if let Err(status) =
+                handle_session(server_session, instance, request_stream, tx.clone()).await
+            {
+                // If the client is gone, there is nobody left to report the error to.
+                let _ = tx.send(Err(status)).await;
+            }
+        });
+        Ok(tonic::Response::new(Box::pin(rx) as Self::OakSessionStream))
+    }
+}
+
+/// Opens `server_session` with the init messages received on `request_stream`,
+/// then handles each request of the open session with `instance`, sending the
+/// responses to `tx`.
+async fn handle_session<H>(
+    mut server_session: ServerSession,
+    instance: Arc<OakFunctionsInstance<H>>,
+    mut request_stream: tonic::Streaming<OakSessionRequest>,
+    mut tx: mpsc::Sender<Result<OakSessionResponse, tonic::Status>>,
+) -> Result<(), tonic::Status>
+where
+    H: Handler,
+    H::HandlerType: Send + Sync,
+{
+    {
+        let mut init_requests = (&mut request_stream).map(|result_request| {
+            result_request?.request.context("No request in OakSessionRequest")
+        });
+        let mut init_responses = (&mut tx).with(|response| {
+            future::ready(Ok::<_, mpsc::SendError>(Ok(OakSessionResponse {
+                response: Some(response),
+                ..Default::default()
+            })))
+        });
+        drive_server_handshake(&mut server_session, &mut init_requests, &mut init_responses)
+            .await
+            .map_err(|e| tonic::Status::internal(format!("{e:?}")))?;
+    }
+
+    while let Some(result_request) = request_stream.next().await {
+        let oak_session_request = result_request?;
+        let request_id = oak_session_request.request_id;
+        let session_request = oak_session_request
+            .request
+            .ok_or(tonic::Status::invalid_argument("No request in OakSessionRequest"))?;
+        let decrypted_request = server_session
+            .decrypt(session_request)
+            .map_err(|e| tonic::Status::internal(format!("{e:?}")))?;
+        println!("Request received");
+        let invoke_response =
+            instance.handle_user_request(decrypted_request).map_err(map_status)?;
 
-              let session_response = server_session.encrypt(invoke_response.unwrap()).map_err(|e| tonic::Status::internal(format!("{e:?}")))?;
+        let session_response = server_session
+            .encrypt(invoke_response)
+            .map_err(|e| tonic::Status::internal(format!("{e:?}")))?;
 
-              let oak_session_response = OakSessionResponse {
-                response: Some(session_response),
-              };
-              println!("Sending response");
-              yield oak_session_response;
+        let oak_session_response =
+            OakSessionResponse { response: Some(session_response), request_id };
+        println!("Sending response");
+        if tx.send(Ok(oak_session_response)).await.is_err() {
+            // The client has stopped listening for responses.
+            return Ok(());
+        }
+    }
+    Ok(())
+}
 
-            } else {
-              server_session.handle_init_message(session_request).map_err(|e| tonic::Status::internal(format!("{e:?}")))?;
-              if !server_session.is_open() {
-                let session_response = server_session.next_init_message().map_err(|e| tonic::Status::internal(format!("{e:?}")))?;
-                let oak_session_response = OakSessionResponse {
-                  response: Some(session_response),
-                };
-                yield oak_session_response;
-              }
-            }
-          }
-        };
-        Ok(tonic::Response::new(Box::pin(response_stream) as Self::OakSessionStream))
+/// Advances `server_session` through its initialization until it is open,
+/// reading the client's init messages from `incoming` and sending the server's
+/// init messages to `outgoing`.
+///
+/// This is the server-side counterpart of
+/// `oak_functions_standalone_client_lib::drive_client_handshake`.
+pub async fn drive_server_handshake<I, O>(
+    server_session: &mut ServerSession,
+    incoming: &mut I,
+    outgoing: &mut O,
+) -> anyhow::Result<()>
+where
+    I: Stream<Item = anyhow::Result<SessionRequest>> + Unpin,
+    O: Sink<SessionResponse> + Unpin,
+    O::Error: Error + Send + Sync + 'static,
+{
+    while !server_session.is_open() {
+        let request = incoming
+            .next()
+            .await
+            .context("the client closed the stream before the session was open")??;
+        server_session.handle_init_message(request).context("failed to handle init request")?;
+        if !server_session.is_open() {
+            let response =
+                server_session.next_init_message().context("expected server init message")?;
+            outgoing.send(response).await.context("failed to send to client")?;
+        }
     }
+    Ok(())
 }
 
 // Equivalent to `tonic::status::GRPC_STATUS_HEADER_CODE`.
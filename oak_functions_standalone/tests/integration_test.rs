@@ -15,14 +15,21 @@
 //
 
 use std::{
+    collections::BTreeMap,
     fs,
     net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::{Arc, Mutex},
     time::Duration,
 };
 
 use futures::channel::mpsc;
 use oak_functions_service::wasm::wasmtime::WasmtimeHandler;
-use oak_functions_standalone::{serve, AttestationArgs, OakFunctionsSessionArgs};
+use oak_functions_standalone::{
+    drive_server_handshake, serve, AttestationArgs, OakFunctionsSessionArgs,
+};
+use oak_functions_standalone_client_lib::{
+    drive_client_handshake, OakFunctionsClient, ReattestingClient,
+};
 use oak_grpc::oak::functions::standalone::oak_functions_session_client::OakFunctionsSessionClient;
 use oak_proto_rust::oak::functions::{
     standalone::{OakSessionRequest, OakSessionResponse},
@@ -33,25 +40,25 @@ use oak_session::{
     channel::{SessionChannel, SessionInitializer},
     config::SessionConfig,
     handshake::HandshakeType,
-    Session,
+    ClientSession, ServerSession, Session,
+};
+use oak_time::{clock::FixedClock, UNIX_EPOCH};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
 };
-use tokio::net::TcpListener;
 use tokio_stream::{wrappers::TcpListenerStream, StreamExt};
 use tonic::{codec::CompressionEncoding, transport::Endpoint};
 
-#[tokio::test]
-async fn test_echo() {
-    // To be used to load the Wasm module.
-    let wasm_path = "oak_functions/examples/echo/echo.wasm";
-
-    let (addr, stream) = {
-        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0);
-        let listener = TcpListener::bind(addr).await.unwrap();
-        let addr = listener.local_addr().unwrap();
-        (addr, Box::new(TcpListenerStream::new(listener)))
-    };
+const ECHO_WASM_PATH: &str = "oak_functions/examples/echo/echo.wasm";
 
-    let oak_functions_session_args = OakFunctionsSessionArgs {
+/// Returns the arguments of an unattested session that runs the Wasm module at
+/// `wasm_path`, optionally with `lookup_data`.
+fn unattested_session_args(
+    wasm_path: &str,
+    lookup_data: Option<LookupDataChunk>,
+) -> OakFunctionsSessionArgs {
+    OakFunctionsSessionArgs {
         wasm_initialization: InitializeRequest {
             constant_response_size: 100, // This value is ultimately ignored.
             wasm_module: fs::read(wasm_path).expect("failed to read wasm module"),
@@ -61,14 +68,31 @@ async fn test_echo() {
             binding_key: None,
             endorsement: None,
         },
-        lookup_data: None,
-    };
+        lookup_data,
+    }
+}
+
+/// Starts a server with `oak_functions_session_args` on a new port, returning
+/// its address and the task serving it.
+async fn start_server(
+    oak_functions_session_args: OakFunctionsSessionArgs,
+) -> (SocketAddr, JoinHandle<anyhow::Result<()>>) {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0);
+    let listener = TcpListener::bind(addr).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let stream = Box::new(TcpListenerStream::new(listener));
 
     let server_handle = tokio::spawn(serve::<WasmtimeHandler>(
         stream,
         Default::default(),
         oak_functions_session_args,
     ));
+    (addr, server_handle)
+}
+
+#[tokio::test]
+async fn test_echo() {
+    let (addr, server_handle) = start_server(unattested_session_args(ECHO_WASM_PATH, None)).await;
 
     let mut oak_functions_session_client: OakFunctionsSessionClient<
         tonic::transport::channel::Channel,
@@ -97,7 +121,8 @@ async fn test_echo() {
     while !client_session.is_open() {
         let session_request =
             client_session.next_init_message().expect("expected client init message");
-        let oak_session_request = OakSessionRequest { request: Some(session_request) };
+        let oak_session_request =
+            OakSessionRequest { request: Some(session_request), ..Default::default() };
         tx.try_send(oak_session_request).expect("failed to send to server");
         if !client_session.is_open() {
             let oak_session_response = resp_stream
@@ -116,7 +141,8 @@ async fn test_echo() {
     let encrypted_request = client_session
         .encrypt(test_message.as_bytes().to_vec())
         .expect("failed to encrypt message");
-    let oak_session_request = OakSessionRequest { request: Some(encrypted_request) };
+    let oak_session_request =
+        OakSessionRequest { request: Some(encrypted_request), ..Default::default() };
 
     // Send our request and close the channel since we have no more messages to
     // send.
@@ -146,49 +172,196 @@ async fn test_echo() {
 }
 
 #[tokio::test]
-async fn test_lookup() {
-    let wasm_path = "oak_functions/examples/key_value_lookup/key_value_lookup.wasm";
+async fn test_pipelined_echo() {
+    let (addr, server_handle) = start_server(unattested_session_args(ECHO_WASM_PATH, None)).await;
+
+    let mut client = OakFunctionsClient::create(
+        format!("http://{addr}"),
+        AttestationType::Unattested,
+        Arc::new(FixedClock::at_instant(UNIX_EPOCH)),
+        BTreeMap::new(),
+    )
+    .await
+    .expect("couldn't create client");
 
-    let (addr, stream) = {
-        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0);
-        let listener = TcpListener::bind(addr).await.unwrap();
-        let addr = listener.local_addr().unwrap();
-        (addr, Box::new(TcpListenerStream::new(listener)))
-    };
+    // Send more requests than the client keeps in flight, so that sending and
+    // receiving interleave.
+    let requests: Vec<String> = (0..32).map(|i| format!("request {i}")).collect();
+    let responses = client.invoke_many(&requests).await.expect("couldn't invoke requests");
 
-    let oak_functions_session_args = OakFunctionsSessionArgs {
-        wasm_initialization: InitializeRequest {
-            constant_response_size: 100, // This value is ultimately ignored.
-            wasm_module: fs::read(wasm_path).expect("failed to read wasm module"),
-        },
-        attestation_args: AttestationArgs {
-            attestation_type: AttestationType::Unattested,
-            binding_key: None,
-            endorsement: None,
-        },
-        lookup_data: Some(LookupDataChunk {
-            items: vec![
-                LookupDataEntry {
-                    key: b"key_0".to_vec().into(),
-                    value: b"value_0".to_vec().into(),
-                },
-                LookupDataEntry {
-                    key: b"key_1".to_vec().into(),
-                    value: b"value_1".to_vec().into(),
-                },
-                LookupDataEntry {
-                    key: b"key_2".to_vec().into(),
-                    value: b"value_2".to_vec().into(),
-                },
-            ],
-        }),
-    };
+    let responses: Vec<String> = responses
+        .into_iter()
+        .map(|response| String::from_utf8(response).expect("unable to convert bytes to string"))
+        .collect();
+    assert_eq!(responses, requests);
 
-    let server_handle = tokio::spawn(serve::<WasmtimeHandler>(
-        stream,
-        Default::default(),
-        oak_functions_session_args,
-    ));
+    // Requests sent afterwards continue the same sequence.
+    let response = client.invoke(b"Hello World").await.expect("couldn't invoke request");
+    assert_eq!(response, b"Hello World");
+
+    client.close().await.expect("couldn't close client");
+
+    server_handle.abort();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_pipelined_echo_with_single_request_buffer() {
+    let (addr, server_handle) = start_server(unattested_session_args(ECHO_WASM_PATH, None)).await;
+
+    let mut client = OakFunctionsClient::create_with_channel_capacity(
+        format!("http://{addr}"),
+        AttestationType::Unattested,
+        Arc::new(FixedClock::at_instant(UNIX_EPOCH)),
+        BTreeMap::new(),
+        1,
+    )
+    .await
+    .expect("couldn't create client");
+
+    // Sending waits whenever the single-request buffer is full, so a burst of
+    // requests is throttled rather than rejected.
+    let requests: Vec<String> = (0..32).map(|i| format!("request {i}")).collect();
+    let responses = tokio::time::timeout(Duration::from_secs(60), client.invoke_many(&requests))
+        .await
+        .expect("requests didn't complete")
+        .expect("couldn't invoke requests");
+
+    let responses: Vec<String> = responses
+        .into_iter()
+        .map(|response| String::from_utf8(response).expect("unable to convert bytes to string"))
+        .collect();
+    assert_eq!(responses, requests);
+
+    client.close().await.expect("couldn't close client");
+
+    server_handle.abort();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_close() {
+    let (addr, server_handle) = start_server(unattested_session_args(ECHO_WASM_PATH, None)).await;
+
+    let mut client = OakFunctionsClient::create(
+        format!("http://{addr}"),
+        AttestationType::Unattested,
+        Arc::new(FixedClock::at_instant(UNIX_EPOCH)),
+        BTreeMap::new(),
+    )
+    .await
+    .expect("couldn't create client");
+
+    let response = client.invoke(b"Hello World").await.expect("couldn't invoke request");
+    assert_eq!(response, b"Hello World");
+
+    // The server only ends the response stream once it has seen the end of the
+    // request stream, so this completes only if the server observed it.
+    tokio::time::timeout(Duration::from_secs(60), client.close())
+        .await
+        .expect("server didn't end the session")
+        .expect("couldn't close client");
+
+    server_handle.abort();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_attestation_results_unattested() {
+    let (addr, server_handle) = start_server(unattested_session_args(ECHO_WASM_PATH, None)).await;
+
+    let client = OakFunctionsClient::create(
+        format!("http://{addr}"),
+        AttestationType::Unattested,
+        Arc::new(FixedClock::at_instant(UNIX_EPOCH)),
+        BTreeMap::new(),
+    )
+    .await
+    .expect("couldn't create client");
+
+    // An unattested session has no verified peer attestations.
+    let attestation_results =
+        client.attestation_results().expect("couldn't get attestation results");
+    assert!(attestation_results.is_empty());
+
+    // Nor does it have any evidence to collect.
+    let attestation = client
+        .fetch_attestation(format!("http://{addr}"), Arc::new(FixedClock::at_instant(UNIX_EPOCH)))
+        .expect("couldn't fetch attestation");
+    assert!(attestation.is_none());
+
+    server_handle.abort();
+    let _ = server_handle.await;
+}
+
+/// Forwards connections from a new local port to `server_addr`, returning the
+/// proxy's address and the tasks forwarding the accepted connections. Aborting
+/// those tasks resets the connections, like a restart of the server would.
+async fn start_proxy(server_addr: SocketAddr) -> (SocketAddr, Arc<Mutex<Vec<JoinHandle<()>>>>) {
+    let listener =
+        TcpListener::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let connections = Arc::new(Mutex::new(Vec::new()));
+    let accepted = connections.clone();
+    tokio::spawn(async move {
+        while let Ok((mut inbound, _)) = listener.accept().await {
+            let connection = tokio::spawn(async move {
+                let mut outbound = TcpStream::connect(server_addr).await.unwrap();
+                let _ = tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await;
+            });
+            accepted.lock().unwrap().push(connection);
+        }
+    });
+    (addr, connections)
+}
+
+#[tokio::test]
+async fn test_reattest_after_session_reset() {
+    let (addr, server_handle) = start_server(unattested_session_args(ECHO_WASM_PATH, None)).await;
+    let (proxy_addr, connections) =
+        start_proxy(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), addr.port())).await;
+
+    let mut client = ReattestingClient::create(
+        format!("http://{proxy_addr}"),
+        AttestationType::Unattested,
+        Arc::new(FixedClock::at_instant(UNIX_EPOCH)),
+        BTreeMap::new,
+    )
+    .await
+    .expect("couldn't create client");
+
+    let response = client.invoke(b"Hello World").await.expect("couldn't invoke request");
+    assert_eq!(response.response, b"Hello World");
+    assert!(response.reattestation.is_none());
+
+    // Reset the session mid-stream by dropping the connection under it.
+    for connection in connections.lock().unwrap().drain(..) {
+        connection.abort();
+    }
+
+    let response = client.invoke(b"Hello again").await.expect("couldn't invoke request");
+    assert_eq!(response.response, b"Hello again");
+    let reattestation = response.reattestation.expect("server wasn't re-attested");
+    assert!(reattestation.is_empty());
+
+    server_handle.abort();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_lookup() {
+    let lookup_data = LookupDataChunk {
+        items: vec![
+            LookupDataEntry { key: b"key_0".to_vec().into(), value: b"value_0".to_vec().into() },
+            LookupDataEntry { key: b"key_1".to_vec().into(), value: b"value_1".to_vec().into() },
+            LookupDataEntry { key: b"key_2".to_vec().into(), value: b"value_2".to_vec().into() },
+        ],
+    };
+    let (addr, server_handle) = start_server(unattested_session_args(
+        "oak_functions/examples/key_value_lookup/key_value_lookup.wasm",
+        Some(lookup_data),
+    ))
+    .await;
 
     let mut oak_functions_session_client: OakFunctionsSessionClient<
         tonic::transport::channel::Channel,
@@ -217,7 +390,8 @@ async fn test_lookup() {
     while !client_session.is_open() {
         let session_request =
             client_session.next_init_message().expect("expected client init message");
-        let oak_session_request = OakSessionRequest { request: Some(session_request) };
+        let oak_session_request =
+            OakSessionRequest { request: Some(session_request), ..Default::default() };
         tx.try_send(oak_session_request).expect("failed to send to server");
         if !client_session.is_open() {
             let oak_session_response = resp_stream
@@ -237,7 +411,8 @@ async fn test_lookup() {
     for key_query in query_keys {
         let encrypted_request =
             client_session.encrypt(key_query).expect("failed to encrypt message");
-        let oak_session_request = OakSessionRequest { request: Some(encrypted_request) };
+        let oak_session_request =
+            OakSessionRequest { request: Some(encrypted_request), ..Default::default() };
         tx.try_send(oak_session_request).expect("failed to send message");
     }
 
@@ -264,3 +439,40 @@ async fn test_lookup() {
     server_handle.abort();
     let _ = server_handle.await;
 }
+
+#[tokio::test]
+async fn test_drive_handshake() {
+    let session_config =
+        || SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN).build();
+    let mut client_session =
+        ClientSession::create(session_config()).expect("could not create client session");
+    let mut server_session =
+        ServerSession::create(session_config()).expect("could not create server session");
+
+    let (mut client_tx, server_rx) = mpsc::channel(1);
+    let (mut server_tx, client_rx) = mpsc::channel(1);
+    let mut client_rx = client_rx.map(Ok::<_, anyhow::Error>);
+    let mut server_rx = server_rx.map(Ok::<_, anyhow::Error>);
+
+    let (client_result, server_result) = tokio::join!(
+        drive_client_handshake(&mut client_session, &mut client_rx, &mut client_tx),
+        drive_server_handshake(&mut server_session, &mut server_rx, &mut server_tx),
+    );
+    client_result.expect("client handshake failed");
+    server_result.expect("server handshake failed");
+    assert!(client_session.is_open());
+    assert!(server_session.is_open());
+
+    let encrypted_request =
+        client_session.encrypt(b"Hello".to_vec()).expect("failed to encrypt request");
+    assert_eq!(
+        server_session.decrypt(encrypted_request).expect("failed to decrypt request"),
+        b"Hello"
+    );
+    let encrypted_response =
+        server_session.encrypt(b"World".to_vec()).expect("failed to encrypt response");
+    assert_eq!(
+        client_session.decrypt(encrypted_response).expect("failed to decrypt response"),
+        b"World"
+    );
+}
@@ -85,6 +85,7 @@ impl EchoClient {
                     &developer_public_key,
                 )),
                 rekor_public_key: Some(p256_ecdsa_verifying_key_to_proto(&rekor_public_key)),
+                ..Default::default()
             })),
         };
         let policy = confidential_space_policy_from_reference_values(&reference_values)?;
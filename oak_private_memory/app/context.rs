@@ -26,6 +26,8 @@ pub struct UserSessionContext {
     pub uid: String,
     pub message_type: MessageType,
 
+    /// The user's database. Its Icing database owns the directory it is stored
+    /// in, which thus lives as long as the session and is removed with it.
     pub database: DatabaseWithCache,
     pub database_service_client: SealedMemoryDatabaseServiceClient<Channel>,
 }
@@ -13,34 +13,134 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 //
-use std::net::SocketAddr;
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
 
 use anyhow::bail;
 use log::info;
 use metrics::get_global_metrics;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use sealed_memory_grpc_proto::oak::private_memory::sealed_memory_database_service_client::SealedMemoryDatabaseServiceClient;
+use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 use tonic::transport::{Channel, Endpoint};
 const MAX_CONNECT_RETRIES: usize = 5;
 const INITIAL_BACKOFF_MS: u64 = 100;
 const MAX_DECODE_SIZE: usize = 10 * 1024 * 1024; // 10 MB
+/// By default, all requests share a single connection to the database service.
+pub const DEFAULT_DB_CONNECTION_POOL_SIZE: usize = 1;
 
+/// How `SharedDbClient` retries connecting to the database service.
+///
+/// The backoff between attempts starts at `initial_backoff_ms` and doubles
+/// after every failed attempt, up to `max_backoff_ms` if set. The actual delay
+/// is drawn uniformly between 0 and the backoff ("full jitter"), so that many
+/// servers reconnecting at once don't retry in lockstep.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct DbConnectRetryConfig {
+    pub max_connect_retries: usize,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: Option<u64>,
+    /// Seeds the jitter, so that delays are reproducible in tests. If unset,
+    /// the jitter is seeded from the OS.
+    pub jitter_seed: Option<u64>,
+}
+
+impl Default for DbConnectRetryConfig {
+    fn default() -> Self {
+        Self {
+            max_connect_retries: MAX_CONNECT_RETRIES,
+            initial_backoff_ms: INITIAL_BACKOFF_MS,
+            max_backoff_ms: None,
+            jitter_seed: None,
+        }
+    }
+}
+
+impl DbConnectRetryConfig {
+    fn next_backoff_ms(&self, backoff_ms: u64) -> u64 {
+        let backoff_ms = backoff_ms.saturating_mul(2);
+        match self.max_backoff_ms {
+            Some(max_backoff_ms) => backoff_ms.min(max_backoff_ms),
+            None => backoff_ms,
+        }
+    }
+}
+
+/// A fixed-size pool of lazily connected database service clients.
+///
+/// Each client uses its own HTTP/2 connection, and `get_or_connect` hands them
+/// out in round-robin order, so that concurrent requests are spread over
+/// `pool_size` connections instead of being multiplexed over a single one.
 pub struct SharedDbClient {
     database_service_host: SocketAddr,
-    client: RwLock<Option<SealedMemoryDatabaseServiceClient<Channel>>>,
+    retry_config: DbConnectRetryConfig,
+    clients: Vec<RwLock<Option<SealedMemoryDatabaseServiceClient<Channel>>>>,
+    next_client: AtomicUsize,
+    jitter_rng: Mutex<StdRng>,
 }
 
 impl SharedDbClient {
-    pub fn new(database_service_host: SocketAddr) -> Self {
-        Self { database_service_host, client: RwLock::new(None) }
+    /// Creates a pool of `pool_size` clients. A `pool_size` of 0 is treated as
+    /// 1.
+    pub fn new(
+        database_service_host: SocketAddr,
+        retry_config: DbConnectRetryConfig,
+        pool_size: usize,
+    ) -> Self {
+        let clients = (0..pool_size.max(1)).map(|_| RwLock::new(None)).collect();
+        let jitter_rng = match retry_config.jitter_seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        };
+        Self {
+            database_service_host,
+            retry_config,
+            clients,
+            next_client: AtomicUsize::new(0),
+            jitter_rng: Mutex::new(jitter_rng),
+        }
+    }
+
+    /// Returns whether the database service accepts a new connection right
+    /// now, trying once and giving up after `timeout`. Unlike the pooled
+    /// clients, which stay cached after the service goes away, this reflects
+    /// the current state of the service.
+    pub async fn check_connection(&self, timeout: Duration) -> bool {
+        let db_url = format!("http://{}", self.database_service_host);
+        let Ok(endpoint) = Endpoint::from_shared(db_url) else {
+            return false;
+        };
+        matches!(
+            tokio::time::timeout(timeout, endpoint.connect_timeout(timeout).connect()).await,
+            Ok(Ok(_))
+        )
+    }
+
+    fn next_client_index(&self) -> usize {
+        self.next_client.fetch_add(1, Ordering::Relaxed) % self.clients.len()
+    }
+
+    /// Returns how long to wait before the next attempt, given the current
+    /// `backoff_ms`.
+    fn jittered_delay_ms(&self, backoff_ms: u64) -> u64 {
+        self.jitter_rng.lock().unwrap().random_range(0..=backoff_ms)
     }
 
     pub async fn get_or_connect(
         &self,
     ) -> anyhow::Result<SealedMemoryDatabaseServiceClient<Channel>> {
+        let client = &self.clients[self.next_client_index()];
         // First, try to get a read lock and check if the client is already initialized.
         {
-            let read_guard = self.client.read().await;
+            let read_guard = client.read().await;
             if let Some(client) = read_guard.as_ref() {
                 info!("Reusing cached DB client");
                 return Ok(client.clone());
@@ -48,7 +148,7 @@ impl SharedDbClient {
         }
 
         // If the client is not initialized, get a write lock to initialize it.
-        let mut write_guard = self.client.write().await;
+        let mut write_guard = client.write().await;
         // Check again in case another thread initialized it while we were waiting for
         // the write lock.
         if let Some(client) = write_guard.as_ref() {
@@ -56,12 +156,15 @@ impl SharedDbClient {
             return Ok(client.clone());
         }
 
-        let mut backoff = INITIAL_BACKOFF_MS;
+        let mut backoff = self.retry_config.initial_backoff_ms;
+        if let Some(max_backoff_ms) = self.retry_config.max_backoff_ms {
+            backoff = backoff.min(max_backoff_ms);
+        }
         let db_addr = self.database_service_host;
         let db_url = format!("http://{db_addr}");
         info!("Database service URL: {}", db_url);
         let endpoint = Endpoint::from_shared(db_url.clone())?;
-        for attempt in 0..MAX_CONNECT_RETRIES {
+        for attempt in 0..self.retry_config.max_connect_retries {
             info!("Creating new DB client, attempt {}", attempt + 1);
 
             match endpoint.connect().await {
@@ -77,10 +180,111 @@ impl SharedDbClient {
                 }
             }
 
-            tokio::time::sleep(tokio::time::Duration::from_millis(backoff)).await;
-            backoff *= 2;
+            let delay = self.jittered_delay_ms(backoff);
+            tokio::time::sleep(tokio::time::Duration::from_millis(delay)).await;
+            backoff = self.retry_config.next_backoff_ms(backoff);
             get_global_metrics().inc_db_connect_retries();
         }
-        bail!("Failed to connect to database service after {} attempts", MAX_CONNECT_RETRIES);
+        bail!(
+            "Failed to connect to database service after {} attempts",
+            self.retry_config.max_connect_retries
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr, TcpListener};
+
+    use super::*;
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let retry_config = DbConnectRetryConfig {
+            initial_backoff_ms: 100,
+            max_backoff_ms: Some(300),
+            ..Default::default()
+        };
+        assert_eq!(retry_config.next_backoff_ms(100), 200);
+        assert_eq!(retry_config.next_backoff_ms(200), 300);
+        assert_eq!(retry_config.next_backoff_ms(300), 300);
+
+        let retry_config = DbConnectRetryConfig::default();
+        assert_eq!(retry_config.next_backoff_ms(u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn test_jittered_delay_is_within_backoff_and_reproducible() {
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+        let retry_config = DbConnectRetryConfig { jitter_seed: Some(42), ..Default::default() };
+        let delays = |db_client: &SharedDbClient| -> Vec<u64> {
+            (0..20).map(|_| db_client.jittered_delay_ms(1000)).collect()
+        };
+
+        let db_client = SharedDbClient::new(addr, retry_config.clone(), 1);
+        let first_delays = delays(&db_client);
+        assert!(first_delays.iter().all(|delay| *delay <= 1000));
+        // The delays are spread out rather than all equal to the backoff.
+        assert!(first_delays.iter().any(|delay| *delay != first_delays[0]));
+
+        let db_client = SharedDbClient::new(addr, retry_config, 1);
+        assert_eq!(delays(&db_client), first_delays);
+
+        assert_eq!(db_client.jittered_delay_ms(0), 0);
+    }
+
+    #[tokio::test]
+    async fn test_get_or_connect_gives_up_after_max_retries() {
+        // Reserve a port and release it again, so that nothing listens on it.
+        let unavailable_addr =
+            TcpListener::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0))
+                .unwrap()
+                .local_addr()
+                .unwrap();
+        let db_client = SharedDbClient::new(
+            unavailable_addr,
+            DbConnectRetryConfig {
+                max_connect_retries: 3,
+                initial_backoff_ms: 1,
+                max_backoff_ms: Some(2),
+                jitter_seed: Some(0),
+            },
+            DEFAULT_DB_CONNECTION_POOL_SIZE,
+        );
+
+        let result = db_client.get_or_connect().await;
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Failed to connect to database service after 3 attempts"
+        );
+        assert!(db_client.clients[0].read().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_check_connection_reflects_current_state() {
+        let listener =
+            TcpListener::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let db_client =
+            SharedDbClient::new(addr, Default::default(), DEFAULT_DB_CONNECTION_POOL_SIZE);
+        assert!(db_client.check_connection(Duration::from_secs(1)).await);
+
+        drop(listener);
+        assert!(!db_client.check_connection(Duration::from_secs(1)).await);
+    }
+
+    #[test]
+    fn test_clients_are_selected_round_robin() {
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0);
+
+        let db_client = SharedDbClient::new(addr, DbConnectRetryConfig::default(), 3);
+        let indices: Vec<usize> = (0..7).map(|_| db_client.next_client_index()).collect();
+        assert_eq!(indices, vec![0, 1, 2, 0, 1, 2, 0]);
+
+        let db_client = SharedDbClient::new(addr, DbConnectRetryConfig::default(), 0);
+        assert_eq!(db_client.clients.len(), 1);
+        assert_eq!(db_client.next_client_index(), 0);
+        assert_eq!(db_client.next_client_index(), 0);
     }
 }
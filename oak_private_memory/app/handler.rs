@@ -13,16 +13,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 //
-use std::sync::Arc;
+use std::{collections::BTreeSet, future::Future, path::Path, sync::Arc, time::Duration};
 
 use anyhow::{bail, Context};
 use encryption::{decrypt, encrypt, generate_nonce};
 use external_db_client::{BlobId, DataBlobHandler};
-use log::{debug, info};
+use log::{debug, info, warn};
 use metrics::{get_global_metrics, RequestMetricName};
 use oak_private_memory_database::{
     encryption::{decrypt_database, encrypt_database},
-    DatabaseWithCache, IcingMetaDatabase, MemoryId, PageToken,
+    DatabaseWithCache, IcingMetaDatabase, MemoryId, PageToken, DEFAULT_MEMORY_CACHE_CAPACITY,
 };
 use prost::Message;
 use rand::Rng;
@@ -36,13 +36,39 @@ use tokio::{
 use tonic::transport::Channel;
 
 use crate::{
-    context::UserSessionContext, db_client::SharedDbClient, packing::ResponsePacking, MessageType,
+    context::UserSessionContext, db_client::SharedDbClient, packing::ResponsePacking, user_info,
+    DbIntegrityCheck, MessageType,
 };
+
+// How long a ping waits for the database service to accept a connection.
+const PING_TIMEOUT: Duration = Duration::from_secs(1);
+
+// The settings that every session handler is created with. It's built once
+// from the `ApplicationConfig` on startup.
+#[derive(Clone, Default)]
+pub struct SessionHandlerConfig {
+    // The server-managed key that the metadata of the user info is encrypted
+    // with, if configured.
+    pub metadata_key: Option<Arc<Vec<u8>>>,
+    // Whether the meta database of the user is checked for orphaned memories
+    // when it's loaded.
+    pub db_integrity_check: DbIntegrityCheck,
+    // The format of responses sent before the session's message format is
+    // known.
+    pub default_message_type: MessageType,
+    // How long a cancel-safe request may take before a
+    // `RequestTimeoutResponse` is returned instead, if limited.
+    pub request_timeout: Option<Duration>,
+    // The dimension of the embeddings in the user databases, if known.
+    pub embedding_dimension: Option<usize>,
+}
+
 // The implementation for one active Oak Private Memory session.
 // A new instances of this struct is created per-request.
 pub struct SealedMemorySessionHandler {
     session_context: Mutex<Option<UserSessionContext>>,
     db_client: Arc<SharedDbClient>,
+    config: SessionHandlerConfig,
     metrics: Arc<metrics::Metrics>,
     persistence_tx: mpsc::UnboundedSender<UserSessionContext>,
 }
@@ -51,6 +77,7 @@ impl Drop for SealedMemorySessionHandler {
     fn drop(&mut self) {
         info!("Dropping handler and sending session context to persistence service");
         if let Some(context) = self.session_context.get_mut().take() {
+            self.metrics.dec_active_sessions();
             if let Err(e) = self.persistence_tx.send(context) {
                 info!("Failed to send session context to persistence service: {}", e);
             }
@@ -63,8 +90,13 @@ impl SealedMemorySessionHandler {
         metrics: Arc<metrics::Metrics>,
         persistence_tx: mpsc::UnboundedSender<UserSessionContext>,
         db_client: Arc<SharedDbClient>,
+        config: SessionHandlerConfig,
     ) -> Self {
-        Self { session_context: Default::default(), db_client, metrics, persistence_tx }
+        Self { session_context: Default::default(), db_client, config, metrics, persistence_tx }
+    }
+
+    fn metadata_key(&self) -> Option<&[u8]> {
+        self.config.metadata_key.as_deref().map(Vec::as_slice)
     }
 
     pub async fn session_context(&self) -> MutexGuard<'_, Option<UserSessionContext>> {
@@ -85,18 +117,14 @@ impl SealedMemorySessionHandler {
         request_bytes: &[u8],
     ) -> anyhow::Result<SealedMemoryRequest> {
         Ok(match self.session_message_type().await {
-            Some(MessageType::BinaryProto) => SealedMemoryRequest::decode(request_bytes)?,
-            Some(MessageType::Json) => {
-                serde_json::from_slice::<SealedMemoryRequest>(request_bytes)?
-            }
+            Some(MessageType::BinaryProto) => decode_proto_request(request_bytes)?,
+            Some(MessageType::Json) => decode_json_request(request_bytes)?,
             None => {
                 // Default to trying all the options.
-                if let Ok(request) = SealedMemoryRequest::decode(request_bytes) {
+                if let Ok(request) = decode_proto_request(request_bytes) {
                     info!("Request is in binary proto format");
                     request
-                } else if let Ok(request) =
-                    serde_json::from_slice::<SealedMemoryRequest>(request_bytes)
-                {
+                } else if let Ok(request) = decode_json_request(request_bytes) {
                     info!("Request is in json format {:?}", request);
                     request
                 } else {
@@ -115,8 +143,8 @@ impl SealedMemorySessionHandler {
             .session_message_type()
             .await
             // If no session, use the caller-provided type.
-            // If no caller-provided type, default to binaryproto.
-            .unwrap_or(message_type.unwrap_or(MessageType::BinaryProto));
+            // If no caller-provided type, use the configured default.
+            .unwrap_or(message_type.unwrap_or(self.config.default_message_type));
 
         Ok(match message_type {
             MessageType::BinaryProto => response.encode_to_vec(),
@@ -153,9 +181,16 @@ impl SealedMemorySessionHandler {
         let page_token = PageToken::try_from(request.page_token)
             .map_err(|e| anyhow::anyhow!("Invalid page token: {}", e))?;
         let (memories, next_page_token) = database
-            .get_memories_by_tag(&request.tag, &request.result_mask, request.page_size, page_token)
+            .get_memories_by_tag(
+                &request.tag,
+                &request.result_mask,
+                request.sort_by.as_ref(),
+                request.page_size,
+                page_token,
+            )
             .await?;
-        Ok(GetMemoriesResponse { memories, next_page_token: next_page_token.into() })
+        let total_count = database.count_memories_by_tag(&request.tag)?.try_into()?;
+        Ok(GetMemoriesResponse { memories, next_page_token: next_page_token.into(), total_count })
     }
 
     pub async fn get_memory_by_id_handler(
@@ -166,8 +201,8 @@ impl SealedMemorySessionHandler {
         let database = &mut mutex_guard.as_mut().context("call key sync first")?.database;
 
         let memory = database.get_memory_by_id(request.id, &request.result_mask).await?;
-        let success = memory.is_some();
-        Ok(GetMemoryByIdResponse { memory, success })
+        let found = memory.is_some();
+        Ok(GetMemoryByIdResponse { memory, success: found, found })
     }
 
     pub async fn reset_memory_handler(
@@ -181,6 +216,26 @@ impl SealedMemorySessionHandler {
         Ok(ResetMemoryResponse { success: true, ..Default::default() })
     }
 
+    fn new_database_with_cache(
+        &self,
+        database: IcingMetaDatabase,
+        dek: Vec<u8>,
+        db_client: SealedMemoryDatabaseServiceClient<Channel>,
+        key_derivation_info: KeyDerivationInfo,
+    ) -> DatabaseWithCache {
+        let database = DatabaseWithCache::new(
+            database,
+            dek,
+            db_client,
+            key_derivation_info,
+            DEFAULT_MEMORY_CACHE_CAPACITY,
+        );
+        match self.config.embedding_dimension {
+            Some(dimension) => database.with_embedding_dimension(dimension),
+            None => database,
+        }
+    }
+
     async fn setup_user_session_context(
         &self,
         uid: String,
@@ -193,16 +248,35 @@ impl SealedMemorySessionHandler {
 
         let message_type = if is_json { MessageType::Json } else { MessageType::BinaryProto };
         let mut mutex_guard = self.session_context().await;
-        let database =
-            DatabaseWithCache::new(database, dek.clone(), db_client.clone(), key_derivation_info);
+        let mut database = self.new_database_with_cache(
+            database,
+            dek.clone(),
+            db_client.clone(),
+            key_derivation_info,
+        );
+        if self.config.db_integrity_check != DbIntegrityCheck::Disabled {
+            let prune = self.config.db_integrity_check == DbIntegrityCheck::Prune;
+            // A failed check shouldn't lock the user out of their memories.
+            match database.check_integrity(prune).await {
+                Ok(orphaned_ids) if !orphaned_ids.is_empty() => {
+                    warn!("Found {} orphaned memories for {}", orphaned_ids.len(), uid)
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Failed to check the integrity of the database: {:?}", e),
+            }
+        }
 
-        *mutex_guard = Some(UserSessionContext {
+        let previous_context = mutex_guard.replace(UserSessionContext {
             dek,
             uid,
             message_type,
             database_service_client: db_client,
             database,
         });
+        // A session that syncs its key again keeps counting as one session.
+        if previous_context.is_none() {
+            self.metrics.inc_active_sessions();
+        }
         Ok(())
     }
 
@@ -211,12 +285,15 @@ impl SealedMemorySessionHandler {
         request: UserRegistrationRequest,
         is_json: bool,
     ) -> anyhow::Result<UserRegistrationResponse> {
-        if request.key_encryption_key.is_empty() {
-            bail!("key_encryption_key not set in UserRegistrationRequest");
-        }
         if request.pm_uid.is_empty() {
             bail!("pm_uid not set in UserRegistrationRequest");
         }
+        if request.check_only {
+            return self.check_user_registration(&request.pm_uid).await;
+        }
+        if request.key_encryption_key.is_empty() {
+            bail!("key_encryption_key not set in UserRegistrationRequest");
+        }
         let boot_strap_info = request
             .boot_strap_info
             .context("boot_strap_info (KeyDerivationInfo) not set in UserRegistrationRequest")?;
@@ -234,13 +311,9 @@ impl SealedMemorySessionHandler {
             .await
             .context("Failed to get DB client for bootstrap operation")?;
 
-        if let Some(data_blob) = db_client.get_unencrypted_blob(&uid, true).await? {
-            // User already exists
-            let plain_text_info = PlainTextUserInfo::decode(&*data_blob.blob)
-                .context("Failed to decode PlainTextUserInfo")?;
-            let key_derivation_info =
-                plain_text_info.key_derivation_info.clone().context("Empty key derivation info")?;
-
+        if let Some(key_derivation_info) =
+            registered_key_derivation_info(&mut db_client, &uid, self.metadata_key()).await?
+        {
             info!("User have been registered!, {}", uid);
             return Ok(UserRegistrationResponse {
                 status: user_registration_response::Status::UserAlreadyExists.into(),
@@ -256,22 +329,27 @@ impl SealedMemorySessionHandler {
         rand::rng().fill(&mut dek);
         let dek: Vec<u8> = dek.into();
         let nonce = generate_nonce();
-        let wrapped_key = EncryptedDataBlob { data: encrypt(&key, &nonce, &dek)?, nonce };
+        let wrapped_key =
+            EncryptedDataBlob { data: encrypt(&key, &nonce, &dek)?, nonce, ..Default::default() };
 
         let new_plain_text_info = PlainTextUserInfo {
             key_derivation_info: Some(boot_strap_info.clone()),
             wrapped_dek: Some(WrappedDataEncryptionKey { wrapped_key: Some(wrapped_key) }),
+            ..Default::default()
         };
         let initial_encrypted_info = EncryptedUserInfo { icing_db: None };
 
-        let encrypted_db_blob = encrypt_database(&initial_encrypted_info, &dek)
+        let encrypted_db_blob = encrypt_database(&initial_encrypted_info, &dek, false)
             .context("Failed to encrypt initial user info")?;
 
         db_client
             .add_mixed_blobs(
                 vec![encrypted_db_blob],
                 Some(vec![uid.clone()]),
-                vec![DataBlob { id: uid.clone(), blob: new_plain_text_info.encode_to_vec() }],
+                vec![DataBlob {
+                    id: uid.clone(),
+                    blob: user_info::encode(new_plain_text_info, self.metadata_key())?,
+                }],
             )
             .await
             .context("Failed to write blobs")?;
@@ -291,6 +369,30 @@ impl SealedMemorySessionHandler {
         })
     }
 
+    /// Reports whether `uid` is registered without changing any state: no DEK
+    /// is generated and no blobs are written.
+    async fn check_user_registration(
+        &self,
+        uid: &BlobId,
+    ) -> anyhow::Result<UserRegistrationResponse> {
+        let mut db_client = self
+            .db_client
+            .get_or_connect()
+            .await
+            .context("Failed to get DB client for registration check")?;
+
+        Ok(match registered_key_derivation_info(&mut db_client, uid, self.metadata_key()).await? {
+            Some(key_derivation_info) => UserRegistrationResponse {
+                status: user_registration_response::Status::UserAlreadyExists.into(),
+                key_derivation_info: Some(key_derivation_info),
+            },
+            None => UserRegistrationResponse {
+                status: user_registration_response::Status::Success.into(),
+                key_derivation_info: None,
+            },
+        })
+    }
+
     pub async fn key_sync_handler(
         &self,
         request: KeySyncRequest,
@@ -319,19 +421,20 @@ impl SealedMemorySessionHandler {
         let dek: Vec<u8>;
 
         if let Some(data_blob) = db_client.clone().get_unencrypted_blob(&uid, true).await? {
-            let plain_text_info = PlainTextUserInfo::decode(&*data_blob.blob)
-                .context("Failed to decode PlainTextUserInfo")?;
+            let plain_text_info = user_info::decode(&data_blob.blob, self.metadata_key())?;
             key_derivation_info =
                 plain_text_info.key_derivation_info.clone().context("Empty key derivation info")?;
-            let wrapped_dek = plain_text_info
-                .wrapped_dek
-                .clone()
-                .context("Empty wrapped dek")?
-                .wrapped_key
-                .clone()
-                .context("Empty wrapped dek")?;
-            dek = decrypt(&key, &wrapped_dek.nonce, &wrapped_dek.data)
-                .context("Failed to decrypt DEK")?;
+            dek = unwrap_dek(&plain_text_info, &key)?;
+            if let Some(metadata_key) = self.metadata_key() {
+                if plain_text_info.version != user_info::ENCRYPTED_METADATA_VERSION {
+                    // Failing to migrate doesn't prevent using the plaintext user info.
+                    match user_info::migrate(&mut db_client.clone(), &uid, metadata_key).await {
+                        Ok(true) => info!("Encrypted the user info metadata of {}", uid),
+                        Ok(false) => {}
+                        Err(err) => warn!("Failed to migrate user info of {}: {:#}", uid, err),
+                    }
+                }
+            }
         } else {
             return Ok(KeySyncResponse { status: key_sync_response::Status::InvalidPmUid.into() });
         }
@@ -343,6 +446,66 @@ impl SealedMemorySessionHandler {
         Ok(KeySyncResponse { status: key_sync_response::Status::Success.into() })
     }
 
+    pub async fn rotate_kek_handler(
+        &self,
+        request: RotateKekRequest,
+    ) -> anyhow::Result<RotateKekResponse> {
+        Ok(match self.rotate_kek(request).await {
+            Ok(()) => RotateKekResponse { success: true, ..Default::default() },
+            Err(err) => RotateKekResponse { success: false, error_message: format!("{err:#}") },
+        })
+    }
+
+    /// Re-wraps the DEK of the user with the new KEK and rewrites the
+    /// plaintext user info. Nothing is written unless the old KEK unwraps the
+    /// stored DEK.
+    async fn rotate_kek(&self, request: RotateKekRequest) -> anyhow::Result<()> {
+        if request.pm_uid.is_empty() {
+            bail!("pm_uid not set in RotateKekRequest");
+        }
+        if !Self::is_valid_key(&request.old_key_encryption_key)
+            || !Self::is_valid_key(&request.new_key_encryption_key)
+        {
+            bail!("Not a valid key!");
+        }
+        let uid = request.pm_uid;
+
+        let mut db_client = self
+            .db_client
+            .get_or_connect()
+            .await
+            .context("Failed to get DB client for KEK rotation")?;
+        let data_blob =
+            db_client.get_unencrypted_blob(&uid, true).await?.context("User is not registered")?;
+        let mut plain_text_info = user_info::decode(&data_blob.blob, self.metadata_key())?;
+        let dek = unwrap_dek(&plain_text_info, &request.old_key_encryption_key)?;
+
+        let nonce = generate_nonce();
+        let wrapped_key = EncryptedDataBlob {
+            data: encrypt(&request.new_key_encryption_key, &nonce, &dek)?,
+            nonce,
+            ..Default::default()
+        };
+        plain_text_info.wrapped_dek =
+            Some(WrappedDataEncryptionKey { wrapped_key: Some(wrapped_key) });
+        if let Some(key_derivation_info) = request.new_key_derivation_info {
+            plain_text_info.key_derivation_info = Some(key_derivation_info);
+        }
+
+        db_client
+            .add_unencrypted_blob(
+                DataBlob {
+                    id: uid.clone(),
+                    blob: user_info::encode(plain_text_info, self.metadata_key())?,
+                },
+                Some(uid.clone()),
+            )
+            .await
+            .context("Failed to write user info")?;
+        info!("Rotated the key encryption key of user {}", uid);
+        Ok(())
+    }
+
     pub async fn search_memory_handler(
         &self,
         request: SearchMemoryRequest,
@@ -369,6 +532,56 @@ impl SealedMemorySessionHandler {
             ..Default::default()
         })
     }
+
+    /// Unlike the other handlers, this doesn't need a user session context.
+    /// It doesn't retry, so that probes get a prompt answer while the database
+    /// service is down.
+    pub async fn ping_handler(&self, _request: PingRequest) -> anyhow::Result<PingResponse> {
+        let ready = self.db_client.check_connection(PING_TIMEOUT).await;
+        Ok(PingResponse { ready })
+    }
+
+    pub async fn rename_tag_handler(
+        &self,
+        request: RenameTagRequest,
+    ) -> anyhow::Result<RenameTagResponse> {
+        let mut mutex_guard = self.session_context().await;
+        let database = &mut mutex_guard.as_mut().context("call key sync first")?.database;
+
+        Ok(match database.rename_tag(&request.old_tag, &request.new_tag).await {
+            Ok(updated_memory_count) => RenameTagResponse {
+                success: true,
+                updated_memory_count: updated_memory_count.try_into()?,
+                ..Default::default()
+            },
+            Err(err) => RenameTagResponse {
+                success: false,
+                error_message: format!("{err:#}"),
+                ..Default::default()
+            },
+        })
+    }
+
+    pub async fn merge_tags_handler(
+        &self,
+        request: MergeTagsRequest,
+    ) -> anyhow::Result<MergeTagsResponse> {
+        let mut mutex_guard = self.session_context().await;
+        let database = &mut mutex_guard.as_mut().context("call key sync first")?.database;
+
+        Ok(match database.merge_tags(&request.source_tags, &request.target_tag).await {
+            Ok(updated_memory_count) => MergeTagsResponse {
+                success: true,
+                updated_memory_count: updated_memory_count.try_into()?,
+                ..Default::default()
+            },
+            Err(err) => MergeTagsResponse {
+                success: false,
+                error_message: format!("{err:#}"),
+                ..Default::default()
+            },
+        })
+    }
 }
 
 impl SealedMemorySessionHandler {
@@ -381,7 +594,6 @@ impl SealedMemorySessionHandler {
             .deserialize_request(request_bytes)
             .await
             .context("failed to deserialize request")?;
-        let mut message_type = None;
 
         let request_id = request.request_id;
         let request_variant = request.request.context("The request is empty. The json format might be incorrect: the data type should strictly match.")?;
@@ -390,7 +602,50 @@ impl SealedMemorySessionHandler {
         self.metrics.inc_requests(metric_name.clone());
 
         let start_time = Instant::now();
-        let mut response = match request_variant {
+        let (mut response, message_type) = if is_cancel_safe(&request_variant) {
+            self.with_request_timeout(&metric_name, self.dispatch(request_variant, request_bytes))
+                .await?
+        } else {
+            self.dispatch(request_variant, request_bytes).await?
+        };
+        let elapsed_time = start_time.elapsed().as_millis() as u64;
+        self.metrics.record_latency(elapsed_time, metric_name);
+        response.request_id = request_id;
+
+        self.serialize_response(&response, message_type).await
+    }
+
+    /// Awaits `dispatch`, or returns a `RequestTimeoutResponse` if it doesn't
+    /// complete within the request timeout.
+    async fn with_request_timeout(
+        &self,
+        metric_name: &RequestMetricName,
+        dispatch: impl Future<Output = anyhow::Result<(SealedMemoryResponse, Option<MessageType>)>>,
+    ) -> anyhow::Result<(SealedMemoryResponse, Option<MessageType>)> {
+        let Some(request_timeout) = self.config.request_timeout else {
+            return dispatch.await;
+        };
+        match tokio::time::timeout(request_timeout, dispatch).await {
+            Ok(result) => result,
+            Err(_) => {
+                warn!("{:?} timed out after {:?}", metric_name, request_timeout);
+                self.metrics.inc_failures(metric_name.clone());
+                self.metrics.inc_failures(RequestMetricName::total());
+                let timeout_ms = request_timeout.as_millis().try_into().unwrap_or(u64::MAX);
+                Ok((RequestTimeoutResponse { timeout_ms }.into_response(), None))
+            }
+        }
+    }
+
+    /// Handles `request_variant`, returning the response and the message
+    /// format it should be serialized in, if the request determined it.
+    async fn dispatch(
+        &self,
+        request_variant: sealed_memory_request::Request,
+        request_bytes: &[u8],
+    ) -> anyhow::Result<(SealedMemoryResponse, Option<MessageType>)> {
+        let mut message_type = None;
+        let response = match request_variant {
             sealed_memory_request::Request::UserRegistrationRequest(request) => {
                 let is_json = self.is_message_type_json(request_bytes);
                 if is_json {
@@ -420,13 +675,126 @@ impl SealedMemorySessionHandler {
             sealed_memory_request::Request::DeleteMemoryRequest(request) => {
                 self.delete_memory_handler(request).await?.into_response()
             }
+            sealed_memory_request::Request::RenameTagRequest(request) => {
+                self.rename_tag_handler(request).await?.into_response()
+            }
+            sealed_memory_request::Request::MergeTagsRequest(request) => {
+                self.merge_tags_handler(request).await?.into_response()
+            }
+            sealed_memory_request::Request::PingRequest(request) => {
+                self.ping_handler(request).await?.into_response()
+            }
+            sealed_memory_request::Request::RotateKekRequest(request) => {
+                self.rotate_kek_handler(request).await?.into_response()
+            }
         };
-        let elapsed_time = start_time.elapsed().as_millis() as u64;
-        self.metrics.record_latency(elapsed_time, metric_name);
-        response.request_id = request_id;
+        Ok((response, message_type))
+    }
+}
 
-        self.serialize_response(&response, message_type).await
+/// Whether the handler of `request` can be abandoned midway, and hence whether
+/// the request timeout applies to it.
+///
+/// Only read-only requests are cancel-safe. The others may make several writes
+/// to the database, the cache or the database service, e.g. to update every
+/// memory with a merged tag or to rewrap the DEK, and dropping them between
+/// two writes would leave the user's data half-updated. They always run to
+/// completion.
+fn is_cancel_safe(request: &sealed_memory_request::Request) -> bool {
+    matches!(
+        request,
+        sealed_memory_request::Request::GetMemoriesRequest(_)
+            | sealed_memory_request::Request::GetMemoryByIdRequest(_)
+            | sealed_memory_request::Request::SearchMemoryRequest(_)
+            | sealed_memory_request::Request::PingRequest(_)
+    )
+}
+
+/// Decodes a binary proto request.
+///
+/// The proto wire format is permissive: unrelated bytes may decode without
+/// setting the `request` oneof, and if several of its fields are present the
+/// last one silently wins. Such requests are rejected rather than handled as
+/// whatever they happen to decode to.
+fn decode_proto_request(request_bytes: &[u8]) -> anyhow::Result<SealedMemoryRequest> {
+    let request = SealedMemoryRequest::decode(request_bytes)?;
+    let mut oneof_fields = BTreeSet::new();
+    let mut remaining = request_bytes;
+    while !remaining.is_empty() {
+        let field_start = request_bytes.len() - remaining.len();
+        let (tag, wire_type) = prost::encoding::decode_key(&mut remaining)?;
+        prost::encoding::skip_field(wire_type, tag, &mut remaining, Default::default())?;
+        let field = &request_bytes[field_start..request_bytes.len() - remaining.len()];
+        if SealedMemoryRequest::decode(field)?.request.is_some() {
+            oneof_fields.insert(tag);
+        }
+    }
+    ensure_single_request(&request, oneof_fields.len())?;
+    Ok(request)
+}
+
+/// Decodes a JSON request, rejecting requests that set more than one field of
+/// the `request` oneof, which would otherwise be ignored but for one.
+fn decode_json_request(request_bytes: &[u8]) -> anyhow::Result<SealedMemoryRequest> {
+    let request = serde_json::from_slice::<SealedMemoryRequest>(request_bytes)?;
+    let fields =
+        serde_json::from_slice::<serde_json::Map<String, serde_json::Value>>(request_bytes)?;
+    let oneof_field_count = fields
+        .into_iter()
+        .filter(|(name, value)| {
+            let field = serde_json::Map::from_iter([(name.clone(), value.clone())]);
+            serde_json::from_value::<SealedMemoryRequest>(field.into())
+                .is_ok_and(|field| field.request.is_some())
+        })
+        .count();
+    ensure_single_request(&request, oneof_field_count)?;
+    Ok(request)
+}
+
+fn ensure_single_request(
+    request: &SealedMemoryRequest,
+    oneof_field_count: usize,
+) -> anyhow::Result<()> {
+    if request.request.is_none() {
+        bail!("Invalid request: no request type is set");
+    }
+    if oneof_field_count > 1 {
+        bail!("Invalid request: {oneof_field_count} request types are set, expected exactly one");
+    }
+    Ok(())
+}
+
+/// Decrypts the DEK in `plain_text_info` with `key`.
+///
+/// The length of the DEK is checked, so that corrupted key material is
+/// reported here rather than as an opaque error when the DEK is first used.
+fn unwrap_dek(plain_text_info: &PlainTextUserInfo, key: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let wrapped_dek = plain_text_info
+        .wrapped_dek
+        .as_ref()
+        .context("Empty wrapped dek")?
+        .wrapped_key
+        .as_ref()
+        .context("Empty wrapped dek")?;
+    let dek =
+        decrypt(key, &wrapped_dek.nonce, &wrapped_dek.data).context("Failed to decrypt DEK")?;
+    if !SealedMemorySessionHandler::is_valid_key(&dek) {
+        bail!("Corrupted key material: the unwrapped DEK has {} bytes", dek.len());
     }
+    Ok(dek)
+}
+
+/// Returns the key derivation info of `uid` if the user is registered.
+async fn registered_key_derivation_info(
+    db_client: &mut SealedMemoryDatabaseServiceClient<Channel>,
+    uid: &BlobId,
+    metadata_key: Option<&[u8]>,
+) -> anyhow::Result<Option<KeyDerivationInfo>> {
+    let Some(data_blob) = db_client.get_unencrypted_blob(uid, true).await? else {
+        return Ok(None);
+    };
+    let plain_text_info = user_info::decode(&data_blob.blob, metadata_key)?;
+    Ok(Some(plain_text_info.key_derivation_info.context("Empty key derivation info")?))
 }
 
 async fn get_or_create_db(
@@ -440,8 +808,9 @@ async fn get_or_create_db(
         if let Some(icing_db) = encrypted_info.icing_db {
             let now = Instant::now();
             info!("Loaded database successfully!!");
-            let temp_dir = tempdir()?;
-            let db = IcingMetaDatabase::import(temp_dir, icing_db.encode_to_vec().as_slice())?;
+            let db = create_db_in_temp_dir(|db_dir| {
+                IcingMetaDatabase::import(db_dir, icing_db.encode_to_vec().as_slice())
+            })?;
             let elapsed = now.elapsed();
             get_global_metrics().record_db_init_latency(elapsed.as_millis() as u64);
             return Ok(db);
@@ -452,7 +821,439 @@ async fn get_or_create_db(
 
     // This case can happen if the user is just registered, but the initial database
     // has not been created, or if the blob exists but is empty.
-    let temp_path = tempfile::tempdir()?.path().to_str().context("invalid temp path")?.to_string();
-    let db = IcingMetaDatabase::new(&temp_path)?;
+    create_db_in_temp_dir(|db_dir| IcingMetaDatabase::new(db_dir))
+}
+
+/// Creates an Icing database with `create` in a new temporary directory.
+///
+/// The directory outlives this function: it is owned by the returned
+/// database, which removes it when dropped. If `create` fails, the directory
+/// is removed right away.
+fn create_db_in_temp_dir(
+    create: impl FnOnce(&Path) -> anyhow::Result<IcingMetaDatabase>,
+) -> anyhow::Result<IcingMetaDatabase> {
+    let temp_dir = tempdir()
+        .inspect_err(|_| get_global_metrics().inc_db_dir_creation_failures())
+        .context("Failed to create a temporary directory for the Icing database")?;
+    let db = create(temp_dir.path())?;
+    let _ = temp_dir.keep();
     Ok(db)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEK: &[u8; 32] = b"aaaabbbbccccddddeeeeffffgggghhhh";
+
+    fn wrapped_plain_text_user_info(dek: &[u8]) -> PlainTextUserInfo {
+        let nonce = generate_nonce();
+        let wrapped_key = EncryptedDataBlob {
+            data: encrypt(KEK, &nonce, dek).unwrap(),
+            nonce,
+            ..Default::default()
+        };
+        PlainTextUserInfo {
+            wrapped_dek: Some(WrappedDataEncryptionKey { wrapped_key: Some(wrapped_key) }),
+            ..Default::default()
+        }
+    }
+
+    fn session_handler(config: SessionHandlerConfig) -> SealedMemorySessionHandler {
+        let (persistence_tx, _) = mpsc::unbounded_channel();
+        let db_client = SharedDbClient::new(
+            std::net::SocketAddr::from(([127, 0, 0, 1], 0)),
+            Default::default(),
+            crate::DEFAULT_DB_CONNECTION_POOL_SIZE,
+        );
+        SealedMemorySessionHandler::new(
+            get_global_metrics(),
+            persistence_tx,
+            Arc::new(db_client),
+            config,
+        )
+    }
+
+    // Sets up a session context with an empty database, which is enough for
+    // the handlers that don't need the blobs from the database service.
+    async fn set_up_empty_session_context(handler: &SealedMemorySessionHandler) {
+        let database_service_client = SealedMemoryDatabaseServiceClient::new(
+            Channel::from_static("http://127.0.0.1:0").connect_lazy(),
+        );
+        let database = handler.new_database_with_cache(
+            create_db_in_temp_dir(|db_dir| IcingMetaDatabase::new(db_dir)).unwrap(),
+            KEK.to_vec(),
+            database_service_client.clone(),
+            KeyDerivationInfo::default(),
+        );
+        *handler.session_context().await = Some(UserSessionContext {
+            dek: KEK.to_vec(),
+            uid: "uid".to_string(),
+            message_type: MessageType::BinaryProto,
+            database,
+            database_service_client,
+        });
+    }
+
+    #[tokio::test]
+    async fn test_serialize_pre_session_response_uses_configured_default() {
+        let response = SealedMemoryResponse { request_id: 42, ..Default::default() };
+
+        let binary = session_handler(SessionHandlerConfig::default())
+            .serialize_response(&response, None)
+            .await
+            .unwrap();
+        assert_eq!(SealedMemoryResponse::decode(binary.as_slice()).unwrap(), response);
+
+        let handler = session_handler(SessionHandlerConfig {
+            default_message_type: MessageType::Json,
+            ..Default::default()
+        });
+        let json = handler.serialize_response(&response, None).await.unwrap();
+        assert_eq!(serde_json::from_slice::<SealedMemoryResponse>(&json).unwrap(), response);
+
+        // A format chosen by the caller still takes precedence.
+        let binary =
+            handler.serialize_response(&response, Some(MessageType::BinaryProto)).await.unwrap();
+        assert_eq!(binary, response.encode_to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_slow_request_times_out() {
+        let handler = session_handler(SessionHandlerConfig {
+            request_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        });
+        let metric_name = RequestMetricName::new_sealed_memory_request(
+            &sealed_memory_request::Request::PingRequest(PingRequest {}),
+        );
+
+        let ping = PingResponse { ready: true }.into_response();
+        let fast_handler = async { Ok((ping.clone(), None)) };
+        let (response, _) = handler.with_request_timeout(&metric_name, fast_handler).await.unwrap();
+        assert_eq!(response, ping);
+
+        let slow_handler = std::future::pending();
+        let (response, message_type) = tokio::time::timeout(
+            Duration::from_secs(10),
+            handler.with_request_timeout(&metric_name, slow_handler),
+        )
+        .await
+        .expect("the request timeout didn't apply")
+        .unwrap();
+        assert_eq!(response, RequestTimeoutResponse { timeout_ms: 50 }.into_response());
+        assert!(message_type.is_none());
+
+        // Errors of a handler that completes in time are returned as they are.
+        let failing_handler = async { Err(anyhow::anyhow!("failed")) };
+        assert!(handler.with_request_timeout(&metric_name, failing_handler).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_active_sessions_counts_each_session_once() {
+        let db_listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let db_addr = db_listener.local_addr().unwrap();
+        let db_server =
+            tokio::spawn(private_memory_test_database_server_lib::service::create(db_listener));
+        let mut observer = oak_containers_agent::metrics::OakObserver::create(
+            "http://127.0.0.1:0".to_string(),
+            "handler_test",
+            vec![],
+        )
+        .unwrap();
+        let metrics = Arc::new(metrics::Metrics::new(&mut observer));
+        let db_client = Arc::new(SharedDbClient::new(
+            db_addr,
+            Default::default(),
+            crate::DEFAULT_DB_CONNECTION_POOL_SIZE,
+        ));
+        let new_handler = || {
+            let (persistence_tx, _) = mpsc::unbounded_channel();
+            SealedMemorySessionHandler::new(
+                metrics.clone(),
+                persistence_tx,
+                db_client.clone(),
+                SessionHandlerConfig::default(),
+            )
+        };
+        let key_sync_request =
+            || KeySyncRequest { key_encryption_key: KEK.to_vec(), pm_uid: "uid".to_string() };
+
+        // Registering sets up the session, and syncing the key again on the
+        // same session doesn't count it twice.
+        let first_handler = new_handler();
+        first_handler
+            .boot_strap_handler(
+                UserRegistrationRequest {
+                    pm_uid: "uid".to_string(),
+                    key_encryption_key: KEK.to_vec(),
+                    boot_strap_info: Some(KeyDerivationInfo::default()),
+                    check_only: false,
+                },
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(metrics.active_sessions(), 1);
+        first_handler.key_sync_handler(key_sync_request(), false).await.unwrap();
+        assert_eq!(metrics.active_sessions(), 1);
+
+        let second_handler = new_handler();
+        assert_eq!(metrics.active_sessions(), 1);
+        second_handler.key_sync_handler(key_sync_request(), false).await.unwrap();
+        second_handler.key_sync_handler(key_sync_request(), false).await.unwrap();
+        assert_eq!(metrics.active_sessions(), 2);
+
+        drop(first_handler);
+        assert_eq!(metrics.active_sessions(), 1);
+        drop(second_handler);
+        assert_eq!(metrics.active_sessions(), 0);
+
+        // A handler that never set up a session doesn't decrement the gauge.
+        drop(new_handler());
+        assert_eq!(metrics.active_sessions(), 0);
+
+        db_server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_blocked_read_request_times_out() {
+        let handler = session_handler(SessionHandlerConfig {
+            request_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        });
+        set_up_empty_session_context(&handler).await;
+        let request = sealed_memory_request::Request::GetMemoriesRequest(GetMemoriesRequest {
+            page_size: 10,
+            ..Default::default()
+        });
+        let metric_name = RequestMetricName::new_sealed_memory_request(&request);
+        assert!(is_cancel_safe(&request));
+
+        // Holding the session context blocks the handler until it's abandoned.
+        let session_context = handler.session_context().await;
+        let (response, _) = tokio::time::timeout(
+            Duration::from_secs(10),
+            handler.with_request_timeout(&metric_name, handler.dispatch(request.clone(), &[])),
+        )
+        .await
+        .expect("the request timeout didn't apply")
+        .unwrap();
+        assert_eq!(response, RequestTimeoutResponse { timeout_ms: 50 }.into_response());
+
+        drop(session_context);
+        let (response, _) = handler
+            .with_request_timeout(&metric_name, handler.dispatch(request, &[]))
+            .await
+            .unwrap();
+        assert!(
+            matches!(
+                response.response,
+                Some(sealed_memory_response::Response::GetMemoriesResponse(_))
+            ),
+            "{response:?}"
+        );
+    }
+
+    #[test]
+    fn test_mutating_requests_are_not_cancel_safe() {
+        for request in [
+            sealed_memory_request::Request::AddMemoryRequest(Default::default()),
+            sealed_memory_request::Request::MergeTagsRequest(Default::default()),
+            sealed_memory_request::Request::RotateKekRequest(Default::default()),
+            sealed_memory_request::Request::KeySyncRequest(Default::default()),
+        ] {
+            assert!(!is_cancel_safe(&request), "{request:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_rejects_embedding_with_unconfigured_dimension() {
+        let handler = session_handler(SessionHandlerConfig {
+            embedding_dimension: Some(3),
+            ..Default::default()
+        });
+        set_up_empty_session_context(&handler).await;
+        let search_request = |values: &[f32]| SearchMemoryRequest {
+            query: Some(SearchMemoryQuery {
+                clause: Some(search_memory_query::Clause::EmbeddingQuery(EmbeddingQuery {
+                    embedding: vec![Embedding {
+                        identifier: "model".to_string(),
+                        values: values.to_vec(),
+                    }],
+                    ..Default::default()
+                })),
+            }),
+            page_size: 10,
+            ..Default::default()
+        };
+
+        assert!(handler.search_memory_handler(search_request(&[1.0, 0.0])).await.is_err());
+        assert!(handler.search_memory_handler(search_request(&[1.0, 0.0, 1.0])).await.is_ok());
+    }
+
+    #[test]
+    fn test_decode_request_with_single_request_type() {
+        let request = SealedMemoryRequest {
+            request: Some(sealed_memory_request::Request::PingRequest(PingRequest {})),
+            request_id: 7,
+        };
+
+        assert_eq!(decode_proto_request(&request.encode_to_vec()).unwrap(), request);
+        assert_eq!(decode_json_request(br#"{"pingRequest":{},"requestId":7}"#).unwrap(), request);
+    }
+
+    #[test]
+    fn test_decode_request_rejects_empty_request_type() {
+        let request = SealedMemoryRequest { request: None, request_id: 7 };
+
+        assert_eq!(
+            decode_proto_request(&request.encode_to_vec()).unwrap_err().to_string(),
+            "Invalid request: no request type is set"
+        );
+        assert_eq!(
+            decode_json_request(br#"{"requestId":7}"#).unwrap_err().to_string(),
+            "Invalid request: no request type is set"
+        );
+    }
+
+    #[test]
+    fn test_decode_request_rejects_multiple_request_types() {
+        let add_memory = SealedMemoryRequest {
+            request: Some(sealed_memory_request::Request::AddMemoryRequest(
+                AddMemoryRequest::default(),
+            )),
+            request_id: 7,
+        };
+        let ping = SealedMemoryRequest {
+            request: Some(sealed_memory_request::Request::PingRequest(PingRequest {})),
+            request_id: 7,
+        };
+        // Both decode on their own, with the last request type winning.
+        let request_bytes = [add_memory.encode_to_vec(), ping.encode_to_vec()].concat();
+        assert_eq!(SealedMemoryRequest::decode(request_bytes.as_slice()).unwrap(), ping);
+
+        assert_eq!(
+            decode_proto_request(&request_bytes).unwrap_err().to_string(),
+            "Invalid request: 2 request types are set, expected exactly one"
+        );
+        assert_eq!(
+            decode_json_request(br#"{"addMemoryRequest":{},"pingRequest":{},"requestId":7}"#)
+                .unwrap_err()
+                .to_string(),
+            "Invalid request: 2 request types are set, expected exactly one"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_db_in_temp_dir_lives_as_long_as_db() {
+        let mut db = create_db_in_temp_dir(|db_dir| IcingMetaDatabase::new(db_dir)).unwrap();
+        let base_dir = std::path::PathBuf::from(db.base_dir());
+        tokio::task::yield_now().await;
+
+        let memory = Memory {
+            id: "memory".to_string(),
+            tags: vec!["tag".to_string()],
+            ..Default::default()
+        };
+        db.add_memory(&memory, "12345".to_string()).unwrap();
+        tokio::task::yield_now().await;
+
+        let (blob_ids, _) = db.get_memories_by_tag("tag", 10, PageToken::Start).unwrap();
+        assert_eq!(blob_ids, vec!["12345".to_string()]);
+        assert!(base_dir.exists());
+
+        drop(db);
+        assert!(!base_dir.exists());
+    }
+
+    #[tokio::test]
+    async fn test_stored_db_is_imported_into_temp_dir_living_as_long_as_db() {
+        let db_listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let db_addr = db_listener.local_addr().unwrap();
+        let db_server =
+            tokio::spawn(private_memory_test_database_server_lib::service::create(db_listener));
+        let mut db_client = SharedDbClient::new(
+            db_addr,
+            Default::default(),
+            crate::DEFAULT_DB_CONNECTION_POOL_SIZE,
+        )
+        .get_or_connect()
+        .await
+        .unwrap();
+        let uid = "uid".to_string();
+
+        // Store a database the way the persistence worker does.
+        let mut stored_db = create_db_in_temp_dir(|db_dir| IcingMetaDatabase::new(db_dir)).unwrap();
+        let memory = Memory {
+            id: "stored".to_string(),
+            tags: vec!["tag".to_string()],
+            ..Default::default()
+        };
+        stored_db.add_memory(&memory, "12345".to_string()).unwrap();
+        let encrypted_info = EncryptedUserInfo { icing_db: Some(stored_db.export().unwrap()) };
+        drop(stored_db);
+        db_client
+            .add_blob(encrypt_database(&encrypted_info, KEK, false).unwrap(), Some(uid.clone()))
+            .await
+            .unwrap();
+
+        let mut db = get_or_create_db(&mut db_client, &uid, KEK).await.unwrap();
+        let base_dir = std::path::PathBuf::from(db.base_dir());
+        tokio::task::yield_now().await;
+
+        let memory =
+            Memory { id: "added".to_string(), tags: vec!["tag".to_string()], ..Default::default() };
+        db.add_memory(&memory, "67890".to_string()).unwrap();
+        tokio::task::yield_now().await;
+
+        let (mut blob_ids, _) = db.get_memories_by_tag("tag", 10, PageToken::Start).unwrap();
+        blob_ids.sort();
+        assert_eq!(blob_ids, vec!["12345".to_string(), "67890".to_string()]);
+        assert!(base_dir.exists());
+
+        drop(db);
+        assert!(!base_dir.exists());
+
+        db_server.abort();
+    }
+
+    #[test]
+    fn test_create_db_in_temp_dir_removes_dir_on_failure() {
+        let mut db_dir = None;
+
+        let result = create_db_in_temp_dir(|dir| {
+            db_dir = Some(dir.to_path_buf());
+            bail!("failed to create database")
+        });
+
+        assert!(result.is_err());
+        assert!(!db_dir.unwrap().exists());
+    }
+
+    #[test]
+    fn test_unwrap_dek() {
+        let dek = [7u8; 32];
+
+        assert_eq!(unwrap_dek(&wrapped_plain_text_user_info(&dek), KEK).unwrap(), dek);
+    }
+
+    #[test]
+    fn test_unwrap_dek_rejects_tampered_dek() {
+        let mut info = wrapped_plain_text_user_info(&[7u8; 32]);
+        let wrapped_key = info.wrapped_dek.as_mut().unwrap().wrapped_key.as_mut().unwrap();
+        wrapped_key.data[0] ^= 1;
+
+        assert_eq!(unwrap_dek(&info, KEK).unwrap_err().to_string(), "Failed to decrypt DEK");
+    }
+
+    #[test]
+    fn test_unwrap_dek_rejects_wrong_length() {
+        let info = wrapped_plain_text_user_info(&[7u8; 16]);
+
+        assert_eq!(
+            unwrap_dek(&info, KEK).unwrap_err().to_string(),
+            "Corrupted key material: the unwrapped DEK has 16 bytes"
+        );
+    }
+}
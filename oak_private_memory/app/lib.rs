@@ -23,11 +23,14 @@ mod handler;
 mod packing;
 mod persistence_worker;
 pub mod service;
+mod user_info;
 
+pub use db_client::{DbConnectRetryConfig, DEFAULT_DB_CONNECTION_POOL_SIZE};
 pub use persistence_worker::run_persistence_service;
 
 // The message format for the plaintext.
-#[derive(Default, Copy, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Default, Copy, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
 pub enum MessageType {
     #[default]
     BinaryProto,
@@ -38,4 +41,63 @@ pub enum MessageType {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ApplicationConfig {
     pub database_service_host: SocketAddr,
+    /// Optional; defaults to 5 attempts with a 100ms initial backoff.
+    #[serde(default)]
+    pub db_connect_retry_config: DbConnectRetryConfig,
+    /// Optional; the number of connections to the database service that
+    /// requests are spread over. Defaults to a single connection.
+    #[serde(default = "default_db_connection_pool_size")]
+    pub db_connection_pool_size: usize,
+    /// Optional; a 256-bit key that the metadata of the stored user info,
+    /// such as the key derivation info, is encrypted with. Existing user info
+    /// is migrated when the user next syncs their key. Once set, it must not
+    /// be removed, as the migrated user info can't be read without it. The
+    /// server fails to start if the key isn't 32 bytes long.
+    #[serde(default)]
+    pub metadata_encryption_key: Option<Vec<u8>>,
+    /// Optional; whether the meta database of a user is checked for memories
+    /// whose blob is missing when it is loaded. Disabled by default, as the
+    /// check reads every memory of the user.
+    #[serde(default)]
+    pub db_integrity_check: DbIntegrityCheck,
+    /// Optional; the format of responses sent before the message format of
+    /// the session is known, e.g. to report that the first request failed.
+    /// Defaults to binary proto; set it to JSON for deployments behind a JSON
+    /// gateway.
+    #[serde(default)]
+    pub default_message_type: MessageType,
+    /// Optional; how long a read-only request may take before it is abandoned
+    /// and a `RequestTimeoutResponse` is returned instead. Requests that
+    /// modify the user's data always run to completion, since abandoning them
+    /// midway could leave the data half-updated. Unlimited by default.
+    #[serde(default)]
+    pub request_timeout_ms: Option<u64>,
+    /// Optional; the number of values in the embeddings of the memories.
+    /// Search queries with embeddings of any other dimension are rejected.
+    /// Unchecked by default.
+    #[serde(default)]
+    pub embedding_dimension: Option<usize>,
+    /// Optional; whether persisted databases are compressed with xz before
+    /// they are encrypted. Databases written either way can be read
+    /// regardless of this setting. Disabled by default.
+    #[serde(default)]
+    pub compress_database: bool,
+}
+
+/// What to do about memories in a loaded meta database that reference a blob
+/// that doesn't exist, such as after a partially failed write.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DbIntegrityCheck {
+    /// The meta database isn't checked.
+    #[default]
+    Disabled,
+    /// Orphaned memories are logged.
+    Report,
+    /// Orphaned memories are logged and removed from the meta database.
+    Prune,
+}
+
+fn default_db_connection_pool_size() -> usize {
+    DEFAULT_DB_CONNECTION_POOL_SIZE
 }
@@ -115,13 +115,22 @@ impl_packing!(Request => GetMemoryByIdRequest);
 impl_packing!(Request => SearchMemoryRequest);
 impl_packing!(Request => UserRegistrationRequest);
 impl_packing!(Request => DeleteMemoryRequest);
+impl_packing!(Request => RenameTagRequest);
+impl_packing!(Request => MergeTagsRequest);
+impl_packing!(Request => PingRequest);
+impl_packing!(Request => RotateKekRequest);
 
 impl_packing!(Response => AddMemoryResponse);
 impl_packing!(Response => GetMemoriesResponse);
 impl_packing!(Response => ResetMemoryResponse);
 impl_packing!(Response => InvalidRequestResponse);
+impl_packing!(Response => RequestTimeoutResponse);
 impl_packing!(Response => KeySyncResponse);
 impl_packing!(Response => GetMemoryByIdResponse);
 impl_packing!(Response => SearchMemoryResponse);
 impl_packing!(Response => DeleteMemoryResponse);
+impl_packing!(Response => RenameTagResponse);
+impl_packing!(Response => MergeTagsResponse);
+impl_packing!(Response => PingResponse);
+impl_packing!(Response => RotateKekResponse);
 impl_packing!(Response => UserRegistrationResponse);
@@ -18,10 +18,15 @@ use external_db_client::DataBlobHandler;
 use log::info;
 use metrics::get_global_metrics;
 use oak_private_memory_database::encryption::encrypt_database;
+use prost::Message;
 use tokio::{sync::mpsc, time::Instant};
 
 use crate::context::UserSessionContext;
-async fn persist_database(user_context: &mut UserSessionContext) -> anyhow::Result<()> {
+
+async fn persist_database(
+    user_context: &mut UserSessionContext,
+    compress_database: bool,
+) -> anyhow::Result<()> {
     if !user_context.database.changed() {
         info!("Database is not changed, skip saving");
         return Ok(());
@@ -29,11 +34,13 @@ async fn persist_database(user_context: &mut UserSessionContext) -> anyhow::Resu
 
     let exported_db = user_context.database.export()?;
     let encrypted_info = exported_db.encrypted_info.context("Encrypted info is empty")?;
-    let database = encrypt_database(&encrypted_info, &user_context.dek)?;
+    let uncompressed_db_size = encrypted_info.encoded_len() as u64;
+    let database = encrypt_database(&encrypted_info, &user_context.dek, compress_database)?;
 
     let db_size = database.data.len() as u64;
-    info!("Saving db size: {}", db_size);
+    info!("Saving db size: {} (uncompressed: {})", db_size, uncompressed_db_size);
     get_global_metrics().record_db_size(db_size);
+    get_global_metrics().record_db_compression_ratio(db_size, uncompressed_db_size);
 
     let now = Instant::now();
     user_context.database_service_client.add_blob(database, Some(user_context.uid.clone())).await?;
@@ -43,12 +50,17 @@ async fn persist_database(user_context: &mut UserSessionContext) -> anyhow::Resu
     Ok(())
 }
 
-pub async fn run_persistence_service(mut rx: mpsc::UnboundedReceiver<UserSessionContext>) {
+/// Persists the databases of the sessions received on `rx`, compressing them
+/// first if `compress_database` is set.
+pub async fn run_persistence_service(
+    mut rx: mpsc::UnboundedReceiver<UserSessionContext>,
+    compress_database: bool,
+) {
     info!("Persistence service started");
     while let Some(mut user_context) = rx.recv().await {
         info!("Persistence service received a session to save");
         get_global_metrics().record_db_persist_queue_size(rx.len() as u64);
-        if let Err(e) = persist_database(&mut user_context).await {
+        if let Err(e) = persist_database(&mut user_context, compress_database).await {
             get_global_metrics().inc_db_persist_failures();
             info!("Failed to persist database: {:?}", e);
         }
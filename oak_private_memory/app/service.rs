@@ -13,7 +13,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::{pin::Pin, sync::Arc};
+use std::{
+    pin::Pin,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use anyhow::anyhow;
 use log::debug;
@@ -34,8 +38,10 @@ use tokio::{net::TcpListener, sync::mpsc};
 use tokio_stream::{wrappers::TcpListenerStream, Stream, StreamExt};
 
 use crate::{
-    context::UserSessionContext, db_client::SharedDbClient, handler::SealedMemorySessionHandler,
-    ApplicationConfig,
+    context::UserSessionContext,
+    db_client::SharedDbClient,
+    handler::{SealedMemorySessionHandler, SessionHandlerConfig},
+    user_info, ApplicationConfig,
 };
 
 // The struct that holds the service implementation.
@@ -44,6 +50,7 @@ struct SealedMemoryServiceImplementation {
     metrics: Arc<metrics::Metrics>,
     persistence_tx: mpsc::UnboundedSender<UserSessionContext>,
     db_client: Arc<SharedDbClient>,
+    handler_config: SessionHandlerConfig,
 }
 
 impl SealedMemoryServiceImplementation {
@@ -51,16 +58,35 @@ impl SealedMemoryServiceImplementation {
         application_config: ApplicationConfig,
         metrics: Arc<metrics::Metrics>,
         persistence_tx: mpsc::UnboundedSender<UserSessionContext>,
-    ) -> Self {
-        Self {
+    ) -> anyhow::Result<Self> {
+        if let Some(metadata_key) = &application_config.metadata_encryption_key {
+            user_info::check_metadata_key(metadata_key)?;
+        }
+        Ok(Self {
             metrics,
             persistence_tx,
-            db_client: Arc::new(SharedDbClient::new(application_config.database_service_host)),
-        }
+            db_client: Arc::new(SharedDbClient::new(
+                application_config.database_service_host,
+                application_config.db_connect_retry_config,
+                application_config.db_connection_pool_size,
+            )),
+            handler_config: SessionHandlerConfig {
+                metadata_key: application_config.metadata_encryption_key.map(Arc::new),
+                db_integrity_check: application_config.db_integrity_check,
+                default_message_type: application_config.default_message_type,
+                request_timeout: application_config.request_timeout_ms.map(Duration::from_millis),
+                embedding_dimension: application_config.embedding_dimension,
+            },
+        })
     }
 
     fn new_oak_session_handler(&self) -> anyhow::Result<OakSessionHandler> {
-        OakSessionHandler::new(&self.metrics, &self.persistence_tx, self.db_client.clone())
+        OakSessionHandler::new(
+            &self.metrics,
+            &self.persistence_tx,
+            self.db_client.clone(),
+            self.handler_config.clone(),
+        )
     }
 }
 
@@ -71,6 +97,9 @@ impl SealedMemoryServiceImplementation {
 struct OakSessionHandler {
     metrics: Arc<metrics::Metrics>,
     server_session: ServerSession,
+    attestation_type: AttestationType,
+    // The time at which the first handshake message was received.
+    handshake_start: Option<Instant>,
     application_handler: SealedMemorySessionHandler,
 }
 
@@ -79,16 +108,21 @@ impl OakSessionHandler {
         metrics: &Arc<metrics::Metrics>,
         persistence_tx: &mpsc::UnboundedSender<UserSessionContext>,
         db_client: Arc<SharedDbClient>,
+        handler_config: SessionHandlerConfig,
     ) -> anyhow::Result<Self> {
+        let attestation_type = AttestationType::Unattested;
         Ok(Self {
             metrics: metrics.clone(),
             server_session: ServerSession::create(
-                SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN).build(),
+                SessionConfig::builder(attestation_type, HandshakeType::NoiseNN).build(),
             )?,
+            attestation_type,
+            handshake_start: None,
             application_handler: SealedMemorySessionHandler::new(
                 metrics.clone(),
                 persistence_tx.clone(),
                 db_client,
+                handler_config,
             ),
         })
     }
@@ -128,26 +162,35 @@ impl OakSessionHandler {
         session_request: SessionRequest,
     ) -> tonic::Result<Option<SessionResponse>> {
         self.metrics.inc_requests(RequestMetricName::handshake());
+        let handshake_start = *self.handshake_start.get_or_insert_with(Instant::now);
         self.server_session
             .handle_init_message(session_request)
             .into_tonic_result("failed to handle init request")?;
 
         // The server may optionally need to send an init response.
-        if !self.server_session.is_open() {
+        let response = if !self.server_session.is_open() {
             match self
                 .server_session
                 .next_init_message()
                 .into_tonic_result("failed to get next init message")
             {
-                Ok(r) => Ok(Some(r)),
+                Ok(r) => Some(r),
                 Err(e) => {
                     self.metrics.inc_failures(RequestMetricName::handshake());
-                    Err(e)
+                    return Err(e);
                 }
             }
         } else {
-            Ok(None)
+            None
+        };
+
+        if self.server_session.is_open() {
+            self.metrics.record_handshake_latency(
+                handshake_start.elapsed().as_millis() as u64,
+                self.attestation_type,
+            );
         }
+        Ok(response)
     }
 
     async fn handle_app_request(
@@ -244,7 +287,7 @@ pub async fn create(
                 application_config,
                 metrics,
                 persistence_tx,
-            ))
+            )?)
             .max_decoding_message_size(20 * 1024 * 1024), /* 20MB */
         )
         .serve_with_incoming(TcpListenerStream::new(listener))
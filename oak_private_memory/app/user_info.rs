@@ -0,0 +1,291 @@
+//
+// Copyright 2025 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Storage format of [`PlainTextUserInfo`].
+//!
+//! The user info is stored as an unencrypted blob under the uid of the user,
+//! so that it can be looked up before the user provides their KEK. If the
+//! server is configured with a metadata key, only the fields needed before the
+//! user info can be decrypted stay in plaintext: `version`, and `wrapped_dek`,
+//! which is already encrypted with the KEK. All other fields are moved into
+//! `encrypted_metadata`.
+
+use anyhow::{bail, ensure, Context};
+use encryption::{decrypt, encrypt, generate_nonce};
+use external_db_client::{BlobId, DataBlobHandler};
+use prost::Message;
+use sealed_memory_rust_proto::prelude::v1::*;
+
+/// The version of user info with all fields in plaintext.
+pub const PLAINTEXT_METADATA_VERSION: i32 = 1;
+
+/// The version of user info whose metadata is encrypted with the server's
+/// metadata key.
+pub const ENCRYPTED_METADATA_VERSION: i32 = 2;
+
+/// The length in bytes of the metadata key, which is an AES-256 key.
+pub const METADATA_KEY_LENGTH: usize = 32;
+
+/// Checks that `metadata_key` can be used to encrypt the metadata, so that a
+/// misconfigured key is rejected on startup rather than on the first request.
+pub fn check_metadata_key(metadata_key: &[u8]) -> anyhow::Result<()> {
+    ensure!(
+        metadata_key.len() == METADATA_KEY_LENGTH,
+        "The metadata key must be {} bytes long, got {}",
+        METADATA_KEY_LENGTH,
+        metadata_key.len()
+    );
+    Ok(())
+}
+
+/// Decodes the user info stored in `blob`, decrypting its metadata with
+/// `metadata_key` if needed. Versions this server doesn't know how to
+/// interpret are rejected.
+///
+/// The returned user info has all its fields in plaintext, but keeps the
+/// stored version.
+pub fn decode(blob: &[u8], metadata_key: Option<&[u8]>) -> anyhow::Result<PlainTextUserInfo> {
+    let mut info = PlainTextUserInfo::decode(blob).context("Failed to decode PlainTextUserInfo")?;
+    match info.version {
+        // Version 0 is used by users registered before the version was recorded.
+        0 | PLAINTEXT_METADATA_VERSION => {}
+        ENCRYPTED_METADATA_VERSION => {
+            let encrypted_metadata =
+                info.encrypted_metadata.take().context("Empty encrypted metadata")?;
+            let metadata_key = metadata_key.context(
+                "The user info metadata is encrypted, but no metadata key is configured",
+            )?;
+            let metadata =
+                decrypt(metadata_key, &encrypted_metadata.nonce, &encrypted_metadata.data)
+                    .context("Failed to decrypt user info metadata")?;
+            let metadata = UserMetadata::decode(metadata.as_slice())
+                .context("Failed to decode user info metadata")?;
+            info.key_derivation_info = metadata.key_derivation_info;
+        }
+        version => bail!(
+            "Unsupported PlainTextUserInfo version {}, expected at most {}",
+            version,
+            ENCRYPTED_METADATA_VERSION
+        ),
+    }
+    Ok(info)
+}
+
+/// Encodes `info` for storage, encrypting its metadata if a `metadata_key` is
+/// configured. The version of `info` is ignored.
+pub fn encode(mut info: PlainTextUserInfo, metadata_key: Option<&[u8]>) -> anyhow::Result<Vec<u8>> {
+    match metadata_key {
+        Some(metadata_key) => {
+            let metadata = UserMetadata { key_derivation_info: info.key_derivation_info.take() };
+            let nonce = generate_nonce();
+            info.encrypted_metadata = Some(EncryptedDataBlob {
+                data: encrypt(metadata_key, &nonce, &metadata.encode_to_vec())
+                    .context("Failed to encrypt user info metadata")?,
+                nonce,
+                ..Default::default()
+            });
+            info.version = ENCRYPTED_METADATA_VERSION;
+        }
+        None => {
+            info.encrypted_metadata = None;
+            info.version = PLAINTEXT_METADATA_VERSION;
+        }
+    }
+    Ok(info.encode_to_vec())
+}
+
+/// Rewrites the user info of `uid` with its metadata encrypted, if it was
+/// stored before a metadata key was configured. Returns whether the user info
+/// was rewritten.
+pub async fn migrate<D: DataBlobHandler + Send>(
+    db_client: &mut D,
+    uid: &BlobId,
+    metadata_key: &[u8],
+) -> anyhow::Result<bool> {
+    let data_blob =
+        db_client.get_unencrypted_blob(uid, true).await?.context("User is not registered")?;
+    let info = decode(&data_blob.blob, Some(metadata_key))?;
+    if info.version == ENCRYPTED_METADATA_VERSION {
+        return Ok(false);
+    }
+    db_client
+        .add_unencrypted_blob(
+            DataBlob { id: uid.clone(), blob: encode(info, Some(metadata_key))? },
+            Some(uid.clone()),
+        )
+        .await
+        .context("Failed to write user info")?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use async_trait::async_trait;
+
+    use super::*;
+
+    const METADATA_KEY: &[u8; METADATA_KEY_LENGTH] = b"00001111222233334444555566667777";
+
+    fn user_info(version: i32) -> PlainTextUserInfo {
+        PlainTextUserInfo {
+            key_derivation_info: Some(KeyDerivationInfo {
+                kek_salt: b"salt".to_vec(),
+                kek_version: 1,
+            }),
+            wrapped_dek: Some(WrappedDataEncryptionKey::default()),
+            version,
+            ..Default::default()
+        }
+    }
+
+    /// Stores unencrypted blobs in memory. Encrypted blobs are not supported.
+    #[derive(Default)]
+    struct FakeDbClient {
+        unencrypted_blobs: HashMap<BlobId, DataBlob>,
+    }
+
+    #[async_trait]
+    impl DataBlobHandler for FakeDbClient {
+        async fn add_blob(
+            &mut self,
+            _data_blob: EncryptedDataBlob,
+            _id: Option<BlobId>,
+        ) -> anyhow::Result<BlobId> {
+            bail!("not supported by FakeDbClient")
+        }
+        async fn add_blobs(
+            &mut self,
+            _data_blobs: Vec<EncryptedDataBlob>,
+            _ids: Option<Vec<BlobId>>,
+        ) -> anyhow::Result<Vec<BlobId>> {
+            bail!("not supported by FakeDbClient")
+        }
+        async fn get_blob(
+            &mut self,
+            _id: &BlobId,
+            _strong_read: bool,
+        ) -> anyhow::Result<Option<EncryptedDataBlob>> {
+            bail!("not supported by FakeDbClient")
+        }
+        async fn get_blobs(
+            &mut self,
+            _ids: &[BlobId],
+            _strong_read: bool,
+        ) -> anyhow::Result<Vec<Option<EncryptedDataBlob>>> {
+            bail!("not supported by FakeDbClient")
+        }
+        async fn add_unencrypted_blob(
+            &mut self,
+            data_blob: DataBlob,
+            id: Option<BlobId>,
+        ) -> anyhow::Result<BlobId> {
+            let id = id.unwrap_or_else(|| data_blob.id.clone());
+            self.unencrypted_blobs.insert(id.clone(), data_blob);
+            Ok(id)
+        }
+        async fn get_unencrypted_blob(
+            &mut self,
+            id: &BlobId,
+            _strong_read: bool,
+        ) -> anyhow::Result<Option<DataBlob>> {
+            Ok(self.unencrypted_blobs.get(id).cloned())
+        }
+        async fn add_mixed_blobs(
+            &mut self,
+            _encrypted_contents: Vec<EncryptedDataBlob>,
+            _encrypted_ids: Option<Vec<BlobId>>,
+            _unencrypted_blobs: Vec<DataBlob>,
+        ) -> anyhow::Result<()> {
+            bail!("not supported by FakeDbClient")
+        }
+    }
+
+    #[test]
+    fn test_decode_accepts_plaintext_versions() {
+        for version in [0, PLAINTEXT_METADATA_VERSION] {
+            let info = user_info(version);
+            assert_eq!(decode(&info.encode_to_vec(), None).unwrap(), info);
+        }
+    }
+
+    #[test]
+    fn test_decode_rejects_unknown_version() {
+        let info = user_info(ENCRYPTED_METADATA_VERSION + 1);
+
+        assert_eq!(
+            decode(&info.encode_to_vec(), None).unwrap_err().to_string(),
+            "Unsupported PlainTextUserInfo version 3, expected at most 2"
+        );
+    }
+
+    #[test]
+    fn test_encode_encrypts_metadata() {
+        let blob = encode(user_info(0), Some(METADATA_KEY)).unwrap();
+
+        let stored = PlainTextUserInfo::decode(blob.as_slice()).unwrap();
+        assert_eq!(stored.version, ENCRYPTED_METADATA_VERSION);
+        assert_eq!(stored.key_derivation_info, None);
+        assert_eq!(stored.wrapped_dek, Some(WrappedDataEncryptionKey::default()));
+        assert!(stored.encrypted_metadata.is_some());
+
+        assert_eq!(
+            decode(&blob, Some(METADATA_KEY)).unwrap(),
+            user_info(ENCRYPTED_METADATA_VERSION)
+        );
+        assert_eq!(
+            decode(&blob, None).unwrap_err().to_string(),
+            "The user info metadata is encrypted, but no metadata key is configured"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_migrate_encrypts_plaintext_user_info() {
+        let uid = "uid".to_string();
+        let mut db_client = FakeDbClient::default();
+        db_client
+            .add_unencrypted_blob(
+                DataBlob { id: uid.clone(), blob: encode(user_info(0), None).unwrap() },
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert!(migrate(&mut db_client, &uid, METADATA_KEY).await.unwrap());
+
+        let blob = db_client.get_unencrypted_blob(&uid, true).await.unwrap().unwrap().blob;
+        assert_eq!(PlainTextUserInfo::decode(blob.as_slice()).unwrap().key_derivation_info, None);
+        assert_eq!(
+            decode(&blob, Some(METADATA_KEY)).unwrap(),
+            user_info(ENCRYPTED_METADATA_VERSION)
+        );
+
+        // Migrated user info is left as is.
+        assert!(!migrate(&mut db_client, &uid, METADATA_KEY).await.unwrap());
+        assert_eq!(db_client.get_unencrypted_blob(&uid, true).await.unwrap().unwrap().blob, blob);
+    }
+
+    #[test]
+    fn test_check_metadata_key() {
+        assert!(check_metadata_key(METADATA_KEY).is_ok());
+        assert_eq!(
+            check_metadata_key(b"short").unwrap_err().to_string(),
+            "The metadata key must be 32 bytes long, got 5"
+        );
+        assert!(check_metadata_key(&[0; METADATA_KEY_LENGTH + 1]).is_err());
+    }
+}
@@ -13,8 +13,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use anyhow::Context;
-use external_db_client::ExternalDbClient;
+use std::{cmp::Ordering, collections::HashSet, ops::Range};
+
+use anyhow::{bail, ensure, Context};
+use external_db_client::{BlobId, ExternalDbClient};
+use log::{error, info};
 use rand::Rng;
 use sealed_memory_rust_proto::prelude::v1::*;
 
@@ -31,16 +34,35 @@ pub struct DatabaseWithCache {
     database: IcingMetaDatabase,
     cache: MemoryCache,
     key_derivation_info: KeyDerivationInfo,
+    // Dimension of the embeddings in the index, if it is known. Search queries
+    // with embeddings of any other dimension are rejected.
+    embedding_dimension: Option<usize>,
 }
 
 impl DatabaseWithCache {
+    /// Creates a database that caches up to `cache_capacity` decrypted
+    /// memories, see [`DEFAULT_MEMORY_CACHE_CAPACITY`].
     pub fn new(
         database: IcingMetaDatabase,
         dek: Vec<u8>,
         db_client: ExternalDbClient,
         key_derivation_info: KeyDerivationInfo,
+        cache_capacity: usize,
     ) -> Self {
-        Self { database, cache: MemoryCache::new(db_client, dek), key_derivation_info }
+        Self {
+            database,
+            cache: MemoryCache::new(db_client, dek, cache_capacity),
+            key_derivation_info,
+            embedding_dimension: None,
+        }
+    }
+
+    /// Rejects search queries with embeddings that don't have `dimension`
+    /// values, which could otherwise not be compared with the embeddings in
+    /// the index.
+    pub fn with_embedding_dimension(mut self, dimension: usize) -> Self {
+        self.embedding_dimension = Some(dimension);
+        self
     }
 
     pub fn meta_db(&mut self) -> &mut IcingMetaDatabase {
@@ -54,10 +76,22 @@ impl DatabaseWithCache {
             plaintext_info: Some(PlainTextUserInfo {
                 key_derivation_info: Some(self.key_derivation_info.clone()),
                 wrapped_dek: None,
+                ..Default::default()
             }),
         })
     }
 
+    /// Exports the meta database as chunks encrypted with `key`, without
+    /// holding it in memory as a whole like [`Self::export`]. See
+    /// [`crate::export`].
+    pub fn export_encrypted_chunks<'a>(
+        &'a self,
+        key: &[u8],
+        chunk_size: usize,
+    ) -> anyhow::Result<impl Iterator<Item = anyhow::Result<EncryptedDataBlob>> + 'a> {
+        crate::export::export_encrypted_chunks(&self.database, key, chunk_size)
+    }
+
     /// Returns true if the cached database contains content that doesnt exist
     /// in durable storage, and should be written back.
     pub fn changed(&self) -> bool {
@@ -77,6 +111,7 @@ impl DatabaseWithCache {
         &mut self,
         tag: &str,
         result_mask: &Option<ResultMask>,
+        sort_by: Option<&SortBy>,
         page_size: i32,
         page_token: PageToken,
     ) -> anyhow::Result<(Vec<Memory>, PageToken)> {
@@ -88,11 +123,20 @@ impl DatabaseWithCache {
         }
 
         let mut memories = self.cache.get_memories_by_blob_ids(&all_blob_ids).await?;
+        // Sort before masking, as the mask may remove the field to sort by.
+        if let Some(sort_by) = sort_by {
+            Self::sort_memories(&mut memories, sort_by)?;
+        }
         Self::apply_mask_to_memories(&mut memories, result_mask);
 
         Ok((memories, next_page_token))
     }
 
+    /// Returns the number of memories with `tag`, without loading them.
+    pub fn count_memories_by_tag(&self, tag: &str) -> anyhow::Result<usize> {
+        self.database.count_memories_by_tag(tag)
+    }
+
     pub async fn get_memory_by_id(
         &mut self,
         id: MemoryId,
@@ -119,9 +163,16 @@ impl DatabaseWithCache {
     ) -> anyhow::Result<(Vec<SearchMemoryResultItem>, PageToken)> {
         let page_token = PageToken::try_from(request.page_token)
             .map_err(|e| anyhow::anyhow!("Invalid page token: {}", e))?;
+        let query = request.query.context("the query must be non-empty")?;
+        if let Some(dimension) = self.embedding_dimension {
+            Self::check_embedding_dimension(&query, dimension)?;
+        }
         let (blob_ids, scores, next_page_token) = self.meta_db().search(
-            &request.query.context("the query must be non-empty")?,
+            &query,
             request.page_size,
+            usize::try_from(request.max_candidates)
+                .ok()
+                .filter(|&max_candidates| max_candidates > 0),
             page_token,
         )?;
         let mut memories = self.cache.get_memories_by_blob_ids(&blob_ids).await?;
@@ -141,6 +192,135 @@ impl DatabaseWithCache {
         Ok(())
     }
 
+    /// Returns the ids of the memories in the meta database whose blob can't
+    /// be found, for example because a previous write only partially
+    /// succeeded. If `prune` is set, these memories are also removed from the
+    /// meta database.
+    ///
+    /// Every memory that isn't cached is read from the external database, so
+    /// this is expensive for large databases.
+    pub async fn check_integrity(&mut self, prune: bool) -> anyhow::Result<Vec<MemoryId>> {
+        let memory_blob_ids = self.database.get_all_memory_blob_ids()?;
+        let blob_ids: Vec<BlobId> =
+            memory_blob_ids.iter().map(|(_, blob_id)| blob_id.clone()).collect();
+        let missing_blob_ids: HashSet<BlobId> =
+            self.cache.find_missing_blob_ids(&blob_ids).await?.into_iter().collect();
+
+        let orphaned_ids: Vec<MemoryId> = memory_blob_ids
+            .into_iter()
+            .filter(|(_, blob_id)| missing_blob_ids.contains(blob_id))
+            .map(|(memory_id, _)| memory_id)
+            .collect();
+        for memory_id in &orphaned_ids {
+            error!("Memory {} references a blob that doesn't exist", memory_id);
+        }
+        if prune && !orphaned_ids.is_empty() {
+            info!("Removing {} orphaned memories from the meta database", orphaned_ids.len());
+            self.meta_db().delete_memories(&orphaned_ids)?;
+        }
+        Ok(orphaned_ids)
+    }
+
+    /// Replaces `old_tag` with `new_tag` on every memory tagged with `old_tag`,
+    /// returning the number of updated memories. If some memories already
+    /// carry `new_tag`, the two tags are merged.
+    pub async fn rename_tag(&mut self, old_tag: &str, new_tag: &str) -> anyhow::Result<usize> {
+        self.merge_tags(&[old_tag.to_string()], new_tag).await
+    }
+
+    /// Replaces each of `source_tags` with `target_tag` on every memory tagged
+    /// with any of them, returning the number of updated memories.
+    ///
+    /// Each updated memory is written to a new blob and re-indexed, so the
+    /// change is persisted along with the rest of the meta database. The old
+    /// blobs are left behind in the external database.
+    pub async fn merge_tags(
+        &mut self,
+        source_tags: &[String],
+        target_tag: &str,
+    ) -> anyhow::Result<usize> {
+        ensure!(!target_tag.is_empty(), "the target tag must be non-empty");
+        ensure!(source_tags.iter().all(|tag| !tag.is_empty()), "source tags must be non-empty");
+
+        let mut blob_ids = Vec::new();
+        for source_tag in source_tags.iter().filter(|tag| *tag != target_tag) {
+            for blob_id in self.all_blob_ids_by_tag(source_tag)? {
+                if !blob_ids.contains(&blob_id) {
+                    blob_ids.push(blob_id);
+                }
+            }
+        }
+        if blob_ids.is_empty() {
+            return Ok(0);
+        }
+
+        let memories = self.cache.get_memories_by_blob_ids(&blob_ids).await?;
+        let updated_memory_count = memories.len();
+        for mut memory in memories {
+            let mut tags = Vec::with_capacity(memory.tags.len());
+            for tag in memory.tags.drain(..) {
+                let tag = if source_tags.contains(&tag) { target_tag.to_string() } else { tag };
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+            memory.tags = tags;
+
+            // Adding a memory with an existing id replaces its metadata.
+            let blob_id = self.cache.add_memory(&memory).await?;
+            self.meta_db().add_memory(&memory, blob_id)?;
+        }
+        // The replaced blobs are no longer referenced, but are only dropped from
+        // the cache.
+        // TODO: Delete them from the external database as well, once deleted
+        // memories are; see `MemoryCache::delete_memories`.
+        self.cache.delete_memories(&blob_ids).await?;
+        Ok(updated_memory_count)
+    }
+
+    // Collects the blob ids of all memories with the given tag, across pages.
+    fn all_blob_ids_by_tag(&mut self, tag: &str) -> anyhow::Result<Vec<BlobId>> {
+        const PAGE_SIZE: i32 = 100;
+        let mut blob_ids = Vec::new();
+        let mut page_token = PageToken::Start;
+        loop {
+            let (page_blob_ids, next_page_token) =
+                self.meta_db().get_memories_by_tag(tag, PAGE_SIZE, page_token)?;
+            blob_ids.extend(page_blob_ids);
+            if next_page_token == PageToken::Start {
+                return Ok(blob_ids);
+            }
+            page_token = next_page_token;
+        }
+    }
+
+    /// Checks that all the embeddings in `query`, including those of nested
+    /// clauses, have `dimension` values.
+    fn check_embedding_dimension(
+        query: &SearchMemoryQuery,
+        dimension: usize,
+    ) -> anyhow::Result<()> {
+        match &query.clause {
+            Some(search_memory_query::Clause::EmbeddingQuery(embedding_query)) => {
+                for embedding in &embedding_query.embedding {
+                    ensure!(
+                        embedding.values.len() == dimension,
+                        "the query embedding {:?} has dimension {}, but the index has dimension {}",
+                        embedding.identifier,
+                        embedding.values.len(),
+                        dimension
+                    );
+                }
+                Ok(())
+            }
+            Some(search_memory_query::Clause::QueryClauses(clauses)) => clauses
+                .clauses
+                .iter()
+                .try_for_each(|clause| Self::check_embedding_dimension(clause, dimension)),
+            Some(search_memory_query::Clause::TextQuery(_)) | None => Ok(()),
+        }
+    }
+
     // Helper function to apply the result mask to a single Memory object.
     fn apply_mask_to_memory(memory: &mut Memory, mask: &Option<ResultMask>) {
         if let Some(mask) = mask {
@@ -165,9 +345,76 @@ impl DatabaseWithCache {
                         .retain(|key, _| mask.include_content_fields.contains(key));
                 }
             }
+
+            if let Some(content_struct) = memory.content.as_mut() {
+                for (key, range) in &mask.content_ranges {
+                    if let Some(value) = content_struct.contents.get_mut(key) {
+                        Self::apply_range_to_value(value, range);
+                    }
+                }
+            }
         }
     }
 
+    /// Narrows `value` to the bytes selected by `range`, with the semantics
+    /// documented on [`ContentRange`].
+    fn apply_range_to_value(value: &mut MemoryValue, range: &ContentRange) {
+        match value.value.as_mut() {
+            Some(memory_value::Value::BytesVal(bytes)) => {
+                let range = Self::clamp_range(range, bytes.len());
+                bytes.truncate(range.end);
+                bytes.drain(..range.start);
+            }
+            Some(memory_value::Value::StringVal(string)) => {
+                let Range { mut start, mut end } = Self::clamp_range(range, string.len());
+                while !string.is_char_boundary(start) {
+                    start += 1;
+                }
+                while !string.is_char_boundary(end) {
+                    end -= 1;
+                }
+                *string = string[start..end.max(start)].to_string();
+            }
+            Some(memory_value::Value::Int64Val(_)) | None => {}
+        }
+    }
+
+    fn clamp_range(range: &ContentRange, len: usize) -> Range<usize> {
+        let start = usize::try_from(range.offset).unwrap_or(usize::MAX).min(len);
+        let end = match usize::try_from(range.length) {
+            Ok(0) => len,
+            Ok(length) => start.saturating_add(length).min(len),
+            Err(_) => len,
+        };
+        start..end
+    }
+
+    /// Stably sorts `memories` by the field and in the direction given by
+    /// `sort_by`.
+    fn sort_memories(memories: &mut [Memory], sort_by: &SortBy) -> anyhow::Result<()> {
+        fn timestamp(timestamp: &Option<prost_types::Timestamp>) -> Option<(i64, i32)> {
+            timestamp.as_ref().map(|timestamp| (timestamp.seconds, timestamp.nanos))
+        }
+        let compare: fn(&Memory, &Memory) -> Ordering = match sort_by.field() {
+            MemoryField::Id => |a, b| a.id.cmp(&b.id),
+            MemoryField::CreatedTimestamp => {
+                |a, b| timestamp(&a.created_timestamp).cmp(&timestamp(&b.created_timestamp))
+            }
+            MemoryField::EventTimestamp => |a, b| {
+                let event_timestamp = |memory: &Memory| {
+                    timestamp(&memory.event_timestamp).or(timestamp(&memory.created_timestamp))
+                };
+                event_timestamp(a).cmp(&event_timestamp(b))
+            },
+            field => bail!("unsupported field for sorting: {}", field.as_str_name()),
+        };
+        match sort_by.direction() {
+            SortDirection::Descending => memories.sort_by(|a, b| compare(b, a)),
+            SortDirection::Ascending | SortDirection::Unspecified => memories.sort_by(compare),
+        }
+        Ok(())
+    }
+
     fn apply_mask_to_memories<'a>(
         memories: impl IntoIterator<Item = &'a mut Memory>,
         mask: &Option<ResultMask>,
@@ -181,3 +428,213 @@ impl DatabaseWithCache {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use googletest::prelude::*;
+    use sealed_memory_rust_proto::oak::private_memory::{QueryClauses, QueryOperator, TextQuery};
+
+    use super::*;
+
+    fn memory(id: &str, created_seconds: i64, event_seconds: Option<i64>) -> Memory {
+        Memory {
+            id: id.to_string(),
+            created_timestamp: Some(prost_types::Timestamp { seconds: created_seconds, nanos: 0 }),
+            event_timestamp: event_seconds
+                .map(|seconds| prost_types::Timestamp { seconds, nanos: 0 }),
+            ..Default::default()
+        }
+    }
+
+    fn sorted_ids(field: MemoryField, direction: SortDirection) -> anyhow::Result<Vec<String>> {
+        let mut memories = vec![
+            memory("b", 2, None),
+            memory("c", 1, Some(3)),
+            memory("a", 3, Some(1)),
+            memory("d", 2, None),
+        ];
+        let sort_by = SortBy { field: field.into(), direction: direction.into() };
+        DatabaseWithCache::sort_memories(&mut memories, &sort_by)?;
+        Ok(memories.into_iter().map(|memory| memory.id).collect())
+    }
+
+    #[gtest]
+    fn sort_memories_ascending() -> anyhow::Result<()> {
+        assert_that!(
+            sorted_ids(MemoryField::Id, SortDirection::Ascending)?,
+            elements_are![eq("a"), eq("b"), eq("c"), eq("d")]
+        );
+        // Memories with the same timestamp keep their order.
+        assert_that!(
+            sorted_ids(MemoryField::CreatedTimestamp, SortDirection::Unspecified)?,
+            elements_are![eq("c"), eq("b"), eq("d"), eq("a")]
+        );
+        // Memories without an event timestamp are sorted by their creation time.
+        assert_that!(
+            sorted_ids(MemoryField::EventTimestamp, SortDirection::Ascending)?,
+            elements_are![eq("a"), eq("b"), eq("d"), eq("c")]
+        );
+        Ok(())
+    }
+
+    #[gtest]
+    fn sort_memories_descending() -> anyhow::Result<()> {
+        assert_that!(
+            sorted_ids(MemoryField::Id, SortDirection::Descending)?,
+            elements_are![eq("d"), eq("c"), eq("b"), eq("a")]
+        );
+        assert_that!(
+            sorted_ids(MemoryField::CreatedTimestamp, SortDirection::Descending)?,
+            elements_are![eq("a"), eq("b"), eq("d"), eq("c")]
+        );
+        assert_that!(
+            sorted_ids(MemoryField::EventTimestamp, SortDirection::Descending)?,
+            elements_are![eq("c"), eq("b"), eq("d"), eq("a")]
+        );
+        Ok(())
+    }
+
+    #[gtest]
+    fn sort_memories_unsupported_field() {
+        assert_that!(
+            sorted_ids(MemoryField::Tags, SortDirection::Ascending),
+            err(displays_as(eq("unsupported field for sorting: TAGS")))
+        );
+    }
+
+    fn memory_with_content(key: &str, value: memory_value::Value) -> Memory {
+        Memory {
+            content: Some(MemoryContent {
+                contents: [(
+                    key.to_string(),
+                    MemoryValue { value: Some(value), ..Default::default() },
+                )]
+                .into(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the value of `key` after applying a mask that selects `range`
+    /// of it.
+    fn masked_value(
+        value: memory_value::Value,
+        offset: u64,
+        length: u64,
+    ) -> Option<memory_value::Value> {
+        let mut memory = memory_with_content("key", value);
+        let mask = ResultMask {
+            include_fields: vec![MemoryField::Content as i32],
+            content_ranges: [("key".to_string(), ContentRange { offset, length })].into(),
+            ..Default::default()
+        };
+        DatabaseWithCache::apply_mask_to_memory(&mut memory, &Some(mask));
+        memory.content.unwrap().contents.remove("key").unwrap().value
+    }
+
+    #[gtest]
+    fn apply_content_range_in_range() {
+        let bytes = || memory_value::Value::BytesVal(b"0123456789".to_vec());
+        assert_that!(
+            masked_value(bytes(), 2, 3),
+            some(eq(&memory_value::Value::BytesVal(b"234".to_vec())))
+        );
+        assert_that!(
+            masked_value(bytes(), 7, 0),
+            some(eq(&memory_value::Value::BytesVal(b"789".to_vec())))
+        );
+        assert_that!(
+            masked_value(memory_value::Value::StringVal("hello world".to_string()), 6, 5),
+            some(eq(&memory_value::Value::StringVal("world".to_string())))
+        );
+        assert_that!(
+            masked_value(memory_value::Value::Int64Val(42), 1, 1),
+            some(eq(&memory_value::Value::Int64Val(42)))
+        );
+    }
+
+    #[gtest]
+    fn apply_content_range_out_of_range_is_clamped() {
+        let bytes = || memory_value::Value::BytesVal(b"0123456789".to_vec());
+        assert_that!(
+            masked_value(bytes(), 8, 100),
+            some(eq(&memory_value::Value::BytesVal(b"89".to_vec())))
+        );
+        assert_that!(
+            masked_value(bytes(), 20, 5),
+            some(eq(&memory_value::Value::BytesVal(vec![])))
+        );
+        assert_that!(
+            masked_value(bytes(), u64::MAX, u64::MAX),
+            some(eq(&memory_value::Value::BytesVal(vec![])))
+        );
+        // "é" takes two bytes, and the range only covers its second one.
+        assert_that!(
+            masked_value(memory_value::Value::StringVal("café au lait".to_string()), 4, 4),
+            some(eq(&memory_value::Value::StringVal(" au".to_string())))
+        );
+        assert_that!(
+            masked_value(memory_value::Value::StringVal("é".to_string()), 0, 1),
+            some(eq(&memory_value::Value::StringVal(String::new())))
+        );
+    }
+
+    #[gtest]
+    fn apply_content_range_ignores_missing_keys() {
+        let mut memory = memory_with_content("key", memory_value::Value::Int64Val(1));
+        let mask = ResultMask {
+            include_fields: vec![MemoryField::Content as i32],
+            content_ranges: [("other".to_string(), ContentRange { offset: 1, length: 1 })].into(),
+            ..Default::default()
+        };
+        DatabaseWithCache::apply_mask_to_memory(&mut memory, &Some(mask));
+        assert_that!(memory, eq(&memory_with_content("key", memory_value::Value::Int64Val(1))));
+    }
+
+    fn embedding_query(values: &[f32]) -> SearchMemoryQuery {
+        SearchMemoryQuery {
+            clause: Some(search_memory_query::Clause::EmbeddingQuery(EmbeddingQuery {
+                embedding: vec![Embedding {
+                    identifier: "model".to_string(),
+                    values: values.to_vec(),
+                }],
+                ..Default::default()
+            })),
+        }
+    }
+
+    #[gtest]
+    fn check_embedding_dimension_matching() {
+        assert_that!(
+            DatabaseWithCache::check_embedding_dimension(&embedding_query(&[1.0, 0.0, 1.0]), 3),
+            ok(anything())
+        );
+    }
+
+    #[gtest]
+    fn check_embedding_dimension_mismatch() {
+        assert_that!(
+            DatabaseWithCache::check_embedding_dimension(&embedding_query(&[1.0, 0.0]), 3),
+            err(displays_as(eq(
+                "the query embedding \"model\" has dimension 2, but the index has dimension 3"
+            )))
+        );
+    }
+
+    #[gtest]
+    fn check_embedding_dimension_mismatch_in_nested_clause() {
+        let query = SearchMemoryQuery {
+            clause: Some(search_memory_query::Clause::QueryClauses(QueryClauses {
+                query_operator: QueryOperator::And.into(),
+                clauses: vec![
+                    SearchMemoryQuery {
+                        clause: Some(search_memory_query::Clause::TextQuery(TextQuery::default())),
+                    },
+                    embedding_query(&[1.0, 0.0, 1.0, 0.0]),
+                ],
+            })),
+        };
+
+        assert_that!(DatabaseWithCache::check_embedding_dimension(&query, 3), err(anything()));
+    }
+}
@@ -13,27 +13,47 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::io::{Read, Write};
+
 use anyhow::Context;
 use encryption::{decrypt, encrypt, generate_nonce};
 use log::error;
 use prost::Message;
+use rand::Rng;
 use sealed_memory_rust_proto::prelude::v1::*;
+use xz2::{read::XzDecoder, write::XzEncoder};
+
+/// Memories and databases smaller than this are stored uncompressed, since
+/// compression would barely reduce their size.
+const MIN_COMPRESSION_SIZE: usize = 1024;
+const XZ_COMPRESSION_LEVEL: u32 = 6;
 
 /// Helpers for encryption/decryting the database blobs.
+///
+/// If `compress` is set, databases are compressed like memories before
+/// encryption; the blob records which compression was applied, so that
+/// `decrypt_database` can undo it.
 pub fn encrypt_database(
     database: &EncryptedUserInfo,
     key: &[u8],
+    compress: bool,
 ) -> anyhow::Result<EncryptedDataBlob> {
     let nonce = generate_nonce();
     let datablob = database.encode_to_vec();
+    let (compression, datablob) = if compress {
+        compress_data(&datablob).context("Failed to compress database")?
+    } else {
+        (CompressionType::Uncompressed, datablob)
+    };
     let data = encrypt(key, &nonce, &datablob)?;
-    Ok(EncryptedDataBlob { nonce, data })
+    Ok(EncryptedDataBlob { nonce, data, compression: compression.into(), ..Default::default() })
 }
 
 pub fn decrypt_database(
     datablob: EncryptedDataBlob,
     key: &[u8],
 ) -> anyhow::Result<EncryptedUserInfo> {
+    let compression = datablob.compression();
     let nonce = datablob.nonce;
     let data = datablob.data;
     let decrypted_data = match decrypt(key, &nonce, &data) {
@@ -49,7 +69,258 @@ pub fn decrypt_database(
             return Err(err);
         }
     };
+    let decrypted_data =
+        decompress_data(compression, decrypted_data).context("Failed to decompress database")?;
     let user_db = EncryptedUserInfo::decode(decrypted_data.as_slice())
         .context("Failed to decode EncryptedUserInfo")?;
     Ok(user_db)
 }
+
+/// Encrypts the content of a single memory with a freshly generated per-memory
+/// key, and stores that key wrapped with `dek` alongside the ciphertext.
+///
+/// Memories of at least `MIN_COMPRESSION_SIZE` bytes are compressed before
+/// encryption if that makes them smaller; the blob records which compression
+/// was applied.
+pub fn encrypt_memory(memory_data: &[u8], dek: &[u8]) -> anyhow::Result<EncryptedDataBlob> {
+    let (compression, memory_data) = compress_memory(memory_data)?;
+
+    let mut memory_key = [0u8; 32];
+    rand::rng().fill(&mut memory_key);
+
+    let key_nonce = generate_nonce();
+    let wrapped_key = encrypt(dek, &key_nonce, &memory_key).context("Failed to wrap memory key")?;
+
+    let nonce = generate_nonce();
+    let data = encrypt(&memory_key, &nonce, &memory_data)?;
+    Ok(EncryptedDataBlob {
+        nonce,
+        data,
+        wrapped_memory_key: Some(WrappedMemoryKey { nonce: key_nonce, wrapped_key }),
+        compression: compression.into(),
+    })
+}
+
+/// Decrypts the content of a single memory, unwrapping its per-memory key with
+/// `dek` first. Blobs written before per-memory keys were introduced have no
+/// wrapped key and are decrypted with `dek` directly.
+pub fn decrypt_memory(datablob: &EncryptedDataBlob, dek: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let memory_data = match &datablob.wrapped_memory_key {
+        Some(wrapped_memory_key) => {
+            let memory_key =
+                decrypt(dek, &wrapped_memory_key.nonce, &wrapped_memory_key.wrapped_key)
+                    .context("Failed to unwrap memory key")?;
+            anyhow::ensure!(memory_key.len() == 32, "Invalid memory key length");
+            decrypt(&memory_key, &datablob.nonce, &datablob.data)
+                .context("Failed to decrypt memory")
+        }
+        None => decrypt(dek, &datablob.nonce, &datablob.data).context("Failed to decrypt memory"),
+    }?;
+    decompress_memory(datablob.compression(), memory_data)
+}
+
+fn compress_memory(memory_data: &[u8]) -> anyhow::Result<(CompressionType, Vec<u8>)> {
+    compress_data(memory_data).context("Failed to compress memory")
+}
+
+fn decompress_memory(
+    compression: CompressionType,
+    memory_data: Vec<u8>,
+) -> anyhow::Result<Vec<u8>> {
+    decompress_data(compression, memory_data).context("Failed to decompress memory")
+}
+
+/// Compresses `data` with xz, unless it is too small or doesn't get any
+/// smaller. Returns the compression that was actually applied.
+fn compress_data(data: &[u8]) -> anyhow::Result<(CompressionType, Vec<u8>)> {
+    if data.len() < MIN_COMPRESSION_SIZE {
+        return Ok((CompressionType::Uncompressed, data.to_vec()));
+    }
+    let mut encoder = XzEncoder::new(Vec::new(), XZ_COMPRESSION_LEVEL);
+    encoder.write_all(data)?;
+    let compressed = encoder.finish()?;
+    if compressed.len() >= data.len() {
+        return Ok((CompressionType::Uncompressed, data.to_vec()));
+    }
+    Ok((CompressionType::Xz, compressed))
+}
+
+fn decompress_data(compression: CompressionType, data: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    match compression {
+        CompressionType::Uncompressed => Ok(data),
+        CompressionType::Xz => {
+            let mut decompressed = Vec::new();
+            XzDecoder::new(data.as_slice()).read_to_end(&mut decompressed)?;
+            Ok(decompressed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use googletest::prelude::*;
+    use sealed_memory_rust_proto::oak::private_memory::IcingGroundTruthFiles;
+
+    use super::*;
+
+    const DEK: [u8; 32] = [7; 32];
+
+    #[gtest]
+    fn memory_round_trip_test() -> anyhow::Result<()> {
+        let memory = Memory { id: "memory_id".to_string(), ..Default::default() };
+
+        let datablob = encrypt_memory(&memory.encode_to_vec(), &DEK)?;
+        assert_that!(datablob.wrapped_memory_key, some(anything()));
+
+        let decrypted = Memory::decode(decrypt_memory(&datablob, &DEK)?.as_slice())?;
+        assert_that!(decrypted, eq(&memory));
+        Ok(())
+    }
+
+    #[gtest]
+    fn memory_keys_are_unique_test() -> anyhow::Result<()> {
+        let first = encrypt_memory(b"content", &DEK)?;
+        let second = encrypt_memory(b"content", &DEK)?;
+
+        let first_key = decrypt_memory_key(&first)?;
+        let second_key = decrypt_memory_key(&second)?;
+        assert_that!(first_key, not(eq(&second_key)));
+        Ok(())
+    }
+
+    #[gtest]
+    fn memory_without_wrapped_key_test() -> anyhow::Result<()> {
+        let nonce = generate_nonce();
+        let datablob = EncryptedDataBlob {
+            data: encrypt(&DEK, &nonce, b"content")?,
+            nonce,
+            ..Default::default()
+        };
+
+        assert_that!(decrypt_memory(&datablob, &DEK)?, eq(b"content"));
+        Ok(())
+    }
+
+    #[gtest]
+    fn corrupted_wrapped_memory_key_test() -> anyhow::Result<()> {
+        let mut datablob = encrypt_memory(b"content", &DEK)?;
+        let wrapped_memory_key = datablob.wrapped_memory_key.as_mut().unwrap();
+        wrapped_memory_key.wrapped_key[0] ^= 0xff;
+
+        let result = decrypt_memory(&datablob, &DEK);
+        assert_that!(result, err(displays_as(contains_substring("Failed to unwrap memory key"))));
+        Ok(())
+    }
+
+    #[gtest]
+    fn wrong_dek_test() -> anyhow::Result<()> {
+        let datablob = encrypt_memory(b"content", &DEK)?;
+
+        assert_that!(decrypt_memory(&datablob, &[8; 32]), err(anything()));
+        Ok(())
+    }
+
+    #[gtest]
+    fn small_memory_is_not_compressed_test() -> anyhow::Result<()> {
+        let memory_data = vec![b'a'; MIN_COMPRESSION_SIZE - 1];
+
+        let datablob = encrypt_memory(&memory_data, &DEK)?;
+        assert_that!(datablob.compression(), eq(CompressionType::Uncompressed));
+        assert_that!(decrypt_memory(&datablob, &DEK)?, eq(&memory_data));
+        Ok(())
+    }
+
+    #[gtest]
+    fn large_memory_is_compressed_test() -> anyhow::Result<()> {
+        let memory_data = b"a compressible memory ".repeat(1000);
+
+        let datablob = encrypt_memory(&memory_data, &DEK)?;
+        assert_that!(datablob.compression(), eq(CompressionType::Xz));
+        assert_that!(datablob.data.len(), lt(memory_data.len()));
+        assert_that!(decrypt_memory(&datablob, &DEK)?, eq(&memory_data));
+        Ok(())
+    }
+
+    #[gtest]
+    fn incompressible_memory_is_not_compressed_test() -> anyhow::Result<()> {
+        let mut memory_data = vec![0u8; 4 * MIN_COMPRESSION_SIZE];
+        rand::rng().fill(memory_data.as_mut_slice());
+
+        let datablob = encrypt_memory(&memory_data, &DEK)?;
+        assert_that!(datablob.compression(), eq(CompressionType::Uncompressed));
+        assert_that!(decrypt_memory(&datablob, &DEK)?, eq(&memory_data));
+        Ok(())
+    }
+
+    #[gtest]
+    fn compression_flag_is_honored_test() -> anyhow::Result<()> {
+        let memory_data = b"a compressible memory ".repeat(1000);
+        let mut datablob = encrypt_memory(&memory_data, &DEK)?;
+
+        // Without the flag, the compressed plaintext is returned as is.
+        datablob.set_compression(CompressionType::Uncompressed);
+        let decrypted = decrypt_memory(&datablob, &DEK)?;
+        assert_that!(decrypted, not(eq(&memory_data)));
+        assert_that!(decompress_memory(CompressionType::Xz, decrypted)?, eq(&memory_data));
+
+        // Uncompressed content flagged as compressed fails to decompress.
+        let mut datablob = encrypt_memory(b"content", &DEK)?;
+        datablob.set_compression(CompressionType::Xz);
+        assert_that!(
+            decrypt_memory(&datablob, &DEK),
+            err(displays_as(contains_substring("Failed to decompress memory")))
+        );
+        Ok(())
+    }
+
+    fn text_heavy_database() -> EncryptedUserInfo {
+        EncryptedUserInfo {
+            icing_db: Some(IcingGroundTruthFiles {
+                document_log: b"a compressible memory ".repeat(1000),
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[gtest]
+    fn compressed_database_round_trip_test() -> anyhow::Result<()> {
+        let database = text_heavy_database();
+
+        let datablob = encrypt_database(&database, &DEK, true)?;
+        assert_that!(datablob.compression(), eq(CompressionType::Xz));
+        assert_that!(datablob.data.len(), lt(database.encoded_len()));
+        assert_that!(decrypt_database(datablob, &DEK)?, eq(&database));
+        Ok(())
+    }
+
+    #[gtest]
+    fn uncompressed_database_round_trip_test() -> anyhow::Result<()> {
+        let database = text_heavy_database();
+
+        let datablob = encrypt_database(&database, &DEK, false)?;
+        assert_that!(datablob.compression(), eq(CompressionType::Uncompressed));
+        assert_that!(decrypt_database(datablob, &DEK)?, eq(&database));
+        Ok(())
+    }
+
+    #[gtest]
+    fn database_without_compression_flag_test() -> anyhow::Result<()> {
+        // Blobs written before databases were compressed have no compression
+        // flag set.
+        let database = text_heavy_database();
+        let nonce = generate_nonce();
+        let datablob = EncryptedDataBlob {
+            data: encrypt(&DEK, &nonce, &database.encode_to_vec())?,
+            nonce,
+            ..Default::default()
+        };
+
+        assert_that!(decrypt_database(datablob, &DEK)?, eq(&database));
+        Ok(())
+    }
+
+    fn decrypt_memory_key(datablob: &EncryptedDataBlob) -> anyhow::Result<Vec<u8>> {
+        let wrapped_memory_key = datablob.wrapped_memory_key.as_ref().unwrap();
+        decrypt(&DEK, &wrapped_memory_key.nonce, &wrapped_memory_key.wrapped_key)
+    }
+}
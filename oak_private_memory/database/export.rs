@@ -0,0 +1,206 @@
+//
+// Copyright 2025 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Streamed export and import of meta databases, e.g. for backups.
+//!
+//! An export is a sequence of [`DatabaseExportChunk`]s, each encrypted into its
+//! own [`EncryptedDataBlob`]; see `internal.proto` for how the database is
+//! split into chunks. Only one chunk is held in memory at a time, so that
+//! large databases can be backed up without serializing them as a whole.
+
+use std::path::Path;
+
+use anyhow::{anyhow, ensure, Context};
+use encryption::{decrypt, encrypt, generate_nonce};
+use prost::Message;
+use sealed_memory_rust_proto::{
+    oak::private_memory::DatabaseExportChunk, prelude::v1::EncryptedDataBlob,
+};
+
+use crate::icing::IcingMetaDatabase;
+
+/// The default maximum number of bytes of the database in a single chunk.
+pub const DEFAULT_EXPORT_CHUNK_SIZE: usize = 1 << 20;
+
+/// Exports `database` as chunks encrypted with `key`, each holding at most
+/// `chunk_size` bytes of the database.
+///
+/// Chunks are read from disk as the iterator advances. If any item is an
+/// error, the export is incomplete.
+pub fn export_encrypted_chunks<'a>(
+    database: &'a IcingMetaDatabase,
+    key: &[u8],
+    chunk_size: usize,
+) -> anyhow::Result<impl Iterator<Item = anyhow::Result<EncryptedDataBlob>> + 'a> {
+    let key = key.to_vec();
+    let chunks = database.export_chunks(chunk_size)?.map(Some).chain([None]).enumerate().map(
+        move |(index, file_chunk)| {
+            let chunk = match file_chunk {
+                Some(file_chunk) => {
+                    let (file, data) = file_chunk?;
+                    DatabaseExportChunk {
+                        index: index as u64,
+                        file: file.into(),
+                        data,
+                        last: false,
+                    }
+                }
+                None => {
+                    DatabaseExportChunk { index: index as u64, last: true, ..Default::default() }
+                }
+            };
+            encrypt_chunk(&chunk, &key)
+        },
+    );
+    Ok(chunks)
+}
+
+/// Creates a new meta database in `base_dir` from chunks exported with
+/// [`export_encrypted_chunks`] and the same `key`.
+///
+/// Fails if chunks are missing, out of order, or follow the last chunk.
+pub fn import_encrypted_chunks(
+    base_dir: impl AsRef<Path>,
+    key: &[u8],
+    chunks: impl IntoIterator<Item = EncryptedDataBlob>,
+) -> anyhow::Result<IcingMetaDatabase> {
+    let mut chunks = chunks.into_iter().enumerate();
+    let mut finished = false;
+    let file_chunks = std::iter::from_fn(|| {
+        if finished {
+            return None;
+        }
+        let result = match chunks.next() {
+            Some((index, datablob)) => decrypt_chunk(datablob, key).and_then(|chunk| {
+                ensure!(
+                    chunk.index == index as u64,
+                    "expected chunk {} of the export, but got chunk {}",
+                    index,
+                    chunk.index
+                );
+                Ok(chunk)
+            }),
+            None => Err(anyhow!("the export ended before its last chunk")),
+        };
+        match result {
+            Ok(chunk) if chunk.last => {
+                finished = true;
+                chunks.next().map(|_| Err(anyhow!("the export has chunks after its last chunk")))
+            }
+            Ok(chunk) => Some(Ok((chunk.file(), chunk.data))),
+            Err(err) => {
+                finished = true;
+                Some(Err(err))
+            }
+        }
+    });
+    IcingMetaDatabase::import_chunks(base_dir, file_chunks)
+}
+
+fn encrypt_chunk(chunk: &DatabaseExportChunk, key: &[u8]) -> anyhow::Result<EncryptedDataBlob> {
+    let nonce = generate_nonce();
+    let data = encrypt(key, &nonce, &chunk.encode_to_vec())?;
+    Ok(EncryptedDataBlob { nonce, data, ..Default::default() })
+}
+
+fn decrypt_chunk(datablob: EncryptedDataBlob, key: &[u8]) -> anyhow::Result<DatabaseExportChunk> {
+    let data = decrypt(key, &datablob.nonce, &datablob.data)
+        .context("Failed to decrypt the export chunk")?;
+    DatabaseExportChunk::decode(data.as_slice()).context("Failed to decode the export chunk")
+}
+
+#[cfg(test)]
+mod tests {
+    use googletest::prelude::*;
+    use sealed_memory_rust_proto::prelude::v1::Memory;
+    use tempfile::tempdir;
+
+    use super::*;
+
+    const KEY: [u8; 32] = [7; 32];
+    const CHUNK_SIZE: usize = 256;
+
+    fn database_with_memories(base_dir: &Path) -> anyhow::Result<IcingMetaDatabase> {
+        let mut database = IcingMetaDatabase::new(base_dir)?;
+        for i in 0..20 {
+            let memory = Memory {
+                id: format!("memory_{i}"),
+                tags: vec![format!("tag_{}", i % 3)],
+                ..Default::default()
+            };
+            database.add_memory(&memory, format!("blob_{i}"))?;
+        }
+        Ok(database)
+    }
+
+    fn sorted_memory_blob_ids(
+        database: &IcingMetaDatabase,
+    ) -> anyhow::Result<Vec<(String, String)>> {
+        let mut ids = database.get_all_memory_blob_ids()?;
+        ids.sort();
+        Ok(ids)
+    }
+
+    #[gtest]
+    fn export_import_round_trip_test() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let database = database_with_memories(temp_dir.path())?;
+
+        let chunks = export_encrypted_chunks(&database, &KEY, CHUNK_SIZE)?
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        // Every ground truth file takes at least one chunk, and the document
+        // log several.
+        assert_that!(chunks.len(), gt(5));
+
+        let import_dir = tempdir()?;
+        let imported = import_encrypted_chunks(import_dir.path(), &KEY, chunks)?;
+        assert_that!(sorted_memory_blob_ids(&imported)?, eq(&sorted_memory_blob_ids(&database)?));
+        assert_that!(imported.count_memories_by_tag("tag_0")?, eq(7));
+        Ok(())
+    }
+
+    #[gtest]
+    fn import_truncated_export_fails_test() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let database = database_with_memories(temp_dir.path())?;
+        let mut chunks = export_encrypted_chunks(&database, &KEY, CHUNK_SIZE)?
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        chunks.pop();
+
+        let import_dir = tempdir()?;
+        assert_that!(
+            import_encrypted_chunks(import_dir.path(), &KEY, chunks).map(|_| ()),
+            err(displays_as(contains_substring("ended before its last chunk")))
+        );
+        Ok(())
+    }
+
+    #[gtest]
+    fn import_reordered_export_fails_test() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let database = database_with_memories(temp_dir.path())?;
+        let mut chunks = export_encrypted_chunks(&database, &KEY, CHUNK_SIZE)?
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let last_data_chunk = chunks.len() - 2;
+        chunks.swap(last_data_chunk - 1, last_data_chunk);
+
+        let import_dir = tempdir()?;
+        assert_that!(
+            import_encrypted_chunks(import_dir.path(), &KEY, chunks).map(|_| ()),
+            err(displays_as(contains_substring("expected chunk")))
+        );
+        Ok(())
+    }
+}
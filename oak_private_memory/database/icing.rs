@@ -253,6 +253,29 @@ impl IcingMetaDatabase {
         })
     }
 
+    /// Creates a new icing database in `base_dir` from the chunks of an export
+    /// made with [`Self::export_chunks`], which are written to disk one at a
+    /// time.
+    pub fn import_chunks(
+        base_dir: impl AsRef<Path>,
+        chunks: impl IntoIterator<Item = anyhow::Result<(icing::IcingGroundTruthFile, Vec<u8>)>>,
+    ) -> anyhow::Result<Self> {
+        let base_dir_str = base_dir.as_ref().to_str().context("failed to convert path to str")?;
+        let mut writer = icing::GroundTruthFilesWriter::new(base_dir_str)?;
+        for chunk in chunks {
+            let (file, data) = chunk?;
+            writer.append(file, &data)?;
+        }
+        writer.finish()?;
+
+        let icing_search_engine = Self::initialize_icing_database(base_dir_str)?;
+        Ok(Self {
+            icing_search_engine,
+            base_dir: base_dir_str.to_string(),
+            applied_operations: vec![],
+        })
+    }
+
     fn initialize_icing_database(
         base_dir_str: &str,
     ) -> anyhow::Result<cxx::UniquePtr<icing::IcingSearchEngine>> {
@@ -297,13 +320,7 @@ impl IcingMetaDatabase {
             bail!("Invalid page token provided");
         }
 
-        let search_spec = icing::SearchSpecProto {
-            query: Some(tag.to_string()),
-            // Match exactly as defined in the schema for tags.
-            term_match_type: Some(icing::term_match_type::Code::ExactOnly.into()),
-            type_property_filters: vec![Self::create_search_filter(TAG_NAME)],
-            ..Default::default()
-        };
+        let search_spec = Self::create_tag_search_spec(tag);
 
         // Default to 10 if page size is 0.
         if page_size <= 0 {
@@ -343,6 +360,108 @@ impl IcingMetaDatabase {
         Ok((blob_ids, next_page_token))
     }
 
+    /// Returns the number of memories with `tag` at the time of the call.
+    ///
+    /// Only the tag index is searched; no document properties are retrieved.
+    pub fn count_memories_by_tag(&self, tag: &str) -> anyhow::Result<usize> {
+        const PAGE_SIZE: i32 = 1000;
+        let result_spec = icing::ResultSpecProto {
+            num_per_page: Some(PAGE_SIZE),
+            // An empty mask leaves out all the properties of the results.
+            type_property_masks: vec![icing::TypePropertyMask {
+                schema_type: Some(SCHMA_NAME.to_string()),
+                paths: vec![],
+            }],
+            ..Default::default()
+        };
+
+        let mut search_result = self.icing_search_engine.search(
+            &Self::create_tag_search_spec(tag),
+            &icing::get_default_scoring_spec(),
+            &result_spec,
+        );
+        let mut count = 0;
+        loop {
+            if search_result.status.clone().context("no status")?.code
+                != Some(icing::status_proto::Code::Ok.into())
+            {
+                bail!("Icing search failed: {:?}", search_result.status);
+            }
+            if search_result.results.is_empty() {
+                return Ok(count);
+            }
+            count += search_result.results.len();
+            match search_result.next_page_token {
+                Some(token) if token != 0 => {
+                    search_result = self.icing_search_engine.get_next_page(token)
+                }
+                _ => return Ok(count),
+            }
+        }
+    }
+
+    /// Returns the memory id and blob id of every memory in the database.
+    ///
+    /// This pages through the whole database, so its cost grows with the
+    /// number of memories.
+    pub fn get_all_memory_blob_ids(&self) -> anyhow::Result<Vec<(MemoryId, BlobId)>> {
+        const PAGE_SIZE: i32 = 1000;
+        // An empty query matches every document.
+        let search_spec = icing::SearchSpecProto {
+            query: Some(String::new()),
+            term_match_type: Some(icing::term_match_type::Code::ExactOnly.into()),
+            ..Default::default()
+        };
+        let result_spec = icing::ResultSpecProto {
+            num_per_page: Some(PAGE_SIZE),
+            type_property_masks: vec![icing::TypePropertyMask {
+                schema_type: Some(SCHMA_NAME.to_string()),
+                paths: vec![MEMORY_ID_NAME.to_string(), BLOB_ID_NAME.to_string()],
+            }],
+            ..Default::default()
+        };
+
+        let mut search_result = self.icing_search_engine.search(
+            &search_spec,
+            &icing::get_default_scoring_spec(),
+            &result_spec,
+        );
+        let mut ids = Vec::new();
+        loop {
+            if search_result.status.clone().context("no status")?.code
+                != Some(icing::status_proto::Code::Ok.into())
+            {
+                bail!("Icing search failed: {:?}", search_result.status);
+            }
+            if search_result.results.is_empty() {
+                return Ok(ids);
+            }
+            for doc_hit in &search_result.results {
+                let memory_id = Self::extract_string_property_from_doc(doc_hit, MEMORY_ID_NAME)
+                    .context("memory without a memory id")?;
+                let blob_id = Self::extract_blob_id_from_doc(doc_hit)
+                    .with_context(|| format!("memory {} without a blob id", memory_id))?;
+                ids.push((memory_id, blob_id));
+            }
+            match search_result.next_page_token {
+                Some(token) if token != 0 => {
+                    search_result = self.icing_search_engine.get_next_page(token)
+                }
+                _ => return Ok(ids),
+            }
+        }
+    }
+
+    fn create_tag_search_spec(tag: &str) -> icing::SearchSpecProto {
+        icing::SearchSpecProto {
+            query: Some(tag.to_string()),
+            // Match exactly as defined in the schema for tags.
+            term_match_type: Some(icing::term_match_type::Code::ExactOnly.into()),
+            type_property_filters: vec![Self::create_search_filter(TAG_NAME)],
+            ..Default::default()
+        }
+    }
+
     pub fn get_blob_id_by_memory_id(&self, memory_id: MemoryId) -> anyhow::Result<Option<BlobId>> {
         let search_spec = icing::SearchSpecProto {
             query: Some(memory_id.to_string()),
@@ -376,13 +495,19 @@ impl IcingMetaDatabase {
     fn extract_blob_id_from_doc(
         doc_hit: &icing::search_result_proto::ResultProto,
     ) -> Option<BlobId> {
-        let blob_id_name = BLOB_ID_NAME.to_string();
+        Self::extract_string_property_from_doc(doc_hit, BLOB_ID_NAME)
+    }
+
+    fn extract_string_property_from_doc(
+        doc_hit: &icing::search_result_proto::ResultProto,
+        name: &str,
+    ) -> Option<String> {
         doc_hit
             .document
             .as_ref()?
             .properties
             .iter()
-            .find(|prop| prop.name.as_ref() == Some(&blob_id_name))?
+            .find(|prop| prop.name.as_deref() == Some(name))?
             .string_values
             .first()
             .cloned()
@@ -400,6 +525,7 @@ impl IcingMetaDatabase {
         search_spec: &icing::SearchSpecProto,
         scoring_spec: &icing::ScoringSpecProto,
         page_size: i32,
+        max_results: Option<usize>,
         page_token: PageToken,
     ) -> anyhow::Result<(Vec<BlobId>, Vec<f32>, PageToken)> {
         const DEFAULT_LIMIT: i32 = 10;
@@ -408,6 +534,20 @@ impl IcingMetaDatabase {
         let mut result_spec =
             icing::ResultSpecProto { num_per_page: Some(limit), ..Default::default() };
 
+        // All memories are in the same namespace, so limiting the results of
+        // its group limits the results of the whole search, across all pages.
+        if let Some(max_results) = max_results {
+            result_spec.result_group_type =
+                Some(icing::result_spec_proto::ResultGroupingType::Namespace.into());
+            result_spec.result_groupings.push(icing::result_spec_proto::ResultGrouping {
+                entry_groupings: vec![icing::result_spec_proto::result_grouping::Entry {
+                    namespace: Some(NAMESPACE_NAME.to_string()),
+                    ..Default::default()
+                }],
+                max_results: Some(i32::try_from(max_results).unwrap_or(i32::MAX)),
+            });
+        }
+
         // We only need the `BlobId`.
         result_spec.type_property_masks.push(Self::create_blob_id_projection());
 
@@ -440,14 +580,28 @@ impl IcingMetaDatabase {
         Ok((blob_ids, scores, next_page_token))
     }
 
+    /// Searches for memories matching `query`.
+    ///
+    /// If `max_candidates` is set, only the `max_candidates` highest ranked
+    /// results are returned, across all pages of the search. Results outside
+    /// the score range of an embedding query are filtered out before they are
+    /// counted. Icing keeps the bound with the state of the search, so it only
+    /// needs to be passed when starting the search.
     pub fn search(
         &self,
         query: &SearchMemoryQuery,
         page_size: i32,
+        max_candidates: Option<usize>,
         page_token: PageToken,
     ) -> anyhow::Result<(Vec<BlobId>, Vec<f32>, PageToken)> {
         let (search_spec, scoring_spec) = self.build_query_specs(query)?;
-        self.execute_search(&search_spec, &scoring_spec.unwrap_or_default(), page_size, page_token)
+        self.execute_search(
+            &search_spec,
+            &scoring_spec.unwrap_or_default(),
+            page_size,
+            max_candidates,
+            page_token,
+        )
     }
 
     fn build_query_specs(
@@ -609,7 +763,13 @@ impl IcingMetaDatabase {
         page_token: PageToken,
     ) -> anyhow::Result<(Vec<BlobId>, Vec<f32>, PageToken)> {
         let (search_spec, scoring_spec) = self.build_embedding_query_specs(embedding_query)?;
-        self.execute_search(&search_spec, &scoring_spec.unwrap_or_default(), page_size, page_token)
+        self.execute_search(
+            &search_spec,
+            &scoring_spec.unwrap_or_default(),
+            page_size,
+            None,
+            page_token,
+        )
     }
 
     pub fn text_search(
@@ -623,6 +783,7 @@ impl IcingMetaDatabase {
             &search_spec,
             &icing::ScoringSpecProto::default(),
             page_size,
+            None,
             page_token,
         )
     }
@@ -694,6 +855,24 @@ impl IcingMetaDatabase {
     }
 
     pub fn export(&self) -> anyhow::Result<icing::IcingGroundTruthFiles> {
+        self.persist_to_disk()?;
+        icing::IcingGroundTruthFiles::new(&self.base_dir)
+    }
+
+    /// Exports the same files as [`Self::export`], but read from disk as
+    /// chunks of at most `chunk_size` bytes, so that the whole database is
+    /// never held in memory.
+    pub fn export_chunks(
+        &self,
+        chunk_size: usize,
+    ) -> anyhow::Result<
+        impl Iterator<Item = anyhow::Result<(icing::IcingGroundTruthFile, Vec<u8>)>> + '_,
+    > {
+        self.persist_to_disk()?;
+        icing::GroundTruthFileChunks::new(&self.base_dir, chunk_size)
+    }
+
+    fn persist_to_disk(&self) -> anyhow::Result<()> {
         let result_proto =
             self.icing_search_engine.persist_to_disk(icing::persist_type::Code::Full.into());
         let result_proto = icing::PersistToDiskResultProto::decode(result_proto.as_slice())?;
@@ -701,7 +880,7 @@ impl IcingMetaDatabase {
             result_proto.status.context("no status")?.code
                 == Some(icing::status_proto::Code::Ok.into())
         );
-        icing::IcingGroundTruthFiles::new(&self.base_dir)
+        Ok(())
     }
 }
 
@@ -788,6 +967,51 @@ mod tests {
         Ok(())
     }
 
+    #[gtest]
+    fn icing_count_memories_by_tag_test() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let mut icing_database = IcingMetaDatabase::new(temp_dir.path())?;
+
+        for i in 0..5 {
+            let tags = if i % 2 == 0 { vec!["even".to_string()] } else { vec![] };
+            let memory = Memory { id: format!("memory_{i}"), tags, ..Default::default() };
+            icing_database.add_memory(&memory, i.to_string())?;
+        }
+
+        let (even_blob_ids, _) =
+            icing_database.get_memories_by_tag("even", 10, PageToken::Start)?;
+        assert_that!(icing_database.count_memories_by_tag("even")?, eq(even_blob_ids.len()));
+        assert_that!(icing_database.count_memories_by_tag("even")?, eq(3));
+        assert_that!(icing_database.count_memories_by_tag("unknown")?, eq(0));
+
+        icing_database.delete_memories(&["memory_0".to_string()])?;
+        assert_that!(icing_database.count_memories_by_tag("even")?, eq(2));
+        Ok(())
+    }
+
+    #[gtest]
+    fn icing_get_all_memory_blob_ids_test() -> anyhow::Result<()> {
+        let temp_dir = tempdir()?;
+        let mut icing_database = IcingMetaDatabase::new(temp_dir.path())?;
+        assert_that!(icing_database.get_all_memory_blob_ids()?, is_empty());
+
+        for i in 0..3 {
+            let tags = if i == 0 { vec!["tag".to_string()] } else { vec![] };
+            let memory = Memory { id: format!("memory_{i}"), tags, ..Default::default() };
+            icing_database.add_memory(&memory, format!("blob_{i}"))?;
+        }
+        icing_database.delete_memories(&["memory_1".to_string()])?;
+
+        assert_that!(
+            icing_database.get_all_memory_blob_ids()?,
+            unordered_elements_are![
+                eq(&("memory_0".to_string(), "blob_0".to_string())),
+                eq(&("memory_2".to_string(), "blob_2".to_string())),
+            ]
+        );
+        Ok(())
+    }
+
     #[gtest]
     fn icing_import_export_test() -> anyhow::Result<()> {
         let temp_dir = tempdir()?;
@@ -16,12 +16,14 @@
 
 mod database_with_cache;
 pub mod encryption;
+pub mod export;
 pub mod icing;
 mod memory_cache;
 
 pub use crate::{
     database_with_cache::DatabaseWithCache,
     icing::{IcingMetaDatabase, PageToken},
+    memory_cache::DEFAULT_MEMORY_CACHE_CAPACITY,
 };
 
 // The unique id for a memory, responding to `struct Memory`.
@@ -16,38 +16,88 @@
 use std::collections::HashMap;
 
 use anyhow::{bail, Context};
-use encryption::{decrypt, encrypt, generate_nonce};
 use external_db_client::{BlobId, DataBlobHandler, ExternalDbClient};
 use prost::Message;
 use sealed_memory_rust_proto::prelude::v1::*;
 
+use crate::encryption::{decrypt_memory, encrypt_memory};
+
+/// The default number of decrypted memories kept in a [`MemoryCache`].
+pub const DEFAULT_MEMORY_CACHE_CAPACITY: usize = 256;
+
+/// Decrypted memories, of which at most `capacity` are kept. When full, the
+/// least recently used memory is evicted.
+struct ContentCache {
+    capacity: usize,
+    // Each memory with the time it was last used.
+    entries: HashMap<BlobId, (Memory, u64)>,
+    clock: u64,
+}
+
+impl ContentCache {
+    fn new(capacity: usize) -> Self {
+        Self { capacity, entries: HashMap::new(), clock: 0 }
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn get(&mut self, blob_id: &BlobId) -> Option<Memory> {
+        let now = self.tick();
+        self.entries.get_mut(blob_id).map(|(memory, last_used)| {
+            *last_used = now;
+            memory.clone()
+        })
+    }
+
+    fn insert(&mut self, blob_id: BlobId, memory: Memory) {
+        if self.capacity == 0 {
+            return;
+        }
+        if !self.entries.contains_key(&blob_id) && self.entries.len() >= self.capacity {
+            let least_recently_used = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(blob_id, _)| blob_id.clone());
+            if let Some(least_recently_used) = least_recently_used {
+                self.entries.remove(&least_recently_used);
+            }
+        }
+        let now = self.tick();
+        self.entries.insert(blob_id, (memory, now));
+    }
+
+    fn remove(&mut self, blob_id: &BlobId) {
+        self.entries.remove(blob_id);
+    }
+}
+
 /// In memory cache for memories.
 ///
 /// When a memory is added, it is cached in `MemoryCache` and also persisted at
 /// disk. When a memory is fetched, if the memory is cached, it is returned
 /// directly from the cached. Otherwise, it will further fetched from the
 /// external storage.
-/// TODO: b/412698203 - Add eviction to avoid OOM.
+///
+/// At most `capacity` memories are cached. As every cached memory has already
+/// been persisted, evicting one never loses changes: it is fetched again the
+/// next time it is accessed.
 pub(crate) struct MemoryCache {
     db_client: ExternalDbClient,
-    content_cache: HashMap<BlobId, Memory>,
+    content_cache: ContentCache,
     dek: Vec<u8>,
 }
 
 impl MemoryCache {
-    pub fn new(db_client: ExternalDbClient, dek: Vec<u8>) -> Self {
-        let content_cache = HashMap::<BlobId, Memory>::default();
-        Self { db_client, dek, content_cache }
+    pub fn new(db_client: ExternalDbClient, dek: Vec<u8>, capacity: usize) -> Self {
+        Self { db_client, dek, content_cache: ContentCache::new(capacity) }
     }
 
     fn add_cache_entry(&mut self, blob_id: BlobId, memory: Memory) {
-        const MAX_CACHE_SIZE: usize = 5;
-        if self.content_cache.len() > MAX_CACHE_SIZE {
-            // TODO: b/412698203 - Add eviction to avoid OOM.
-            // Avoid OOM.
-            self.content_cache.clear();
-        }
-        self.content_cache.insert(blob_id.clone(), memory);
+        self.content_cache.insert(blob_id, memory);
     }
 
     async fn fetch_decrypt_decode_memory(&self, blob_id: &BlobId) -> anyhow::Result<Memory> {
@@ -57,14 +107,14 @@ impl MemoryCache {
             .get_blob(blob_id, false)
             .await?
             .context(format!("Blob not found for id: {}", blob_id))?;
-        let decrypted_data = decrypt(&self.dek, &encrypted_blob.nonce, &encrypted_blob.data)?;
+        let decrypted_data = decrypt_memory(&encrypted_blob, &self.dek)?;
         Ok(Memory::decode(&*decrypted_data)?)
     }
 
     pub async fn get_memory_by_blob_id(&mut self, blob_id: &BlobId) -> anyhow::Result<Memory> {
         // Check cache first
         if let Some(memory) = self.content_cache.get(blob_id) {
-            return Ok(memory.clone());
+            return Ok(memory);
         }
         // If not in cache, fetch from external DB
         let memory = self.fetch_decrypt_decode_memory(blob_id).await?;
@@ -82,7 +132,7 @@ impl MemoryCache {
         // Check cache first
         for blob_id in blob_ids {
             if let Some(memory) = self.content_cache.get(blob_id) {
-                results.insert(blob_id.clone(), memory.clone());
+                results.insert(blob_id.clone(), memory);
             } else {
                 missing_ids.push(blob_id.clone());
             }
@@ -93,10 +143,9 @@ impl MemoryCache {
             for (blob_id, encrypted_blob_opt) in missing_ids.iter().zip(encrypted_blobs.into_iter())
             {
                 if let Some(encrypted_blob) = encrypted_blob_opt {
-                    let decrypted_data =
-                        decrypt(&self.dek, &encrypted_blob.nonce, &encrypted_blob.data)?;
+                    let decrypted_data = decrypt_memory(&encrypted_blob, &self.dek)?;
                     let memory: Memory = Memory::decode(&*decrypted_data)?;
-                    self.content_cache.insert(blob_id.clone(), memory.clone());
+                    self.add_cache_entry(blob_id.clone(), memory.clone());
                     results.insert(blob_id.clone(), memory);
                 } else {
                     bail!("Blob not found for id: {}", blob_id);
@@ -111,18 +160,38 @@ impl MemoryCache {
             .collect::<anyhow::Result<Vec<_>>>()
     }
 
-    /// Encodes and encrypts a memory, returning the blob and a generated nonce.
-    fn encode_encrypt_memory(&self, memory: &Memory) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
-        let memory_data = memory.encode_to_vec();
-        let nonce = generate_nonce();
-        let encrypted_data = encrypt(&self.dek, &nonce, &memory_data)?;
-        Ok((encrypted_data, nonce))
+    /// Returns the ids in `blob_ids` that are neither cached nor stored in the
+    /// external database.
+    pub async fn find_missing_blob_ids(
+        &mut self,
+        blob_ids: &[BlobId],
+    ) -> anyhow::Result<Vec<BlobId>> {
+        // Bounds the number of concurrent reads from the external database.
+        const BATCH_SIZE: usize = 100;
+        let uncached_ids: Vec<BlobId> = blob_ids
+            .iter()
+            .filter(|blob_id| !self.content_cache.entries.contains_key(*blob_id))
+            .cloned()
+            .collect();
+
+        let mut missing_ids = Vec::new();
+        for batch in uncached_ids.chunks(BATCH_SIZE) {
+            let encrypted_blobs = self.db_client.get_blobs(batch, false).await?;
+            missing_ids.extend(
+                batch
+                    .iter()
+                    .zip(encrypted_blobs)
+                    .filter(|(_, encrypted_blob)| encrypted_blob.is_none())
+                    .map(|(blob_id, _)| blob_id.clone()),
+            );
+        }
+        Ok(missing_ids)
     }
 
     pub async fn add_memory(&mut self, memory: &Memory) -> anyhow::Result<BlobId> {
         let blob_id: BlobId = rand::random::<u128>().to_string();
-        let (encrypted_data, nonce) = self.encode_encrypt_memory(memory)?;
-        let encrypted_blob = EncryptedDataBlob { nonce, data: encrypted_data };
+        // Each memory is encrypted with its own key, wrapped by the DEK.
+        let encrypted_blob = encrypt_memory(&memory.encode_to_vec(), &self.dek)?;
 
         // Store in external DB, explicitly providing the generated ID
         self.db_client.add_blob(encrypted_blob, Some(blob_id.clone())).await?;
@@ -142,3 +211,56 @@ impl MemoryCache {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use googletest::prelude::*;
+
+    use super::*;
+
+    fn memory(id: &str) -> Memory {
+        Memory { id: id.to_string(), ..Default::default() }
+    }
+
+    fn cached_ids(cache: &ContentCache) -> Vec<String> {
+        let mut ids: Vec<String> = cache.entries.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    #[gtest]
+    fn content_cache_evicts_least_recently_used() {
+        let mut cache = ContentCache::new(2);
+        cache.insert("a".to_string(), memory("a"));
+        cache.insert("b".to_string(), memory("b"));
+        // Accessing "a" makes "b" the least recently used memory.
+        assert_that!(cache.get(&"a".to_string()), some(eq(memory("a"))));
+
+        cache.insert("c".to_string(), memory("c"));
+
+        assert_that!(cached_ids(&cache), elements_are![eq("a"), eq("c")]);
+        // An evicted memory is a cache miss, so it is fetched again.
+        assert_that!(cache.get(&"b".to_string()), none());
+    }
+
+    #[gtest]
+    fn content_cache_replaces_without_evicting() {
+        let mut cache = ContentCache::new(2);
+        cache.insert("a".to_string(), memory("a"));
+        cache.insert("b".to_string(), memory("b"));
+
+        let updated = Memory { tags: vec!["tag".to_string()], ..memory("a") };
+        cache.insert("a".to_string(), updated.clone());
+
+        assert_that!(cached_ids(&cache), elements_are![eq("a"), eq("b")]);
+        assert_that!(cache.get(&"a".to_string()), some(eq(updated)));
+    }
+
+    #[gtest]
+    fn content_cache_with_zero_capacity_caches_nothing() {
+        let mut cache = ContentCache::new(0);
+        cache.insert("a".to_string(), memory("a"));
+
+        assert_that!(cache.get(&"a".to_string()), none());
+    }
+}
@@ -39,6 +39,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         "oak.private_memory.ResetMemoryRequest",
         "oak.private_memory.ResetMemoryResponse",
         "oak.private_memory.InvalidRequestResponse",
+        "oak.private_memory.RequestTimeoutResponse",
         "oak.private_memory.KeySyncRequest",
         "oak.private_memory.KeySyncResponse",
         "oak.private_memory.GetMemoryByIdRequest",
@@ -56,12 +57,22 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         "oak.private_memory.EmbeddingQuery",
         "oak.private_memory.ScoreRange",
         "oak.private_memory.ResultMask",
+        "oak.private_memory.ContentRange",
         "oak.private_memory.SealedMemoryWrapperRequest",
         "oak.private_memory.SealedMemoryWrapperResponse",
         "oak.private_memory.DeleteMemoryRequest",
         "oak.private_memory.DeleteMemoryResponse",
+        "oak.private_memory.RenameTagRequest",
+        "oak.private_memory.RenameTagResponse",
+        "oak.private_memory.MergeTagsRequest",
+        "oak.private_memory.MergeTagsResponse",
+        "oak.private_memory.PingRequest",
+        "oak.private_memory.PingResponse",
+        "oak.private_memory.RotateKekRequest",
+        "oak.private_memory.RotateKekResponse",
         "oak.private_memory.TextQuery",
         "oak.private_memory.QueryClauses",
+        "oak.private_memory.SortBy",
     ];
 
     let oneof_field_names = [
@@ -129,6 +140,14 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         "oak.private_memory.QueryClauses.operator",
         "#[serde(with=\"crate::operator_converter\")]",
     );
+    config.field_attribute(
+        "oak.private_memory.SortBy.field",
+        "#[serde(with=\"crate::sort_field_converter\")]",
+    );
+    config.field_attribute(
+        "oak.private_memory.SortBy.direction",
+        "#[serde(with=\"crate::sort_direction_converter\")]",
+    );
 
     // Timestamp converters
     config.field_attribute(
@@ -331,6 +331,23 @@ enum_converter!(
     valid_variants = &["OPERATOR_UNSPECIFIED", "OPERATOR_AND", "OPERATOR_OR"]
 );
 
+enum_converter!(
+    module_name = sort_field_converter,
+    enum_type = crate::oak::private_memory::MemoryField,
+    unspecified_variant = crate::oak::private_memory::MemoryField::Unknown,
+    doc_string = "a string or an integer representing a MemoryField variant",
+    valid_variants = &["ID", "CREATED_TIMESTAMP", "EVENT_TIMESTAMP"]
+);
+
+enum_converter!(
+    module_name = sort_direction_converter,
+    enum_type = crate::oak::private_memory::SortDirection,
+    unspecified_variant = crate::oak::private_memory::SortDirection::Unspecified,
+    doc_string = "a string or an integer representing a SortDirection variant",
+    valid_variants =
+        &["SORT_DIRECTION_UNSPECIFIED", "SORT_DIRECTION_ASCENDING", "SORT_DIRECTION_DESCENDING"]
+);
+
 pub mod timestamp_converter {
     use chrono::{DateTime, Utc};
     use prost_types::Timestamp;
@@ -17,14 +17,18 @@ pub mod v1 {
     pub use crate::oak::private_memory::{
         key_sync_response, memory_value, sealed_memory_request, sealed_memory_response,
         search_memory_query, user_registration_response, AddMemoryRequest, AddMemoryResponse,
-        DataBlob, DeleteMemoryRequest, DeleteMemoryResponse, Embedding, EmbeddingQuery,
-        EmbeddingQueryMetricType, EncryptedDataBlob, EncryptedUserInfo, GetMemoriesRequest,
-        GetMemoriesResponse, GetMemoryByIdRequest, GetMemoryByIdResponse, InvalidRequestResponse,
-        KeyDerivationInfo, KeySyncRequest, KeySyncResponse, Memory, MemoryContent, MemoryField,
-        MemoryValue, PlainTextUserInfo, ResetMemoryRequest, ResetMemoryResponse, ResultMask,
-        ScoreRange, SealedMemoryCredentials, SealedMemoryRequest, SealedMemoryResponse,
-        SealedMemorySessionRequest, SealedMemorySessionResponse, SearchMemoryQuery,
-        SearchMemoryRequest, SearchMemoryResponse, SearchMemoryResultItem, UserDb,
+        CompressionType, ContentRange, DataBlob, DeleteMemoryRequest, DeleteMemoryResponse,
+        Embedding, EmbeddingQuery, EmbeddingQueryMetricType, EncryptedDataBlob, EncryptedUserInfo,
+        GetMemoriesRequest, GetMemoriesResponse, GetMemoryByIdRequest, GetMemoryByIdResponse,
+        InvalidRequestResponse, KeyDerivationInfo, KeySyncRequest, KeySyncResponse, Memory,
+        MemoryContent, MemoryField, MemoryValue, MergeTagsRequest, MergeTagsResponse, PingRequest,
+        PingResponse, PlainTextUserInfo, RenameTagRequest, RenameTagResponse,
+        RequestTimeoutResponse, ResetMemoryRequest, ResetMemoryResponse, ResultMask,
+        RotateKekRequest, RotateKekResponse, ScoreRange, SealedMemoryCredentials,
+        SealedMemoryRequest, SealedMemoryResponse, SealedMemorySessionRequest,
+        SealedMemorySessionResponse, SearchMemoryQuery, SearchMemoryRequest, SearchMemoryResponse,
+        SearchMemoryResultItem, SortBy, SortDirection, UserDb, UserMetadata,
         UserRegistrationRequest, UserRegistrationResponse, WrappedDataEncryptionKey,
+        WrappedMemoryKey,
     };
 }
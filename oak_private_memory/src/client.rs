@@ -106,10 +106,25 @@ pub struct PrivateMemoryClient {
 
 impl PrivateMemoryClient {
     pub async fn new(
-        mut transport: Box<dyn Transport + Send>,
+        transport: Box<dyn Transport + Send>,
         pm_uid: &str,
         kek: &[u8],
         format: SerializationFormat,
+    ) -> Result<Self> {
+        let mut client = Self::new_without_user(transport, format).await?;
+
+        client.register_user(pm_uid, kek).await?;
+        client.key_sync(pm_uid, kek).await?;
+
+        Ok(client)
+    }
+
+    /// Opens a session without registering or syncing keys for a user. Only
+    /// requests that don't need a user, such as [`Self::ping`], can be sent
+    /// with such a client.
+    pub async fn new_without_user(
+        mut transport: Box<dyn Transport + Send>,
+        format: SerializationFormat,
     ) -> Result<Self> {
         let mut client_session = oak_session::ClientSession::create(
             SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN).build(),
@@ -129,12 +144,7 @@ impl PrivateMemoryClient {
             }
         }
 
-        let mut client = Self { client_session, transport, format };
-
-        client.register_user(pm_uid, kek).await?;
-        client.key_sync(pm_uid, kek).await?;
-
-        Ok(client)
+        Ok(Self { client_session, transport, format })
     }
 
     pub async fn create_with_start_session(
@@ -143,6 +153,19 @@ impl PrivateMemoryClient {
         kek: &[u8],
         format: SerializationFormat,
     ) -> Result<Self> {
+        let transport = Self::start_session_transport(server_addr).await?;
+        Self::new(transport, pm_uid, kek, format).await
+    }
+
+    pub async fn create_with_start_session_without_user(
+        server_addr: &str,
+        format: SerializationFormat,
+    ) -> Result<Self> {
+        let transport = Self::start_session_transport(server_addr).await?;
+        Self::new_without_user(transport, format).await
+    }
+
+    async fn start_session_transport(server_addr: &str) -> Result<Box<dyn Transport + Send>> {
         let channel = Channel::from_shared(server_addr.to_string())
             .context("failed to create shared channel")?
             .connect()
@@ -153,9 +176,7 @@ impl PrivateMemoryClient {
         let rx =
             client.start_session(rx_stream).await.context("failed to start session")?.into_inner();
 
-        let transport = Box::new(TonicStartSessionTransport { tx, rx });
-
-        Self::new(transport, pm_uid, kek, format).await
+        Ok(Box::new(TonicStartSessionTransport { tx, rx }))
     }
 
     async fn invoke(
@@ -197,6 +218,7 @@ impl PrivateMemoryClient {
             pm_uid: pm_uid.to_string(),
             key_encryption_key: kek.to_vec(),
             boot_strap_info: Some(KeyDerivationInfo::default()),
+            check_only: false,
         };
         let response =
             self.invoke(sealed_memory_request::Request::UserRegistrationRequest(request)).await?;
@@ -212,6 +234,26 @@ impl PrivateMemoryClient {
         }
     }
 
+    /// Returns whether `pm_uid` is registered, without registering it.
+    pub async fn is_user_registered(&mut self, pm_uid: &str) -> Result<bool> {
+        let request = UserRegistrationRequest {
+            pm_uid: pm_uid.to_string(),
+            check_only: true,
+            ..Default::default()
+        };
+        let response =
+            self.invoke(sealed_memory_request::Request::UserRegistrationRequest(request)).await?;
+        let response = expect_response_type!(
+            response,
+            sealed_memory_response::Response::UserRegistrationResponse
+        )?;
+        match response.status() {
+            user_registration_response::Status::UserAlreadyExists => Ok(true),
+            user_registration_response::Status::Success => Ok(false),
+            s => Err(anyhow!("user registration check failed with status: {:?}", s)),
+        }
+    }
+
     async fn key_sync(&mut self, pm_uid: &str, kek: &[u8]) -> Result<()> {
         let request =
             KeySyncRequest { pm_uid: pm_uid.to_string(), key_encryption_key: kek.to_vec() };
@@ -244,6 +286,7 @@ impl PrivateMemoryClient {
             page_size,
             result_mask,
             page_token: page_token.to_string(),
+            ..Default::default()
         };
         let response =
             self.invoke(sealed_memory_request::Request::GetMemoriesRequest(request)).await?;
@@ -273,6 +316,7 @@ impl PrivateMemoryClient {
             page_size,
             result_mask,
             page_token: page_token.to_string(),
+            ..Default::default()
         };
         let response =
             self.invoke(sealed_memory_request::Request::SearchMemoryRequest(request)).await?;
@@ -286,6 +330,49 @@ impl PrivateMemoryClient {
         expect_response_type!(response, sealed_memory_response::Response::DeleteMemoryResponse)
     }
 
+    pub async fn rename_tag(&mut self, old_tag: &str, new_tag: &str) -> Result<RenameTagResponse> {
+        let request =
+            RenameTagRequest { old_tag: old_tag.to_string(), new_tag: new_tag.to_string() };
+        let response =
+            self.invoke(sealed_memory_request::Request::RenameTagRequest(request)).await?;
+        expect_response_type!(response, sealed_memory_response::Response::RenameTagResponse)
+    }
+
+    pub async fn merge_tags(
+        &mut self,
+        source_tags: Vec<String>,
+        target_tag: &str,
+    ) -> Result<MergeTagsResponse> {
+        let request = MergeTagsRequest { source_tags, target_tag: target_tag.to_string() };
+        let response =
+            self.invoke(sealed_memory_request::Request::MergeTagsRequest(request)).await?;
+        expect_response_type!(response, sealed_memory_response::Response::MergeTagsResponse)
+    }
+
+    pub async fn rotate_kek(
+        &mut self,
+        pm_uid: &str,
+        old_kek: &[u8],
+        new_kek: &[u8],
+    ) -> Result<RotateKekResponse> {
+        let request = RotateKekRequest {
+            pm_uid: pm_uid.to_string(),
+            old_key_encryption_key: old_kek.to_vec(),
+            new_key_encryption_key: new_kek.to_vec(),
+            new_key_derivation_info: None,
+        };
+        let response =
+            self.invoke(sealed_memory_request::Request::RotateKekRequest(request)).await?;
+        expect_response_type!(response, sealed_memory_response::Response::RotateKekResponse)
+    }
+
+    pub async fn ping(&mut self) -> Result<PingResponse> {
+        let response = self
+            .invoke(sealed_memory_request::Request::PingRequest(PingRequest::default()))
+            .await?;
+        expect_response_type!(response, sealed_memory_response::Response::PingResponse)
+    }
+
     pub async fn reset_memory(&mut self) -> Result<ResetMemoryResponse> {
         let request = ResetMemoryRequest::default();
         let response =
@@ -16,15 +16,19 @@
 
 use std::{
     fs,
+    fs::File,
+    io::{Read, Write},
     path::{Path, PathBuf},
 };
 
-use anyhow::{bail, Context, Result};
+use anyhow::{bail, ensure, Context, Result};
 // Rexport the ffi functions from the bridge.
 pub use icing_ffi_bridge::*;
 use icing_rust_proto::icing::lib::scoring_spec_proto::ranking_strategy;
 pub use icing_rust_proto::icing::lib::*;
-pub use sealed_memory_rust_proto::oak::private_memory::IcingGroundTruthFiles;
+pub use sealed_memory_rust_proto::oak::private_memory::{
+    IcingGroundTruthFile, IcingGroundTruthFiles,
+};
 
 use crate::property_proto::VectorProto;
 
@@ -86,23 +90,7 @@ impl IcingGroundTruthFilesHelper for IcingGroundTruthFiles {
     /// Recreates the ground truth files in the specified new directory path.
     fn migrate(&self, new_path: &str) -> Result<()> {
         let new_base_dir = PathBuf::from(new_path);
-
-        // Ensure the target path is empty by removing it if it exists
-        if new_base_dir.exists() {
-            if new_base_dir.is_dir() {
-                fs::remove_dir_all(&new_base_dir).with_context(|| {
-                    format!("Failed to remove existing directory {}", new_base_dir.display())
-                })?;
-            } else {
-                fs::remove_file(&new_base_dir).with_context(|| {
-                    format!("Failed to remove existing file {}", new_base_dir.display())
-                })?;
-            }
-        }
-
-        // Create base directory and subdirectories
-        create_subdir(&new_base_dir, "schema_dir")?;
-        create_subdir(&new_base_dir, "document_dir")?;
+        prepare_base_dir(&new_base_dir)?;
 
         // Write schema_pb
         write_file(&new_base_dir, SCHEMA_PB_PATH, &self.schema_pb)?;
@@ -122,6 +110,156 @@ impl IcingGroundTruthFilesHelper for IcingGroundTruthFiles {
     }
 }
 
+/// The path of `file` relative to the base directory of the database.
+fn ground_truth_file_path(file: IcingGroundTruthFile) -> Result<&'static str> {
+    match file {
+        IcingGroundTruthFile::SchemaPb => Ok(SCHEMA_PB_PATH),
+        IcingGroundTruthFile::OverlaySchemaPb => Ok(OVERLAY_SCHEMA_PB_PATH),
+        IcingGroundTruthFile::SchemaStoreHeader => Ok(SCHEMA_STORE_HEADER_PATH),
+        IcingGroundTruthFile::DocumentLog => Ok(DOCUMENT_LOG_PATH),
+        IcingGroundTruthFile::Unspecified => bail!("unspecified ground truth file"),
+    }
+}
+
+/// The ground truth files in the order they are exported in.
+static GROUND_TRUTH_FILES: [IcingGroundTruthFile; 4] = [
+    IcingGroundTruthFile::SchemaPb,
+    IcingGroundTruthFile::OverlaySchemaPb,
+    IcingGroundTruthFile::SchemaStoreHeader,
+    IcingGroundTruthFile::DocumentLog,
+];
+
+/// Reads the ground truth files in `base_dir` as consecutive chunks of at most
+/// `chunk_size` bytes, in the order of [`IcingGroundTruthFile`]. Unlike
+/// [`IcingGroundTruthFilesHelper::new`], only one chunk is held in memory at a
+/// time.
+///
+/// The overlay schema is optional, and skipped if it doesn't exist.
+pub struct GroundTruthFileChunks {
+    base_dir: PathBuf,
+    chunk_size: usize,
+    remaining_files: std::slice::Iter<'static, IcingGroundTruthFile>,
+    current_file: Option<(IcingGroundTruthFile, File)>,
+}
+
+impl GroundTruthFileChunks {
+    pub fn new(base_dir: &str, chunk_size: usize) -> Result<Self> {
+        ensure!(chunk_size > 0, "the chunk size must be positive");
+        let base_dir = PathBuf::from(base_dir);
+        if !base_dir.is_dir() {
+            bail!("{} is not a valid directory", base_dir.display());
+        }
+        Ok(Self {
+            base_dir,
+            chunk_size,
+            remaining_files: GROUND_TRUTH_FILES.iter(),
+            current_file: None,
+        })
+    }
+
+    fn open_next_file(&mut self) -> Result<Option<(IcingGroundTruthFile, File)>> {
+        for &file in self.remaining_files.by_ref() {
+            let path = self.base_dir.join(ground_truth_file_path(file)?);
+            if file == IcingGroundTruthFile::OverlaySchemaPb && !path.is_file() {
+                continue;
+            }
+            let opened =
+                File::open(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+            return Ok(Some((file, opened)));
+        }
+        Ok(None)
+    }
+
+    fn next_chunk(&mut self) -> Result<Option<(IcingGroundTruthFile, Vec<u8>)>> {
+        loop {
+            if self.current_file.is_none() {
+                match self.open_next_file()? {
+                    Some(next_file) => self.current_file = Some(next_file),
+                    None => return Ok(None),
+                }
+            }
+            let (file, opened) = self.current_file.as_mut().context("no file to read from")?;
+            let mut chunk = Vec::with_capacity(self.chunk_size);
+            opened
+                .by_ref()
+                .take(self.chunk_size as u64)
+                .read_to_end(&mut chunk)
+                .with_context(|| format!("Failed to read {:?}", file))?;
+            if !chunk.is_empty() {
+                return Ok(Some((*file, chunk)));
+            }
+            self.current_file = None;
+        }
+    }
+}
+
+impl Iterator for GroundTruthFileChunks {
+    type Item = Result<(IcingGroundTruthFile, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_chunk().transpose()
+    }
+}
+
+/// Recreates the ground truth files in a new directory from the chunks read by
+/// [`GroundTruthFileChunks`], which must be appended in the same order.
+pub struct GroundTruthFilesWriter {
+    base_dir: PathBuf,
+    current_file: Option<(IcingGroundTruthFile, File)>,
+}
+
+impl GroundTruthFilesWriter {
+    /// Prepares `new_path` for the files. Anything that already exists there
+    /// is removed.
+    pub fn new(new_path: &str) -> Result<Self> {
+        let base_dir = PathBuf::from(new_path);
+        prepare_base_dir(&base_dir)?;
+        Ok(Self { base_dir, current_file: None })
+    }
+
+    pub fn append(&mut self, file: IcingGroundTruthFile, data: &[u8]) -> Result<()> {
+        let current = self.current_file.as_ref().map(|(current, _)| *current);
+        if current != Some(file) {
+            if let Some(current) = current {
+                ensure!(file > current, "{:?} is out of order after {:?}", file, current);
+            }
+            let path = self.base_dir.join(ground_truth_file_path(file)?);
+            let created = File::create_new(&path)
+                .with_context(|| format!("Failed to create {}", path.display()))?;
+            self.current_file = Some((file, created));
+        }
+        let (_, opened) = self.current_file.as_mut().context("no file to append to")?;
+        opened.write_all(data).with_context(|| format!("Failed to write {:?}", file))
+    }
+
+    /// Checks that all the required files were written.
+    pub fn finish(self) -> Result<()> {
+        for path in [SCHEMA_PB_PATH, SCHEMA_STORE_HEADER_PATH, DOCUMENT_LOG_PATH] {
+            ensure!(self.base_dir.join(path).is_file(), "Missing {} in the import", path);
+        }
+        Ok(())
+    }
+}
+
+/// Creates an empty `base_dir` with the subdirectories of the ground truth
+/// files, removing anything that already exists at that path.
+fn prepare_base_dir(base_dir: &Path) -> Result<()> {
+    if base_dir.exists() {
+        if base_dir.is_dir() {
+            fs::remove_dir_all(base_dir).with_context(|| {
+                format!("Failed to remove existing directory {}", base_dir.display())
+            })?;
+        } else {
+            fs::remove_file(base_dir).with_context(|| {
+                format!("Failed to remove existing file {}", base_dir.display())
+            })?;
+        }
+    }
+
+    create_subdir(base_dir, "schema_dir")?;
+    create_subdir(base_dir, "document_dir")
+}
+
 fn create_subdir(base_dir: &Path, subdir_name: &str) -> Result<()> {
     fs::create_dir_all(base_dir.join(subdir_name))
         .with_context(|| format!("Failed to create {} in {}", subdir_name, base_dir.display()))
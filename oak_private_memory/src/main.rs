@@ -48,7 +48,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let listener = TcpListener::bind(addr).await?;
 
     let (persistence_tx, persistence_rx) = mpsc::unbounded_channel();
-    let persistence_join_handle = tokio::spawn(run_persistence_service(persistence_rx));
+    let persistence_join_handle =
+        tokio::spawn(run_persistence_service(persistence_rx, application_config.compress_database));
 
     let metrics = private_memory_server_lib::metrics::get_global_metrics();
     let join_handle = tokio::spawn(private_memory_server_lib::app::service::create(
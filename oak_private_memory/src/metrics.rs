@@ -20,10 +20,14 @@
 /// When adding new metrics, try to create clear, easy-to-use API additions, so
 /// that the usage site needs just a line or two of code to correctly record the
 /// metrics.
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
 
 use lazy_static::lazy_static;
 use oak_containers_agent::metrics::OakObserver;
+use oak_session::attestation::AttestationType;
 use opentelemetry::{
     metrics::{Counter, Histogram, ObservableGauge},
     KeyValue, Value,
@@ -38,8 +42,12 @@ pub struct Metrics {
     rpc_failure_count: Counter<u64>,
     // Latency of each RPC.
     rpc_latency: Histogram<u64>,
+    // Latency of establishing a session, including attestation.
+    handshake_latency: Histogram<u64>,
     // Size of the database in bytes.
     db_size: Histogram<u64>,
+    // Size of the persisted database as a percentage of its uncompressed size.
+    db_compression_ratio: Histogram<u64>,
     // Latency of Icing database initialization.
     db_init_latency: Histogram<u64>,
     // Latency of persisting the database.
@@ -48,8 +56,14 @@ pub struct Metrics {
     db_connect_retries: Counter<u64>,
     // Number of failures when persisting the database.
     db_persist_failures: Counter<u64>,
+    // Number of failures to create the directory of a database.
+    db_dir_creation_failures: Counter<u64>,
     // Queue size of the in the database persist queue.
     db_persist_queue_size: ObservableGauge<u64>,
+    // Number of sessions with an established user session context.
+    active_sessions: ObservableGauge<u64>,
+    // The value last observed by `active_sessions`.
+    active_session_count: AtomicU64,
 }
 
 /// The possible metrics request types.
@@ -85,12 +99,29 @@ impl Metrics {
             // Update the version of opentelemetry to support custom buckets.
             //.with_boundaries(vec![0, 100, 200, 300, 400, 500, 1000, 2000, 5000, 50000])
             .init();
+        let handshake_latency = observer
+            .meter
+            .u64_histogram("handshake_latency")
+            .with_description(
+                "Latency in ms of establishing a session, from the first handshake message until \
+                 the session is open.",
+            )
+            .with_unit("ms")
+            .init();
         let db_size = observer
             .meter
             .u64_histogram("db_size")
             .with_description("Size of the database in bytes.")
             .with_unit("By")
             .init();
+        let db_compression_ratio = observer
+            .meter
+            .u64_histogram("db_compression_ratio")
+            .with_description(
+                "Size of the persisted database as a percentage of its uncompressed size.",
+            )
+            .with_unit("%")
+            .init();
         let db_init_latency = observer
             .meter
             .u64_histogram("db_init_latency")
@@ -115,42 +146,67 @@ impl Metrics {
             .with_description("Number of failures when persisting the database.")
             .init();
 
+        let db_dir_creation_failures = observer
+            .meter
+            .u64_counter("db_dir_creation_failures")
+            .with_description("Number of failures when creating the directory of a database.")
+            .init();
+
         let db_persist_queue_size = observer
             .meter
             .u64_observable_gauge("db_persist_queue_size")
             .with_description("Number of items in the database persist queue.")
             .init();
 
+        let active_sessions = observer
+            .meter
+            .u64_observable_gauge("active_sessions")
+            .with_description("Number of sessions with an established user session context.")
+            .init();
+
         // Initialize the total count to 0 to trigger the metric registration.
         // Otherwise, the metric will only show up once it has been incremented.
         rpc_count.add(0, &[KeyValue::new("request_type", "total")]);
         rpc_failure_count.add(0, &[KeyValue::new("request_type", "total")]);
         rpc_latency.record(1, &[KeyValue::new("request_type", "test")]);
+        handshake_latency.record(1, &[KeyValue::new("attestation_type", "test")]);
         db_size.record(1, &[]);
+        db_compression_ratio.record(100, &[]);
         db_init_latency.record(1, &[]);
         db_persist_latency.record(1, &[]);
         db_connect_retries.add(0, &[]);
         db_persist_failures.add(0, &[]);
+        db_dir_creation_failures.add(0, &[]);
         db_persist_queue_size.observe(0, &[]);
+        active_sessions.observe(0, &[]);
         observer.register_metric(rpc_count.clone());
         observer.register_metric(rpc_failure_count.clone());
         observer.register_metric(rpc_latency.clone());
+        observer.register_metric(handshake_latency.clone());
         observer.register_metric(db_size.clone());
+        observer.register_metric(db_compression_ratio.clone());
         observer.register_metric(db_init_latency.clone());
         observer.register_metric(db_persist_latency.clone());
         observer.register_metric(db_connect_retries.clone());
         observer.register_metric(db_persist_failures.clone());
+        observer.register_metric(db_dir_creation_failures.clone());
         observer.register_metric(db_persist_queue_size.clone());
+        observer.register_metric(active_sessions.clone());
         Self {
             rpc_count,
             rpc_failure_count,
             rpc_latency,
+            handshake_latency,
             db_size,
+            db_compression_ratio,
             db_init_latency,
             db_persist_latency,
             db_connect_retries,
             db_persist_failures,
+            db_dir_creation_failures,
             db_persist_queue_size,
+            active_sessions,
+            active_session_count: AtomicU64::new(0),
         }
     }
 
@@ -180,6 +236,26 @@ impl Metrics {
         self.rpc_latency.record(elapsed_time_ms, &[KeyValue::new("request_type", "total")]);
     }
 
+    /// Record the time it took to establish a session with the given
+    /// attestation type.
+    pub fn record_handshake_latency(
+        &self,
+        elapsed_time_ms: u64,
+        attestation_type: AttestationType,
+    ) {
+        // Round up as 1ms.
+        let elapsed_time_ms = std::cmp::max(1, elapsed_time_ms);
+
+        let attestation_type = match attestation_type {
+            AttestationType::Bidirectional => "bidirectional",
+            AttestationType::SelfUnidirectional => "self_unidirectional",
+            AttestationType::PeerUnidirectional => "peer_unidirectional",
+            AttestationType::Unattested => "unattested",
+        };
+        self.handshake_latency
+            .record(elapsed_time_ms, &[KeyValue::new("attestation_type", attestation_type)]);
+    }
+
     /// Record the time it took to save the DB.
     pub fn record_db_save_speed(&self, speed: u64) {
         // Round up as 1ms.
@@ -202,6 +278,15 @@ impl Metrics {
         self.db_size.record(size, &[]);
     }
 
+    /// Record the size of a persisted database relative to its uncompressed
+    /// size.
+    pub fn record_db_compression_ratio(&self, size: u64, uncompressed_size: u64) {
+        if uncompressed_size == 0 {
+            return;
+        }
+        self.db_compression_ratio.record(size * 100 / uncompressed_size, &[]);
+    }
+
     pub fn record_db_init_latency(&self, latency: u64) {
         self.db_init_latency.record(latency, &[]);
     }
@@ -218,9 +303,34 @@ impl Metrics {
         self.db_persist_failures.add(1, &[]);
     }
 
+    pub fn inc_db_dir_creation_failures(&self) {
+        self.db_dir_creation_failures.add(1, &[]);
+    }
+
     pub fn record_db_persist_queue_size(&self, max: u64) {
         self.db_persist_queue_size.observe(max, &[]);
     }
+
+    /// Record that a user session context was established.
+    pub fn inc_active_sessions(&self) {
+        let count = self.active_session_count.fetch_add(1, Ordering::Relaxed) + 1;
+        self.active_sessions.observe(count, &[]);
+    }
+
+    /// Record that a session with an established user session context ended.
+    pub fn dec_active_sessions(&self) {
+        let previous = self
+            .active_session_count
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| count.checked_sub(1))
+            .unwrap_or(0);
+        self.active_sessions.observe(previous.saturating_sub(1), &[]);
+    }
+
+    /// Returns the number of sessions with an established user session
+    /// context.
+    pub fn active_sessions(&self) -> u64 {
+        self.active_session_count.load(Ordering::Relaxed)
+    }
 }
 
 fn create_metrics() -> (OakObserver, Arc<Metrics>) {
@@ -274,6 +384,35 @@ impl RequestMetricName {
             sealed_memory_request::Request::GetMemoryByIdRequest(r) => get_name(r),
             sealed_memory_request::Request::SearchMemoryRequest(r) => get_name(r),
             sealed_memory_request::Request::DeleteMemoryRequest(r) => get_name(r),
+            sealed_memory_request::Request::RenameTagRequest(r) => get_name(r),
+            sealed_memory_request::Request::MergeTagsRequest(r) => get_name(r),
+            sealed_memory_request::Request::PingRequest(r) => get_name(r),
+            sealed_memory_request::Request::RotateKekRequest(r) => get_name(r),
         }))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_active_sessions_tracks_create_and_drop() {
+        let mut observer =
+            OakObserver::create("http://127.0.0.1:0".to_string(), "metrics_test", vec![]).unwrap();
+        let metrics = Metrics::new(&mut observer);
+        assert_eq!(metrics.active_sessions(), 0);
+
+        metrics.inc_active_sessions();
+        metrics.inc_active_sessions();
+        assert_eq!(metrics.active_sessions(), 2);
+
+        metrics.dec_active_sessions();
+        metrics.dec_active_sessions();
+        assert_eq!(metrics.active_sessions(), 0);
+
+        // A spurious drop doesn't wrap the gauge around.
+        metrics.dec_active_sessions();
+        assert_eq!(metrics.active_sessions(), 0);
+    }
+}
@@ -32,6 +32,7 @@ use sealed_memory_rust_proto::{
 use tokio::net::TcpListener;
 
 static TEST_EK: &[u8; 32] = b"aaaabbbbccccddddeeeeffffgggghhhh";
+static TEST_METADATA_KEY: &[u8; 32] = b"00001111222233334444555566667777";
 
 async fn start_server() -> Result<(
     SocketAddr,
@@ -47,11 +48,22 @@ async fn start_server() -> Result<(
     let db_listener = TcpListener::bind(db_addr).await?;
     let db_addr = db_listener.local_addr()?;
 
-    let application_config = ApplicationConfig { database_service_host: db_addr };
+    let application_config = ApplicationConfig {
+        database_service_host: db_addr,
+        db_connect_retry_config: Default::default(),
+        db_connection_pool_size: app::DEFAULT_DB_CONNECTION_POOL_SIZE,
+        metadata_encryption_key: Some(TEST_METADATA_KEY.to_vec()),
+        db_integrity_check: Default::default(),
+        default_message_type: Default::default(),
+        request_timeout_ms: None,
+        embedding_dimension: None,
+        compress_database: true,
+    };
 
     let metrics = private_memory_server_lib::metrics::get_global_metrics();
     let (persistence_tx, persistence_rx) = tokio::sync::mpsc::unbounded_channel();
-    let persistence_join_handle = tokio::spawn(run_persistence_service(persistence_rx));
+    let persistence_join_handle =
+        tokio::spawn(run_persistence_service(persistence_rx, application_config.compress_database));
     Ok((
         addr,
         tokio::spawn(app::service::create(listener, application_config, metrics, persistence_tx)),
@@ -89,6 +101,49 @@ async fn test_client() {
     }
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_client_get_memory_by_id_found() {
+    let (addr, _server_join_handle, _db_join_handle, _persistence_join_handle) =
+        start_server().await.unwrap();
+    let url = format!("http://{}", addr);
+    let pm_uid = "test_client_get_memory_by_id_found_user";
+
+    for &format in [SerializationFormat::BinaryProto, SerializationFormat::Json].iter() {
+        let mut client =
+            PrivateMemoryClient::create_with_start_session(&url, pm_uid, TEST_EK, format)
+                .await
+                .unwrap();
+
+        let memory_id = "found_memory_id";
+        let memory_to_add = Memory {
+            id: memory_id.to_string(),
+            tags: vec!["found_tag".to_string()],
+            ..Default::default()
+        };
+        client.add_memory(memory_to_add).await.unwrap();
+
+        let response = client.get_memory_by_id("missing_memory_id", None).await.unwrap();
+        assert!(!response.found);
+        assert!(!response.success);
+        assert_eq!(response.memory, None);
+
+        let response = client.get_memory_by_id(memory_id, None).await.unwrap();
+        assert!(response.found);
+        let memory = response.memory.unwrap();
+        assert_eq!(memory.id, memory_id);
+        assert_eq!(memory.tags, vec!["found_tag".to_string()]);
+
+        // A mask that includes no fields clears the memory, which is still found.
+        let response =
+            client.get_memory_by_id(memory_id, Some(ResultMask::default())).await.unwrap();
+        assert!(response.found);
+        let memory = response.memory.unwrap();
+        assert!(memory.id.is_empty());
+        assert!(memory.tags.is_empty());
+        assert_eq!(memory.content, None);
+    }
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_client_pagination() {
     let (addr, _server_join_handle, _db_join_handle, _persistence_join_handle) =
@@ -243,3 +298,150 @@ async fn test_client_text_query() {
         assert_eq!(response.results[0].memory.as_ref().unwrap().id, "memory1");
     }
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_client_rename_and_merge_tags() {
+    let (addr, _server_join_handle, _db_join_handle, _persistence_join_handle) =
+        start_server().await.unwrap();
+    let url = format!("http://{}", addr);
+    let pm_uid = "test_client_rename_and_merge_tags_user";
+
+    for (i, &format) in
+        [SerializationFormat::BinaryProto, SerializationFormat::Json].iter().enumerate()
+    {
+        let mut client =
+            PrivateMemoryClient::create_with_start_session(&url, pm_uid, TEST_EK, format)
+                .await
+                .unwrap();
+
+        let old_tag = format!("old_tag_{i}");
+        let new_tag = format!("new_tag_{i}");
+        let other_tag = format!("other_tag_{i}");
+        let memories = [
+            (format!("memory_a_{i}"), vec![old_tag.clone(), other_tag.clone()]),
+            (format!("memory_b_{i}"), vec![old_tag.clone(), new_tag.clone()]),
+            (format!("memory_c_{i}"), vec![new_tag.clone()]),
+            (format!("memory_d_{i}"), vec![other_tag.clone()]),
+        ];
+        for (id, tags) in memories.iter() {
+            client
+                .add_memory(Memory { id: id.clone(), tags: tags.clone(), ..Default::default() })
+                .await
+                .unwrap();
+        }
+
+        // Renaming onto an existing tag merges the two tags.
+        let response = client.rename_tag(&old_tag, &new_tag).await.unwrap();
+        assert!(response.success, "{}", response.error_message);
+        assert_eq!(response.updated_memory_count, 2);
+
+        let response = client.get_memories(&old_tag, 10, None, "").await.unwrap();
+        assert!(response.memories.is_empty());
+        let response = client.get_memories(&new_tag, 10, None, "").await.unwrap();
+        let ids: HashSet<String> = response.memories.into_iter().map(|m| m.id).collect();
+        assert_eq!(
+            ids,
+            HashSet::from([
+                format!("memory_a_{i}"),
+                format!("memory_b_{i}"),
+                format!("memory_c_{i}")
+            ])
+        );
+
+        let memory = client.get_memory_by_id(&format!("memory_a_{i}"), None).await.unwrap();
+        assert_eq!(memory.memory.unwrap().tags, vec![new_tag.clone(), other_tag.clone()]);
+        let memory = client.get_memory_by_id(&format!("memory_b_{i}"), None).await.unwrap();
+        assert_eq!(memory.memory.unwrap().tags, vec![new_tag.clone()]);
+
+        let merged_tag = format!("merged_tag_{i}");
+        let response =
+            client.merge_tags(vec![new_tag.clone(), other_tag.clone()], &merged_tag).await.unwrap();
+        assert!(response.success, "{}", response.error_message);
+        assert_eq!(response.updated_memory_count, 4);
+
+        let response = client.get_memories(&merged_tag, 10, None, "").await.unwrap();
+        assert_eq!(response.memories.len(), 4);
+        for memory in response.memories {
+            assert_eq!(memory.tags, vec![merged_tag.clone()]);
+        }
+        let response = client.get_memories(&other_tag, 10, None, "").await.unwrap();
+        assert!(response.memories.is_empty());
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_client_check_user_registration() {
+    let (addr, _server_join_handle, _db_join_handle, _persistence_join_handle) =
+        start_server().await.unwrap();
+    let url = format!("http://{}", addr);
+    let pm_uid = "test_client_check_user_registration_user";
+
+    for (i, &format) in
+        [SerializationFormat::BinaryProto, SerializationFormat::Json].iter().enumerate()
+    {
+        let mut client =
+            PrivateMemoryClient::create_with_start_session(&url, pm_uid, TEST_EK, format)
+                .await
+                .unwrap();
+
+        assert!(client.is_user_registered(pm_uid).await.unwrap());
+
+        // Checking an unknown uid must not register it.
+        let unregistered_uid = format!("test_client_check_user_registration_unregistered_{i}");
+        assert!(!client.is_user_registered(&unregistered_uid).await.unwrap());
+        assert!(!client.is_user_registered(&unregistered_uid).await.unwrap());
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_client_ping_without_user() {
+    let (addr, _server_join_handle, _db_join_handle, _persistence_join_handle) =
+        start_server().await.unwrap();
+    let url = format!("http://{}", addr);
+
+    for &format in [SerializationFormat::BinaryProto, SerializationFormat::Json].iter() {
+        let mut client = PrivateMemoryClient::create_with_start_session_without_user(&url, format)
+            .await
+            .unwrap();
+
+        let response = client.ping().await.unwrap();
+        assert!(response.ready);
+
+        // Requests that need a user still fail without key sync.
+        assert!(client.get_memories("tag", 10, None, "").await.is_err());
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_client_rotate_kek() {
+    let (addr, _server_join_handle, _db_join_handle, _persistence_join_handle) =
+        start_server().await.unwrap();
+    let url = format!("http://{}", addr);
+    let new_ek: &[u8; 32] = b"iiiijjjjkkkkllllmmmmnnnnoooopppp";
+
+    for (i, &format) in
+        [SerializationFormat::BinaryProto, SerializationFormat::Json].iter().enumerate()
+    {
+        let pm_uid = format!("test_client_rotate_kek_user_{i}");
+        let mut client =
+            PrivateMemoryClient::create_with_start_session(&url, &pm_uid, TEST_EK, format)
+                .await
+                .unwrap();
+
+        // The old KEK must unwrap the stored DEK.
+        let response = client.rotate_kek(&pm_uid, new_ek, new_ek).await.unwrap();
+        assert!(!response.success);
+        assert!(response.error_message.contains("Failed to decrypt DEK"));
+
+        let response = client.rotate_kek(&pm_uid, TEST_EK, new_ek).await.unwrap();
+        assert!(response.success, "{}", response.error_message);
+
+        // Key sync unwraps the DEK, so it only succeeds with the new KEK.
+        assert!(PrivateMemoryClient::create_with_start_session(&url, &pm_uid, TEST_EK, format)
+            .await
+            .is_err());
+        PrivateMemoryClient::create_with_start_session(&url, &pm_uid, new_ek, format)
+            .await
+            .unwrap();
+    }
+}
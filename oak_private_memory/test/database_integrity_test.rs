@@ -0,0 +1,60 @@
+// Copyright 2025 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use external_db_client::ExternalDbClient;
+use googletest::prelude::*;
+use oak_private_memory_database::{DatabaseWithCache, IcingMetaDatabase};
+use sealed_memory_rust_proto::prelude::v1::*;
+use tempfile::tempdir;
+use tokio::net::TcpListener;
+
+static TEST_DEK: &[u8; 32] = b"aaaabbbbccccddddeeeeffffgggghhhh";
+
+async fn connect_to_test_database() -> anyhow::Result<ExternalDbClient> {
+    let listener = TcpListener::bind(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0)).await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(private_memory_test_database_server_lib::service::create(listener));
+    Ok(ExternalDbClient::connect(format!("http://{addr}")).await?)
+}
+
+fn tagged_memory(id: &str) -> Memory {
+    Memory { id: id.to_string(), tags: vec!["tag".to_string()], ..Default::default() }
+}
+
+#[tokio::test]
+async fn test_check_integrity_with_inconsistent_database() -> anyhow::Result<()> {
+    let temp_dir = tempdir()?;
+    // Without a cache, every blob has to be looked up in the external database.
+    let mut database = DatabaseWithCache::new(
+        IcingMetaDatabase::new(temp_dir.path())?,
+        TEST_DEK.to_vec(),
+        connect_to_test_database().await?,
+        KeyDerivationInfo::default(),
+        0,
+    );
+    database.add_memory(tagged_memory("stored")).await?;
+    // Index a memory as a partially failed write would, without its blob.
+    database.meta_db().add_memory(&tagged_memory("orphaned"), "missing_blob".to_string())?;
+
+    assert_that!(database.check_integrity(false).await?, elements_are![eq("orphaned")]);
+    assert_that!(database.count_memories_by_tag("tag")?, eq(2));
+
+    assert_that!(database.check_integrity(true).await?, elements_are![eq("orphaned")]);
+    assert_that!(database.count_memories_by_tag("tag")?, eq(1));
+    assert_that!(database.meta_db().get_blob_id_by_memory_id("orphaned".to_string())?, none());
+    assert_that!(database.check_integrity(false).await?, is_empty());
+    Ok(())
+}
@@ -59,7 +59,8 @@ fn test_embedding_search_returns_scores() -> anyhow::Result<()> {
         })),
     };
 
-    let (blob_ids, scores, _) = icing_database.search(&embedding_query, 10, PageToken::Start)?;
+    let (blob_ids, scores, _) =
+        icing_database.search(&embedding_query, 10, None, PageToken::Start)?;
     assert_that!(scores, not(is_empty()));
     assert_that!(scores.len(), eq(blob_ids.len()));
     assert_that!(scores, each(predicate(|&x| x > 0.0)));
@@ -67,3 +68,59 @@ fn test_embedding_search_returns_scores() -> anyhow::Result<()> {
     assert_that!(scores[1], eq(6.0));
     Ok(())
 }
+
+fn search_all_pages(
+    icing_database: &mut IcingMetaDatabase,
+    query: &SearchMemoryQuery,
+    max_candidates: Option<usize>,
+) -> anyhow::Result<Vec<String>> {
+    let mut all_blob_ids = Vec::new();
+    let mut page_token = PageToken::Start;
+    loop {
+        let (blob_ids, _, next_page_token) =
+            icing_database.search(query, 2, max_candidates, page_token)?;
+        all_blob_ids.extend(blob_ids);
+        if next_page_token == PageToken::Start {
+            return Ok(all_blob_ids);
+        }
+        page_token = next_page_token;
+    }
+}
+
+#[gtest]
+fn test_embedding_search_max_candidates() -> anyhow::Result<()> {
+    let temp_dir = tempdir()?;
+    let mut icing_database =
+        IcingMetaDatabase::new(temp_dir.path().to_str().context("invalid temp path")?)?;
+
+    for i in 1..=5 {
+        let memory = Memory {
+            id: format!("memory{i}"),
+            embeddings: vec![Embedding {
+                identifier: "test_model".to_string(),
+                values: vec![i as f32, 0.0, 0.0],
+            }],
+            ..Default::default()
+        };
+        icing_database.add_memory(&memory, format!("blob{i}"))?;
+    }
+
+    let embedding_query = SearchMemoryQuery {
+        clause: Some(search_memory_query::Clause::EmbeddingQuery(EmbeddingQuery {
+            embedding: vec![Embedding {
+                identifier: "test_model".to_string(),
+                values: vec![1.0, 0.0, 0.0],
+            }],
+            ..Default::default()
+        })),
+    };
+
+    let unbounded = search_all_pages(&mut icing_database, &embedding_query, None)?;
+    assert_that!(unbounded, len(eq(5)));
+
+    // Only the highest scoring candidates are returned, even though the last
+    // page isn't full.
+    let bounded = search_all_pages(&mut icing_database, &embedding_query, Some(3))?;
+    assert_that!(bounded, elements_are![eq("blob5"), eq("blob4"), eq("blob3")]);
+    Ok(())
+}
@@ -75,9 +75,11 @@ fn test_result_mask_serialization() {
     let result_mask = ResultMask {
         include_fields: vec![MemoryField::Id as i32, MemoryField::Tags as i32],
         include_content_fields: vec!["content_key_str".to_string()],
+        content_ranges: [("content_key_str".to_string(), ContentRange { offset: 2, length: 3 })]
+            .into(),
     };
-    let json_str5 =
-        r#"{"includeFields":["ID", "TAGS"],"includeContentFields":["content_key_str"]}"#;
+    let json_str5 = r#"{"includeFields":["ID", "TAGS"],"includeContentFields":["content_key_str"],
+        "contentRanges":{"content_key_str":{"offset":2,"length":3}}}"#;
     let result_mask_from_string_num = serde_json::from_str::<ResultMask>(json_str5).unwrap();
     assert_eq!(result_mask.encode_to_vec(), result_mask_from_string_num.encode_to_vec());
 }
@@ -48,11 +48,22 @@ async fn start_server() -> Result<(
     let db_listener = TcpListener::bind(db_addr).await?;
     let db_addr = db_listener.local_addr()?;
 
-    let application_config = ApplicationConfig { database_service_host: db_addr };
+    let application_config = ApplicationConfig {
+        database_service_host: db_addr,
+        db_connect_retry_config: Default::default(),
+        db_connection_pool_size: app::DEFAULT_DB_CONNECTION_POOL_SIZE,
+        metadata_encryption_key: None,
+        db_integrity_check: Default::default(),
+        default_message_type: Default::default(),
+        request_timeout_ms: None,
+        embedding_dimension: None,
+        compress_database: true,
+    };
 
     let metrics = private_memory_server_lib::metrics::get_global_metrics();
     let (persistence_tx, persistence_rx) = tokio_mpsc::unbounded_channel();
-    let persistence_join_handle = tokio::spawn(run_persistence_service(persistence_rx));
+    let persistence_join_handle =
+        tokio::spawn(run_persistence_service(persistence_rx, application_config.compress_database));
     Ok((
         addr,
         tokio::spawn(app::service::create(listener, application_config, metrics, persistence_tx)),
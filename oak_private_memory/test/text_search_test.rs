@@ -167,7 +167,7 @@ fn test_query_clauses_and_operator() -> anyhow::Result<()> {
         })),
     };
 
-    let (blob_ids, _, _) = icing_database.search(&and_query, 10, PageToken::Start)?;
+    let (blob_ids, _, _) = icing_database.search(&and_query, 10, None, PageToken::Start)?;
     assert_that!(blob_ids, unordered_elements_are![eq("blob2")]);
 
     Ok(())
@@ -226,7 +226,7 @@ fn test_query_clauses_or_operator() -> anyhow::Result<()> {
         })),
     };
 
-    let (blob_ids, _, _) = icing_database.search(&or_query, 10, PageToken::Start)?;
+    let (blob_ids, _, _) = icing_database.search(&or_query, 10, None, PageToken::Start)?;
     assert_that!(blob_ids, unordered_elements_are![eq("blob1"), eq("blob3")]);
 
     Ok(())
@@ -86,6 +86,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         "oak.attestation.v1.Signature",
         "oak.attestation.v1.Endorsement",
         "oak.attestation.v1.EventLog",
+        "oak.attestation.v1.VerifyingKey",
+        "oak.attestation.v1.CosignReferenceValues",
+        "oak.attestation.v1.CertificateAuthorityReferenceValue",
+        "oak.attestation.v1.CertificateBasedReferenceValues",
+        "oak.attestation.v1.ConfidentialSpaceReferenceValues",
         "oak.Variant",
     ] {
         needed_types.insert(t.to_string());
@@ -301,6 +301,9 @@ pub struct TcbVersion {
 }
 #[derive(Clone, Copy, PartialEq, ::prost::Message)]
 pub struct SkipVerification {}
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct VerifyingKey {
     /// The type of the verifying key.
@@ -313,6 +316,7 @@ pub struct VerifyingKey {
     /// The key serialized in raw format. The key type is needed to interpret
     /// the contents.
     #[prost(bytes = "vec", tag = "3")]
+    #[serde(with = "crate::base64data")]
     pub raw: ::prost::alloc::vec::Vec<u8>,
 }
 /// Verifies a single UTC timestamp.
@@ -476,10 +480,14 @@ pub struct FileReferenceValue {
 }
 /// Reference value that provides a CA public key which can be used to verify CA
 /// certificates.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct CertificateAuthorityReferenceValue {
     /// <https://developers.google.com/tink/wire-format>
     #[prost(bytes = "vec", tag = "1")]
+    #[serde(with = "crate::base64data")]
     pub tink_proto_keyset: ::prost::alloc::vec::Vec<u8>,
 }
 /// Reference value for a public key.
@@ -682,12 +690,17 @@ pub struct ApplicationKeysReferenceValues {
     #[prost(message, optional, tag = "3")]
     pub signing_public_key: ::core::option::Option<PublicKeyReferenceValue>,
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct CosignReferenceValues {
     #[prost(message, optional, tag = "1")]
     pub developer_public_key: ::core::option::Option<VerifyingKey>,
     #[prost(message, optional, tag = "2")]
     pub rekor_public_key: ::core::option::Option<VerifyingKey>,
+    #[prost(enumeration = "RekorPolicy", tag = "3")]
+    pub rekor_policy: i32,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct OakRestrictedKernelReferenceValues {
@@ -716,11 +729,17 @@ pub struct CbReferenceValues {
     #[prost(message, repeated, tag = "5")]
     pub layers: ::prost::alloc::vec::Vec<EventReferenceValues>,
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct CertificateBasedReferenceValues {
     #[prost(message, optional, tag = "1")]
     pub ca: ::core::option::Option<CertificateAuthorityReferenceValue>,
 }
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(default)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct ConfidentialSpaceReferenceValues {
     /// The PEM-encoded root certificate for verifying Confidential Space
@@ -732,6 +751,7 @@ pub struct ConfidentialSpaceReferenceValues {
         oneof = "confidential_space_reference_values::ContainerImage",
         tags = "2, 3"
     )]
+    #[serde(flatten)]
     pub container_image: ::core::option::Option<
         confidential_space_reference_values::ContainerImage,
     >,
@@ -739,6 +759,8 @@ pub struct ConfidentialSpaceReferenceValues {
 /// Nested message and enum types in `ConfidentialSpaceReferenceValues`.
 pub mod confidential_space_reference_values {
     /// Reference values specific to the workload container.
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
     #[derive(Clone, PartialEq, ::prost::Oneof)]
     pub enum ContainerImage {
         #[prost(message, tag = "2")]
@@ -813,6 +835,48 @@ impl KeyType {
         }
     }
 }
+/// Determines how the Rekor transparency log entry of a Cosign endorsement is
+/// checked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum RekorPolicy {
+    /// Same as REKOR_POLICY_REQUIRED if `rekor_public_key` is set, and as
+    /// REKOR_POLICY_DISABLED otherwise.
+    Unspecified = 0,
+    /// The endorsement must come with a Rekor log entry, verified with
+    /// `rekor_public_key`.
+    Required = 1,
+    /// A Rekor log entry is verified with `rekor_public_key` if the endorsement
+    /// comes with one, but it may be missing.
+    Optional = 2,
+    /// Rekor log entries are not verified, e.g. in air-gapped environments where
+    /// Rekor can't be reached. The developer signature is still required.
+    Disabled = 3,
+}
+impl RekorPolicy {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Unspecified => "REKOR_POLICY_UNSPECIFIED",
+            Self::Required => "REKOR_POLICY_REQUIRED",
+            Self::Optional => "REKOR_POLICY_OPTIONAL",
+            Self::Disabled => "REKOR_POLICY_DISABLED",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "REKOR_POLICY_UNSPECIFIED" => Some(Self::Unspecified),
+            "REKOR_POLICY_REQUIRED" => Some(Self::Required),
+            "REKOR_POLICY_OPTIONAL" => Some(Self::Optional),
+            "REKOR_POLICY_DISABLED" => Some(Self::Disabled),
+            _ => None,
+        }
+    }
+}
 #[derive(serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[serde(default)]
@@ -3,9 +3,17 @@
 pub struct OakSessionRequest {
     #[prost(message, optional, tag = "1")]
     pub request: ::core::option::Option<super::super::session::v1::SessionRequest>,
+    /// Identifier chosen by the client to correlate this request with its
+    /// response. Requests of a session are handled in the order in which they are
+    /// sent, so clients may have multiple requests in flight at the same time.
+    #[prost(uint64, tag = "2")]
+    pub request_id: u64,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct OakSessionResponse {
     #[prost(message, optional, tag = "1")]
     pub response: ::core::option::Option<super::super::session::v1::SessionResponse>,
+    /// The `request_id` of the request that this is a response to.
+    #[prost(uint64, tag = "2")]
+    pub request_id: u64,
 }
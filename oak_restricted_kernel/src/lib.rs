@@ -137,7 +137,11 @@ pub fn start_kernel(info: &BootParams) -> ! {
     if sev_es_enabled {
         ghcb::init(sev_snp_enabled);
     }
-    logging::init_logging(sev_es_enabled);
+    // Fail the boot rather than carry on without a console. The panic shuts the
+    // VM down, so the launcher sees the boot fail even though the message can't
+    // be logged.
+    logging::init_logging(sev_es_enabled)
+        .unwrap_or_else(|err| panic!("couldn't initialize logging on the serial port: {err}"));
 
     // Safety: we shouldn't have anything else but the PICs on the I/O ports.
     // If we get an error, we will still try to continue.
@@ -556,7 +560,15 @@ fn get_channel<'a, A: Allocator + Sync>(
         #[cfg(feature = "vsock_channel")]
         ChannelType::VirtioVsock => Box::new(virtio::get_vsock_channel(alloc)),
         #[cfg(feature = "serial_channel")]
-        ChannelType::Serial => Box::new(serial::Serial::new()),
+        ChannelType::Serial => {
+            let mut serial = serial::Serial::new();
+            // Only on request, for bring-up on new VMMs: while the test runs, the
+            // port doesn't receive from the launcher.
+            if kernel_args.get("serial_self_test").is_some() && !serial.self_test() {
+                log::warn!("serial channel failed the loopback self-test");
+            }
+            Box::new(serial)
+        }
         #[cfg(feature = "simple_io_channel")]
         ChannelType::SimpleIo => Box::new(simpleio::SimpleIoChannel::new(alloc, sev_status)),
     }
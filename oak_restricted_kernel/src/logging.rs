@@ -48,7 +48,11 @@ impl log::Log for Logger {
 
 static LOGGER: Logger = Logger {};
 
-pub fn init_logging(sev_es_enabled: bool) {
+/// Sets up logging to the first serial port.
+///
+/// Returns an error if the port can't be initialized, e.g. because the port
+/// access via the GHCB failed, in which case nothing is logged.
+pub fn init_logging(sev_es_enabled: bool) -> Result<(), &'static str> {
     let port_factory = if sev_es_enabled {
         crate::ghcb::get_ghcb_port_factory()
     } else {
@@ -57,7 +61,7 @@ pub fn init_logging(sev_es_enabled: bool) {
     // Our contract with the launcher requires the first serial port to be
     // available, so assuming the loader adheres to it, this is safe.
     let mut port = unsafe { SerialPort::new(COM1_BASE, port_factory) };
-    port.init().expect("couldn't initialize logging serial port");
+    port.init()?;
 
     if SERIAL1.lock().replace(port).is_some() {
         panic!("serial port 1 is already initialized");
@@ -67,4 +71,5 @@ pub fn init_logging(sev_es_enabled: bool) {
     log::set_max_level(log::LevelFilter::Debug);
     // Log a message to ensure the serial logging channel is intialized.
     info!("Logging initialised.");
+    Ok(())
 }
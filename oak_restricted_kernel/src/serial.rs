@@ -16,6 +16,7 @@
 
 use atomic_refcell::AtomicRefCell;
 use uart_16550::SerialPort;
+use x86_64::instructions::port::Port;
 
 pub struct Serial {
     port: AtomicRefCell<SerialPort>,
@@ -25,6 +26,22 @@ pub struct Serial {
 // COM2)
 static COM2_BASE: u16 = 0x2f8;
 
+// Offsets of the 16550 UART registers used by the loopback self-test.
+const MODEM_CONTROL: u16 = 4;
+const LINE_STATUS: u16 = 5;
+
+// Modem control bit that routes the transmitter output back to the receiver.
+const LOOPBACK: u8 = 1 << 4;
+// Line status bit that indicates that received data is available.
+const DATA_READY: u8 = 1;
+
+// Byte sent during the self-test; any value other than 0x00 and 0xFF, which a
+// missing device would read as, will do.
+const SELF_TEST_BYTE: u8 = 0xAE;
+// Upper bound on the number of line status polls while waiting for the test
+// byte, so that a broken port can't hang the self-test.
+const SELF_TEST_MAX_POLLS: usize = 100_000;
+
 impl Serial {
     pub fn new() -> Serial {
         // Our contract with the loader requires the second serial port to be
@@ -33,6 +50,46 @@ impl Serial {
         port.init();
         Serial { port: AtomicRefCell::new(port) }
     }
+
+    /// Checks that the port is wired up by sending a byte in loopback mode and
+    /// reading it back.
+    ///
+    /// The modem control register is restored afterwards. Data that is already
+    /// waiting in the receive buffer is never discarded: as receiving it shows
+    /// that the port works, the test passes without entering loopback mode.
+    /// While in loopback mode the port doesn't receive from the peer, so this
+    /// is only meant for bring-up, before the peer starts sending, and only
+    /// runs at boot if the `serial_self_test` kernel argument is set.
+    pub fn self_test(&mut self) -> bool {
+        let port = self.port.get_mut();
+        let mut data = Port::<u8>::new(COM2_BASE);
+        let mut modem_control = Port::<u8>::new(COM2_BASE + MODEM_CONTROL);
+        let mut line_status = Port::<u8>::new(COM2_BASE + LINE_STATUS);
+
+        // Safety: `Serial::new` relies on the second serial port being available, so
+        // accessing its registers is safe.
+        unsafe {
+            if line_status.read() & DATA_READY != 0 {
+                return true;
+            }
+            let saved_modem_control = modem_control.read();
+            modem_control.write(saved_modem_control | LOOPBACK);
+
+            port.send_raw(SELF_TEST_BYTE);
+
+            let mut received = None;
+            for _ in 0..SELF_TEST_MAX_POLLS {
+                if line_status.read() & DATA_READY != 0 {
+                    received = Some(data.read());
+                    break;
+                }
+                core::hint::spin_loop();
+            }
+
+            modem_control.write(saved_modem_control);
+            received == Some(SELF_TEST_BYTE)
+        }
+    }
 }
 
 impl oak_channel::Write for Serial {
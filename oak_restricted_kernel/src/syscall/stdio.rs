@@ -36,7 +36,8 @@ impl FileDescriptor for Stderr {
         // bytes.
         let buf = &buf[..min(buf.len(), isize::MAX as usize)];
         let mut lock = SERIAL1.lock();
-        let port = lock.as_mut().unwrap();
+        // The port isn't set up if initializing it failed at boot.
+        let port = lock.as_mut().ok_or(Errno::EIO)?;
         for &byte in buf {
             // We don't log the error, as (a) we're holding the mutex on the serial port so
             // logging wouldn't work, and (b) it's the write to the serial port
@@ -158,6 +158,10 @@ impl FfiAttestationVerifier {
 /// Consumes and builds the config for the provided builder.
 /// The returned SessionConfig pointer is still owned by Rust.
 ///
+/// If the builder holds an inconsistent configuration,
+/// `ErrorOrSessionConfig::error` will contain a pointer to an error describing
+/// it, and the builder is freed.
+///
 /// When passing it as a constructor argument to new_client_session or
 /// new_server_session, ownership of the object will be returned fully to Rust,
 /// and it should not be used on the C++ side anymore.
@@ -168,10 +172,13 @@ impl FfiAttestationVerifier {
 #[no_mangle]
 pub unsafe extern "C" fn session_config_builder_build(
     builder: *mut SessionConfigBuilder,
-) -> *mut SessionConfig {
+) -> ErrorOrSessionConfig {
     // Take back ownership.
     let builder = Box::from_raw(builder);
-    Box::into_raw(Box::new(builder.build()))
+    match builder.try_build() {
+        Ok(config) => ErrorOrSessionConfig::ok(Box::into_raw(Box::new(config))),
+        Err(err) => ErrorOrSessionConfig::err(err),
+    }
 }
 
 /// Call add_self_attestion on the provided builder.
@@ -352,6 +359,22 @@ impl ErrorOrSessionConfigBuilder {
     }
 }
 
+#[repr(C)]
+pub struct ErrorOrSessionConfig {
+    pub result: *mut SessionConfig,
+    pub error: *const Error,
+}
+
+impl ErrorOrSessionConfig {
+    pub fn ok(result: *mut SessionConfig) -> Self {
+        Self { result, error: std::ptr::null() }
+    }
+
+    pub fn err(message: impl std::fmt::Display) -> Self {
+        Self { result: std::ptr::null_mut(), error: Error::new_raw(message) }
+    }
+}
+
 #[repr(C)]
 pub struct ErrorOrIdentityKey {
     pub result: *mut IdentityKey,
@@ -59,6 +59,22 @@ fn test_nn_handshake() {
     unsafe { server_ffi::free_server_session(server_session_ptr) };
 }
 
+#[test]
+fn test_build_invalid_session_config_returns_error() {
+    let session_config_builder = config_ffi::new_session_config_builder(
+        config_ffi::ATTESTATION_TYPE_PEER_UNIDIRECTIONAL,
+        config_ffi::HANDSHAKE_TYPE_NOISE_NN,
+    );
+    assert_no_error!(session_config_builder.error);
+
+    let session_config_result =
+        unsafe { config_ffi::session_config_builder_build(session_config_builder.result) };
+
+    assert!(session_config_result.result.is_null());
+    assert!(!session_config_result.error.is_null());
+    unsafe { oak_ffi_error::free_error(session_config_result.error) };
+}
+
 #[test]
 fn test_nk_handshake() {
     let identity_key = new_identity_key();
@@ -290,7 +306,7 @@ fn create_unattested_nn_session_config() -> *mut oak_session::config::SessionCon
         config_ffi::HANDSHAKE_TYPE_NOISE_NN,
     );
     assert_no_error!(session_config_builder.error);
-    unsafe { config_ffi::session_config_builder_build(session_config_builder.result) }
+    build_session_config(session_config_builder.result)
 }
 
 fn create_unattested_nk_client_session_config(
@@ -314,7 +330,7 @@ fn create_unattested_nk_client_session_config(
     };
 
     unsafe { free_rust_bytes(public_key_result.result) }
-    unsafe { config_ffi::session_config_builder_build(session_config_builder) }
+    build_session_config(session_config_builder)
 }
 
 fn create_unattested_nk_server_session_config(
@@ -331,7 +347,7 @@ fn create_unattested_nk_server_session_config(
         session_config_builder_set_self_static_private_key(session_config_builder, identity_key)
     };
 
-    unsafe { config_ffi::session_config_builder_build(session_config_builder) }
+    build_session_config(session_config_builder)
 }
 
 fn create_unattested_kk_session_config(
@@ -356,7 +372,7 @@ fn create_unattested_kk_session_config(
         session_config_builder_set_peer_static_public_key(session_config_builder, peer_public_key)
     };
 
-    unsafe { config_ffi::session_config_builder_build(session_config_builder) }
+    build_session_config(session_config_builder)
 }
 
 fn create_attested_nn_server_session_config() -> *mut oak_session::config::SessionConfig {
@@ -412,7 +428,7 @@ fn create_attested_nn_server_session_config() -> *mut oak_session::config::Sessi
         )
     };
     unsafe { free_signing_key(signing_key) };
-    unsafe { config_ffi::session_config_builder_build(session_config_builder) }
+    build_session_config(session_config_builder)
 }
 
 fn create_attested_nn_client_session_config() -> *mut oak_session::config::SessionConfig {
@@ -438,7 +454,15 @@ fn create_attested_nn_client_session_config() -> *mut oak_session::config::Sessi
         )
     };
 
-    unsafe { config_ffi::session_config_builder_build(session_config_builder) }
+    build_session_config(session_config_builder)
+}
+
+fn build_session_config(
+    builder: *mut oak_session::config::SessionConfigBuilder,
+) -> *mut oak_session::config::SessionConfig {
+    let session_config_result = unsafe { config_ffi::session_config_builder_build(builder) };
+    assert_no_error!(session_config_result.error);
+    session_config_result.result
 }
 
 fn create_client_session(config: *mut oak_session::config::SessionConfig) -> *mut ClientSession {
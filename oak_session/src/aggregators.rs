@@ -32,6 +32,7 @@ pub enum AggregatedVerificationError {
     AssertionVerificationFailure { failures: BTreeMap<String, AssertionVerificationError> },
     NoMatchedLegacyVerifier,
     NoMatchedAssertionVerifier,
+    AssertionOrderViolation { assertion_id: String, predecessor: String },
     ConfigurationError,
 }
 
@@ -96,14 +96,14 @@ use alloc::{
 use anyhow::{anyhow, Error};
 use itertools::{EitherOrBoth, Itertools};
 use oak_proto_rust::oak::{
-    attestation::v1::{attestation_results, AttestationResults},
+    attestation::v1::{attestation_results, AttestationResults, Endorsements, Evidence},
     session::v1::{Assertion, AttestRequest, AttestResponse, EndorsedEvidence},
 };
 use prost::Message;
 
 use crate::{
     aggregators::AggregatedVerificationError,
-    config::{AttestationHandlerConfig, PeerAttestationVerifier},
+    config::{AttestationHandlerConfig, EvidenceLimits, PeerAttestationVerifier},
     generator::BindableAssertion,
     session_binding::SessionBindingVerifier,
     verifier::{AssertionVerifier, AssertionVerifierResult},
@@ -169,6 +169,21 @@ impl PeerAttestationVerdict {
         }
     }
 
+    /// Returns the attestation IDs of the evidence the peer provided but no
+    /// verifier is configured for, i.e. with a [`VerifierResult::Unverified`]
+    /// result.
+    ///
+    /// Such evidence doesn't necessarily make the attestation fail, but may
+    /// point at a misconfiguration of either party, or at a peer probing for
+    /// accepted evidence types, so it's worth logging.
+    pub fn unverified_ids(&self) -> Vec<&str> {
+        self.get_legacy_verification_results()
+            .iter()
+            .filter(|(_, result)| matches!(result, VerifierResult::Unverified { .. }))
+            .map(|(id, _)| id.as_str())
+            .collect()
+    }
+
     /// Checks whether any evidence or assertions were provided by the peer that
     /// would require session binding.
     ///
@@ -244,6 +259,35 @@ pub enum VerifierResult {
     Unverified { evidence: EndorsedEvidence },
 }
 
+impl VerifierResult {
+    /// Returns true if the verifier accepted the evidence.
+    pub fn is_success(&self) -> bool {
+        matches!(self, VerifierResult::Success { .. })
+    }
+
+    /// Returns the evidence that was supplied for the verifier, whether or not
+    /// it was verified.
+    pub fn evidence(&self) -> Option<&EndorsedEvidence> {
+        match self {
+            VerifierResult::Success { evidence, .. }
+            | VerifierResult::Failure { evidence, .. }
+            | VerifierResult::Unverified { evidence } => Some(evidence),
+            VerifierResult::Missing => None,
+        }
+    }
+
+    /// Returns the results of the verifier, if it verified the evidence,
+    /// whether or not it accepted it.
+    pub fn results(&self) -> Option<&AttestationResults> {
+        match self {
+            VerifierResult::Success { result, .. } | VerifierResult::Failure { result, .. } => {
+                Some(result)
+            }
+            VerifierResult::Missing | VerifierResult::Unverified { .. } => None,
+        }
+    }
+}
+
 /// Defines the contract for an attestation handler.
 ///
 /// An `AttestationHandler` is responsible for managing the attestation process
@@ -327,6 +371,14 @@ impl ClientAttestationHandler {
             attestation_binding_token: Vec::new(),
         })
     }
+
+    /// Returns the attestation IDs for which a peer verifier is configured.
+    ///
+    /// Evidence provided by the server under any other ID is not verified and
+    /// is reported as `VerifierResult::Unverified`.
+    pub fn expected_peer_attestation_ids(&self) -> impl Iterator<Item = &str> {
+        self.config.peer_verifiers.keys().map(String::as_str)
+    }
 }
 
 impl AttestationHandler for ClientAttestationHandler {
@@ -404,6 +456,7 @@ impl ProtocolEngine<AttestResponse, AttestRequest> for ClientAttestationHandler
         let legacy_results = combine_attestation_results(
             &self.config.peer_verifiers,
             incoming_message.endorsed_evidence,
+            &self.config.peer_evidence_limits,
         )?;
         let assertion_results = combine_assertion_results(
             &self.config.peer_assertion_verifiers,
@@ -415,7 +468,10 @@ impl ProtocolEngine<AttestResponse, AttestRequest> for ClientAttestationHandler
                 .process_assertion_results(&legacy_results),
             self.config
                 .assertion_attestation_aggregator
-                .process_assertion_results(&assertion_results),
+                .process_assertion_results(&assertion_results)
+                .and_then(|()| {
+                    check_assertion_order(&self.config.peer_assertion_order, &assertion_results)
+                }),
             legacy_results,
             assertion_results,
         ));
@@ -567,6 +623,7 @@ impl ProtocolEngine<AttestRequest, AttestResponse> for ServerAttestationHandler
         let legacy_results = combine_attestation_results(
             &self.config.peer_verifiers,
             incoming_message.endorsed_evidence,
+            &self.config.peer_evidence_limits,
         )?;
         let assertion_results = combine_assertion_results(
             &self.config.peer_assertion_verifiers,
@@ -578,7 +635,10 @@ impl ProtocolEngine<AttestRequest, AttestResponse> for ServerAttestationHandler
                 .process_assertion_results(&legacy_results),
             self.config
                 .assertion_attestation_aggregator
-                .process_assertion_results(&assertion_results),
+                .process_assertion_results(&assertion_results)
+                .and_then(|()| {
+                    check_assertion_order(&self.config.peer_assertion_order, &assertion_results)
+                }),
             legacy_results,
             assertion_results,
         ));
@@ -595,18 +655,63 @@ impl ProtocolEngine<AttestRequest, AttestResponse> for ServerAttestationHandler
 /// `VerifierResult::Missing` or `VerifierResult::Unverified` result
 /// respectively.`
 ///
+/// Evidence without endorsements results in a `VerifierResult::Failure`,
+/// unless the verifier doesn't require endorsements, in which case it is
+/// verified with empty endorsements. Likewise, endorsements without evidence
+/// are only verified, with empty evidence, by verifiers that don't require
+/// evidence.
+///
+/// Evidence exceeding the size in `limits` is not verified, and results in a
+/// `VerifierResult::Failure`. If the peer sent more evidence entries than
+/// `limits` allow, none of them is verified and they all result in a
+/// `VerifierResult::Failure`.
+///
 /// Returns a map of `VerifierResult` keyed by attestation ID.
 fn combine_attestation_results(
     verifiers: &BTreeMap<String, PeerAttestationVerifier>,
     attested_evidence: BTreeMap<String, EndorsedEvidence>,
+    limits: &EvidenceLimits,
 ) -> Result<BTreeMap<String, VerifierResult>, Error> {
+    let evidence_count = attested_evidence.len();
+    let too_many_evidence_failure = || {
+        (evidence_count > limits.max_evidence_count).then(|| {
+            evidence_too_large_failure(format!(
+                "{} evidence entries provided, at most {} allowed",
+                evidence_count, limits.max_evidence_count
+            ))
+        })
+    };
     verifiers
         .iter()
         .merge_join_by(attested_evidence, |(id1, _), (id2, _)| Ord::cmp(id1, &id2))
         .map(|v| match v {
             EitherOrBoth::Both((_, peer_verifier), (id, ee)) => {
-                match (ee.evidence.as_ref(), ee.endorsements.as_ref()) {
-                    (Some(evidence), Some(endorsements)) => {
+                if let Some(result) = too_many_evidence_failure() {
+                    return Ok((id, VerifierResult::Failure { evidence: ee, result }));
+                }
+                let evidence_size = ee.encoded_len();
+                if evidence_size > limits.max_evidence_size {
+                    let result = evidence_too_large_failure(format!(
+                        "{} bytes, at most {} allowed",
+                        evidence_size, limits.max_evidence_size
+                    ));
+                    return Ok((id, VerifierResult::Failure { evidence: ee, result }));
+                }
+                let default_evidence = Evidence::default();
+                let evidence = match ee.evidence.as_ref() {
+                    None if !peer_verifier.require_evidence => Some(&default_evidence),
+                    evidence => evidence,
+                };
+                let default_endorsements = Endorsements::default();
+                let endorsements = match ee.endorsements.as_ref() {
+                    None if !peer_verifier.require_endorsements => Some(&default_endorsements),
+                    endorsements => endorsements,
+                };
+                // Even if neither is required on its own, there must be
+                // something to verify.
+                let is_empty = ee.evidence.is_none() && ee.endorsements.is_none();
+                match (evidence, endorsements) {
+                    (Some(evidence), Some(endorsements)) if !is_empty => {
                         let result = peer_verifier.verifier.verify(evidence, endorsements)?;
                         Ok((
                             id,
@@ -618,28 +723,49 @@ fn combine_attestation_results(
                             },
                         ))
                     }
-                    _ => Ok((
-                        id,
-                        VerifierResult::Failure {
-                            evidence: ee,
-                            result: AttestationResults {
-                                status: attestation_results::Status::GenericFailure.into(),
-                                reason: "Both evidence and endorsements need to be provided"
-                                    .to_string(),
-                                ..Default::default()
+                    _ => {
+                        let reason = match (
+                            peer_verifier.require_evidence,
+                            peer_verifier.require_endorsements,
+                        ) {
+                            (true, true) => "Both evidence and endorsements need to be provided",
+                            (true, false) => "Evidence needs to be provided",
+                            (false, true) => "Endorsements need to be provided",
+                            (false, false) => "Evidence or endorsements need to be provided",
+                        };
+                        Ok((
+                            id,
+                            VerifierResult::Failure {
+                                evidence: ee,
+                                result: AttestationResults {
+                                    status: attestation_results::Status::GenericFailure.into(),
+                                    reason: reason.to_string(),
+                                    ..Default::default()
+                                },
                             },
-                        },
-                    )),
+                        ))
+                    }
                 }
             }
             EitherOrBoth::Left((id, _)) => Ok((id.clone(), VerifierResult::Missing)),
-            EitherOrBoth::Right((id, evidence)) => {
-                Ok((id, VerifierResult::Unverified { evidence }))
-            }
+            EitherOrBoth::Right((id, evidence)) => match too_many_evidence_failure() {
+                Some(result) => Ok((id, VerifierResult::Failure { evidence, result })),
+                None => Ok((id, VerifierResult::Unverified { evidence })),
+            },
         })
         .collect::<Result<BTreeMap<String, VerifierResult>, Error>>()
 }
 
+/// Returns the results of evidence rejected for exceeding the
+/// [`EvidenceLimits`], with `details` on the exceeded limit.
+fn evidence_too_large_failure(details: String) -> AttestationResults {
+    AttestationResults {
+        status: attestation_results::Status::GenericFailure.into(),
+        reason: format!("evidence too large: {details}"),
+        ..Default::default()
+    }
+}
+
 /// Combines received `assertions` with configured `assertion_verifiers`.
 ///
 /// This function performs a merge-join between the set of verifiers (keyed by
@@ -678,6 +804,39 @@ fn combine_assertion_results(
         .collect()
 }
 
+/// Checks that the assertion verification `results` follow the chain of
+/// assertion IDs in `order`.
+///
+/// Assertions are carried in a map, so the order in which the peer produced
+/// them is only observable through their dependencies: an assertion in the
+/// chain may only be present if all the assertions before it were verified
+/// successfully, e.g. a response only together with its challenge.
+fn check_assertion_order(
+    order: &[String],
+    results: &BTreeMap<String, AssertionVerifierResult>,
+) -> Result<(), AggregatedVerificationError> {
+    let mut unverified_predecessor: Option<&String> = None;
+    for id in order {
+        let result = results.get(id);
+        match unverified_predecessor {
+            Some(predecessor) => {
+                if !matches!(result, None | Some(AssertionVerifierResult::Missing)) {
+                    return Err(AggregatedVerificationError::AssertionOrderViolation {
+                        assertion_id: id.clone(),
+                        predecessor: predecessor.clone(),
+                    });
+                }
+            }
+            None => {
+                if !matches!(result, Some(AssertionVerifierResult::Success { .. })) {
+                    unverified_predecessor = Some(id);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Combines the aggregated results of legacy and assertion-based verification
 /// into a single verdict.
 ///
@@ -38,7 +38,13 @@
 //! step-by-step. This approach allows for flexible and clear configuration of
 //! complex session establishment logic.
 
-use alloc::{boxed::Box, collections::BTreeMap, string::String, sync::Arc, vec::Vec};
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, BTreeSet},
+    string::String,
+    sync::Arc,
+    vec::Vec,
+};
 
 use anyhow::Error;
 use oak_attestation_types::{attester::Attester, endorser::Endorser};
@@ -46,6 +52,7 @@ use oak_attestation_verification_types::verifier::AttestationVerifier;
 use oak_crypto::{
     encryptor::Encryptor, identity_key::IdentityKeyHandle, noise_handshake::OrderedCrypter,
 };
+use thiserror::Error;
 
 use crate::{
     aggregators::{
@@ -102,11 +109,33 @@ impl SessionConfig {
     }
 }
 
+/// Errors that make a [`SessionConfig`] unusable, detected when it is built.
+#[derive(Error, Debug)]
+pub enum SessionConfigError {
+    #[error("attestation type {0:?} requires at least one self attester or assertion generator")]
+    MissingSelfAttestation(AttestationType),
+    #[error("attestation type {0:?} requires at least one peer verifier or assertion verifier")]
+    MissingPeerVerification(AttestationType),
+    #[error(
+        "Assertion attestation aggregator is not compatible with the configured peer assertion verifiers"
+    )]
+    IncompatibleAssertionAggregator,
+    #[error(
+        "assertion order lists assertion ID {0:?} more than once or without a peer assertion verifier"
+    )]
+    InvalidAssertionOrder(String),
+    #[error("evidence requirements set for attestation ID {0:?} without a peer verifier")]
+    MissingPeerVerifier(String),
+}
+
 /// A builder for creating [`SessionConfig`] instances.
 ///
 /// Provides a fluent API to configure all aspects of a secure session.
 pub struct SessionConfigBuilder {
     config: SessionConfig,
+    // The first attestation ID that evidence requirements were set for before
+    // a peer verifier was added for it, reported by `try_build`.
+    unknown_peer_verifier_id: Option<String>,
 }
 
 /// Trait for objects that can provide an [`Encryptor`] instance.
@@ -154,7 +183,7 @@ impl SessionConfigBuilder {
             encryptor_config,
             attestation_publisher: None,
         };
-        Self { config }
+        Self { config, unknown_peer_verifier_id: None }
     }
 
     /// Add an Attester that generates [`Evidence`] for this party (self).
@@ -316,6 +345,8 @@ impl SessionConfigBuilder {
             binding_verifier_provider: Arc::new(SignatureBindingVerifierProvider::new(Arc::new(
                 DefaultSigningKeyExtractor {},
             ))),
+            require_endorsements: true,
+            require_evidence: true,
         };
         self.config.attestation_handler_config.peer_verifiers.insert(attester_id, peer_verifier);
         self
@@ -342,6 +373,8 @@ impl SessionConfigBuilder {
             binding_verifier_provider: Arc::new(SignatureBindingVerifierProvider::new(Arc::new(
                 DefaultSigningKeyExtractor {},
             ))),
+            require_endorsements: true,
+            require_evidence: true,
         };
         self.config.attestation_handler_config.peer_verifiers.insert(attester_id, peer_verifier);
         self
@@ -374,6 +407,8 @@ impl SessionConfigBuilder {
             binding_verifier_provider: Arc::new(SignatureBindingVerifierProvider::new(
                 key_extractor.into(),
             )),
+            require_endorsements: true,
+            require_evidence: true,
         };
         self.config.attestation_handler_config.peer_verifiers.insert(attester_id, peer_verifier);
         self
@@ -401,6 +436,8 @@ impl SessionConfigBuilder {
             binding_verifier_provider: Arc::new(SignatureBindingVerifierProvider::new(
                 key_extractor.clone(),
             )),
+            require_endorsements: true,
+            require_evidence: true,
         };
         self.config.attestation_handler_config.peer_verifiers.insert(attester_id, peer_verifier);
         self
@@ -431,6 +468,8 @@ impl SessionConfigBuilder {
         let peer_verifier = PeerAttestationVerifier {
             verifier: verifier.into(),
             binding_verifier_provider: binding_verifier_provider.into(),
+            require_endorsements: true,
+            require_evidence: true,
         };
         self.config.attestation_handler_config.peer_verifiers.insert(attester_id, peer_verifier);
         self
@@ -457,11 +496,60 @@ impl SessionConfigBuilder {
         let peer_verifier = PeerAttestationVerifier {
             verifier: verifier.clone(),
             binding_verifier_provider: binding_verifier_provider.clone(),
+            require_endorsements: true,
+            require_evidence: true,
         };
         self.config.attestation_handler_config.peer_verifiers.insert(attester_id, peer_verifier);
         self
     }
 
+    /// Sets whether the peer's [`Evidence`] for `attester_id` must come with
+    /// [`Endorsements`]. Endorsements are required by default; verifiers that
+    /// can work without them can opt out, so that evidence without
+    /// endorsements is verified instead of rejected.
+    ///
+    /// A peer verifier must already have been added for `attester_id`,
+    /// otherwise [`Self::try_build`] fails.
+    pub fn set_require_endorsements(
+        mut self,
+        attester_id: &str,
+        require_endorsements: bool,
+    ) -> Self {
+        if let Some(peer_verifier) = self.peer_verifier_mut(attester_id) {
+            peer_verifier.require_endorsements = require_endorsements;
+        }
+        self
+    }
+
+    /// Sets whether the peer's [`Endorsements`] for `attester_id` must come
+    /// with [`Evidence`]. Evidence is required by default; endorsement-only
+    /// verifiers can opt out, so that endorsements without evidence are
+    /// verified instead of rejected.
+    ///
+    /// A peer verifier must already have been added for `attester_id`,
+    /// otherwise [`Self::try_build`] fails.
+    pub fn set_require_evidence(mut self, attester_id: &str, require_evidence: bool) -> Self {
+        if let Some(peer_verifier) = self.peer_verifier_mut(attester_id) {
+            peer_verifier.require_evidence = require_evidence;
+        }
+        self
+    }
+
+    /// Returns the peer verifier added for `attester_id`, recording the ID for
+    /// [`Self::try_build`] to report if there is none.
+    fn peer_verifier_mut(&mut self, attester_id: &str) -> Option<&mut PeerAttestationVerifier> {
+        let peer_verifier =
+            self.config.attestation_handler_config.peer_verifiers.get_mut(attester_id);
+        if peer_verifier.is_none() && self.unknown_peer_verifier_id.is_none() {
+            self.unknown_peer_verifier_id = Some(attester_id.into());
+        }
+        peer_verifier
+    }
+
+    /// Add an [`AssertionVerifier`] to verify the [`Assertion`] received from
+    /// the peer with the same `assertion_id`. Verification failures make the
+    /// attestation fail, depending on the [`AssertionResultsAggregator`] set
+    /// with [`Self::set_assertion_attestation_aggregator`].
     pub fn add_peer_assertion_verifier(
         mut self,
         assertion_id: String,
@@ -482,6 +570,9 @@ impl SessionConfigBuilder {
         self
     }
 
+    /// Add an [`AssertionVerifier`] by reference, retaining ownership of the
+    /// verifier object. See [`Self::add_peer_assertion_verifier`] for more
+    /// details.
     pub fn add_peer_assertion_verifier_ref(
         mut self,
         assertion_id: String,
@@ -612,18 +703,89 @@ impl SessionConfigBuilder {
         self
     }
 
+    /// Requires the peer's assertions to form a chain in the order of
+    /// `assertion_ids`, e.g. a challenge before its response.
+    ///
+    /// An assertion in the chain is only accepted if all the assertions before
+    /// it were received and verified successfully; otherwise the attestation
+    /// fails regardless of the [`AssertionResultsAggregator`]. Every ID must
+    /// have a verifier added with [`Self::add_peer_assertion_verifier`].
+    pub fn set_peer_assertion_order(mut self, assertion_ids: Vec<String>) -> Self {
+        self.config.attestation_handler_config.peer_assertion_order = assertion_ids;
+        self
+    }
+
+    /// Sets the [`EvidenceLimits`] on the evidence received from the peer,
+    /// overriding the defaults.
+    pub fn set_peer_evidence_limits(mut self, limits: EvidenceLimits) -> Self {
+        self.config.attestation_handler_config.peer_evidence_limits = limits;
+        self
+    }
+
     /// Consumes the builder and returns the configured [`SessionConfig`].
+    ///
+    /// # Panics
+    ///
+    /// Panics with the [`SessionConfigError`] message if the configuration is
+    /// invalid, e.g. if a peer attestation type has no peer verifier. Use
+    /// [`Self::try_build`] to handle these errors instead.
+    #[track_caller]
     pub fn build(self) -> SessionConfig {
-        assert!(
-            self.config
-                .attestation_handler_config
-                .assertion_attestation_aggregator
-                .is_compatible_with_configuration(
-                    &self.config.attestation_handler_config.peer_assertion_verifiers
-                ),
-            "Assertion attestation aggregator is not compatible with the configured peer assertion verifiers",
-        );
-        self.config
+        // Panic directly here rather than in a closure so that the reported
+        // location is the caller's.
+        match self.try_build() {
+            Ok(config) => config,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Consumes the builder and returns the configured [`SessionConfig`], or
+    /// an error if the configuration can't be used to establish a session.
+    ///
+    /// The configuration is invalid if the attestation type requires this
+    /// party to attest but no attesters or assertion generators were added, if
+    /// it requires verifying the peer but no verifiers or assertion verifiers
+    /// were added, if the assertion aggregator is not compatible with the
+    /// configured assertion verifiers, if the assertion order refers to an
+    /// assertion ID more than once or without a verifier, or if evidence
+    /// requirements were set for an attestation ID without a peer verifier.
+    pub fn try_build(self) -> Result<SessionConfig, SessionConfigError> {
+        if let Some(attester_id) = self.unknown_peer_verifier_id {
+            return Err(SessionConfigError::MissingPeerVerifier(attester_id));
+        }
+        let attestation_type = self.config.attestation_type;
+        let attestation_handler_config = &self.config.attestation_handler_config;
+        if matches!(
+            attestation_type,
+            AttestationType::Bidirectional | AttestationType::SelfUnidirectional
+        ) && attestation_handler_config.self_attesters.is_empty()
+            && attestation_handler_config.self_assertion_generators.is_empty()
+        {
+            return Err(SessionConfigError::MissingSelfAttestation(attestation_type));
+        }
+        if matches!(
+            attestation_type,
+            AttestationType::Bidirectional | AttestationType::PeerUnidirectional
+        ) && attestation_handler_config.peer_verifiers.is_empty()
+            && attestation_handler_config.peer_assertion_verifiers.is_empty()
+        {
+            return Err(SessionConfigError::MissingPeerVerification(attestation_type));
+        }
+        if !attestation_handler_config
+            .assertion_attestation_aggregator
+            .is_compatible_with_configuration(&attestation_handler_config.peer_assertion_verifiers)
+        {
+            return Err(SessionConfigError::IncompatibleAssertionAggregator);
+        }
+        let mut ordered_ids = BTreeSet::new();
+        for id in &attestation_handler_config.peer_assertion_order {
+            if !attestation_handler_config.peer_assertion_verifiers.contains_key(id)
+                || !ordered_ids.insert(id)
+            {
+                return Err(SessionConfigError::InvalidAssertionOrder(id.clone()));
+            }
+        }
+        Ok(self.config)
     }
 }
 
@@ -637,6 +799,46 @@ pub struct PeerAttestationVerifier {
     /// successfully verified attestation results. This is used to verify that
     /// the peer has bound its attestation to the current session.
     pub binding_verifier_provider: Arc<dyn SessionBindingVerifierProvider>,
+    /// Whether the peer's [`EndorsedEvidence`] must contain [`Endorsements`].
+    /// If false, evidence without endorsements is verified with empty
+    /// [`Endorsements`], for verifiers that don't need any.
+    pub require_endorsements: bool,
+    /// Whether the peer's [`EndorsedEvidence`] must contain [`Evidence`]. If
+    /// false, endorsements without evidence are verified with empty
+    /// [`Evidence`], for endorsement-only schemes such as signed statements
+    /// without a hardware quote.
+    pub require_evidence: bool,
+}
+
+/// Limits on the [`EndorsedEvidence`] accepted from the peer.
+///
+/// They are checked before any evidence is verified, so that a peer can't make
+/// this party spend unbounded memory and time verifying evidence. Evidence
+/// exceeding them is reported as a
+/// [`VerifierResult::Failure`](crate::attestation::VerifierResult::Failure).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EvidenceLimits {
+    /// The maximum encoded size in bytes of a single [`EndorsedEvidence`].
+    pub max_evidence_size: usize,
+    /// The maximum number of [`EndorsedEvidence`] entries.
+    pub max_evidence_count: usize,
+}
+
+impl EvidenceLimits {
+    /// Default for [`Self::max_evidence_size`]. Well above the size of
+    /// evidence with full certificate chains and event logs.
+    pub const DEFAULT_MAX_EVIDENCE_SIZE: usize = 4 * 1024 * 1024;
+    /// Default for [`Self::max_evidence_count`].
+    pub const DEFAULT_MAX_EVIDENCE_COUNT: usize = 32;
+}
+
+impl Default for EvidenceLimits {
+    fn default() -> Self {
+        Self {
+            max_evidence_size: Self::DEFAULT_MAX_EVIDENCE_SIZE,
+            max_evidence_count: Self::DEFAULT_MAX_EVIDENCE_COUNT,
+        }
+    }
 }
 
 /// Configuration for the attestation phase of a session.
@@ -650,7 +852,8 @@ pub struct AttestationHandlerConfig {
     /// generate its own attestation [`Evidence`].
     pub self_attesters: BTreeMap<String, Arc<dyn Attester>>,
     /// A map of [`AssertionGenerator`]s (keyed by `assertion_id`) used by this
-    /// party to generate its own [`Assertion`]. Not yet used.
+    /// party to generate its own [`Assertion`]s, which are sent to the peer and
+    /// bound to the session.
     pub self_assertion_generators: BTreeMap<String, Arc<dyn AssertionGenerator>>,
     /// A map of endorsers (keyed by `attestation_id`) used by this party to
     /// generate [`Endorsements`] for its own [`Evidence`]. The key must match
@@ -662,7 +865,9 @@ pub struct AttestationHandlerConfig {
     /// provided with the peer's evidence.
     pub peer_verifiers: BTreeMap<String, PeerAttestationVerifier>,
     /// A map of [`AssertionVerifier`]s (keyed by `assertion_id`) used to
-    /// verify an [`Assertion`] received from the peer. Not yet used,
+    /// verify an [`Assertion`] received from the peer. The results are combined
+    /// by `assertion_attestation_aggregator` and contribute to the
+    /// [`PeerAttestationVerdict`](crate::attestation::PeerAttestationVerdict).
     pub peer_assertion_verifiers: BTreeMap<String, Arc<dyn AssertionVerifier>>,
     /// Logic to combine multiple attestation verification results in the legacy
     /// format (if the peer provides evidence from different attesters) into
@@ -677,6 +882,11 @@ pub struct AttestationHandlerConfig {
     /// and `assertion_attestation_aggregator` must succeed for the
     /// attestation to succeed.
     pub assertion_attestation_aggregator: Box<dyn AssertionResultsAggregator>,
+    /// IDs of the peer's assertions that must form a chain, in order. See
+    /// [`SessionConfigBuilder::set_peer_assertion_order`].
+    pub peer_assertion_order: Vec<String>,
+    /// Limits on the [`EndorsedEvidence`] received from the peer.
+    pub peer_evidence_limits: EvidenceLimits,
 }
 
 impl Default for alloc::boxed::Box<dyn LegacyVerifierResultsAggregator> {
@@ -111,7 +111,7 @@ use crate::{
 ///
 /// Each variant corresponds to a specific Noise pattern (e.g., KK, NK, NN),
 /// determining how parties authenticate and exchange keys.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum HandshakeType {
     NoiseKK,
     NoiseKN,
@@ -119,6 +119,21 @@ pub enum HandshakeType {
     NoiseNN,
 }
 
+impl HandshakeType {
+    /// Returns the full Noise protocol name of the handshake, which also names
+    /// the DH function, cipher and hash function used by the session.
+    ///
+    /// See <https://noiseprotocol.org/noise.html#protocol-names-and-modifiers>.
+    pub fn noise_protocol_name(&self) -> &'static str {
+        match self {
+            HandshakeType::NoiseKK => "Noise_KK_P256_AESGCM_SHA256",
+            HandshakeType::NoiseKN => "Noise_KN_P256_AESGCM_SHA256",
+            HandshakeType::NoiseNK => "Noise_NK_P256_AESGCM_SHA256",
+            HandshakeType::NoiseNN => "Noise_NN_P256_AESGCM_SHA256",
+        }
+    }
+}
+
 /// Holds the results of a successfully completed Oak Session handshake.
 ///
 /// This structure encapsulates the essential cryptographic material derived
@@ -136,6 +151,8 @@ pub struct HandshakeResult {
 
 /// Contains the state of a completed handshake.
 pub struct HandshakeState {
+    /// The Noise handshake pattern that was used to establish the session.
+    pub handshake_type: HandshakeType,
     /// A cryptographic hash of the entire transcript of messages exchanged
     /// during the handshake. This hash is crucial for binding the
     /// attestation results to the handshake, ensuring that the attested
@@ -247,6 +264,8 @@ pub trait HandshakeHandler: Send {
 /// empty), it may also prepare a follow-up message containing the client's
 /// session binding.
 pub struct ClientHandshakeHandler {
+    /// The Noise handshake pattern used by the `handshake_initiator`.
+    handshake_type: HandshakeType,
     /// The `HandshakeInitiator` is used to generate the initial handshake
     /// message and process the server's response.
     handshake_initiator: HandshakeInitiator,
@@ -324,6 +343,7 @@ impl ClientHandshakeHandler {
             ..Default::default()
         };
         Ok(Self {
+            handshake_type,
             handshake_initiator,
             session_binders: handshake_handler_config.session_binders,
             attestation_state,
@@ -409,6 +429,7 @@ impl ProtocolEngine<HandshakeResponse, HandshakeRequest> for ClientHandshakeHand
                     .map(|(handshake_hash, crypter)| HandshakeResult {
                         crypter,
                         handshake_state: HandshakeState {
+                            handshake_type: self.handshake_type,
                             handshake_binding_token: handshake_hash.to_vec(),
                             peer_session_bindings: incoming_message.attestation_bindings,
                             peer_assertion_bindings: incoming_message.assertion_bindings,
@@ -574,6 +595,7 @@ impl ProtocolEngine<HandshakeRequest, HandshakeResponse> for ServerHandshakeHand
             self.handshake_result = Some(HandshakeResult {
                 crypter: noise_response.crypter,
                 handshake_state: HandshakeState {
+                    handshake_type: self.handshake_type,
                     handshake_binding_token: noise_response.handshake_hash.to_vec(),
                     peer_session_bindings: incoming_message.attestation_bindings,
                     peer_assertion_bindings: incoming_message.assertion_bindings,
@@ -653,6 +675,7 @@ impl ProtocolEngine<HandshakeRequest, HandshakeResponse> for ServerHandshakeHand
                 self.handshake_result = Some(HandshakeResult {
                     crypter: noise_response.crypter,
                     handshake_state: HandshakeState {
+                        handshake_type: self.handshake_type,
                         handshake_binding_token: noise_response.handshake_hash.to_vec(),
                         peer_session_bindings: BTreeMap::new(),
                         peer_assertion_bindings: BTreeMap::new(),
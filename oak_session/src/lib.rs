@@ -32,6 +32,7 @@ pub mod handshake;
 pub mod key_extractor;
 pub mod session;
 pub mod session_binding;
+pub mod timestamp_assertion;
 pub mod verifier;
 
 #[cfg(test)]
@@ -58,7 +58,7 @@
 
 use alloc::{
     boxed::Box,
-    collections::{BTreeMap, VecDeque},
+    collections::{BTreeMap, BTreeSet, VecDeque},
     string::String,
     sync::Arc,
     vec::Vec,
@@ -67,20 +67,23 @@ use core::mem;
 
 use anyhow::{anyhow, Context, Error, Ok};
 use oak_crypto::{encryptor::Encryptor, noise_handshake::session_binding_token_hash};
-use oak_proto_rust::oak::session::v1::{
-    session_request::Request, session_response::Response, EncryptedMessage, EndorsedEvidence,
-    PlaintextMessage, SessionBinding, SessionRequest, SessionResponse,
+use oak_proto_rust::oak::{
+    attestation::v1::AttestationResults,
+    session::v1::{
+        session_request::Request, session_response::Response, EncryptedMessage, EndorsedEvidence,
+        PlaintextMessage, SessionBinding, SessionRequest, SessionResponse,
+    },
 };
 
 use crate::{
     attestation::{
-        AttestationHandler, AttestationState, ClientAttestationHandler, PeerAttestationVerdict,
-        ServerAttestationHandler, VerifierResult,
+        AttestationHandler, AttestationState, AttestationType, ClientAttestationHandler,
+        PeerAttestationVerdict, ServerAttestationHandler, VerifierResult,
     },
     config::{EncryptorProvider, SessionConfig},
     handshake::{
         ClientHandshakeHandler, ClientHandshakeHandlerBuilder, HandshakeHandler,
-        HandshakeHandlerBuilder, HandshakeState, ServerHandshakeHandler,
+        HandshakeHandlerBuilder, HandshakeState, HandshakeType, ServerHandshakeHandler,
         ServerHandshakeHandlerBuilder,
     },
     session_binding::{create_session_binding_token, SessionBindingVerifier},
@@ -140,6 +143,41 @@ pub struct AttestationEvidence {
     pub handshake_hash: Vec<u8>,
 }
 
+/// The outcome of the attestation in both directions of an open session, as
+/// far as one party can tell.
+///
+/// Each party only sees its own [`PeerAttestationVerdict`]; the peer never
+/// reports its verdict back. So `self_not_rejected_by_peer` is inferred
+/// locally from the handshake completing: a peer that rejects this party's
+/// evidence aborts the session instead of completing the handshake. It is not
+/// a verdict of the peer and has some limits:
+/// - a peer that isn't configured to verify this party's evidence ignores it
+///   and completes the handshake anyway, which looks the same;
+/// - the server's session opens when it sends its last handshake message, so
+///   the server can't tell whether the client accepted its session bindings,
+///   only that the client accepted its evidence.
+///
+/// Both flags only become reliable if the peer's configuration is known to
+/// require verifying this party.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BidirectionalAttestationStatus {
+    /// Whether this party successfully verified evidence or assertions of the
+    /// peer.
+    pub peer_verified_by_self: bool,
+    /// Whether this party sent evidence or assertions and the peer completed
+    /// the handshake, i.e. didn't reject them. Inferred locally, see above.
+    pub self_not_rejected_by_peer: bool,
+}
+
+impl BidirectionalAttestationStatus {
+    /// Returns whether this party verified the peer and the peer didn't reject
+    /// this party, i.e. whether the attestation succeeded in both directions
+    /// as far as this party can tell.
+    pub fn is_fully_verified(&self) -> bool {
+        self.peer_verified_by_self && self.self_not_rejected_by_peer
+    }
+}
+
 /// An [`AttestationPublisher`] can be added to a session configuration to allow
 /// publishing received evidence to an external component.
 pub trait AttestationPublisher: Send + Sync {
@@ -211,6 +249,38 @@ pub trait Session: Send {
     ///
     /// This method can only be called successfully when `is_open()` is true.
     fn get_peer_attestation_evidence(&self) -> Result<AttestationEvidence, Error>;
+
+    /// Returns the results of successfully verifying the peer's evidence,
+    /// keyed by attestation ID.
+    ///
+    /// This method can only be called successfully when `is_open()` is true.
+    /// Sessions that don't keep the verification results return an error.
+    fn get_peer_attestation_results(&self) -> Result<BTreeMap<String, AttestationResults>, Error> {
+        Err(anyhow!("peer attestation results are not supported by this session"))
+    }
+
+    /// Returns the outcome of the attestation in both directions. See
+    /// [`BidirectionalAttestationStatus`] for what can be inferred about the
+    /// peer's verdict.
+    ///
+    /// This method can only be called successfully when `is_open()` is true.
+    /// Sessions that don't track the attestation status return an error.
+    fn get_bidirectional_attestation_status(
+        &self,
+    ) -> Result<BidirectionalAttestationStatus, Error> {
+        Err(anyhow!("bidirectional attestation status is not supported by this session"))
+    }
+
+    /// Returns the evidence the peer supplied under the attestation ID `id`.
+    ///
+    /// Returns `None` if the session is not open yet, or if no evidence with
+    /// that ID was successfully verified.
+    fn get_peer_evidence_for_id(&self, id: &str) -> Option<EndorsedEvidence> {
+        if !self.get_peer_attestation_results().ok()?.contains_key(id) {
+            return None;
+        }
+        self.get_peer_attestation_evidence().ok()?.evidence.remove(id)
+    }
 }
 
 /// Represents the internal state machine and data for a session's progression.
@@ -363,6 +433,17 @@ impl<AP: AttestationHandler, H: HandshakeHandler> Step<AP, H> {
         }
     }
 
+    /// Returns the Noise handshake pattern with which the session was
+    /// established.
+    ///
+    /// This method can only be called successfully when `is_open()` is true.
+    fn get_handshake_type(&self) -> Result<HandshakeType, Error> {
+        match &self {
+            Step::Open { handshake_state, .. } => Ok(handshake_state.handshake_type),
+            _ => Err(anyhow!("the session is not open")),
+        }
+    }
+
     /// Returns the attestation results for this session.
     ///
     /// This method can only be called successfully when `is_open()` is true.
@@ -374,6 +455,58 @@ impl<AP: AttestationHandler, H: HandshakeHandler> Step<AP, H> {
             _ => Err(anyhow!("the session is not open")),
         }
     }
+
+    /// Returns the results of the successful verifications of the peer's
+    /// evidence.
+    ///
+    /// This method can only be called successfully when `is_open()` is true.
+    fn get_peer_attestation_results(&self) -> Result<BTreeMap<String, AttestationResults>, Error> {
+        match &self {
+            Step::Open { attestation_state, .. } => Ok(attestation_state
+                .peer_attestation_verdict
+                .get_legacy_verification_results()
+                .iter()
+                .filter_map(|(id, verifier_result)| match verifier_result {
+                    VerifierResult::Success { result, .. } => Some((id.clone(), result.clone())),
+                    _ => None,
+                })
+                .collect()),
+            _ => Err(anyhow!("the session is not open")),
+        }
+    }
+
+    /// Returns the outcome of the attestation in both directions, given the
+    /// `attestation_type` this party was configured with.
+    ///
+    /// This method can only be called successfully when `is_open()` is true.
+    fn get_bidirectional_attestation_status(
+        &self,
+        attestation_type: AttestationType,
+    ) -> Result<BidirectionalAttestationStatus, Error> {
+        match &self {
+            Step::Open { attestation_state, .. } => {
+                let verdict = &attestation_state.peer_attestation_verdict;
+                let peer_verified_by_self =
+                    matches!(verdict, PeerAttestationVerdict::AttestationPassed { .. })
+                        && (verdict
+                            .get_legacy_verification_results()
+                            .values()
+                            .any(|result| matches!(result, VerifierResult::Success { .. }))
+                            || verdict.get_assertion_verification_results().values().any(
+                                |result| matches!(result, AssertionVerifierResult::Success { .. }),
+                            ));
+                let self_not_rejected_by_peer = matches!(
+                    attestation_type,
+                    AttestationType::Bidirectional | AttestationType::SelfUnidirectional
+                );
+                Ok(BidirectionalAttestationStatus {
+                    peer_verified_by_self,
+                    self_not_rejected_by_peer,
+                })
+            }
+            _ => Err(anyhow!("the session is not open")),
+        }
+    }
 }
 
 /// Client-side implementation of an end-to-end secure attested session.
@@ -392,6 +525,12 @@ pub struct ClientSession {
     /// up to the session layer but not yet decrypted and read by the
     /// application.
     incoming_responses: VecDeque<SessionResponse>,
+    /// Attestation IDs for which a peer verifier is configured. Kept
+    /// separately as the attestation handler is consumed once the attestation
+    /// step completes.
+    expected_peer_attestation_ids: BTreeSet<String>,
+    /// The attestation type the session was configured with.
+    attestation_type: AttestationType,
 }
 
 impl ClientSession {
@@ -403,9 +542,12 @@ impl ClientSession {
     /// state. The lifetimes of objects within `config` (e.g., keys in
     /// `HandshakeHandlerConfig`) are now managed by the `ClientSession`.
     pub fn create(config: SessionConfig) -> Result<Self, Error> {
+        let attester = ClientAttestationHandler::create(config.attestation_handler_config)?;
+        let expected_peer_attestation_ids =
+            attester.expected_peer_attestation_ids().map(String::from).collect();
         Ok(Self {
             step: Step::Attestation {
-                attester: ClientAttestationHandler::create(config.attestation_handler_config)?,
+                attester,
                 attestation_publisher: config.attestation_publisher,
                 handshake_handler_provider: Box::new(ClientHandshakeHandlerBuilder {
                     config: config.handshake_handler_config,
@@ -414,8 +556,26 @@ impl ClientSession {
             },
             outgoing_requests: VecDeque::new(),
             incoming_responses: VecDeque::new(),
+            expected_peer_attestation_ids,
+            attestation_type: config.attestation_type,
         })
     }
+
+    /// Returns the attestation IDs for which the session is configured to
+    /// verify the server's evidence.
+    ///
+    /// Useful to diagnose sessions where the server's evidence is reported as
+    /// `VerifierResult::Unverified` because no matching verifier is configured.
+    pub fn expected_peer_attestation_ids(&self) -> impl Iterator<Item = &str> {
+        self.expected_peer_attestation_ids.iter().map(String::as_str)
+    }
+
+    /// Returns the Noise handshake pattern that was negotiated with the server.
+    ///
+    /// Returns an error if the session is not open yet.
+    pub fn handshake_type(&self) -> Result<HandshakeType, Error> {
+        self.step.get_handshake_type()
+    }
 }
 
 impl Session for ClientSession {
@@ -477,6 +637,20 @@ impl Session for ClientSession {
     fn get_peer_attestation_evidence(&self) -> Result<AttestationEvidence, Error> {
         self.step.get_peer_attestation_evidence()
     }
+
+    /// Gets the peer attestation results. See
+    /// `Session::get_peer_attestation_results`.
+    fn get_peer_attestation_results(&self) -> Result<BTreeMap<String, AttestationResults>, Error> {
+        self.step.get_peer_attestation_results()
+    }
+
+    /// Gets the outcome of the attestation in both directions. See
+    /// `Session::get_bidirectional_attestation_status`.
+    fn get_bidirectional_attestation_status(
+        &self,
+    ) -> Result<BidirectionalAttestationStatus, Error> {
+        self.step.get_bidirectional_attestation_status(self.attestation_type)
+    }
 }
 
 impl ProtocolEngine<SessionResponse, SessionRequest> for ClientSession {
@@ -595,6 +769,8 @@ pub struct ServerSession {
     /// to the session layer but not yet decrypted and read by the
     /// application.
     incoming_requests: VecDeque<SessionRequest>,
+    /// The attestation type the session was configured with.
+    attestation_type: AttestationType,
 }
 
 impl ServerSession {
@@ -605,6 +781,7 @@ impl ServerSession {
     /// based on `config.attestation_handler_config.attestation_type` for
     /// the `ServerHandshakeHandler`. The configuration is consumed.
     pub fn create(config: SessionConfig) -> Result<Self, Error> {
+        let attestation_type = config.attestation_type;
         Ok(Self {
             step: Step::Attestation {
                 attester: ServerAttestationHandler::create(config.attestation_handler_config)?,
@@ -616,6 +793,7 @@ impl ServerSession {
             },
             outgoing_responses: VecDeque::new(),
             incoming_requests: VecDeque::new(),
+            attestation_type,
         })
     }
 }
@@ -679,6 +857,20 @@ impl Session for ServerSession {
     fn get_peer_attestation_evidence(&self) -> Result<AttestationEvidence, Error> {
         self.step.get_peer_attestation_evidence()
     }
+
+    /// Gets the peer attestation results. See
+    /// `Session::get_peer_attestation_results`.
+    fn get_peer_attestation_results(&self) -> Result<BTreeMap<String, AttestationResults>, Error> {
+        self.step.get_peer_attestation_results()
+    }
+
+    /// Gets the outcome of the attestation in both directions. See
+    /// `Session::get_bidirectional_attestation_status`.
+    fn get_bidirectional_attestation_status(
+        &self,
+    ) -> Result<BidirectionalAttestationStatus, Error> {
+        self.step.get_bidirectional_attestation_status(self.attestation_type)
+    }
 }
 
 impl ProtocolEngine<SessionRequest, SessionResponse> for ServerSession {
@@ -26,16 +26,18 @@ use mockall::mock;
 use oak_attestation_types::{attester::Attester, endorser::Endorser};
 use oak_attestation_verification_types::verifier::AttestationVerifier;
 use oak_proto_rust::oak::{
-    attestation::v1::{attestation_results, AttestationResults, Endorsements, Evidence},
+    attestation::v1::{
+        attestation_results, AttestationResults, Endorsements, Evidence, RootLayerEvidence,
+    },
     session::v1::{Assertion, AttestRequest, AttestResponse, EndorsedEvidence, SessionBinding},
 };
 use oak_session::{
-    aggregators::{All, PassThrough},
+    aggregators::{All, Any, PassThrough},
     attestation::{
         AttestationHandler, ClientAttestationHandler, PeerAttestationVerdict,
         ServerAttestationHandler, VerifierResult,
     },
-    config::{AttestationHandlerConfig, PeerAttestationVerifier},
+    config::{AttestationHandlerConfig, EvidenceLimits, PeerAttestationVerifier},
     generator::{AssertionGenerationError, AssertionGenerator, BindableAssertion},
     session_binding::{SessionBindingVerifier, SessionBindingVerifierProvider},
     verifier::{
@@ -164,6 +166,12 @@ fn create_failing_mock_verifier() -> Arc<dyn AttestationVerifier> {
     Arc::new(verifier)
 }
 
+fn create_unused_mock_verifier() -> Arc<dyn AttestationVerifier> {
+    let mut verifier = MockTestAttestationVerifier::new();
+    verifier.expect_verify().never();
+    Arc::new(verifier)
+}
+
 fn create_passing_mock_assertion_verifier(assertion: Assertion) -> Arc<dyn AssertionVerifier> {
     let mut verifier = MockTestAssertionVerifier::new();
     verifier.expect_verify_assertion().returning(move |_| {
@@ -237,6 +245,19 @@ fn do_attestation_exchange(
     })
 }
 
+fn endorsed_evidence_with_report_size(report_size: usize) -> EndorsedEvidence {
+    EndorsedEvidence {
+        evidence: Some(Evidence {
+            root_layer: Some(RootLayerEvidence {
+                remote_attestation_report: vec![0; report_size],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        endorsements: Some(Endorsements { ..Default::default() }),
+    }
+}
+
 const MATCHED_ATTESTER_ID1: &str = "MATCHED_ATTESTER_ID1";
 const MATCHED_ATTESTER_ID2: &str = "MATCHED_ATTESTER_ID2";
 const UNMATCHED_ATTESTER_ID: &str = "UNMATCHED_ATTESTER_ID";
@@ -447,6 +468,8 @@ fn peer_attested_client_provides_request_accepts_response() -> anyhow::Result<()
             PeerAttestationVerifier {
                 verifier: create_passing_mock_verifier(),
                 binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
             },
         )]),
         peer_assertion_verifiers: BTreeMap::from([(
@@ -495,6 +518,8 @@ fn peer_attested_server_accepts_request_provides_response() -> anyhow::Result<()
             PeerAttestationVerifier {
                 verifier: create_passing_mock_verifier(),
                 binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
             },
         )]),
         peer_assertion_verifiers: BTreeMap::from([(
@@ -531,6 +556,474 @@ fn peer_attested_server_accepts_request_provides_response() -> anyhow::Result<()
     Ok(())
 }
 
+#[googletest::test]
+fn verdict_lists_unverified_ids() -> anyhow::Result<()> {
+    let client_config = AttestationHandlerConfig {
+        peer_verifiers: BTreeMap::from([
+            (
+                MATCHED_ATTESTER_ID1.to_string(),
+                PeerAttestationVerifier {
+                    verifier: create_passing_mock_verifier(),
+                    binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                    require_endorsements: true,
+                    require_evidence: true,
+                },
+            ),
+            (
+                UNMATCHED_VERIFIER_ID.to_string(),
+                PeerAttestationVerifier {
+                    verifier: create_unused_mock_verifier(),
+                    binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                    require_endorsements: true,
+                    require_evidence: true,
+                },
+            ),
+        ]),
+        ..Default::default()
+    };
+
+    let mut client_attestation_provider = ClientAttestationHandler::create(client_config)?;
+
+    let attest_response = AttestResponse {
+        endorsed_evidence: BTreeMap::from([
+            (MATCHED_ATTESTER_ID1.to_string(), endorsed_evidence_with_report_size(0)),
+            (UNMATCHED_ATTESTER_ID.to_string(), endorsed_evidence_with_report_size(0)),
+        ]),
+        ..Default::default()
+    };
+    assert_that!(client_attestation_provider.put_incoming_message(attest_response), ok(some(())));
+    let verdict = client_attestation_provider.take_attestation_state()?.peer_attestation_verdict;
+    assert_that!(verdict.unverified_ids(), elements_are![eq(&UNMATCHED_ATTESTER_ID)]);
+
+    Ok(())
+}
+
+#[googletest::test]
+fn peer_attested_client_requires_endorsements_by_default() -> anyhow::Result<()> {
+    let client_config = AttestationHandlerConfig {
+        peer_verifiers: BTreeMap::from([(
+            MATCHED_ATTESTER_ID1.to_string(),
+            PeerAttestationVerifier {
+                verifier: create_unused_mock_verifier(),
+                binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
+            },
+        )]),
+        ..Default::default()
+    };
+
+    let mut client_attestation_provider = ClientAttestationHandler::create(client_config)?;
+
+    let attest_response = AttestResponse {
+        endorsed_evidence: BTreeMap::from([(
+            MATCHED_ATTESTER_ID1.to_string(),
+            EndorsedEvidence {
+                evidence: Some(Evidence { ..Default::default() }),
+                endorsements: None,
+            },
+        )]),
+        ..Default::default()
+    };
+    assert_that!(client_attestation_provider.put_incoming_message(attest_response), ok(some(())));
+    assert_that!(
+        client_attestation_provider.take_attestation_state()?.peer_attestation_verdict,
+        matches_pattern!(PeerAttestationVerdict::AttestationFailed {
+            reason: starts_with("Legacy verification failed"),
+            legacy_verification_results: elements_are!((
+                eq(MATCHED_ATTESTER_ID1),
+                matches_pattern!(VerifierResult::Failure {
+                    evidence: anything(),
+                    result: matches_pattern!(AttestationResults {
+                        reason: eq("Both evidence and endorsements need to be provided"),
+                        ..
+                    }),
+                }),
+            )),
+            assertion_verification_results: anything(),
+        })
+    );
+
+    Ok(())
+}
+
+#[googletest::test]
+fn peer_attested_client_verifies_evidence_without_endorsements_if_not_required(
+) -> anyhow::Result<()> {
+    let mut verifier = MockTestAttestationVerifier::new();
+    verifier
+        .expect_verify()
+        .withf(|_, endorsements| *endorsements == Endorsements::default())
+        .returning(|_, _| {
+            Ok(AttestationResults {
+                status: attestation_results::Status::Success.into(),
+                ..Default::default()
+            })
+        });
+    let client_config = AttestationHandlerConfig {
+        peer_verifiers: BTreeMap::from([(
+            MATCHED_ATTESTER_ID1.to_string(),
+            PeerAttestationVerifier {
+                verifier: Arc::new(verifier),
+                binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: false,
+                require_evidence: true,
+            },
+        )]),
+        ..Default::default()
+    };
+
+    let mut client_attestation_provider = ClientAttestationHandler::create(client_config)?;
+
+    let attest_response = AttestResponse {
+        endorsed_evidence: BTreeMap::from([(
+            MATCHED_ATTESTER_ID1.to_string(),
+            EndorsedEvidence {
+                evidence: Some(Evidence { ..Default::default() }),
+                endorsements: None,
+            },
+        )]),
+        ..Default::default()
+    };
+    assert_that!(client_attestation_provider.put_incoming_message(attest_response), ok(some(())));
+    assert_that!(
+        client_attestation_provider.take_attestation_state()?.peer_attestation_verdict,
+        matches_pattern!(PeerAttestationVerdict::AttestationPassed {
+            legacy_verification_results: elements_are!((
+                eq(MATCHED_ATTESTER_ID1),
+                matches_pattern!(VerifierResult::Success { .. }),
+            )),
+            assertion_verification_results: anything(),
+        })
+    );
+
+    Ok(())
+}
+
+#[googletest::test]
+fn peer_attested_client_requires_evidence_by_default() -> anyhow::Result<()> {
+    let client_config = AttestationHandlerConfig {
+        peer_verifiers: BTreeMap::from([(
+            MATCHED_ATTESTER_ID1.to_string(),
+            PeerAttestationVerifier {
+                verifier: create_unused_mock_verifier(),
+                binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
+            },
+        )]),
+        ..Default::default()
+    };
+
+    let mut client_attestation_provider = ClientAttestationHandler::create(client_config)?;
+
+    let attest_response = AttestResponse {
+        endorsed_evidence: BTreeMap::from([(
+            MATCHED_ATTESTER_ID1.to_string(),
+            EndorsedEvidence {
+                evidence: None,
+                endorsements: Some(Endorsements { ..Default::default() }),
+            },
+        )]),
+        ..Default::default()
+    };
+    assert_that!(client_attestation_provider.put_incoming_message(attest_response), ok(some(())));
+    assert_that!(
+        client_attestation_provider.take_attestation_state()?.peer_attestation_verdict,
+        matches_pattern!(PeerAttestationVerdict::AttestationFailed {
+            reason: starts_with("Legacy verification failed"),
+            legacy_verification_results: elements_are!((
+                eq(MATCHED_ATTESTER_ID1),
+                matches_pattern!(VerifierResult::Failure {
+                    evidence: anything(),
+                    result: matches_pattern!(AttestationResults {
+                        reason: eq("Both evidence and endorsements need to be provided"),
+                        ..
+                    }),
+                }),
+            )),
+            assertion_verification_results: anything(),
+        })
+    );
+
+    Ok(())
+}
+
+#[googletest::test]
+fn peer_attested_client_verifies_endorsements_without_evidence_if_not_required(
+) -> anyhow::Result<()> {
+    let mut verifier = MockTestAttestationVerifier::new();
+    verifier.expect_verify().withf(|evidence, _| *evidence == Evidence::default()).returning(
+        |_, _| {
+            Ok(AttestationResults {
+                status: attestation_results::Status::Success.into(),
+                ..Default::default()
+            })
+        },
+    );
+    let client_config = AttestationHandlerConfig {
+        peer_verifiers: BTreeMap::from([(
+            MATCHED_ATTESTER_ID1.to_string(),
+            PeerAttestationVerifier {
+                verifier: Arc::new(verifier),
+                binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: false,
+            },
+        )]),
+        ..Default::default()
+    };
+
+    let mut client_attestation_provider = ClientAttestationHandler::create(client_config)?;
+
+    let attest_response = AttestResponse {
+        endorsed_evidence: BTreeMap::from([(
+            MATCHED_ATTESTER_ID1.to_string(),
+            EndorsedEvidence {
+                evidence: None,
+                endorsements: Some(Endorsements { ..Default::default() }),
+            },
+        )]),
+        ..Default::default()
+    };
+    assert_that!(client_attestation_provider.put_incoming_message(attest_response), ok(some(())));
+    assert_that!(
+        client_attestation_provider.take_attestation_state()?.peer_attestation_verdict,
+        matches_pattern!(PeerAttestationVerdict::AttestationPassed {
+            legacy_verification_results: elements_are!((
+                eq(MATCHED_ATTESTER_ID1),
+                matches_pattern!(VerifierResult::Success { .. }),
+            )),
+            assertion_verification_results: anything(),
+        })
+    );
+
+    Ok(())
+}
+
+#[googletest::test]
+fn peer_attested_client_endorsement_only_verifier_requires_endorsements() -> anyhow::Result<()> {
+    let client_config = AttestationHandlerConfig {
+        peer_verifiers: BTreeMap::from([(
+            MATCHED_ATTESTER_ID1.to_string(),
+            PeerAttestationVerifier {
+                verifier: create_unused_mock_verifier(),
+                binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: false,
+            },
+        )]),
+        ..Default::default()
+    };
+
+    let mut client_attestation_provider = ClientAttestationHandler::create(client_config)?;
+
+    let attest_response = AttestResponse {
+        endorsed_evidence: BTreeMap::from([(
+            MATCHED_ATTESTER_ID1.to_string(),
+            EndorsedEvidence { evidence: None, endorsements: None },
+        )]),
+        ..Default::default()
+    };
+    assert_that!(client_attestation_provider.put_incoming_message(attest_response), ok(some(())));
+    assert_that!(
+        client_attestation_provider.take_attestation_state()?.peer_attestation_verdict,
+        matches_pattern!(PeerAttestationVerdict::AttestationFailed {
+            reason: starts_with("Legacy verification failed"),
+            legacy_verification_results: elements_are!((
+                eq(MATCHED_ATTESTER_ID1),
+                matches_pattern!(VerifierResult::Failure {
+                    evidence: anything(),
+                    result: matches_pattern!(AttestationResults {
+                        reason: eq("Endorsements need to be provided"),
+                        ..
+                    }),
+                }),
+            )),
+            assertion_verification_results: anything(),
+        })
+    );
+
+    Ok(())
+}
+
+#[googletest::test]
+fn peer_attested_client_rejects_empty_evidence_if_nothing_required() -> anyhow::Result<()> {
+    let client_config = AttestationHandlerConfig {
+        peer_verifiers: BTreeMap::from([(
+            MATCHED_ATTESTER_ID1.to_string(),
+            PeerAttestationVerifier {
+                verifier: create_unused_mock_verifier(),
+                binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: false,
+                require_evidence: false,
+            },
+        )]),
+        ..Default::default()
+    };
+
+    let mut client_attestation_provider = ClientAttestationHandler::create(client_config)?;
+
+    let attest_response = AttestResponse {
+        endorsed_evidence: BTreeMap::from([(
+            MATCHED_ATTESTER_ID1.to_string(),
+            EndorsedEvidence { evidence: None, endorsements: None },
+        )]),
+        ..Default::default()
+    };
+    assert_that!(client_attestation_provider.put_incoming_message(attest_response), ok(some(())));
+    assert_that!(
+        client_attestation_provider.take_attestation_state()?.peer_attestation_verdict,
+        matches_pattern!(PeerAttestationVerdict::AttestationFailed {
+            reason: starts_with("Legacy verification failed"),
+            legacy_verification_results: elements_are!((
+                eq(MATCHED_ATTESTER_ID1),
+                matches_pattern!(VerifierResult::Failure {
+                    evidence: anything(),
+                    result: matches_pattern!(AttestationResults {
+                        reason: eq("Evidence or endorsements need to be provided"),
+                        ..
+                    }),
+                }),
+            )),
+            assertion_verification_results: anything(),
+        })
+    );
+
+    Ok(())
+}
+
+#[googletest::test]
+fn peer_attested_client_rejects_oversized_evidence() -> anyhow::Result<()> {
+    let client_config = AttestationHandlerConfig {
+        peer_verifiers: BTreeMap::from([(
+            MATCHED_ATTESTER_ID1.to_string(),
+            PeerAttestationVerifier {
+                verifier: create_unused_mock_verifier(),
+                binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
+            },
+        )]),
+        peer_evidence_limits: EvidenceLimits { max_evidence_size: 1024, ..Default::default() },
+        ..Default::default()
+    };
+
+    let mut client_attestation_provider = ClientAttestationHandler::create(client_config)?;
+
+    let attest_response = AttestResponse {
+        endorsed_evidence: BTreeMap::from([(
+            MATCHED_ATTESTER_ID1.to_string(),
+            endorsed_evidence_with_report_size(2048),
+        )]),
+        ..Default::default()
+    };
+    assert_that!(client_attestation_provider.put_incoming_message(attest_response), ok(some(())));
+    assert_that!(
+        client_attestation_provider.take_attestation_state()?.peer_attestation_verdict,
+        matches_pattern!(PeerAttestationVerdict::AttestationFailed {
+            reason: starts_with("Legacy verification failed"),
+            legacy_verification_results: elements_are!((
+                eq(MATCHED_ATTESTER_ID1),
+                matches_pattern!(VerifierResult::Failure {
+                    evidence: anything(),
+                    result: matches_pattern!(AttestationResults {
+                        reason: starts_with("evidence too large: "),
+                        ..
+                    }),
+                }),
+            )),
+            assertion_verification_results: anything(),
+        })
+    );
+
+    Ok(())
+}
+
+#[googletest::test]
+fn peer_attested_client_accepts_evidence_within_size_limit() -> anyhow::Result<()> {
+    let client_config = AttestationHandlerConfig {
+        peer_verifiers: BTreeMap::from([(
+            MATCHED_ATTESTER_ID1.to_string(),
+            PeerAttestationVerifier {
+                verifier: create_passing_mock_verifier(),
+                binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
+            },
+        )]),
+        peer_evidence_limits: EvidenceLimits { max_evidence_size: 1024, ..Default::default() },
+        ..Default::default()
+    };
+
+    let mut client_attestation_provider = ClientAttestationHandler::create(client_config)?;
+
+    let attest_response = AttestResponse {
+        endorsed_evidence: BTreeMap::from([(
+            MATCHED_ATTESTER_ID1.to_string(),
+            endorsed_evidence_with_report_size(512),
+        )]),
+        ..Default::default()
+    };
+    assert_that!(client_attestation_provider.put_incoming_message(attest_response), ok(some(())));
+    assert_that!(
+        client_attestation_provider.take_attestation_state()?.peer_attestation_verdict,
+        matches_pattern!(PeerAttestationVerdict::AttestationPassed { .. })
+    );
+
+    Ok(())
+}
+
+#[googletest::test]
+fn peer_attested_server_rejects_too_many_evidence_entries() -> anyhow::Result<()> {
+    let server_config = AttestationHandlerConfig {
+        peer_verifiers: BTreeMap::from([(
+            MATCHED_ATTESTER_ID1.to_string(),
+            PeerAttestationVerifier {
+                verifier: create_unused_mock_verifier(),
+                binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
+            },
+        )]),
+        peer_evidence_limits: EvidenceLimits { max_evidence_count: 1, ..Default::default() },
+        ..Default::default()
+    };
+
+    let mut server_attestation_provider = ServerAttestationHandler::create(server_config)?;
+
+    let attest_request = AttestRequest {
+        endorsed_evidence: BTreeMap::from([
+            (MATCHED_ATTESTER_ID1.to_string(), endorsed_evidence_with_report_size(0)),
+            (UNMATCHED_ATTESTER_ID.to_string(), endorsed_evidence_with_report_size(0)),
+        ]),
+        ..Default::default()
+    };
+    assert_that!(server_attestation_provider.put_incoming_message(attest_request), ok(some(())));
+    let too_many_evidence_failure = || {
+        matches_pattern!(VerifierResult::Failure {
+            evidence: anything(),
+            result: matches_pattern!(AttestationResults {
+                reason: eq("evidence too large: 2 evidence entries provided, at most 1 allowed"),
+                ..
+            }),
+        })
+    };
+    assert_that!(
+        server_attestation_provider.take_attestation_state()?.peer_attestation_verdict,
+        matches_pattern!(PeerAttestationVerdict::AttestationFailed {
+            reason: starts_with("Legacy verification failed"),
+            legacy_verification_results: unordered_elements_are![
+                (eq(MATCHED_ATTESTER_ID1), too_many_evidence_failure()),
+                (eq(UNMATCHED_ATTESTER_ID), too_many_evidence_failure()),
+            ],
+            assertion_verification_results: anything(),
+        })
+    );
+
+    Ok(())
+}
+
 #[googletest::test]
 fn bidirectional_client_provides_request_accepts_response() -> anyhow::Result<()> {
     let assertion: Assertion = Assertion { content: "test".as_bytes().to_vec() };
@@ -549,6 +1042,8 @@ fn bidirectional_client_provides_request_accepts_response() -> anyhow::Result<()
             PeerAttestationVerifier {
                 verifier: create_passing_mock_verifier(),
                 binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
             },
         )]),
         peer_assertion_verifiers: BTreeMap::from([(
@@ -613,6 +1108,8 @@ fn bidirectional_server_accepts_request_provides_response() -> anyhow::Result<()
             PeerAttestationVerifier {
                 verifier: create_passing_mock_verifier(),
                 binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
             },
         )]),
         peer_assertion_verifiers: BTreeMap::from([(
@@ -660,6 +1157,42 @@ fn bidirectional_server_accepts_request_provides_response() -> anyhow::Result<()
     Ok(())
 }
 
+#[googletest::test]
+fn client_reports_expected_peer_attestation_ids() -> anyhow::Result<()> {
+    let client_config = AttestationHandlerConfig {
+        peer_verifiers: BTreeMap::from([
+            (
+                MATCHED_ATTESTER_ID1.to_string(),
+                PeerAttestationVerifier {
+                    verifier: create_passing_mock_verifier(),
+                    binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                    require_endorsements: true,
+                    require_evidence: true,
+                },
+            ),
+            (
+                MATCHED_ATTESTER_ID2.to_string(),
+                PeerAttestationVerifier {
+                    verifier: create_passing_mock_verifier(),
+                    binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                    require_endorsements: true,
+                    require_evidence: true,
+                },
+            ),
+        ]),
+        ..Default::default()
+    };
+
+    let client_attestation_provider = ClientAttestationHandler::create(client_config)?;
+
+    assert_that!(
+        client_attestation_provider.expected_peer_attestation_ids().collect::<Vec<_>>(),
+        elements_are![eq(&MATCHED_ATTESTER_ID1), eq(&MATCHED_ATTESTER_ID2)]
+    );
+
+    Ok(())
+}
+
 #[googletest::test]
 fn client_with_empty_peer_verifiers_fails() -> anyhow::Result<()> {
     let client_config = AttestationHandlerConfig::default();
@@ -707,6 +1240,8 @@ fn client_failed_evidence_verification_fails() -> anyhow::Result<()> {
             PeerAttestationVerifier {
                 verifier: create_failing_mock_verifier(),
                 binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
             },
         )]),
         peer_assertion_verifiers: BTreeMap::from([(
@@ -761,6 +1296,8 @@ fn client_failed_assertion_verification_fails() -> anyhow::Result<()> {
             PeerAttestationVerifier {
                 verifier: create_passing_mock_verifier(),
                 binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
             },
         )]),
         peer_assertion_verifiers: BTreeMap::from([(
@@ -815,6 +1352,8 @@ fn server_failed_evidence_verification_fails() -> anyhow::Result<()> {
             PeerAttestationVerifier {
                 verifier: create_failing_mock_verifier(),
                 binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
             },
         )]),
         peer_assertion_verifiers: BTreeMap::from([(
@@ -869,6 +1408,8 @@ fn server_failed_assertion_verification_fails() -> anyhow::Result<()> {
             PeerAttestationVerifier {
                 verifier: create_passing_mock_verifier(),
                 binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
             },
         )]),
         peer_assertion_verifiers: BTreeMap::from([(
@@ -925,6 +1466,8 @@ fn client_aggregated_attestation_succeeds() -> anyhow::Result<()> {
                 PeerAttestationVerifier {
                     verifier: create_passing_mock_verifier(),
                     binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                    require_endorsements: true,
+                    require_evidence: true,
                 },
             ),
             (
@@ -932,6 +1475,8 @@ fn client_aggregated_attestation_succeeds() -> anyhow::Result<()> {
                 PeerAttestationVerifier {
                     verifier: create_passing_mock_verifier(),
                     binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                    require_endorsements: true,
+                    require_evidence: true,
                 },
             ),
         ]),
@@ -1032,6 +1577,8 @@ fn server_aggregated_attestation_succeeds() -> anyhow::Result<()> {
                 PeerAttestationVerifier {
                     verifier: create_passing_mock_verifier(),
                     binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                    require_endorsements: true,
+                    require_evidence: true,
                 },
             ),
             (
@@ -1039,6 +1586,8 @@ fn server_aggregated_attestation_succeeds() -> anyhow::Result<()> {
                 PeerAttestationVerifier {
                     verifier: create_passing_mock_verifier(),
                     binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                    require_endorsements: true,
+                    require_evidence: true,
                 },
             ),
         ]),
@@ -1139,6 +1688,8 @@ fn client_one_failed_evidence_verifier_aggregated_attestation_fails() -> anyhow:
                 PeerAttestationVerifier {
                     verifier: create_passing_mock_verifier(),
                     binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                    require_endorsements: true,
+                    require_evidence: true,
                 },
             ),
             (
@@ -1146,6 +1697,8 @@ fn client_one_failed_evidence_verifier_aggregated_attestation_fails() -> anyhow:
                 PeerAttestationVerifier {
                     verifier: create_failing_mock_verifier(),
                     binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                    require_endorsements: true,
+                    require_evidence: true,
                 },
             ),
             (
@@ -1153,6 +1706,8 @@ fn client_one_failed_evidence_verifier_aggregated_attestation_fails() -> anyhow:
                 PeerAttestationVerifier {
                     verifier: create_failing_mock_verifier(),
                     binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                    require_endorsements: true,
+                    require_evidence: true,
                 },
             ),
         ]),
@@ -1242,6 +1797,8 @@ fn client_one_failed_assertion_verifier_aggregated_attestation_fails() -> anyhow
                 PeerAttestationVerifier {
                     verifier: create_passing_mock_verifier(),
                     binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                    require_endorsements: true,
+                    require_evidence: true,
                 },
             ),
             (
@@ -1249,6 +1806,8 @@ fn client_one_failed_assertion_verifier_aggregated_attestation_fails() -> anyhow
                 PeerAttestationVerifier {
                     verifier: create_passing_mock_verifier(),
                     binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                    require_endorsements: true,
+                    require_evidence: true,
                 },
             ),
             (
@@ -1256,6 +1815,8 @@ fn client_one_failed_assertion_verifier_aggregated_attestation_fails() -> anyhow
                 PeerAttestationVerifier {
                     verifier: create_failing_mock_verifier(),
                     binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                    require_endorsements: true,
+                    require_evidence: true,
                 },
             ),
         ]),
@@ -1342,6 +1903,8 @@ fn server_one_failed_evidence_verifier_aggregated_attestation_fails() -> anyhow:
                 PeerAttestationVerifier {
                     verifier: create_passing_mock_verifier(),
                     binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                    require_endorsements: true,
+                    require_evidence: true,
                 },
             ),
             (
@@ -1349,6 +1912,8 @@ fn server_one_failed_evidence_verifier_aggregated_attestation_fails() -> anyhow:
                 PeerAttestationVerifier {
                     verifier: create_failing_mock_verifier(),
                     binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                    require_endorsements: true,
+                    require_evidence: true,
                 },
             ),
             (
@@ -1356,6 +1921,8 @@ fn server_one_failed_evidence_verifier_aggregated_attestation_fails() -> anyhow:
                 PeerAttestationVerifier {
                     verifier: create_failing_mock_verifier(),
                     binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                    require_endorsements: true,
+                    require_evidence: true,
                 },
             ),
         ]),
@@ -1445,6 +2012,8 @@ fn server_one_failed_assertion_verifier_aggregated_attestation_fails() -> anyhow
                 PeerAttestationVerifier {
                     verifier: create_passing_mock_verifier(),
                     binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                    require_endorsements: true,
+                    require_evidence: true,
                 },
             ),
             (
@@ -1452,6 +2021,8 @@ fn server_one_failed_assertion_verifier_aggregated_attestation_fails() -> anyhow
                 PeerAttestationVerifier {
                     verifier: create_passing_mock_verifier(),
                     binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                    require_endorsements: true,
+                    require_evidence: true,
                 },
             ),
             (
@@ -1459,6 +2030,8 @@ fn server_one_failed_assertion_verifier_aggregated_attestation_fails() -> anyhow
                 PeerAttestationVerifier {
                     verifier: create_failing_mock_verifier(),
                     binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                    require_endorsements: true,
+                    require_evidence: true,
                 },
             ),
         ]),
@@ -1542,6 +2115,8 @@ fn client_unmatched_verifier_attestation_fails() -> anyhow::Result<()> {
             PeerAttestationVerifier {
                 verifier: create_passing_mock_verifier(),
                 binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
             },
         )]),
         ..Default::default()
@@ -1573,6 +2148,8 @@ fn server_unmatched_verifier_attestation_fails() -> anyhow::Result<()> {
             PeerAttestationVerifier {
                 verifier: create_passing_mock_verifier(),
                 binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
             },
         )]),
         ..Default::default()
@@ -1659,6 +2236,8 @@ fn client_additional_attestation_passes() -> anyhow::Result<()> {
             PeerAttestationVerifier {
                 verifier: create_passing_mock_verifier(),
                 binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
             },
         )]),
         peer_assertion_verifiers: BTreeMap::from([(
@@ -1711,6 +2290,8 @@ fn server_additional_attestation_passes() -> anyhow::Result<()> {
             PeerAttestationVerifier {
                 verifier: create_passing_mock_verifier(),
                 binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
             },
         )]),
         peer_assertion_verifiers: BTreeMap::from([(
@@ -1762,6 +2343,8 @@ fn client_receives_additional_attestations() -> anyhow::Result<()> {
             PeerAttestationVerifier {
                 verifier: create_passing_mock_verifier(),
                 binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
             },
         )]),
         ..Default::default()
@@ -1803,6 +2386,8 @@ fn server_receives_additional_attestations() -> anyhow::Result<()> {
             PeerAttestationVerifier {
                 verifier: create_passing_mock_verifier(),
                 binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
             },
         )]),
         ..Default::default()
@@ -1860,6 +2445,8 @@ fn pairwise_bidirectional_attestation_succeeds() -> anyhow::Result<()> {
             PeerAttestationVerifier {
                 verifier: create_passing_mock_verifier(),
                 binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
             },
         )]),
         peer_assertion_verifiers: BTreeMap::from([(
@@ -1888,6 +2475,8 @@ fn pairwise_bidirectional_attestation_succeeds() -> anyhow::Result<()> {
             PeerAttestationVerifier {
                 verifier: create_passing_mock_verifier(),
                 binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
             },
         )]),
         peer_assertion_verifiers: BTreeMap::from([(
@@ -1935,6 +2524,8 @@ fn pairwise_bidirectional_attestation_fails_on_evidence() -> anyhow::Result<()>
             PeerAttestationVerifier {
                 verifier: create_failing_mock_verifier(),
                 binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
             },
         )]),
         peer_assertion_verifiers: BTreeMap::from([(
@@ -1962,6 +2553,8 @@ fn pairwise_bidirectional_attestation_fails_on_evidence() -> anyhow::Result<()>
             PeerAttestationVerifier {
                 verifier: create_failing_mock_verifier(),
                 binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
             },
         )]),
         peer_assertion_verifiers: BTreeMap::from([(
@@ -2028,6 +2621,8 @@ fn pairwise_bidirectional_attestation_fails_on_assertion() -> anyhow::Result<()>
             PeerAttestationVerifier {
                 verifier: create_passing_mock_verifier(),
                 binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
             },
         )]),
         peer_assertion_verifiers: BTreeMap::from([(
@@ -2055,6 +2650,8 @@ fn pairwise_bidirectional_attestation_fails_on_assertion() -> anyhow::Result<()>
             PeerAttestationVerifier {
                 verifier: create_passing_mock_verifier(),
                 binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
             },
         )]),
         peer_assertion_verifiers: BTreeMap::from([(
@@ -2098,6 +2695,113 @@ fn pairwise_bidirectional_attestation_fails_on_assertion() -> anyhow::Result<()>
     Ok(())
 }
 
+const CHALLENGE_ASSERTION_ID: &str = "CHALLENGE_ASSERTION_ID";
+const RESPONSE_ASSERTION_ID: &str = "RESPONSE_ASSERTION_ID";
+
+/// Returns the configuration of a client that expects a challenge assertion
+/// verified by `challenge_verifier`, followed by a response assertion.
+///
+/// The [`Any`] aggregator is used so that only the ordering constraint can
+/// make an attestation with a verified response fail.
+fn ordered_assertions_client_config(
+    challenge_verifier: Arc<dyn AssertionVerifier>,
+) -> AttestationHandlerConfig {
+    let response = Assertion { content: "response".as_bytes().to_vec() };
+    AttestationHandlerConfig {
+        peer_assertion_verifiers: BTreeMap::from([
+            (CHALLENGE_ASSERTION_ID.to_string(), challenge_verifier),
+            (RESPONSE_ASSERTION_ID.to_string(), create_passing_mock_assertion_verifier(response)),
+        ]),
+        assertion_attestation_aggregator: Box::new(Any {}),
+        peer_assertion_order: vec![
+            CHALLENGE_ASSERTION_ID.to_string(),
+            RESPONSE_ASSERTION_ID.to_string(),
+        ],
+        ..Default::default()
+    }
+}
+
+fn put_assertions(
+    config: AttestationHandlerConfig,
+    assertion_ids: &[&str],
+) -> anyhow::Result<PeerAttestationVerdict> {
+    let mut client_attestation_provider = ClientAttestationHandler::create(config)?;
+    let attest_response = AttestResponse {
+        assertions: assertion_ids
+            .iter()
+            .map(|id| (id.to_string(), Assertion { content: id.as_bytes().to_vec() }))
+            .collect(),
+        ..Default::default()
+    };
+    assert_that!(client_attestation_provider.put_incoming_message(attest_response), ok(some(())));
+    Ok(client_attestation_provider.take_attestation_state()?.peer_attestation_verdict)
+}
+
+#[googletest::test]
+fn client_ordered_assertions_pass() -> anyhow::Result<()> {
+    let challenge = Assertion { content: "challenge".as_bytes().to_vec() };
+    let config =
+        ordered_assertions_client_config(create_passing_mock_assertion_verifier(challenge));
+
+    let verdict = put_assertions(config, &[RESPONSE_ASSERTION_ID, CHALLENGE_ASSERTION_ID])?;
+
+    assert_that!(verdict, matches_pattern!(PeerAttestationVerdict::AttestationPassed { .. }));
+    Ok(())
+}
+
+#[googletest::test]
+fn client_assertion_chain_prefix_passes() -> anyhow::Result<()> {
+    let challenge = Assertion { content: "challenge".as_bytes().to_vec() };
+    let config =
+        ordered_assertions_client_config(create_passing_mock_assertion_verifier(challenge));
+
+    let verdict = put_assertions(config, &[CHALLENGE_ASSERTION_ID])?;
+
+    assert_that!(verdict, matches_pattern!(PeerAttestationVerdict::AttestationPassed { .. }));
+    Ok(())
+}
+
+#[googletest::test]
+fn client_assertion_without_predecessor_fails() -> anyhow::Result<()> {
+    let challenge = Assertion { content: "challenge".as_bytes().to_vec() };
+    let config =
+        ordered_assertions_client_config(create_passing_mock_assertion_verifier(challenge));
+
+    let verdict = put_assertions(config, &[RESPONSE_ASSERTION_ID])?;
+
+    assert_that!(
+        verdict,
+        matches_pattern!(PeerAttestationVerdict::AttestationFailed {
+            reason: eq("Assertion verification failed: AssertionOrderViolation"),
+            assertion_verification_results: unordered_elements_are!(
+                (eq(CHALLENGE_ASSERTION_ID), matches_pattern!(AssertionVerifierResult::Missing)),
+                (
+                    eq(RESPONSE_ASSERTION_ID),
+                    matches_pattern!(AssertionVerifierResult::Success { .. })
+                ),
+            ),
+            ..
+        })
+    );
+    Ok(())
+}
+
+#[googletest::test]
+fn client_assertion_after_failed_predecessor_fails() -> anyhow::Result<()> {
+    let config = ordered_assertions_client_config(create_failing_mock_assertion_verifier());
+
+    let verdict = put_assertions(config, &[CHALLENGE_ASSERTION_ID, RESPONSE_ASSERTION_ID])?;
+
+    assert_that!(
+        verdict,
+        matches_pattern!(PeerAttestationVerdict::AttestationFailed {
+            reason: eq("Assertion verification failed: AssertionOrderViolation"),
+            ..
+        })
+    );
+    Ok(())
+}
+
 #[googletest::test]
 fn pairwise_compatible_attestation_types_verification_succeeds() -> anyhow::Result<()> {
     let client_config = AttestationHandlerConfig {
@@ -2114,6 +2818,8 @@ fn pairwise_compatible_attestation_types_verification_succeeds() -> anyhow::Resu
             PeerAttestationVerifier {
                 verifier: create_passing_mock_verifier(),
                 binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
             },
         )]),
         ..Default::default()
@@ -2152,6 +2858,8 @@ fn pairwise_incompatible_attestation_types_verification_fails() -> anyhow::Resul
             PeerAttestationVerifier {
                 verifier: create_passing_mock_verifier(),
                 binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
             },
         )]),
         ..Default::default()
@@ -2171,6 +2879,8 @@ fn pairwise_incompatible_attestation_types_verification_fails() -> anyhow::Resul
             PeerAttestationVerifier {
                 verifier: create_passing_mock_verifier(),
                 binding_verifier_provider: create_mock_session_binding_verifier_provider(),
+                require_endorsements: true,
+                require_evidence: true,
             },
         )]),
         ..Default::default()
@@ -2192,3 +2902,50 @@ fn pairwise_incompatible_attestation_types_verification_fails() -> anyhow::Resul
 
     Ok(())
 }
+
+fn attestation_results(status: attestation_results::Status) -> AttestationResults {
+    AttestationResults { status: status.into(), ..Default::default() }
+}
+
+#[googletest::test]
+fn verifier_result_success_accessors() {
+    let evidence = endorsed_evidence_with_report_size(1);
+    let results = attestation_results(attestation_results::Status::Success);
+    let verifier_result =
+        VerifierResult::Success { evidence: evidence.clone(), result: results.clone() };
+
+    assert_that!(verifier_result.is_success(), eq(true));
+    assert_that!(verifier_result.evidence(), some(eq(&evidence)));
+    assert_that!(verifier_result.results(), some(eq(&results)));
+}
+
+#[googletest::test]
+fn verifier_result_failure_accessors() {
+    let evidence = endorsed_evidence_with_report_size(1);
+    let results = attestation_results(attestation_results::Status::GenericFailure);
+    let verifier_result =
+        VerifierResult::Failure { evidence: evidence.clone(), result: results.clone() };
+
+    assert_that!(verifier_result.is_success(), eq(false));
+    assert_that!(verifier_result.evidence(), some(eq(&evidence)));
+    assert_that!(verifier_result.results(), some(eq(&results)));
+}
+
+#[googletest::test]
+fn verifier_result_missing_accessors() {
+    let verifier_result = VerifierResult::Missing;
+
+    assert_that!(verifier_result.is_success(), eq(false));
+    assert_that!(verifier_result.evidence(), none());
+    assert_that!(verifier_result.results(), none());
+}
+
+#[googletest::test]
+fn verifier_result_unverified_accessors() {
+    let evidence = endorsed_evidence_with_report_size(1);
+    let verifier_result = VerifierResult::Unverified { evidence: evidence.clone() };
+
+    assert_that!(verifier_result.is_success(), eq(false));
+    assert_that!(verifier_result.evidence(), some(eq(&evidence)));
+    assert_that!(verifier_result.results(), none());
+}
@@ -20,3 +20,4 @@ mod handshake_tests;
 mod proptests;
 mod session_binding_tests;
 mod session_tests;
+mod timestamp_assertion_tests;
@@ -15,6 +15,7 @@
 use core::cell::RefCell;
 use std::{
     boxed::Box,
+    collections::BTreeMap,
     string::{String, ToString},
     sync::{Arc, Mutex},
     vec::Vec,
@@ -30,21 +31,21 @@ use oak_crypto::{
     verifier::Verifier,
 };
 use oak_proto_rust::oak::{
-    attestation::v1::{attestation_results, AttestationResults, Endorsements, Evidence},
+    attestation::v1::{attestation_results, AttestationResults, Endorsements, EventLog, Evidence},
     session::v1::{
         session_request::Request, session_response::Response, Assertion, EndorsedEvidence,
         PlaintextMessage, SessionBinding, SessionRequest, SessionResponse,
     },
 };
 use oak_session::{
-    aggregators::PassThrough,
-    attestation::AttestationType,
+    aggregators::{AggregatedVerificationError, LegacyVerifierResultsAggregator, PassThrough},
+    attestation::{AttestationType, VerifierResult},
     channel::{SessionChannel, SessionInitializer},
-    config::SessionConfig,
+    config::{SessionConfig, SessionConfigError},
     generator::{AssertionGenerationError, AssertionGenerator, BindableAssertion},
     handshake::HandshakeType,
     key_extractor::KeyExtractor,
-    session::{AttestationEvidence, AttestationPublisher},
+    session::{AttestationEvidence, AttestationPublisher, BidirectionalAttestationStatus},
     session_binding::{SessionBinder, SessionBindingVerifier, SessionBindingVerifierProvider},
     verifier::{AssertionVerificationError, AssertionVerifier, VerifiedAssertion},
     ClientSession, ProtocolEngine, ServerSession, Session,
@@ -457,6 +458,132 @@ fn pairwise_nn_peer_self_succeeds() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// A legacy aggregator that rejects every set of results, to check that a
+/// custom aggregator configured on the session is the one being used.
+struct RejectingLegacyAggregator {}
+
+impl LegacyVerifierResultsAggregator for RejectingLegacyAggregator {
+    fn process_assertion_results(
+        &self,
+        _results: &BTreeMap<String, VerifierResult>,
+    ) -> Result<(), AggregatedVerificationError> {
+        Err(AggregatedVerificationError::ConfigurationError)
+    }
+}
+
+#[googletest::test]
+fn pairwise_nn_peer_self_custom_legacy_aggregator_rejects() -> anyhow::Result<()> {
+    let client_config =
+        SessionConfig::builder(AttestationType::PeerUnidirectional, HandshakeType::NoiseNN)
+            .add_peer_verifier_with_key_extractor(
+                MATCHED_ATTESTER_ID1.to_string(),
+                create_passing_mock_verifier(),
+                create_mock_key_extractor(),
+            )
+            .set_legacy_attestation_results_aggregator(Box::new(RejectingLegacyAggregator {}))
+            .build();
+    let server_config =
+        SessionConfig::builder(AttestationType::SelfUnidirectional, HandshakeType::NoiseNN)
+            .add_self_attester(MATCHED_ATTESTER_ID1.to_string(), create_mock_attester())
+            .add_self_endorser(MATCHED_ATTESTER_ID1.to_string(), create_mock_endorser())
+            .add_session_binder(MATCHED_ATTESTER_ID1.to_string(), create_mock_binder())
+            .build();
+
+    let mut client_session = ClientSession::create(client_config)?;
+    let mut server_session = ServerSession::create(server_config)?;
+
+    let attest_request = client_session.get_outgoing_message()?.context("no attest request")?;
+    server_session.put_incoming_message(attest_request)?;
+    let attest_response = server_session.get_outgoing_message()?.context("no attest response")?;
+
+    // The same exchange succeeds with the default aggregator (see
+    // `pairwise_nn_peer_self_succeeds`), so the failure comes from the custom
+    // one.
+    assert_that!(client_session.put_incoming_message(attest_response), err(anything()));
+    assert_that!(client_session.is_open(), eq(false));
+
+    Ok(())
+}
+
+#[googletest::test]
+fn client_session_reports_expected_peer_attestation_ids() -> anyhow::Result<()> {
+    let client_config =
+        SessionConfig::builder(AttestationType::PeerUnidirectional, HandshakeType::NoiseNN)
+            .add_peer_verifier_with_key_extractor(
+                MATCHED_ATTESTER_ID1.to_string(),
+                create_passing_mock_verifier(),
+                create_mock_key_extractor(),
+            )
+            .build();
+    let server_config =
+        SessionConfig::builder(AttestationType::SelfUnidirectional, HandshakeType::NoiseNN)
+            .add_self_attester(MATCHED_ATTESTER_ID1.to_string(), create_mock_attester())
+            .add_self_endorser(MATCHED_ATTESTER_ID1.to_string(), create_mock_endorser())
+            .add_session_binder(MATCHED_ATTESTER_ID1.to_string(), create_mock_binder())
+            .build();
+
+    let mut client_session = ClientSession::create(client_config)?;
+    let mut server_session = ServerSession::create(server_config)?;
+
+    assert_that!(
+        client_session.expected_peer_attestation_ids().collect::<Vec<_>>(),
+        elements_are![eq(&MATCHED_ATTESTER_ID1)]
+    );
+
+    do_attest(&mut client_session, &mut server_session)?;
+    do_handshake(&mut client_session, &mut server_session, HandshakeFollowup::NotExpected)?;
+
+    // The IDs remain available once the session is open.
+    assert_that!(
+        client_session.expected_peer_attestation_ids().collect::<Vec<_>>(),
+        elements_are![eq(&MATCHED_ATTESTER_ID1)]
+    );
+
+    Ok(())
+}
+
+#[googletest::test]
+fn client_session_reports_nn_handshake_type() -> anyhow::Result<()> {
+    let client_config =
+        SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN).build();
+    let server_config =
+        SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN).build();
+
+    let mut client_session = ClientSession::create(client_config)?;
+    let mut server_session = ServerSession::create(server_config)?;
+
+    assert_that!(client_session.handshake_type(), err(anything()));
+
+    do_attest(&mut client_session, &mut server_session)?;
+    do_handshake(&mut client_session, &mut server_session, HandshakeFollowup::NotExpected)?;
+
+    assert_that!(client_session.handshake_type(), ok(eq(&HandshakeType::NoiseNN)));
+    assert_that!(HandshakeType::NoiseNN.noise_protocol_name(), eq("Noise_NN_P256_AESGCM_SHA256"));
+
+    Ok(())
+}
+
+#[googletest::test]
+fn client_session_reports_nk_handshake_type() -> anyhow::Result<()> {
+    let identity_key = Box::new(IdentityKey::generate());
+    let client_config = SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNK)
+        .set_peer_static_public_key(identity_key.get_public_key().unwrap().as_slice())
+        .build();
+    let server_config = SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNK)
+        .set_self_static_private_key(identity_key)
+        .build();
+
+    let mut client_session = ClientSession::create(client_config)?;
+    let mut server_session = ServerSession::create(server_config)?;
+
+    do_attest(&mut client_session, &mut server_session)?;
+    do_handshake(&mut client_session, &mut server_session, HandshakeFollowup::NotExpected)?;
+
+    assert_that!(client_session.handshake_type(), ok(eq(&HandshakeType::NoiseNK)));
+
+    Ok(())
+}
+
 #[googletest::test]
 fn pairwise_nn_self_peer_broken() -> anyhow::Result<()> {
     let client_config =
@@ -603,6 +730,115 @@ fn pairwise_nn_bidirectional_succeeds() -> anyhow::Result<()> {
     Ok(())
 }
 
+fn bidirectional_config(self_id: &str, peer_id: &str) -> SessionConfig {
+    SessionConfig::builder(AttestationType::Bidirectional, HandshakeType::NoiseNN)
+        .add_self_attester(self_id.to_string(), create_mock_attester())
+        .add_self_endorser(self_id.to_string(), create_mock_endorser())
+        .add_session_binder(self_id.to_string(), create_mock_binder())
+        .add_peer_verifier_with_key_extractor(
+            peer_id.to_string(),
+            create_passing_mock_verifier(),
+            create_mock_key_extractor(),
+        )
+        .build()
+}
+
+#[googletest::test]
+fn pairwise_nn_bidirectional_status_is_fully_verified() -> anyhow::Result<()> {
+    let mut client_session =
+        ClientSession::create(bidirectional_config(MATCHED_ATTESTER_ID2, MATCHED_ATTESTER_ID1))?;
+    let mut server_session =
+        ServerSession::create(bidirectional_config(MATCHED_ATTESTER_ID1, MATCHED_ATTESTER_ID2))?;
+
+    do_attest(&mut client_session, &mut server_session)?;
+    do_handshake(&mut client_session, &mut server_session, HandshakeFollowup::Expected)?;
+
+    let fully_verified = BidirectionalAttestationStatus {
+        peer_verified_by_self: true,
+        self_not_rejected_by_peer: true,
+    };
+    assert_that!(client_session.get_bidirectional_attestation_status(), ok(eq(&fully_verified)));
+    assert_that!(server_session.get_bidirectional_attestation_status(), ok(eq(&fully_verified)));
+    assert_that!(fully_verified.is_fully_verified(), eq(true));
+
+    Ok(())
+}
+
+#[googletest::test]
+fn pairwise_nn_bidirectional_status_unavailable_if_client_rejected() -> anyhow::Result<()> {
+    let client_config = bidirectional_config(MATCHED_ATTESTER_ID2, MATCHED_ATTESTER_ID1);
+    let server_config =
+        SessionConfig::builder(AttestationType::Bidirectional, HandshakeType::NoiseNN)
+            .add_self_attester(MATCHED_ATTESTER_ID1.to_string(), create_mock_attester())
+            .add_self_endorser(MATCHED_ATTESTER_ID1.to_string(), create_mock_endorser())
+            .add_session_binder(MATCHED_ATTESTER_ID1.to_string(), create_mock_binder())
+            .add_peer_verifier_with_key_extractor(
+                MATCHED_ATTESTER_ID2.to_string(),
+                create_passing_mock_verifier(),
+                create_mock_key_extractor(),
+            )
+            .set_legacy_attestation_results_aggregator(Box::new(RejectingLegacyAggregator {}))
+            .build();
+
+    let mut client_session = ClientSession::create(client_config)?;
+    let mut server_session = ServerSession::create(server_config)?;
+
+    let attest_request = client_session.get_outgoing_message()?.context("no attest request")?;
+    server_session.put_incoming_message(attest_request)?;
+    assert_that!(server_session.get_outgoing_message(), err(anything()));
+
+    // The client never gets a response, so neither side can report an outcome.
+    assert_that!(client_session.get_bidirectional_attestation_status(), err(anything()));
+    assert_that!(server_session.get_bidirectional_attestation_status(), err(anything()));
+
+    Ok(())
+}
+
+#[googletest::test]
+fn pairwise_nn_peer_self_status_is_one_sided() -> anyhow::Result<()> {
+    let client_config =
+        SessionConfig::builder(AttestationType::PeerUnidirectional, HandshakeType::NoiseNN)
+            .add_peer_verifier_with_key_extractor(
+                MATCHED_ATTESTER_ID1.to_string(),
+                create_passing_mock_verifier(),
+                create_mock_key_extractor(),
+            )
+            .build();
+    let server_config =
+        SessionConfig::builder(AttestationType::SelfUnidirectional, HandshakeType::NoiseNN)
+            .add_self_attester(MATCHED_ATTESTER_ID1.to_string(), create_mock_attester())
+            .add_self_endorser(MATCHED_ATTESTER_ID1.to_string(), create_mock_endorser())
+            .add_session_binder(MATCHED_ATTESTER_ID1.to_string(), create_mock_binder())
+            .build();
+
+    let mut client_session = ClientSession::create(client_config)?;
+    let mut server_session = ServerSession::create(server_config)?;
+
+    do_attest(&mut client_session, &mut server_session)?;
+    do_handshake(&mut client_session, &mut server_session, HandshakeFollowup::NotExpected)?;
+
+    let client_status = client_session.get_bidirectional_attestation_status()?;
+    let server_status = server_session.get_bidirectional_attestation_status()?;
+    assert_that!(
+        client_status,
+        eq(BidirectionalAttestationStatus {
+            peer_verified_by_self: true,
+            self_not_rejected_by_peer: false,
+        })
+    );
+    assert_that!(
+        server_status,
+        eq(BidirectionalAttestationStatus {
+            peer_verified_by_self: false,
+            self_not_rejected_by_peer: true,
+        })
+    );
+    assert_that!(client_status.is_fully_verified(), eq(false));
+    assert_that!(server_status.is_fully_verified(), eq(false));
+
+    Ok(())
+}
+
 #[googletest::test]
 fn pairwise_nn_peer_self_succeeds_custom_session_binding_verifier() -> anyhow::Result<()> {
     let client_config =
@@ -686,6 +922,109 @@ fn get_peer_attestation_evidence() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[googletest::test]
+fn get_peer_attestation_results() -> anyhow::Result<()> {
+    let client_config =
+        SessionConfig::builder(AttestationType::PeerUnidirectional, HandshakeType::NoiseNN)
+            .add_peer_verifier_with_binding_verifier_provider(
+                MATCHED_ATTESTER_ID1.to_string(),
+                create_passing_mock_verifier(),
+                create_mock_session_binding_verifier_provider(),
+            )
+            .build();
+    let server_config =
+        SessionConfig::builder(AttestationType::SelfUnidirectional, HandshakeType::NoiseNN)
+            .add_self_attester(MATCHED_ATTESTER_ID1.to_string(), create_mock_attester())
+            .add_self_endorser(MATCHED_ATTESTER_ID1.to_string(), create_mock_endorser())
+            .add_session_binder(MATCHED_ATTESTER_ID1.to_string(), create_mock_binder())
+            .build();
+
+    let mut client_session = ClientSession::create(client_config)?;
+    let mut server_session = ServerSession::create(server_config)?;
+
+    assert_that!(client_session.get_peer_attestation_results(), err(anything()));
+
+    do_attest(&mut client_session, &mut server_session)?;
+
+    do_handshake(&mut client_session, &mut server_session, HandshakeFollowup::NotExpected)?;
+
+    assert_that!(
+        client_session.get_peer_attestation_results(),
+        ok(elements_are![(
+            eq(&MATCHED_ATTESTER_ID1.to_string()),
+            field!(&AttestationResults.status, eq(attestation_results::Status::Success as i32))
+        )])
+    );
+    // The client does not send any attestation evidence to the server.
+    assert_that!(server_session.get_peer_attestation_results(), ok(is_empty()));
+
+    Ok(())
+}
+
+/// Creates an attester whose evidence can be told apart by its `tag`.
+fn create_tagged_mock_attester(tag: &str) -> Box<dyn Attester> {
+    let evidence = tagged_evidence(tag);
+    let mut attester = MockTestAttester::new();
+    attester.expect_quote().returning(move || Ok(evidence.clone()));
+    Box::new(attester)
+}
+
+fn tagged_evidence(tag: &str) -> Evidence {
+    Evidence {
+        event_log: Some(EventLog { encoded_events: vec![tag.as_bytes().to_vec()] }),
+        ..Default::default()
+    }
+}
+
+#[googletest::test]
+fn get_peer_evidence_for_id() -> anyhow::Result<()> {
+    const UNVERIFIED_ATTESTER_ID: &str = "UNVERIFIED_ATTESTER_ID";
+    let client_config =
+        SessionConfig::builder(AttestationType::PeerUnidirectional, HandshakeType::NoiseNN)
+            .add_peer_verifier_with_key_extractor(
+                MATCHED_ATTESTER_ID1.to_string(),
+                create_passing_mock_verifier(),
+                create_mock_key_extractor(),
+            )
+            .add_peer_verifier_with_key_extractor(
+                MATCHED_ATTESTER_ID2.to_string(),
+                create_passing_mock_verifier(),
+                create_mock_key_extractor(),
+            )
+            .build();
+    let mut server_config_builder =
+        SessionConfig::builder(AttestationType::SelfUnidirectional, HandshakeType::NoiseNN);
+    for id in [MATCHED_ATTESTER_ID1, MATCHED_ATTESTER_ID2, UNVERIFIED_ATTESTER_ID] {
+        server_config_builder = server_config_builder
+            .add_self_attester(id.to_string(), create_tagged_mock_attester(id))
+            .add_self_endorser(id.to_string(), create_mock_endorser())
+            .add_session_binder(id.to_string(), create_mock_binder());
+    }
+
+    let mut client_session = ClientSession::create(client_config)?;
+    let mut server_session = ServerSession::create(server_config_builder.build())?;
+
+    assert_that!(client_session.get_peer_evidence_for_id(MATCHED_ATTESTER_ID1), none());
+
+    do_attest(&mut client_session, &mut server_session)?;
+    do_handshake(&mut client_session, &mut server_session, HandshakeFollowup::NotExpected)?;
+
+    for id in [MATCHED_ATTESTER_ID1, MATCHED_ATTESTER_ID2] {
+        assert_that!(
+            client_session.get_peer_evidence_for_id(id),
+            some(eq(&EndorsedEvidence {
+                evidence: Some(tagged_evidence(id)),
+                endorsements: Some(Endorsements { ..Default::default() })
+            }))
+        );
+    }
+    // The server sent this evidence, but the client has no verifier for it.
+    assert_that!(client_session.get_peer_evidence_for_id(UNVERIFIED_ATTESTER_ID), none());
+    assert_that!(client_session.get_peer_evidence_for_id("UNKNOWN_ATTESTER_ID"), none());
+
+    Ok(())
+}
+
 #[googletest::test]
 fn test_session_sendable() -> anyhow::Result<()> {
     fn foo<T: Send>(_: T) {}
@@ -950,3 +1289,127 @@ async fn channel_pair_test(
 
     server_join.await.context("joining server")?.context("server failing")
 }
+
+#[googletest::test]
+fn build_bidirectional_config_without_self_attestation_fails() {
+    let result = SessionConfig::builder(AttestationType::Bidirectional, HandshakeType::NoiseNN)
+        .add_peer_verifier(MATCHED_ATTESTER_ID1.to_string(), create_passing_mock_verifier())
+        .try_build();
+
+    assert_that!(
+        result.err(),
+        some(pat!(SessionConfigError::MissingSelfAttestation(eq(&AttestationType::Bidirectional))))
+    );
+}
+
+#[googletest::test]
+fn build_bidirectional_config_without_peer_verification_fails() {
+    let result = SessionConfig::builder(AttestationType::Bidirectional, HandshakeType::NoiseNN)
+        .add_self_attester(MATCHED_ATTESTER_ID1.to_string(), create_mock_attester())
+        .add_session_binder(MATCHED_ATTESTER_ID1.to_string(), create_mock_binder())
+        .try_build();
+
+    assert_that!(
+        result.err(),
+        some(pat!(SessionConfigError::MissingPeerVerification(eq(
+            &AttestationType::Bidirectional
+        ))))
+    );
+}
+
+#[googletest::test]
+fn build_self_unidirectional_config_without_self_attestation_fails() {
+    let result =
+        SessionConfig::builder(AttestationType::SelfUnidirectional, HandshakeType::NoiseNN)
+            .try_build();
+
+    assert_that!(
+        result.err(),
+        some(pat!(SessionConfigError::MissingSelfAttestation(eq(
+            &AttestationType::SelfUnidirectional
+        ))))
+    );
+}
+
+#[googletest::test]
+fn build_peer_unidirectional_config_without_peer_verification_fails() {
+    let result =
+        SessionConfig::builder(AttestationType::PeerUnidirectional, HandshakeType::NoiseNN)
+            .try_build();
+
+    assert_that!(
+        result.err(),
+        some(pat!(SessionConfigError::MissingPeerVerification(eq(
+            &AttestationType::PeerUnidirectional
+        ))))
+    );
+}
+
+#[googletest::test]
+fn build_config_with_assertions_only_succeeds() {
+    let assertion = Assertion { content: "test".as_bytes().to_vec() };
+    let result = SessionConfig::builder(AttestationType::Bidirectional, HandshakeType::NoiseNN)
+        .add_self_assertion_generator(
+            MATCHED_ATTESTER_ID1.to_string(),
+            create_mock_assertion_generator(assertion.clone()),
+        )
+        .add_peer_assertion_verifier(
+            MATCHED_ATTESTER_ID1.to_string(),
+            create_passing_mock_assertion_verifier(assertion),
+        )
+        .set_assertion_attestation_aggregator(Box::new(PassThrough {}))
+        .try_build();
+
+    assert_that!(result.err(), none());
+}
+
+#[googletest::test]
+fn build_config_with_assertion_order_without_verifier_fails() {
+    let assertion = Assertion { content: "test".as_bytes().to_vec() };
+    let result =
+        SessionConfig::builder(AttestationType::PeerUnidirectional, HandshakeType::NoiseNN)
+            .add_peer_assertion_verifier(
+                MATCHED_ATTESTER_ID1.to_string(),
+                create_passing_mock_assertion_verifier(assertion),
+            )
+            .set_assertion_attestation_aggregator(Box::new(PassThrough {}))
+            .set_peer_assertion_order(vec![
+                MATCHED_ATTESTER_ID1.to_string(),
+                MATCHED_ATTESTER_ID2.to_string(),
+            ])
+            .try_build();
+
+    assert_that!(
+        result.err(),
+        some(pat!(SessionConfigError::InvalidAssertionOrder(eq(MATCHED_ATTESTER_ID2))))
+    );
+}
+
+#[googletest::test]
+fn build_config_with_evidence_requirements_without_verifier_fails() {
+    let result =
+        SessionConfig::builder(AttestationType::PeerUnidirectional, HandshakeType::NoiseNN)
+            .add_peer_verifier(MATCHED_ATTESTER_ID1.to_string(), create_passing_mock_verifier())
+            .set_require_evidence(MATCHED_ATTESTER_ID1, false)
+            .set_require_endorsements(MATCHED_ATTESTER_ID2, false)
+            .try_build();
+
+    assert_that!(
+        result.err(),
+        some(pat!(SessionConfigError::MissingPeerVerifier(eq(MATCHED_ATTESTER_ID2))))
+    );
+}
+
+#[googletest::test]
+fn build_unattested_config_succeeds() {
+    let result =
+        SessionConfig::builder(AttestationType::Unattested, HandshakeType::NoiseNN).try_build();
+
+    assert_that!(result.err(), none());
+}
+
+#[googletest::test]
+#[should_panic(expected = "requires at least one peer verifier")]
+fn build_invalid_config_panics() {
+    SessionConfig::builder(AttestationType::PeerUnidirectional, HandshakeType::NoiseNN).build();
+}
@@ -0,0 +1,111 @@
+//
+// Copyright 2025 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::sync::Arc;
+
+use googletest::prelude::*;
+use oak_proto_rust::oak::session::v1::{Assertion, SessionBinding};
+use oak_session::{
+    generator::AssertionGenerator,
+    timestamp_assertion::{TimestampAssertionGenerator, TimestampAssertionVerifier},
+    verifier::{AssertionVerificationError, AssertionVerifier},
+};
+use oak_time::{clock::FixedClock, Duration, Instant};
+
+const GENERATION_TIME: Instant = Instant::from_unix_millis(1_750_000_000_000);
+const TOLERANCE: Duration = Duration::from_seconds(60);
+const BOUND_DATA: &[u8] = b"bound data";
+
+fn generate_assertion_at(time: Instant) -> Assertion {
+    let generator = TimestampAssertionGenerator::new(Arc::new(FixedClock::at_instant(time)));
+    generator.generate().expect("couldn't generate assertion").assertion().clone()
+}
+
+fn create_verifier_at(time: Instant) -> TimestampAssertionVerifier {
+    TimestampAssertionVerifier::new(Arc::new(FixedClock::at_instant(time)), TOLERANCE)
+}
+
+#[googletest::test]
+fn timestamp_assertion_round_trip_succeeds() -> anyhow::Result<()> {
+    let generator =
+        TimestampAssertionGenerator::new(Arc::new(FixedClock::at_instant(GENERATION_TIME)));
+    let bindable_assertion = generator.generate()?;
+    let binding = bindable_assertion.bind(BOUND_DATA)?;
+
+    let verified_assertion =
+        create_verifier_at(GENERATION_TIME).verify_assertion(bindable_assertion.assertion())?;
+
+    assert_that!(verified_assertion.assertion(), eq(bindable_assertion.assertion()));
+    assert_that!(verified_assertion.verify_binding(BOUND_DATA, &binding), ok(anything()));
+    Ok(())
+}
+
+#[googletest::test]
+fn timestamp_assertion_with_skew_within_tolerance_succeeds() {
+    let assertion = generate_assertion_at(GENERATION_TIME);
+
+    for verification_time in [
+        GENERATION_TIME - TOLERANCE,
+        GENERATION_TIME - Duration::from_seconds(1),
+        GENERATION_TIME + Duration::from_seconds(1),
+        GENERATION_TIME + TOLERANCE,
+    ] {
+        assert_that!(
+            create_verifier_at(verification_time).verify_assertion(&assertion),
+            ok(anything()),
+            "verification at {verification_time}"
+        );
+    }
+}
+
+#[googletest::test]
+fn timestamp_assertion_with_skew_beyond_tolerance_fails() {
+    let assertion = generate_assertion_at(GENERATION_TIME);
+
+    for verification_time in [
+        GENERATION_TIME - TOLERANCE - Duration::from_millis(1),
+        GENERATION_TIME + TOLERANCE + Duration::from_millis(1),
+    ] {
+        assert_that!(
+            create_verifier_at(verification_time).verify_assertion(&assertion),
+            err(matches_pattern!(AssertionVerificationError::GenericFailure { .. })),
+            "verification at {verification_time}"
+        );
+    }
+}
+
+#[googletest::test]
+fn timestamp_assertion_with_invalid_content_fails() {
+    let assertion = Assertion { content: b"not a timestamp".to_vec() };
+
+    assert_that!(
+        create_verifier_at(GENERATION_TIME).verify_assertion(&assertion),
+        err(matches_pattern!(AssertionVerificationError::GenericFailure { .. }))
+    );
+}
+
+#[googletest::test]
+fn timestamp_assertion_with_unexpected_binding_fails() -> anyhow::Result<()> {
+    let assertion = generate_assertion_at(GENERATION_TIME);
+    let verified_assertion = create_verifier_at(GENERATION_TIME).verify_assertion(&assertion)?;
+
+    assert_that!(
+        verified_assertion
+            .verify_binding(BOUND_DATA, &SessionBinding { binding: b"binding".to_vec() }),
+        err(matches_pattern!(AssertionVerificationError::BindingVerificationFailure { .. }))
+    );
+    Ok(())
+}
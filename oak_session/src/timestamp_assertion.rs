@@ -0,0 +1,143 @@
+//
+// Copyright 2025 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Provides an assertion that carries the time at which it was generated.
+//!
+//! The `TimestampAssertionGenerator` embeds the current time of its clock,
+//! encoded as a `google.protobuf.Timestamp`, into the assertion content. The
+//! `TimestampAssertionVerifier` accepts the assertion only if that time is
+//! within a configured tolerance of its own clock, which gives the peer a
+//! simple way to reject replayed attestation messages.
+//!
+//! The timestamp itself is not signed, so its session binding is empty. It is
+//! still covered by the bindings of the other evidence and assertions exchanged
+//! in the same attestation step, since their bound data includes a hash of the
+//! attestation messages.
+
+use alloc::{boxed::Box, string::ToString, sync::Arc};
+
+use oak_proto_rust::oak::session::v1::{Assertion, SessionBinding};
+use oak_time::{Clock, Duration, Instant};
+use prost::Message;
+
+use crate::{
+    generator::{AssertionGenerationError, AssertionGenerator, BindableAssertion},
+    verifier::{AssertionVerificationError, AssertionVerifier, VerifiedAssertion},
+};
+
+/// Generates assertions containing the current time of the provided clock.
+pub struct TimestampAssertionGenerator {
+    clock: Arc<dyn Clock>,
+}
+
+impl TimestampAssertionGenerator {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self { clock }
+    }
+}
+
+impl AssertionGenerator for TimestampAssertionGenerator {
+    fn generate(&self) -> Result<Box<dyn BindableAssertion>, AssertionGenerationError> {
+        let timestamp = self.clock.get_time().into_timestamp();
+        Ok(Box::new(TimestampAssertion {
+            assertion: Assertion { content: timestamp.encode_to_vec() },
+        }))
+    }
+}
+
+struct TimestampAssertion {
+    assertion: Assertion,
+}
+
+impl BindableAssertion for TimestampAssertion {
+    fn assertion(&self) -> &Assertion {
+        &self.assertion
+    }
+
+    fn bind(&self, _bound_data: &[u8]) -> Result<SessionBinding, AssertionGenerationError> {
+        Ok(SessionBinding::default())
+    }
+}
+
+/// Verifies that timestamp assertions are within `tolerance` of the current
+/// time of the provided clock, in either direction.
+pub struct TimestampAssertionVerifier {
+    clock: Arc<dyn Clock>,
+    tolerance: Duration,
+}
+
+impl TimestampAssertionVerifier {
+    pub fn new(clock: Arc<dyn Clock>, tolerance: Duration) -> Self {
+        Self { clock, tolerance }
+    }
+}
+
+impl AssertionVerifier for TimestampAssertionVerifier {
+    fn verify_assertion(
+        &self,
+        assertion: &Assertion,
+    ) -> Result<Box<dyn VerifiedAssertion>, AssertionVerificationError> {
+        let timestamp =
+            prost_types::Timestamp::decode(assertion.content.as_slice()).map_err(|err| {
+                AssertionVerificationError::GenericFailure {
+                    error_msg: format!("couldn't decode timestamp assertion: {err}"),
+                }
+            })?;
+        let timestamp = Instant::from(timestamp);
+        let now = self.clock.get_time();
+        if timestamp < now - self.tolerance || timestamp > now + self.tolerance {
+            return Err(AssertionVerificationError::GenericFailure {
+                error_msg: format!(
+                    "timestamp {timestamp} is not within the tolerance of the current time {now}"
+                ),
+            });
+        }
+        Ok(Box::new(VerifiedTimestampAssertion { assertion: assertion.clone(), timestamp }))
+    }
+}
+
+/// A timestamp assertion that has been checked for freshness.
+#[derive(Debug)]
+pub struct VerifiedTimestampAssertion {
+    assertion: Assertion,
+    timestamp: Instant,
+}
+
+impl VerifiedTimestampAssertion {
+    /// Returns the time at which the peer generated the assertion.
+    pub fn timestamp(&self) -> Instant {
+        self.timestamp
+    }
+}
+
+impl VerifiedAssertion for VerifiedTimestampAssertion {
+    fn assertion(&self) -> &Assertion {
+        &self.assertion
+    }
+
+    fn verify_binding(
+        &self,
+        _bound_data: &[u8],
+        binding: &SessionBinding,
+    ) -> Result<(), AssertionVerificationError> {
+        if !binding.binding.is_empty() {
+            return Err(AssertionVerificationError::BindingVerificationFailure {
+                error_msg: "unexpected non-empty timestamp assertion binding".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
@@ -82,7 +82,8 @@ impl WasmClientSession {
             AttestationType::from(attestation_type),
             HandshakeType::from(handshake_type),
         )
-        .build();
+        .try_build()
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
         let inner = ClientSession::create(config).map_err(|e| JsValue::from_str(&e.to_string()))?;
 
         Ok(WasmClientSession { inner })
@@ -77,6 +77,26 @@ impl<'a> TdxQuoteWrapper<'a> {
         Self { quote_bytes }
     }
 
+    /// Creates a wrapper after checking that every structure in the quote can
+    /// be parsed from the untrusted bytes.
+    ///
+    /// This covers the quote data, the signature data and, if present, the
+    /// Quoting Enclave report body. Malformed input of any length results in
+    /// an error rather than a panic, which makes this a suitable entry point
+    /// for fuzzing. Bytes following the signature data are ignored, as quotes
+    /// are commonly padded.
+    pub fn try_parse(quote_bytes: &'a [u8]) -> Result<Self, TdxQuoteError> {
+        let wrapper = Self::new(quote_bytes);
+        wrapper.parse_quote()?;
+        let signature_data = wrapper.parse_signature_data()?;
+        if let QeCertificationData::QeReportCertificationData(report_certification) =
+            signature_data.certification_data
+        {
+            report_certification.parse_enclave_report_body()?;
+        }
+        Ok(wrapper)
+    }
+
     /// Gets the bytes the represent the TDX Quote data.
     pub fn get_quote_data_bytes(&self) -> Result<&'a [u8], TdxQuoteError> {
         let length = QUOTE_HEADER_SIZE + QUOTE_BODY_SIZE;
@@ -130,6 +150,15 @@ impl<'a> TdxQuoteWrapper<'a> {
     }
 }
 
+/// Takes a fixed-size array reference from the start of a byte slice.
+fn take_array<const N: usize>(bytes: &[u8]) -> nom::IResult<&[u8], &[u8; N]> {
+    let (rest, taken) = take(N)(bytes)?;
+    let array = taken
+        .try_into()
+        .map_err(|_| nom::Err::Failure(nom::error::Error::new(bytes, ErrorKind::Eof)))?;
+    Ok((rest, array))
+}
+
 bitflags! {
     /// The attributes of the Trust Domain.
     #[derive(Debug, Default)]
@@ -180,22 +209,10 @@ impl<'a> TdxQuoteHeader<'a> {
         let (rest, att_key_type) = le_u16(rest)?;
         let (rest, tee_type) = le_u32(rest)?;
         let (rest, reserved) = le_u32(rest)?;
-        let (rest, qe_vendor_id) = take(16usize)(rest)?;
-        let (rest, user_data) = take(20usize)(rest)?;
+        let (rest, qe_vendor_id) = take_array::<16>(rest)?;
+        let (rest, user_data) = take_array::<20>(rest)?;
 
-        Ok((
-            rest,
-            // Unwrapping is OK here since we know the slices must have the right length if we
-            // managed to get this far.
-            Self {
-                version,
-                att_key_type,
-                tee_type,
-                reserved,
-                qe_vendor_id: qe_vendor_id.try_into().unwrap(),
-                user_data: user_data.try_into().unwrap(),
-            },
-        ))
+        Ok((rest, Self { version, att_key_type, tee_type, reserved, qe_vendor_id, user_data }))
     }
 }
 
@@ -237,42 +254,40 @@ pub struct TdxQuoteBody<'a> {
 impl<'a> TdxQuoteBody<'a> {
     /// Parses a TDX quote body from a byte slice.
     fn parse(bytes: &'a [u8]) -> nom::IResult<&'a [u8], Self> {
-        let (rest, tee_tcb_svn) = take(16usize)(bytes)?;
-        let (rest, mr_seam) = take(48usize)(rest)?;
-        let (rest, mrsigner_seam) = take(48usize)(rest)?;
+        let (rest, tee_tcb_svn) = take_array::<16>(bytes)?;
+        let (rest, mr_seam) = take_array::<48>(rest)?;
+        let (rest, mrsigner_seam) = take_array::<48>(rest)?;
         let (rest, seam_attributes) = le_u64(rest)?;
         let (rest, td_attributes) = le_u64(rest)?;
         let (rest, xfam) = le_u64(rest)?;
-        let (rest, mr_td) = take(48usize)(rest)?;
-        let (rest, mr_config_id) = take(48usize)(rest)?;
-        let (rest, mr_owner) = take(48usize)(rest)?;
-        let (rest, mr_owner_config) = take(48usize)(rest)?;
-        let (rest, rtmr_0) = take(48usize)(rest)?;
-        let (rest, rtmr_1) = take(48usize)(rest)?;
-        let (rest, rtmr_2) = take(48usize)(rest)?;
-        let (rest, rtmr_3) = take(48usize)(rest)?;
-        let (rest, report_data) = take(64usize)(rest)?;
+        let (rest, mr_td) = take_array::<48>(rest)?;
+        let (rest, mr_config_id) = take_array::<48>(rest)?;
+        let (rest, mr_owner) = take_array::<48>(rest)?;
+        let (rest, mr_owner_config) = take_array::<48>(rest)?;
+        let (rest, rtmr_0) = take_array::<48>(rest)?;
+        let (rest, rtmr_1) = take_array::<48>(rest)?;
+        let (rest, rtmr_2) = take_array::<48>(rest)?;
+        let (rest, rtmr_3) = take_array::<48>(rest)?;
+        let (rest, report_data) = take_array::<64>(rest)?;
 
         Ok((
             rest,
-            // Unwrapping is OK here since we know the slices must have the right length if we
-            // managed to get this far.
             Self {
-                tee_tcb_svn: tee_tcb_svn.try_into().unwrap(),
-                mr_seam: mr_seam.try_into().unwrap(),
-                mrsigner_seam: mrsigner_seam.try_into().unwrap(),
+                tee_tcb_svn,
+                mr_seam,
+                mrsigner_seam,
                 seam_attributes,
                 td_attributes: TdAttributes::from_bits_truncate(td_attributes),
                 xfam,
-                mr_td: mr_td.try_into().unwrap(),
-                mr_config_id: mr_config_id.try_into().unwrap(),
-                mr_owner: mr_owner.try_into().unwrap(),
-                mr_owner_config: mr_owner_config.try_into().unwrap(),
-                rtmr_0: rtmr_0.try_into().unwrap(),
-                rtmr_1: rtmr_1.try_into().unwrap(),
-                rtmr_2: rtmr_2.try_into().unwrap(),
-                rtmr_3: rtmr_3.try_into().unwrap(),
-                report_data: report_data.try_into().unwrap(),
+                mr_td,
+                mr_config_id,
+                mr_owner,
+                mr_owner_config,
+                rtmr_0,
+                rtmr_1,
+                rtmr_2,
+                rtmr_3,
+                report_data,
             },
         ))
     }
@@ -412,36 +427,34 @@ pub struct EnclaveReportBody<'a> {
 impl<'a> EnclaveReportBody<'a> {
     /// Parses an enclave report body from a byte slice.
     fn parse(bytes: &'a [u8]) -> nom::IResult<&'a [u8], Self> {
-        let (rest, cpu_svn) = take(16usize)(bytes)?;
+        let (rest, cpu_svn) = take_array::<16>(bytes)?;
         let (rest, misc_select) = le_u32(rest)?;
-        let (rest, reserved1) = take(28usize)(rest)?;
-        let (rest, attributes) = take(16usize)(rest)?;
-        let (rest, mr_enclave) = take(32usize)(rest)?;
-        let (rest, reserved2) = take(32usize)(rest)?;
-        let (rest, mr_signer) = take(32usize)(rest)?;
-        let (rest, reserved3) = take(96usize)(rest)?;
+        let (rest, reserved1) = take_array::<28>(rest)?;
+        let (rest, attributes) = take_array::<16>(rest)?;
+        let (rest, mr_enclave) = take_array::<32>(rest)?;
+        let (rest, reserved2) = take_array::<32>(rest)?;
+        let (rest, mr_signer) = take_array::<32>(rest)?;
+        let (rest, reserved3) = take_array::<96>(rest)?;
         let (rest, isv_prod_id) = le_u16(rest)?;
         let (rest, isv_svn) = le_u16(rest)?;
-        let (rest, reserved4) = take(60usize)(rest)?;
-        let (rest, report_data) = take(64usize)(rest)?;
+        let (rest, reserved4) = take_array::<60>(rest)?;
+        let (rest, report_data) = take_array::<64>(rest)?;
 
         Ok((
             rest,
-            // Unwrapping is OK here since we know the slices must have the right length if we
-            // managed to get this far.
             Self {
-                cpu_svn: cpu_svn.try_into().unwrap(),
+                cpu_svn,
                 misc_select,
-                reserved1: reserved1.try_into().unwrap(),
-                attributes: attributes.try_into().unwrap(),
-                mr_enclave: mr_enclave.try_into().unwrap(),
-                reserved2: reserved2.try_into().unwrap(),
-                mr_signer: mr_signer.try_into().unwrap(),
-                reserved3: reserved3.try_into().unwrap(),
+                reserved1,
+                attributes,
+                mr_enclave,
+                reserved2,
+                mr_signer,
+                reserved3,
                 isv_prod_id,
                 isv_svn,
-                reserved4: reserved4.try_into().unwrap(),
-                report_data: report_data.try_into().unwrap(),
+                reserved4,
+                report_data,
             },
         ))
     }
@@ -461,18 +474,12 @@ pub struct QuoteSignatureData<'a> {
 
 impl<'a> QuoteSignatureData<'a> {
     fn parse(bytes: &'a [u8]) -> nom::IResult<&'a [u8], Self> {
-        let (rest, quote_signature) = take(64usize)(bytes)?;
-        let (rest, ecdsa_attestation_key) = take(64usize)(rest)?;
+        let (rest, quote_signature) = take_array::<64>(bytes)?;
+        let (rest, ecdsa_attestation_key) = take_array::<64>(rest)?;
         let (rest, certification_data) = QeCertificationData::parse(rest)?;
         Ok((
             rest,
-            // Unwrapping is OK here since we know the slices must have the right length if
-            // we managed to get this far.
-            QuoteSignatureData {
-                quote_signature: quote_signature.try_into().unwrap(),
-                ecdsa_attestation_key: ecdsa_attestation_key.try_into().unwrap(),
-                certification_data,
-            },
+            QuoteSignatureData { quote_signature, ecdsa_attestation_key, certification_data },
         ))
     }
 }
@@ -492,18 +499,16 @@ pub struct QeReportCertificationData<'a> {
 
 impl<'a> QeReportCertificationData<'a> {
     fn parse(bytes: &'a [u8]) -> nom::IResult<&'a [u8], Self> {
-        let (rest, report_body) = take(384usize)(bytes)?;
-        let (rest, signature) = take(64usize)(rest)?;
+        let (rest, report_body) = take_array::<384>(bytes)?;
+        let (rest, signature) = take_array::<64>(rest)?;
         let (rest, authentication_data_length) = le_u16(rest)?;
         let (rest, authentication_data) = take(authentication_data_length as usize)(rest)?;
         let (rest, certification_data) = QeCertificationData::parse(rest)?;
         Ok((
             rest,
-            // Unwrapping is OK here since we know the slices must have the right length if
-            // we managed to get this far.
             QeReportCertificationData {
-                report_body: report_body.try_into().unwrap(),
-                signature: signature.try_into().unwrap(),
+                report_body,
+                signature,
                 authentication_data,
                 certification_data,
             },
@@ -653,4 +658,54 @@ mod tests {
         assert_that!(enclave_report.reserved4, eq(&[0u8; 60]));
         assert_that!(enclave_report.report_data, not(eq(&[0u8; 64])));
     }
+
+    /// The number of bytes of the evidence quote that are covered by its
+    /// structures. The rest of the buffer is padding.
+    fn get_used_quote_length(quote_buffer: &[u8]) -> usize {
+        let wrapper = TdxQuoteWrapper { quote_bytes: quote_buffer };
+        let signature_data =
+            wrapper.get_signature_data_bytes().expect("couldn't get signature data bytes");
+        QUOTE_HEADER_SIZE + QUOTE_BODY_SIZE + size_of::<u32>() + signature_data.len()
+    }
+
+    #[test]
+    fn try_parse_accepts_valid_quote() {
+        let quote_buffer = get_evidence_quote_bytes();
+        std::assert!(TdxQuoteWrapper::try_parse(quote_buffer.as_slice()).is_ok());
+    }
+
+    #[test]
+    fn try_parse_rejects_truncated_quote() {
+        let quote_buffer = get_evidence_quote_bytes();
+        let used_length = get_used_quote_length(quote_buffer.as_slice());
+        for length in 0..used_length {
+            std::assert!(
+                TdxQuoteWrapper::try_parse(&quote_buffer[..length]).is_err(),
+                "truncated to {length} bytes"
+            );
+        }
+    }
+
+    #[test]
+    fn try_parse_ignores_trailing_bytes() {
+        let mut quote_buffer = get_evidence_quote_bytes();
+        quote_buffer.extend_from_slice(&[0xff; 4096]);
+        std::assert!(TdxQuoteWrapper::try_parse(quote_buffer.as_slice()).is_ok());
+    }
+
+    #[test]
+    fn try_parse_rejects_oversized_lengths() {
+        // Offsets of the length fields in the evidence quote: the signature data
+        // length, the QE report certification data length, the authentication data
+        // length and the PCK certificate chain length.
+        let length_fields: [(usize, usize); 4] = [(632, 4), (766, 4), (1218, 2), (1254, 4)];
+        for (offset, size) in length_fields {
+            let mut quote_buffer = get_evidence_quote_bytes();
+            quote_buffer[offset..offset + size].fill(0xff);
+            std::assert!(
+                TdxQuoteWrapper::try_parse(quote_buffer.as_slice()).is_err(),
+                "oversized length at offset {offset}"
+            );
+        }
+    }
 }
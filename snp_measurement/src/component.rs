@@ -0,0 +1,205 @@
+//
+// Copyright 2025 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::{fs::File, io::BufReader, ops::Range, path::PathBuf, str::FromStr};
+
+use anyhow::Context;
+use x86_64::{
+    structures::paging::{PageSize, Size4KiB},
+    PhysAddr,
+};
+
+use crate::page::{Hasher, PageInfo, PageType};
+
+/// An additional component, such as a kernel or an initial RAM disk, that the
+/// VMM loads into guest memory before launch.
+///
+/// Parsed from `PAGE_TYPE:LOAD_ADDRESS:PATH`, e.g. `normal:0x200000:bzImage`.
+/// The load address is hexadecimal and must be 4KiB-aligned. The page type is
+/// one of `normal`, `zero`, `unmeasured`, `secrets` or `cpuid`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Component {
+    pub page_type: PageType,
+    pub load_address: PhysAddr,
+    pub path: PathBuf,
+}
+
+impl Component {
+    /// Returns the guest-physical memory occupied by the component, rounded up
+    /// to whole pages.
+    pub fn memory_range(&self) -> anyhow::Result<Range<u64>> {
+        let size = std::fs::metadata(&self.path)
+            .with_context(|| format!("couldn't read metadata of {}", self.path.display()))?
+            .len();
+        let start = self.load_address.as_u64();
+        Ok(start..start + size.next_multiple_of(Size4KiB::SIZE))
+    }
+
+    /// Extends the measurement in `page_info` with the component.
+    ///
+    /// The contents are only measured for normal pages. For the other page
+    /// types only the number of pages covered by the file matters.
+    pub fn measure<H: Hasher>(&self, page_info: &mut PageInfo<H>) -> anyhow::Result<()> {
+        if self.page_type == PageType::Normal {
+            let file = File::open(&self.path)
+                .with_context(|| format!("couldn't open {}", self.path.display()))?;
+            return page_info
+                .update_from_reader(BufReader::new(file), self.load_address)
+                .with_context(|| format!("couldn't read {}", self.path.display()));
+        }
+        for address in self.memory_range()?.step_by(Size4KiB::SIZE as usize) {
+            page_info.update_from_snp_page(self.page_type, PhysAddr::new(address));
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Component {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut parts = value.splitn(3, ':');
+        let (Some(page_type), Some(load_address), Some(path)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err("expected PAGE_TYPE:LOAD_ADDRESS:PATH".to_string());
+        };
+        let page_type = match page_type {
+            "normal" => PageType::Normal,
+            "zero" => PageType::Zero,
+            "unmeasured" => PageType::Unmeasured,
+            "secrets" => PageType::Secrets,
+            "cpuid" => PageType::Cpuid,
+            _ => return Err(format!("invalid page type: {page_type}")),
+        };
+        let digits = load_address.strip_prefix("0x").unwrap_or(load_address).replace('_', "");
+        let load_address = u64::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(|address| PhysAddr::try_new(address).ok())
+            .ok_or_else(|| format!("invalid load address: {load_address}"))?;
+        if !load_address.is_aligned(Size4KiB::SIZE) {
+            return Err(format!("load address {load_address:#x} is not 4KiB-aligned"));
+        }
+        if path.is_empty() {
+            return Err("component path must not be empty".to_string());
+        }
+        Ok(Self { page_type, load_address, path: path.into() })
+    }
+}
+
+/// Checks that none of the labelled guest-physical memory `regions` overlap,
+/// as the VMM can't load two things at the same address.
+pub fn ensure_disjoint(regions: &[(String, Range<u64>)]) -> anyhow::Result<()> {
+    let mut regions: Vec<_> = regions.iter().filter(|(_, range)| !range.is_empty()).collect();
+    regions.sort_by_key(|(_, range)| range.start);
+    for pair in regions.windows(2) {
+        let ((label, range), (next_label, next_range)) = (pair[0], pair[1]);
+        anyhow::ensure!(
+            range.end <= next_range.start,
+            "{label} at {:#x}..{:#x} overlaps {next_label} at {:#x}..{:#x}",
+            range.start,
+            range.end,
+            next_range.start,
+            next_range.end
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn component_file(contents: &[u8]) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(contents).unwrap();
+        file
+    }
+
+    fn component(page_type: &str, load_address: u64, file: &tempfile::NamedTempFile) -> Component {
+        format!("{page_type}:{load_address:#x}:{}", file.path().display()).parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_component() {
+        assert_eq!(
+            "normal:0x20_0000:/tmp/some:file".parse::<Component>(),
+            Ok(Component {
+                page_type: PageType::Normal,
+                load_address: PhysAddr::new(0x20_0000),
+                path: "/tmp/some:file".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_component() {
+        assert!("normal:0x200000".parse::<Component>().is_err());
+        assert!("vmsa:0x200000:kernel".parse::<Component>().is_err());
+        assert!("normal:0x200001:kernel".parse::<Component>().is_err());
+        assert!("normal:xyz:kernel".parse::<Component>().is_err());
+        assert!("normal:0x200000:".parse::<Component>().is_err());
+    }
+
+    #[test]
+    fn test_measure_two_components() {
+        let kernel_contents = vec![0xAB; Size4KiB::SIZE as usize + 100];
+        let kernel = component_file(&kernel_contents);
+        let zero_pages = component_file(&[0; 2 * Size4KiB::SIZE as usize]);
+        let components =
+            [component("normal", 0x20_0000, &kernel), component("zero", 0x100_0000, &zero_pages)];
+
+        let mut page_info = PageInfo::new();
+        for component in components.iter() {
+            component.measure(&mut page_info).unwrap();
+        }
+
+        let mut expected = PageInfo::new();
+        expected.update_from_data(&kernel_contents, PhysAddr::new(0x20_0000));
+        expected.update_from_snp_page(PageType::Zero, PhysAddr::new(0x100_0000));
+        expected.update_from_snp_page(PageType::Zero, PhysAddr::new(0x100_1000));
+        assert_eq!(page_info.digest_cur, expected.digest_cur);
+    }
+
+    #[test]
+    fn test_component_memory_range_is_rounded_up() {
+        let file = component_file(&[1; 100]);
+        assert_eq!(component("normal", 0x1000, &file).memory_range().unwrap(), 0x1000..0x2000);
+    }
+
+    #[test]
+    fn test_ensure_disjoint() {
+        let regions = [
+            ("kernel".to_string(), 0x20_0000..0x30_0000),
+            ("stage0".to_string(), 0xFFE0_0000..0x1_0000_0000),
+            ("initrd".to_string(), 0x30_0000..0x40_0000),
+            ("empty".to_string(), 0x20_0000..0x20_0000),
+        ];
+        assert!(ensure_disjoint(&regions).is_ok());
+    }
+
+    #[test]
+    fn test_ensure_disjoint_rejects_overlap() {
+        let regions = [
+            ("kernel".to_string(), 0x20_0000..0x30_0000),
+            ("initrd".to_string(), 0x2F_F000..0x40_0000),
+        ];
+        let err = ensure_disjoint(&regions).unwrap_err().to_string();
+        assert!(err.contains("kernel") && err.contains("initrd"), "{err}");
+    }
+}
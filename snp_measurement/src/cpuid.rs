@@ -0,0 +1,149 @@
+//
+// Copyright 2025 The Project Oak Authors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Support for taking the CPU signature from a CPUID page dumped from the VMM.
+//!
+//! The contents of the CPUID page are not part of the launch measurement, but
+//! the VMM's view of CPUID leaf 0x1 determines the initial value of RDX in
+//! every VMSA, which is. Reading it from the table the VMM actually provisions
+//! avoids measurement mismatches caused by guessing the CPU family, model and
+//! stepping.
+
+use std::path::Path;
+
+use anyhow::Context;
+use oak_sev_guest::cpuid::{CpuidPage, CPUID_PAGE_SIZE};
+use zerocopy::FromBytes;
+
+/// The CPUID leaf that reports the processor signature in EAX.
+const PROCESSOR_INFO_LEAF: u32 = 0x1;
+
+/// The CPU family, model and stepping, as used when calculating the VMSA pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuSignature {
+    pub family: u8,
+    pub model: u8,
+    pub stepping: u8,
+}
+
+impl CpuSignature {
+    /// Decodes the signature from the value of EAX returned for CPUID leaf 0x1.
+    ///
+    /// See <https://en.wikipedia.org/wiki/CPUID#EAX=1:_Processor_Info_and_Feature_Bits>.
+    pub fn from_leaf_1_eax(eax: u32) -> Self {
+        let stepping = (eax & 0xF) as u8;
+        let base_model = ((eax >> 4) & 0xF) as u8;
+        let base_family = ((eax >> 8) & 0xF) as u8;
+        let extended_model = ((eax >> 16) & 0xF) as u8;
+        let extended_family = ((eax >> 20) & 0xFF) as u8;
+
+        let family = if base_family == 0xF { base_family + extended_family } else { base_family };
+        let model = if base_family == 0x6 || base_family == 0xF {
+            (extended_model << 4) | base_model
+        } else {
+            base_model
+        };
+        Self { family, model, stepping }
+    }
+}
+
+/// Reads a dumped CPUID page from `path` and returns the CPU signature it
+/// reports.
+pub fn load_cpu_signature<P: AsRef<Path>>(path: P) -> anyhow::Result<CpuSignature> {
+    let bytes = std::fs::read(path).context("couldn't read CPUID table")?;
+    cpu_signature_from_cpuid_page(&bytes)
+}
+
+fn cpu_signature_from_cpuid_page(bytes: &[u8]) -> anyhow::Result<CpuSignature> {
+    anyhow::ensure!(
+        bytes.len() == CPUID_PAGE_SIZE,
+        "CPUID table has size {}, expected {}",
+        bytes.len(),
+        CPUID_PAGE_SIZE
+    );
+    let page = CpuidPage::read_from_bytes(bytes)
+        .map_err(|_| anyhow::anyhow!("couldn't parse CPUID table"))?;
+    page.validate().map_err(anyhow::Error::msg).context("invalid CPUID table")?;
+
+    let function = page.cpuid_data[..page.count as usize]
+        .iter()
+        .find(|function| function.input.eax == PROCESSOR_INFO_LEAF && function.input.ecx == 0)
+        .context("CPUID table has no entry for leaf 0x1")?;
+    Ok(CpuSignature::from_leaf_1_eax(function.output.eax))
+}
+
+#[cfg(test)]
+mod tests {
+    use oak_sev_guest::vmsa::calculate_rdx_from_fms;
+
+    use super::*;
+
+    /// Leaf 0x1 EAX as reported on an AMD EPYC "Milan" CPU.
+    const MILAN_LEAF_1_EAX: u32 = 0x00A0_0F11;
+
+    /// Builds a sample CPUID page containing leaves 0x0 and 0x1.
+    fn sample_cpuid_page(leaf_1_eax: u32) -> Vec<u8> {
+        let mut page = vec![0u8; CPUID_PAGE_SIZE];
+        page[..4].copy_from_slice(&2u32.to_le_bytes());
+        for (index, (leaf, eax)) in
+            [(0x0, 0x10u32), (PROCESSOR_INFO_LEAF, leaf_1_eax)].into_iter().enumerate()
+        {
+            // Entries start after the 16-byte header and are 48 bytes each; the
+            // input leaf is the first field and the output EAX follows the 24-byte
+            // input.
+            let offset = 16 + index * 48;
+            page[offset..offset + 4].copy_from_slice(&leaf.to_le_bytes());
+            page[offset + 24..offset + 28].copy_from_slice(&eax.to_le_bytes());
+        }
+        page
+    }
+
+    #[test]
+    fn test_cpu_signature_from_cpuid_page() {
+        let signature = cpu_signature_from_cpuid_page(&sample_cpuid_page(MILAN_LEAF_1_EAX))
+            .expect("couldn't read CPU signature");
+
+        assert_eq!(signature, CpuSignature { family: 0x19, model: 0x01, stepping: 0x1 });
+        assert_eq!(
+            calculate_rdx_from_fms(signature.family, signature.model, signature.stepping),
+            MILAN_LEAF_1_EAX as u64
+        );
+    }
+
+    #[test]
+    fn test_cpu_signature_round_trips_default_values() {
+        let eax = calculate_rdx_from_fms(6, 0, 0) as u32;
+
+        assert_eq!(
+            CpuSignature::from_leaf_1_eax(eax),
+            CpuSignature { family: 6, model: 0, stepping: 0 }
+        );
+    }
+
+    #[test]
+    fn test_cpuid_page_without_leaf_1() {
+        let mut page = sample_cpuid_page(MILAN_LEAF_1_EAX);
+        // Only keep the entry for leaf 0x0.
+        page[..4].copy_from_slice(&1u32.to_le_bytes());
+
+        assert!(cpu_signature_from_cpuid_page(&page).is_err());
+    }
+
+    #[test]
+    fn test_cpuid_page_wrong_size() {
+        assert!(cpu_signature_from_cpuid_page(&[0u8; 16]).is_err());
+    }
+}
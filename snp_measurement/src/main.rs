@@ -14,22 +14,27 @@
 // limitations under the License.
 //
 
+mod component;
+mod cpuid;
 mod page;
 mod stage0;
 mod vmsa;
 
-use std::path::PathBuf;
+use std::{fmt::Display, ops::Range, path::PathBuf, time::Instant};
 
 use anyhow::Context;
 use clap::Parser;
-use log::trace;
-use page::PageInfo;
+use log::{trace, warn};
+use page::{ensure_page_aligned, MeasurementTrace, PageInfo};
+use stage0_parsing::Stage0Info;
 use x86_64::structures::paging::{PageSize, Size4KiB};
 
 use crate::{
+    component::{ensure_disjoint, Component},
+    cpuid::{load_cpu_signature, CpuSignature},
     page::PageType,
-    stage0::{load_stage0, SnpRomParsing},
-    vmsa::{get_ap_vmsa, get_boot_vmsa, VMSA_ADDRESS},
+    stage0::{load_stage0, SevEsResetBlock, SnpRomParsing},
+    vmsa::{get_ap_vmsas, get_boot_vmsa, VMSA_ADDRESS},
 };
 
 #[derive(Parser, Clone)]
@@ -39,7 +44,15 @@ struct Cli {
     stage0_rom: Option<PathBuf>,
     #[arg(long, help = "Whether the firwmare is shadowed to support legacy boot")]
     legacy_boot: bool,
-    #[arg(long, help = "The number of vCPUs available to the VM at boot", default_values_t = [1], value_delimiter = ',', num_args = 1..)]
+    #[arg(
+        long,
+        help = "The number of vCPUs available to the VM at boot. A comma-separated list \
+                calculates one measurement per count",
+        default_values_t = [1],
+        value_delimiter = ',',
+        num_args = 1..,
+        value_parser = parse_vcpu_count
+    )]
     vcpu_count: Vec<usize>,
     #[arg(
         long,
@@ -66,62 +79,242 @@ struct Cli {
         default_value_t = 0
     )]
     cpu_stepping: u8,
+    #[arg(
+        long,
+        help = "A CPUID page dumped from the VMM, from which to take the CPU family, model and \
+                stepping used when calculating the VMSA pages instead of the --cpu-* flags. The \
+                contents of the CPUID page itself are not part of the measurement",
+        conflicts_with_all = ["cpu_family", "cpu_model", "cpu_stepping"]
+    )]
+    cpuid_table: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Whether to print the running measurement after each step, to help find the step \
+                at which two measurements diverge"
+    )]
+    trace_measurement: bool,
+    #[arg(
+        long,
+        help = "A measurement trace printed by a previous run with --trace-measurement, to report \
+                the first step at which this measurement diverges from it"
+    )]
+    reference_trace: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "The SEV-ES reset address of each non-boot vCPU, in order, for configurations in \
+                which APs have distinct reset vectors. APs without an explicit address use the \
+                reset address from the firmware",
+        value_delimiter = ',',
+        value_parser = parse_reset_address
+    )]
+    ap_reset_address: Vec<u32>,
+    #[arg(
+        long,
+        help = "Whether to print the wall-clock time taken by each phase of the calculation to \
+                stderr, to help profile runs with large vCPU counts"
+    )]
+    timing: bool,
+    #[arg(
+        long,
+        help = "An additional component the VMM loads into guest memory before launch, such as \
+                a kernel or an initrd, as PAGE_TYPE:LOAD_ADDRESS:PATH (e.g. \
+                normal:0x200000:bzImage). Can be repeated; components are measured in the order \
+                given, after the Stage 0 pages"
+    )]
+    component: Vec<Component>,
+}
+
+fn parse_reset_address(value: &str) -> Result<u32, String> {
+    let digits = value.strip_prefix("0x").unwrap_or(value).replace('_', "");
+    u32::from_str_radix(&digits, 16).map_err(|err| format!("invalid reset address: {err}"))
+}
+
+fn parse_vcpu_count(value: &str) -> Result<usize, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err("vCPU count must not be empty".to_string());
+    }
+    match value.parse::<usize>() {
+        Ok(0) => Err("vCPU count must be at least 1".to_string()),
+        Ok(count) => Ok(count),
+        Err(err) => Err(format!("invalid vCPU count: {err}")),
+    }
 }
 
 impl Cli {
     fn stage0_path(&self) -> PathBuf {
         self.stage0_rom.clone().expect("need to specify --stage0_rom")
     }
+
+    fn cpu_signature(&self) -> anyhow::Result<CpuSignature> {
+        match self.cpuid_table.as_ref() {
+            Some(path) => load_cpu_signature(path),
+            None => Ok(CpuSignature {
+                family: self.cpu_family,
+                model: self.cpu_model,
+                stepping: self.cpu_stepping,
+            }),
+        }
+    }
+}
+
+/// Reports the wall-clock time taken by each phase of the calculation on
+/// stderr, so that it doesn't mix with the measurements on stdout.
+struct PhaseTimer {
+    enabled: bool,
+    start: Instant,
+}
+
+impl PhaseTimer {
+    fn new(enabled: bool) -> Self {
+        Self { enabled, start: Instant::now() }
+    }
+
+    /// Starts timing a new phase, discarding the time since the last one ended.
+    fn restart(&mut self) {
+        self.start = Instant::now();
+    }
+
+    /// Reports the time since the previous phase ended, or since the last
+    /// restart, as the duration of `phase`, and starts timing the next one.
+    fn finish(&mut self, phase: impl Display) {
+        let now = Instant::now();
+        if self.enabled {
+            eprintln!("Timing: {phase} took {:.3?}", now - self.start);
+        }
+        self.start = now;
+    }
 }
 
 fn main() -> anyhow::Result<()> {
     env_logger::init();
     let cli = Cli::parse();
+    let mut timer = PhaseTimer::new(cli.timing);
 
+    let reference_trace = cli
+        .reference_trace
+        .as_ref()
+        .map(|path| -> anyhow::Result<MeasurementTrace> {
+            std::fs::read_to_string(path).context("couldn't read reference trace")?.parse()
+        })
+        .transpose()
+        .context("couldn't parse reference trace")?;
+
+    timer.restart();
     let stage0 = load_stage0(cli.stage0_path())?;
+    let cpu_signature = cli.cpu_signature()?;
+    timer.finish("Stage 0 load");
+
+    if !cli.component.is_empty() {
+        ensure_disjoint(&memory_regions(&cli, &stage0)?)
+            .context("components overlap other regions in guest memory")?;
+    }
 
     let mut base_page_info = PageInfo::new();
+    let mut base_trace = MeasurementTrace::default();
 
     // Add the Stage 0 firmware ROM image.
+    ensure_page_aligned(stage0.rom_bytes(), "Stage 0 firmware ROM image")?;
     base_page_info.update_from_data(stage0.rom_bytes(), stage0.start_address);
+    base_trace.record("Stage 0 ROM image", &base_page_info);
     if cli.legacy_boot {
         // Add the legacy boot shadow of the Stage 0 firmware ROM image.
+        ensure_page_aligned(stage0.legacy_shadow_bytes(), "Stage 0 legacy boot shadow")?;
         base_page_info.update_from_data(stage0.legacy_shadow_bytes(), stage0.legacy_start_address);
+        base_trace.record("Stage 0 legacy boot shadow", &base_page_info);
     }
 
     for snp_page in stage0.get_snp_pages() {
-        let page_type = if cli.qemu && snp_page.page_type == PageType::Unmeasured {
-            // QEMU uses page type Zero for unmeasured pages as well.
-            PageType::Zero
-        } else {
-            snp_page.page_type
-        };
+        let page_type = vmm_page_type(snp_page.page_type, cli.qemu);
         for page_number in 0..snp_page.page_count {
             base_page_info.update_from_snp_page(
                 page_type,
                 snp_page.start_address + (page_number as u64) * Size4KiB::SIZE,
             );
         }
+        base_trace.record(
+            format!(
+                "{} {:?} page(s) at {:#018x}",
+                snp_page.page_count, page_type, snp_page.start_address
+            ),
+            &base_page_info,
+        );
+    }
+
+    timer.finish("page measurement");
+
+    for component in cli.component.iter() {
+        let component = Component {
+            page_type: vmm_page_type(component.page_type, cli.qemu),
+            ..component.clone()
+        };
+        component.measure(&mut base_page_info)?;
+        base_trace.record(
+            format!(
+                "{:?} component {} at {:#018x}",
+                component.page_type,
+                component.path.display(),
+                component.load_address
+            ),
+            &base_page_info,
+        );
+    }
+    if !cli.component.is_empty() {
+        timer.finish("component measurement");
     }
 
     // The boot vCPU has the default VMSA configured.
     base_page_info.update_from_vmsa(
-        &get_boot_vmsa(cli.cpu_family, cli.cpu_model, cli.cpu_stepping, cli.qemu),
+        &get_boot_vmsa(cpu_signature.family, cpu_signature.model, cpu_signature.stepping, cli.qemu),
         VMSA_ADDRESS,
     );
+    base_trace.record("boot vCPU VMSA", &base_page_info);
+    timer.finish("boot vCPU VMSA");
 
     // Subsequent vCPUs use the IP and CS segment specified in the SEV-ES reset
-    // block table in the firmware.
+    // block table in the firmware, unless a reset address is given explicitly.
     let sev_es_reset_block = stage0.get_sev_es_reset_block();
-    let ap_vmsa =
-        get_ap_vmsa(&sev_es_reset_block, cli.cpu_family, cli.cpu_model, cli.cpu_stepping, cli.qemu);
+    let ap_reset_blocks: Vec<SevEsResetBlock> =
+        cli.ap_reset_address.iter().map(|&address| address.into()).collect();
+    let ap_count = cli.vcpu_count.iter().max().copied().unwrap_or(1).saturating_sub(1);
+    anyhow::ensure!(
+        ap_reset_blocks.len() <= ap_count,
+        "got {} AP reset addresses, but at most {} APs are measured",
+        ap_reset_blocks.len(),
+        ap_count
+    );
+    for (ap_index, reset_block) in ap_reset_blocks.iter().enumerate() {
+        if *reset_block != sev_es_reset_block {
+            warn!(
+                "vCPU {} uses reset block {:?}, which differs from the firmware's {:?}",
+                ap_index + 1,
+                reset_block,
+                sev_es_reset_block
+            );
+        }
+    }
+    let ap_vmsas = get_ap_vmsas(
+        &sev_es_reset_block,
+        &ap_reset_blocks,
+        ap_count,
+        cpu_signature.family,
+        cpu_signature.model,
+        cpu_signature.stepping,
+        cli.qemu,
+    );
+    timer.finish(format!("creating {ap_count} AP VMSA(s)"));
+
     // Derive measurements for each vCPU counts specified.
     for vcpu_count in cli.vcpu_count {
+        timer.restart();
         let mut page_info = base_page_info.clone();
+        let mut trace = base_trace.clone();
         // Iterate through all vCPUs up to the specified count.
-        for _ in 1..vcpu_count {
-            page_info.update_from_vmsa(&ap_vmsa, VMSA_ADDRESS);
+        for (vcpu_index, ap_vmsa) in (1..vcpu_count).zip(ap_vmsas.iter()) {
+            page_info.update_from_vmsa(ap_vmsa, VMSA_ADDRESS);
+            trace.record(format!("vCPU {vcpu_index} VMSA"), &page_info);
         }
+        timer.finish(format!("AP VMSAs for {vcpu_count} vCPU"));
 
         trace!("raw measurement for {} vCPU: {:?}", vcpu_count, page_info.digest_cur);
 
@@ -131,6 +324,25 @@ fn main() -> anyhow::Result<()> {
             hex::encode(page_info.digest_cur)
         );
 
+        if cli.trace_measurement {
+            print!("Measurement trace {} vCPU:\n{}", vcpu_count, trace);
+        }
+        if let Some(reference_trace) = reference_trace.as_ref() {
+            match trace.first_divergence(reference_trace) {
+                None => println!("Measurement trace {} vCPU matches the reference", vcpu_count),
+                Some(index) => {
+                    println!(
+                        "Measurement trace {} vCPU first diverges from the reference at step {}: \
+                         {} (reference: {})",
+                        vcpu_count,
+                        index,
+                        step_label(&trace, index),
+                        step_label(reference_trace, index)
+                    );
+                }
+            }
+        }
+
         if let Some(mut path) = cli.attestation_measurements_output_dir.clone() {
             path.push(format!(
                 "sha2_384_measurement_of_initial_memory_with_stage0_and_{:02}_vcpu",
@@ -143,3 +355,85 @@ fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Returns the page type the VMM uses when measuring a page of `page_type`.
+fn vmm_page_type(page_type: PageType, qemu: bool) -> PageType {
+    if qemu && page_type == PageType::Unmeasured {
+        // QEMU uses page type Zero for unmeasured pages as well.
+        PageType::Zero
+    } else {
+        page_type
+    }
+}
+
+/// Lists the guest-physical memory that the Stage 0 image, its SEV-SNP pages
+/// and the additional components are loaded into.
+fn memory_regions(cli: &Cli, stage0: &Stage0Info) -> anyhow::Result<Vec<(String, Range<u64>)>> {
+    let mut regions = vec![(
+        "Stage 0 ROM image".to_string(),
+        stage0.start_address.as_u64()
+            ..stage0.start_address.as_u64() + stage0.rom_bytes().len() as u64,
+    )];
+    if cli.legacy_boot {
+        regions.push((
+            "Stage 0 legacy boot shadow".to_string(),
+            stage0.legacy_start_address.as_u64()
+                ..stage0.legacy_start_address.as_u64() + stage0.legacy_shadow_bytes().len() as u64,
+        ));
+    }
+    regions.extend(stage0.get_snp_pages().into_iter().map(|snp_page| {
+        let start = snp_page.start_address.as_u64();
+        (
+            format!("{:?} page(s)", snp_page.page_type),
+            start..start + snp_page.page_count as u64 * Size4KiB::SIZE,
+        )
+    }));
+    for component in cli.component.iter() {
+        regions
+            .push((format!("component {}", component.path.display()), component.memory_range()?));
+    }
+    Ok(regions)
+}
+
+fn step_label(trace: &MeasurementTrace, index: usize) -> &str {
+    trace.steps().get(index).map_or("<missing>", |step| step.label.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_vcpu_counts(args: &[&str]) -> Result<Vec<usize>, clap::Error> {
+        Cli::try_parse_from(std::iter::once("snp_measurement").chain(args.iter().copied()))
+            .map(|cli| cli.vcpu_count)
+    }
+
+    #[test]
+    fn test_vcpu_count_defaults_to_one() {
+        assert_eq!(parse_vcpu_counts(&[]).unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn test_vcpu_count_list() {
+        assert_eq!(parse_vcpu_counts(&["--vcpu-count=1,2,4"]).unwrap(), vec![1, 2, 4]);
+    }
+
+    #[test]
+    fn test_vcpu_count_zero_is_rejected() {
+        assert!(parse_vcpu_counts(&["--vcpu-count=0"]).is_err());
+        assert!(parse_vcpu_counts(&["--vcpu-count=1,0"]).is_err());
+    }
+
+    #[test]
+    fn test_vcpu_count_empty_is_rejected() {
+        assert!(parse_vcpu_counts(&["--vcpu-count="]).is_err());
+        assert!(parse_vcpu_counts(&["--vcpu-count=1,"]).is_err());
+        assert!(parse_vcpu_counts(&["--vcpu-count=1,,2"]).is_err());
+    }
+
+    #[test]
+    fn test_vcpu_count_large() {
+        assert_eq!(parse_vcpu_counts(&["--vcpu-count=1,512"]).unwrap(), vec![1, 512]);
+        assert!(parse_vcpu_counts(&["--vcpu-count=99999999999999999999999"]).is_err());
+    }
+}
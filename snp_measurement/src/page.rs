@@ -17,6 +17,15 @@
 // TODO(#3703): Remove when fixed.
 #![allow(clippy::extra_unused_type_parameters)]
 
+use std::{
+    fmt,
+    io::{self, Read},
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    str::FromStr,
+};
+
+use anyhow::Context;
 use log::{debug, trace};
 use oak_sev_guest::vmsa::VmsaPage;
 use sha2::{Digest, Sha384};
@@ -30,18 +39,44 @@ use zerocopy::{Immutable, IntoBytes};
 /// The size of the PageInfo struct.
 const PAGE_INFO_SIZE: usize = 112;
 
+/// The size of the digests in the PageInfo struct.
+pub const DIGEST_SIZE: usize = 48;
+
+/// A hash function used to calculate the measurement.
+///
+/// A new hasher is created for each digest. The hardware uses SHA-384, which
+/// is what [`PageInfo`] uses by default; other implementations are useful for
+/// testing. The output must be [`DIGEST_SIZE`] bytes long to fit the fixed
+/// layout of the Page Info structure.
+pub trait Hasher: Default {
+    /// Adds `data` to the hashed input.
+    fn update(&mut self, data: &[u8]);
+    /// Returns the digest of all the data passed to [`Hasher::update`].
+    fn finalize(self) -> [u8; DIGEST_SIZE];
+}
+
+impl Hasher for Sha384 {
+    fn update(&mut self, data: &[u8]) {
+        Digest::update(self, data);
+    }
+
+    fn finalize(self) -> [u8; DIGEST_SIZE] {
+        Digest::finalize(self).into()
+    }
+}
+
 /// Implementation of the Page Info structure used for extending the measurement
 /// in each step.
 ///
 /// See table 67 in <https://www.amd.com/system/files/TechDocs/56860.pdf>.
 #[repr(C)]
 #[derive(Debug, IntoBytes, Clone, Immutable)]
-pub struct PageInfo {
+pub struct RawPageInfo {
     /// The current measurement up to this point.
-    pub digest_cur: [u8; 48],
-    /// The SHA-384 digest of the contents to be measured for normal and VMSA
-    /// pages, or all zeros for all other page types.
-    pub contents: [u8; 48],
+    pub digest_cur: [u8; DIGEST_SIZE],
+    /// The digest of the contents to be measured for normal and VMSA pages, or
+    /// all zeros for all other page types.
+    pub contents: [u8; DIGEST_SIZE],
     /// The length of this struct in bytes.
     _length: u16,
     /// The type of page being measured.
@@ -61,13 +96,28 @@ pub struct PageInfo {
     pub gpa: u64,
 }
 
-static_assertions::assert_eq_size!(PageInfo, [u8; PAGE_INFO_SIZE]);
+static_assertions::assert_eq_size!(RawPageInfo, [u8; PAGE_INFO_SIZE]);
+
+/// The Page Info structure together with the hash function `H` used to extend
+/// the measurement, which defaults to SHA-384 as used by the hardware.
+#[derive(Debug, Clone)]
+pub struct PageInfo<H: Hasher = Sha384> {
+    raw: RawPageInfo,
+    _hasher: PhantomData<H>,
+}
 
 impl PageInfo {
     pub const fn new() -> Self {
-        Self {
-            digest_cur: [0; 48],
-            contents: [0; 48],
+        Self::with_hasher()
+    }
+}
+
+impl<H: Hasher> PageInfo<H> {
+    /// Creates a Page Info structure whose digests are calculated with `H`.
+    pub const fn with_hasher() -> Self {
+        let raw = RawPageInfo {
+            digest_cur: [0; DIGEST_SIZE],
+            contents: [0; DIGEST_SIZE],
             _length: PAGE_INFO_SIZE as u16,
             page_type: PageType::Invalid,
             _imi_page: ImiPage::No,
@@ -76,7 +126,8 @@ impl PageInfo {
             _vmpl2_perms: 0,
             _vmpl3_perms: 0,
             gpa: 0,
-        }
+        };
+        Self { raw, _hasher: PhantomData }
     }
 
     /// Updates the current measurement digest from a byte slice representing
@@ -89,6 +140,22 @@ impl PageInfo {
             data.len(),
             start_address
         );
+        self.update_from_reader(data, start_address)
+            .expect("reading from a byte slice should never fail");
+    }
+
+    /// Updates the current measurement digest from normal data pages read from
+    /// `reader`.
+    ///
+    /// The data is read and hashed one 4KiB page at a time, so it never has to
+    /// be held in memory in full. The result is the same as measuring all of
+    /// the data with [`PageInfo::update_from_data`], regardless of how the
+    /// reads are split.
+    pub fn update_from_reader<R: Read>(
+        &mut self,
+        mut reader: R,
+        start_address: PhysAddr,
+    ) -> io::Result<()> {
         assert_eq!(
             start_address,
             start_address.align_down(Size4KiB::SIZE),
@@ -96,12 +163,21 @@ impl PageInfo {
         );
         self.page_type = PageType::Normal;
         let mut address = start_address;
-        for chunk in data.chunks(Size4KiB::SIZE as usize) {
+        let mut page = [0u8; Size4KiB::SIZE as usize];
+        loop {
+            let length = read_page(&mut reader, &mut page)?;
+            if length == 0 {
+                break;
+            }
             self.gpa = address.as_u64();
             address += Size4KiB::SIZE;
-            self.set_contents_from_page_bytes(chunk);
+            self.set_contents_from_page_bytes(&page[..length]);
             self.update_current_digest();
+            if length < page.len() {
+                break;
+            }
         }
+        Ok(())
     }
 
     /// Updates the current measurement digest from a VMSA page.
@@ -116,29 +192,37 @@ impl PageInfo {
     /// Updates the current measurement digest for a SEV-SNP-specific page.
     ///
     /// Measurement of these pages do not include a measurement of the content,
-    /// only the metadata.
+    /// only the metadata. Since the contents are always zero, the page type
+    /// byte is the only thing that distinguishes e.g. the secrets page or the
+    /// CPUID page from a zero page at the same address, so it must be set
+    /// explicitly for each page type.
     pub fn update_from_snp_page(&mut self, page_type: PageType, start_address: PhysAddr) {
         debug!("Updating measurement with {:?} page at address {:#018x}", page_type, start_address);
         match page_type {
-            PageType::Cpuid | PageType::Secrets | PageType::Unmeasured | PageType::Zero => {
-                self.page_type = page_type;
-                self.gpa = start_address.as_u64();
-                self.contents.fill(0);
-                self.update_current_digest();
+            // The secrets page is populated by the PSP, so its contents are not measured.
+            // The CPUID page is validated by the PSP, but its contents are not measured.
+            // Unmeasured and zero pages have no contents to measure.
+            PageType::Secrets | PageType::Cpuid | PageType::Unmeasured | PageType::Zero => {}
+            // The other page types are not SEV-SNP-specific pages.
+            PageType::Invalid | PageType::Normal | PageType::Vmsa => {
+                panic!("Unexpected page type {:?}", page_type)
             }
-            _ => panic!("Unexpected page type {:?}", page_type),
         }
+        self.page_type = page_type;
+        self.gpa = start_address.as_u64();
+        self.contents.fill(0);
+        self.update_current_digest();
     }
 
-    /// Sets the `contents` field based to the SHA-384 digest of the byte
-    /// contents of a 4KiB memory page.
+    /// Sets the `contents` field based to the digest of the byte contents of a
+    /// 4KiB memory page.
     ///
     /// If fewer than 4KiB of data is received the page is padded with zeros to
     /// fill the entire 4KiB area.
     fn set_contents_from_page_bytes(&mut self, page_bytes: &[u8]) {
         let byte_count = page_bytes.len();
         assert!(byte_count <= Size4KiB::SIZE as usize, "too many bytes in page");
-        let mut contents_hasher = Sha384::new();
+        let mut contents_hasher = H::default();
         if byte_count == Size4KiB::SIZE as usize {
             contents_hasher.update(page_bytes);
         } else {
@@ -147,18 +231,61 @@ impl PageInfo {
             padded_page[..byte_count].copy_from_slice(page_bytes);
             contents_hasher.update(&padded_page);
         }
-        let contents_digest = contents_hasher.finalize();
-        self.contents[..].copy_from_slice(&contents_digest);
+        self.contents = contents_hasher.finalize();
     }
 
-    /// Calculates the SHA-384 digest of the struct's memory and updates
-    /// `digest_cur` to the new value.
+    /// Calculates the digest of the struct's memory and updates `digest_cur`
+    /// to the new value.
     fn update_current_digest(&mut self) {
-        let mut digest_hasher = Sha384::new();
-        digest_hasher.update(self.as_bytes());
-        let current_digest = digest_hasher.finalize();
-        self.digest_cur[..].copy_from_slice(&current_digest);
+        let mut digest_hasher = H::default();
+        digest_hasher.update(self.raw.as_bytes());
+        self.digest_cur = digest_hasher.finalize();
+    }
+}
+
+impl<H: Hasher> Deref for PageInfo<H> {
+    type Target = RawPageInfo;
+
+    fn deref(&self) -> &RawPageInfo {
+        &self.raw
+    }
+}
+
+impl<H: Hasher> DerefMut for PageInfo<H> {
+    fn deref_mut(&mut self) -> &mut RawPageInfo {
+        &mut self.raw
+    }
+}
+
+/// Checks that `data`, described by `description` for the error message,
+/// consists of whole 4KiB pages.
+///
+/// The hardware measures whole pages, so a partial final page would be
+/// measured here with zero padding that the VMM may not apply in the same way,
+/// which is a common source of wrong digests.
+pub fn ensure_page_aligned(data: &[u8], description: &str) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        data.len().is_multiple_of(Size4KiB::SIZE as usize),
+        "{description} size ({:#x} bytes) is not a multiple of the page size ({:#x} bytes)",
+        data.len(),
+        Size4KiB::SIZE
+    );
+    Ok(())
+}
+
+/// Fills `page` with bytes from `reader` and returns the number of bytes read,
+/// which is only less than the size of `page` at the end of the data.
+fn read_page<R: Read>(reader: &mut R, page: &mut [u8]) -> io::Result<usize> {
+    let mut length = 0;
+    while length < page.len() {
+        match reader.read(&mut page[length..]) {
+            Ok(0) => break,
+            Ok(count) => length += count,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
     }
+    Ok(length)
 }
 
 impl Default for PageInfo {
@@ -167,6 +294,84 @@ impl Default for PageInfo {
     }
 }
 
+/// The running measurement digest after a labelled update step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeasurementStep {
+    pub label: String,
+    pub digest: [u8; DIGEST_SIZE],
+}
+
+/// A record of the running measurement digest after each step of a
+/// measurement, used to find the first step at which two measurements diverge.
+///
+/// The text format has one step per line: the hex-encoded digest followed by
+/// the label. It is produced by the `Display` implementation and parsed by the
+/// `FromStr` implementation, so that a trace printed by one run can be used as
+/// the reference for another.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MeasurementTrace {
+    steps: Vec<MeasurementStep>,
+}
+
+impl MeasurementTrace {
+    /// Records the current digest of `page_info` as the result of the step
+    /// described by `label`.
+    pub fn record<H: Hasher>(&mut self, label: impl Into<String>, page_info: &PageInfo<H>) {
+        self.steps.push(MeasurementStep { label: label.into(), digest: page_info.digest_cur });
+    }
+
+    pub fn steps(&self) -> &[MeasurementStep] {
+        &self.steps
+    }
+
+    /// Returns the index of the first step whose digest differs from the step
+    /// at the same position in `other`, or `None` if the traces are equal.
+    ///
+    /// If one trace is a prefix of the other, the first step that is missing
+    /// from the shorter trace is returned.
+    pub fn first_divergence(&self, other: &MeasurementTrace) -> Option<usize> {
+        self.steps
+            .iter()
+            .zip(other.steps.iter())
+            .position(|(step, other_step)| step.digest != other_step.digest)
+            .or_else(|| {
+                (self.steps.len() != other.steps.len())
+                    .then(|| self.steps.len().min(other.steps.len()))
+            })
+    }
+}
+
+impl fmt::Display for MeasurementTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in self.steps.iter() {
+            writeln!(f, "{} {}", hex::encode(step.digest), step.label)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for MeasurementTrace {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let steps = s
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .enumerate()
+            .map(|(index, line)| {
+                let (digest, label) = line.split_once(' ').unwrap_or((line, ""));
+                let digest = hex::decode(digest)
+                    .ok()
+                    .and_then(|digest| <[u8; DIGEST_SIZE]>::try_from(digest).ok())
+                    .with_context(|| format!("invalid digest in trace step {index}"))?;
+                Ok(MeasurementStep { label: label.trim().to_string(), digest })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { steps })
+    }
+}
+
 /// Whether the page is part of an initial migration image (IMI).
 ///
 /// For now we assume we won't have any IMI pages.
@@ -199,3 +404,220 @@ pub enum PageType {
     /// The SEV-SNP CPUID page.
     Cpuid = 6,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    const TEST_ADDRESS: PhysAddr = PhysAddr::new(0x10_0000);
+    const PAGE_TYPE_OFFSET: usize = core::mem::offset_of!(RawPageInfo, page_type);
+
+    fn measure_snp_page(page_type: PageType) -> [u8; 48] {
+        let mut page_info = PageInfo::new();
+        page_info.update_from_snp_page(page_type, TEST_ADDRESS);
+        page_info.digest_cur
+    }
+
+    #[test]
+    fn test_page_type_is_measured() {
+        let mut page_info = PageInfo::new();
+        page_info.update_from_snp_page(PageType::Secrets, TEST_ADDRESS);
+        assert_eq!(page_info.as_bytes()[PAGE_TYPE_OFFSET], PageType::Secrets as u8);
+        page_info.update_from_snp_page(PageType::Cpuid, TEST_ADDRESS);
+        assert_eq!(page_info.as_bytes()[PAGE_TYPE_OFFSET], PageType::Cpuid as u8);
+    }
+
+    #[test]
+    fn test_secrets_page_differs_from_normal_page() {
+        let mut normal = PageInfo::new();
+        normal.update_from_data(&[0; Size4KiB::SIZE as usize], TEST_ADDRESS);
+        assert_ne!(normal.digest_cur, measure_snp_page(PageType::Secrets));
+    }
+
+    #[test]
+    fn test_secrets_page_differs_from_zero_pages() {
+        let secrets = measure_snp_page(PageType::Secrets);
+        assert_ne!(secrets, measure_snp_page(PageType::Zero));
+        assert_ne!(secrets, measure_snp_page(PageType::Unmeasured));
+    }
+
+    #[test]
+    fn test_cpuid_page_differs_from_secrets_page() {
+        assert_ne!(measure_snp_page(PageType::Cpuid), measure_snp_page(PageType::Secrets));
+        assert_ne!(measure_snp_page(PageType::Cpuid), measure_snp_page(PageType::Zero));
+    }
+
+    #[test]
+    #[should_panic(expected = "Unexpected page type")]
+    fn test_normal_page_type_is_rejected() {
+        measure_snp_page(PageType::Normal);
+    }
+
+    /// A reader that returns at most `max_read` bytes from each call to `read`.
+    struct ShortReader<'a> {
+        data: &'a [u8],
+        max_read: usize,
+    }
+
+    impl Read for ShortReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let count = buf.len().min(self.max_read);
+            self.data.read(&mut buf[..count])
+        }
+    }
+
+    /// Three and a half pages of non-uniform data.
+    fn sample_data() -> Vec<u8> {
+        (0..7 * Size4KiB::SIZE as usize / 2).map(|index| (index % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_chunked_measurement_matches_whole_buffer() {
+        let data = sample_data();
+        let mut whole = PageInfo::new();
+        whole.update_from_data(&data, TEST_ADDRESS);
+
+        for max_read in [1, 1000, Size4KiB::SIZE as usize, Size4KiB::SIZE as usize + 1] {
+            let mut chunked = PageInfo::new();
+            chunked
+                .update_from_reader(ShortReader { data: &data, max_read }, TEST_ADDRESS)
+                .unwrap();
+            assert_eq!(chunked.digest_cur, whole.digest_cur, "max_read: {max_read}");
+            assert_eq!(chunked.gpa, whole.gpa, "max_read: {max_read}");
+        }
+    }
+
+    #[test]
+    fn test_data_is_measured_page_by_page() {
+        let data = sample_data();
+        let mut whole = PageInfo::new();
+        whole.update_from_data(&data, TEST_ADDRESS);
+
+        let mut paged = PageInfo::new();
+        for (index, page) in data.chunks(Size4KiB::SIZE as usize).enumerate() {
+            paged.update_from_data(page, TEST_ADDRESS + index as u64 * Size4KiB::SIZE);
+        }
+        assert_eq!(paged.digest_cur, whole.digest_cur);
+    }
+
+    #[test]
+    fn test_empty_reader_is_not_measured() {
+        let mut page_info = PageInfo::new();
+        page_info.update_from_reader(io::empty(), TEST_ADDRESS).unwrap();
+        assert_eq!(page_info.digest_cur, PageInfo::new().digest_cur);
+    }
+
+    #[test]
+    fn test_ensure_page_aligned() {
+        assert!(ensure_page_aligned(&[], "empty").is_ok());
+        assert!(ensure_page_aligned(&[0; 2 * Size4KiB::SIZE as usize], "two pages").is_ok());
+    }
+
+    #[test]
+    fn test_ensure_page_aligned_rejects_partial_page() {
+        let data = vec![0; Size4KiB::SIZE as usize + 16];
+        let err = ensure_page_aligned(&data, "firmware ROM image").unwrap_err();
+        assert!(err.to_string().contains("firmware ROM image size (0x1010 bytes)"), "{err}");
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum HasherCall {
+        Update(Vec<u8>),
+        Finalize,
+    }
+
+    thread_local! {
+        static HASHER_CALLS: RefCell<Vec<HasherCall>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// A hasher that records its calls, and whose digests are the number of
+    /// digests finalized so far.
+    #[derive(Debug, Clone, Default)]
+    struct MockHasher;
+
+    impl Hasher for MockHasher {
+        fn update(&mut self, data: &[u8]) {
+            HASHER_CALLS.with_borrow_mut(|calls| calls.push(HasherCall::Update(data.to_vec())));
+        }
+
+        fn finalize(self) -> [u8; DIGEST_SIZE] {
+            HASHER_CALLS.with_borrow_mut(|calls| {
+                calls.push(HasherCall::Finalize);
+                let count = calls.iter().filter(|call| **call == HasherCall::Finalize).count();
+                [count as u8; DIGEST_SIZE]
+            })
+        }
+    }
+
+    #[test]
+    fn test_hasher_update_order() {
+        let mut page_info = PageInfo::<MockHasher>::with_hasher();
+        page_info.update_from_data(&[0xAB; 16], TEST_ADDRESS);
+
+        let calls = HASHER_CALLS.take();
+        assert_eq!(calls.len(), 4, "{calls:?}");
+        // The contents are hashed first, padded to a whole page.
+        let mut padded_page = vec![0; Size4KiB::SIZE as usize];
+        padded_page[..16].fill(0xAB);
+        assert_eq!(calls[0], HasherCall::Update(padded_page));
+        assert_eq!(calls[1], HasherCall::Finalize);
+        // Then the Page Info structure, containing the contents digest but not
+        // yet the new running digest.
+        let HasherCall::Update(page_info_bytes) = &calls[2] else {
+            panic!("expected an update, got {:?}", calls[2]);
+        };
+        assert_eq!(page_info_bytes.len(), PAGE_INFO_SIZE);
+        assert_eq!(page_info_bytes[..DIGEST_SIZE], [0; DIGEST_SIZE]);
+        assert_eq!(page_info_bytes[DIGEST_SIZE..2 * DIGEST_SIZE], [1; DIGEST_SIZE]);
+        assert_eq!(calls[3], HasherCall::Finalize);
+        assert_eq!(page_info.digest_cur, [2; DIGEST_SIZE]);
+    }
+
+    fn trace_measurement(secrets_page_type: PageType) -> MeasurementTrace {
+        let mut page_info = PageInfo::new();
+        let mut trace = MeasurementTrace::default();
+        page_info.update_from_data(&[1; Size4KiB::SIZE as usize], TEST_ADDRESS);
+        trace.record("data", &page_info);
+        page_info.update_from_snp_page(secrets_page_type, TEST_ADDRESS + Size4KiB::SIZE);
+        trace.record("secrets", &page_info);
+        page_info.update_from_snp_page(PageType::Cpuid, TEST_ADDRESS + 2 * Size4KiB::SIZE);
+        trace.record("cpuid", &page_info);
+        trace
+    }
+
+    #[test]
+    fn test_trace_records_running_digest() {
+        let trace = trace_measurement(PageType::Secrets);
+        assert_eq!(trace.steps().len(), 3);
+        assert_eq!(trace.steps()[1].label, "secrets");
+        assert_ne!(trace.steps()[0].digest, trace.steps()[1].digest);
+        assert_eq!(trace.first_divergence(&trace_measurement(PageType::Secrets)), None);
+    }
+
+    #[test]
+    fn test_trace_first_divergence() {
+        let trace = trace_measurement(PageType::Secrets);
+        let other = trace_measurement(PageType::Zero);
+        assert_eq!(trace.first_divergence(&other), Some(1));
+        assert_eq!(other.first_divergence(&trace), Some(1));
+    }
+
+    #[test]
+    fn test_trace_first_divergence_of_prefix() {
+        let trace = trace_measurement(PageType::Secrets);
+        let mut prefix = trace.clone();
+        prefix.steps.truncate(2);
+        assert_eq!(trace.first_divergence(&prefix), Some(2));
+        assert_eq!(prefix.first_divergence(&trace), Some(2));
+    }
+
+    #[test]
+    fn test_trace_text_round_trip() {
+        let trace = trace_measurement(PageType::Secrets);
+        let parsed: MeasurementTrace = trace.to_string().parse().unwrap();
+        assert_eq!(parsed, trace);
+        assert!("not-a-digest label".parse::<MeasurementTrace>().is_err());
+    }
+}
@@ -196,6 +196,7 @@ impl From<SevMetadataPageType> for PageType {
 
 /// The instruction pointer and code segment base that will be set when a
 /// non-boot vCPU is reset.
+#[derive(Debug, Clone, PartialEq)]
 pub struct SevEsResetBlock {
     pub rip: u64,
     pub segment_base: u64,
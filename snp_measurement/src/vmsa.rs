@@ -55,6 +55,29 @@ pub fn get_boot_vmsa(cpu_family: u8, cpu_model: u8, cpu_stepping: u8, qemu: bool
     result
 }
 
+/// Gets the initial VMSAs for `ap_count` additional vCPUs, in the order in
+/// which they are measured.
+///
+/// The AP at index `i` uses `ap_reset_blocks[i]` if it is present, and the
+/// `default_reset_block` from the firmware otherwise, so that APs with
+/// distinct reset vectors can be measured.
+pub fn get_ap_vmsas(
+    default_reset_block: &SevEsResetBlock,
+    ap_reset_blocks: &[SevEsResetBlock],
+    ap_count: usize,
+    cpu_family: u8,
+    cpu_model: u8,
+    cpu_stepping: u8,
+    qemu: bool,
+) -> Vec<VmsaPage> {
+    (0..ap_count)
+        .map(|ap_index| {
+            let reset_block = ap_reset_blocks.get(ap_index).unwrap_or(default_reset_block);
+            get_ap_vmsa(reset_block, cpu_family, cpu_model, cpu_stepping, qemu)
+        })
+        .collect()
+}
+
 /// Gets the initial VMSA for additional vCPUs that are not the boot vCPU.
 pub fn get_ap_vmsa(
     reset_block: &SevEsResetBlock,
@@ -69,3 +92,49 @@ pub fn get_ap_vmsa(
     trace!("AP VMSA: {:?}", result);
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::page::PageInfo;
+
+    const FIRMWARE_RESET_ADDRESS: u32 = 0xfffff000;
+    const OTHER_RESET_ADDRESS: u32 = 0xffffe010;
+
+    fn measure_aps(ap_vmsas: &[VmsaPage]) -> [u8; 48] {
+        let mut page_info = PageInfo::new();
+        for ap_vmsa in ap_vmsas {
+            page_info.update_from_vmsa(ap_vmsa, VMSA_ADDRESS);
+        }
+        page_info.digest_cur
+    }
+
+    fn ap_vmsas(ap_reset_addresses: &[u32], ap_count: usize) -> Vec<VmsaPage> {
+        let ap_reset_blocks: Vec<SevEsResetBlock> =
+            ap_reset_addresses.iter().map(|&address| address.into()).collect();
+        get_ap_vmsas(&FIRMWARE_RESET_ADDRESS.into(), &ap_reset_blocks, ap_count, 6, 0, 0, false)
+    }
+
+    #[test]
+    fn test_ap_vmsas_default_to_firmware_reset_block() {
+        let ap_vmsas = ap_vmsas(&[OTHER_RESET_ADDRESS], 3);
+        assert_eq!(ap_vmsas.len(), 3);
+        assert_eq!(ap_vmsas[0].vmsa.rip, 0xe010);
+        assert_eq!(ap_vmsas[0].vmsa.cs.base, 0xffff0000);
+        assert_eq!(ap_vmsas[1].vmsa.rip, 0xf000);
+        assert_eq!(ap_vmsas[2].vmsa.rip, 0xf000);
+    }
+
+    #[test]
+    fn test_measurement_reflects_each_ap_reset_vector() {
+        let heterogeneous =
+            measure_aps(&ap_vmsas(&[FIRMWARE_RESET_ADDRESS, OTHER_RESET_ADDRESS], 2));
+
+        assert_ne!(heterogeneous, measure_aps(&ap_vmsas(&[], 2)));
+        assert_ne!(heterogeneous, measure_aps(&ap_vmsas(&[OTHER_RESET_ADDRESS; 2], 2)));
+        assert_ne!(
+            heterogeneous,
+            measure_aps(&ap_vmsas(&[OTHER_RESET_ADDRESS, FIRMWARE_RESET_ADDRESS], 2))
+        );
+    }
+}
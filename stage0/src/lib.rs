@@ -174,8 +174,9 @@ pub fn rust64_start<P: hal::Platform>() -> ! {
     acpi::setup_high_allocator(&mut zero_page).unwrap();
 
     // Look into PCI first as we need to know where the PCI memory ranges are before
-    // we build the ACPI tables.
-    let pci_windows = pci::init::<P>(&mut fwcfg, &mut zero_page).unwrap();
+    // we build the ACPI tables. None of the supported platforms reserve any MMIO
+    // ranges within the PCI holes yet, so the BARs may use the whole windows.
+    let pci_windows = pci::init::<P>(&mut fwcfg, &mut zero_page, &[], &mut |_| {}).unwrap();
 
     let mut acpi_digest = Sha256::default();
     let rsdp = acpi::build_acpi_tables(&mut fwcfg, &mut acpi_digest, pci_windows).unwrap();
@@ -137,13 +137,40 @@ impl PciBarKind {
 #[allow(dead_code)]
 #[derive(Debug, PartialEq)]
 pub enum PciBar {
-    Memory32 { bdf: Bdf, offset: u8, prefetchable: bool, bar_size: u32 },
-    Memory64 { bdf: Bdf, offset: u8, prefetchable: bool, bar_size: u64 },
-    Io { bdf: Bdf, offset: u8, bar_size: u32 },
+    Memory32 {
+        bdf: Bdf,
+        offset: u8,
+        prefetchable: bool,
+        bar_size: u32,
+    },
+    Memory64 {
+        bdf: Bdf,
+        offset: u8,
+        prefetchable: bool,
+        bar_size: u64,
+    },
+    Io {
+        bdf: Bdf,
+        offset: u8,
+        bar_size: u32,
+    },
+    /// Expansion ROM BAR; unlike the other kinds, `register` is the
+    /// configuration space register the BAR lives in.
+    ExpansionRom {
+        bdf: Bdf,
+        register: u8,
+        bar_size: u32,
+    },
 }
 
 impl PciBar {
     const BAR_REGISTER_OFFSET: u8 = 0x4;
+    /// Register of the expansion ROM BAR for normal (type 0) devices.
+    pub const EXPANSION_ROM_REGISTER: u8 = 0xC;
+    /// Register of the expansion ROM BAR for PCI-to-PCI bridges (type 1).
+    pub const BRIDGE_EXPANSION_ROM_REGISTER: u8 = 0xE;
+    const EXPANSION_ROM_ADDRESS_MASK: u32 = 0xFFFF_F800;
+    const EXPANSION_ROM_ENABLE: u32 = 0b1;
 
     pub fn new(
         bdf: Bdf,
@@ -195,6 +222,27 @@ impl PciBar {
         })
     }
 
+    /// Probes the expansion ROM BAR in the given register.
+    ///
+    /// Returns None if the device doesn't implement an expansion ROM.
+    pub fn new_expansion_rom(
+        bdf: Bdf,
+        register: u8,
+        access: &mut dyn ConfigAccess,
+    ) -> Result<Option<Self>, &'static str> {
+        // Probe the BAR by writing all-ones to the address bits, leaving the ROM
+        // disabled.
+        access.write(bdf, register, Self::EXPANSION_ROM_ADDRESS_MASK)?;
+        let value = access.read(bdf, register)? & Self::EXPANSION_ROM_ADDRESS_MASK;
+
+        if value == 0 {
+            // No expansion ROM.
+            return Ok(None);
+        }
+
+        Ok(Some(PciBar::ExpansionRom { bdf, register, bar_size: !value + 1 }))
+    }
+
     pub fn set_address(
         &mut self,
         address: u64,
@@ -217,6 +265,15 @@ impl PciBar {
                 let address: u32 = address.try_into().map_err(|_| "invalid address")?;
                 access.write(*bdf, Self::BAR_REGISTER_OFFSET + *offset, address & !0b11)
             }
+            PciBar::ExpansionRom { bdf, register, .. } => {
+                let address: u32 = address.try_into().map_err(|_| "invalid address")?;
+                // Setting the address also enables decoding of the ROM.
+                access.write(
+                    *bdf,
+                    *register,
+                    (address & Self::EXPANSION_ROM_ADDRESS_MASK) | Self::EXPANSION_ROM_ENABLE,
+                )
+            }
         }
     }
 }
@@ -468,4 +525,70 @@ mod tests {
             .return_const(Ok(()));
         assert_that!(bar.set_address(0x1000, &mut access), ok(eq(())));
     }
+
+    #[test]
+    fn test_unimplemented_expansion_rom() {
+        let bdf = Bdf::new(1, 2, 3).unwrap();
+        let mut access = MockConfigAccess::new();
+        access
+            .expect_write()
+            .with(
+                mockall_eq(bdf),
+                mockall_eq(PciBar::EXPANSION_ROM_REGISTER),
+                mockall_eq(0xFFFF_F800),
+            )
+            .return_const(Ok(()));
+        access
+            .expect_read()
+            .with(mockall_eq(bdf), mockall_eq(PciBar::EXPANSION_ROM_REGISTER))
+            .return_const(Ok(0));
+        assert_that!(
+            PciBar::new_expansion_rom(bdf, PciBar::EXPANSION_ROM_REGISTER, &mut access),
+            ok(none())
+        );
+    }
+
+    #[test]
+    fn test_expansion_rom() {
+        let mut access = MockConfigAccess::new();
+        access.expect_write().return_const(Ok(()));
+        // Expansion ROM of size 256 KiB; the enable bit reads back as zero.
+        access
+            .expect_read()
+            .with(mockall_eq(Bdf::root()), mockall_eq(PciBar::BRIDGE_EXPANSION_ROM_REGISTER))
+            .return_const(Ok(0xFFFC_0000));
+        let bar = PciBar::new_expansion_rom(
+            Bdf::root(),
+            PciBar::BRIDGE_EXPANSION_ROM_REGISTER,
+            &mut access,
+        );
+
+        assert_that!(
+            bar,
+            ok(some(matches_pattern!(PciBar::ExpansionRom {
+                bdf: eq(&Bdf::root()),
+                register: eq(&PciBar::BRIDGE_EXPANSION_ROM_REGISTER),
+                bar_size: eq(&0x4_0000)
+            })))
+        );
+    }
+
+    #[test]
+    fn test_set_address_expansion_rom() {
+        let mut access = MockConfigAccess::new();
+        let mut bar = PciBar::ExpansionRom {
+            bdf: Bdf::root(),
+            register: PciBar::EXPANSION_ROM_REGISTER,
+            bar_size: 0x4_0000,
+        };
+        access
+            .expect_write()
+            .with(
+                mockall_eq(Bdf::root()),
+                mockall_eq(PciBar::EXPANSION_ROM_REGISTER),
+                mockall_eq(0x1004_0001),
+            )
+            .return_const(Ok(()));
+        assert_that!(bar.set_address(0x1004_0000, &mut access), ok(eq(())));
+    }
 }
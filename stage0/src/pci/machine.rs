@@ -16,9 +16,10 @@
 
 use core::{ffi::CStr, ops::Range};
 
-use x86_64::align_down;
+use x86_64::{align_down, align_up};
 use zerocopy::IntoBytes;
 
+use super::read_pci_crs_allowlist;
 use crate::{fw_cfg::Firmware, Platform, ZeroPage};
 
 const PCI_MMIO32_HOLE_BASE_FILE_NAME: &CStr = c"etc/pci-mmio32-hole-base";
@@ -175,41 +176,105 @@ impl Machine for I440fx {
         // Oops.
         //
         // We'll have to come back to this and figure out how to see through the VMM's
-        // lies, but for now, let's lie and say 40 bits. This will break if the VM has
-        // more than ~800 GiB of memory.
-        let addr_size = 40;
-        //let addr_size = P::guest_phys_addr_size();
-        let top_of_memory: u64 = 1 << addr_size;
-        // We'll also be relatively conservative and try to get away with just reserving
-        // 32 GiB for the hole.
-
-        // The hole should be aligned to 1G addresses. With enough bits, that should be
-        // vacuously true, but just in case let's ensure that the top_of_memory is a
-        // multiple of the hole size.
-        let top_of_memory = align_down(top_of_memory, MMIO64_HOLE_SIZE as u64) as usize;
-
-        // Let's start by sticking it at the very end of the address space.
-        let mut mmio64_hole = top_of_memory - MMIO64_HOLE_SIZE..top_of_memory;
-
-        // Keep scaling down until we find a hole or run out of memory.
-        // In theory we could scale down by 1G chunks until we get to the 4G boundary,
-        // but there should be enough address space available to use bigger, hole-sized
-        // chunks.
-        while !zero_page.check_e820_gap(mmio64_hole.clone())
-            && mmio64_hole.start >= MMIO64_HOLE_SIZE
-        {
-            mmio64_hole.start -= MMIO64_HOLE_SIZE;
-            mmio64_hole.end -= MMIO64_HOLE_SIZE;
-        }
+        // lies, but for now, let's lie and say 40 bits unless the VM has so much
+        // memory that the hole no longer fits underneath that.
+        place_mmio64_hole(zero_page, P::guest_phys_addr_size())
+    }
+}
 
-        if mmio64_hole.start < MMIO64_HOLE_SIZE {
-            Err("could not find memory region for 64-bit PCI MMIO hole")
-        } else {
-            Ok(mmio64_hole.start as u64..mmio64_hole.end as u64)
-        }
+/// The address space size we assume by default when placing the 64-bit hole;
+/// see the discussion in `I440fx::mmio64_hole`.
+const DEFAULT_MMIO64_ADDR_SIZE: u8 = 40;
+
+/// Finds a place for the 64-bit PCI MMIO hole in the address space.
+///
+/// If the memory map reaches past what fits underneath the default 40-bit
+/// boundary, we trust `guest_phys_addr_size` instead and put the hole directly
+/// above the top of RAM, so that large-memory VMs still get a 64-bit window.
+fn place_mmio64_hole(
+    zero_page: &ZeroPage,
+    guest_phys_addr_size: u8,
+) -> Result<Range<u64>, &'static str> {
+    let top_of_ram = zero_page.e820_table().iter().map(|entry| entry.end()).max().unwrap_or(0);
+    let hole_size = MMIO64_HOLE_SIZE as u64;
+
+    let mut top_of_memory: u64 = 1 << DEFAULT_MMIO64_ADDR_SIZE;
+    if top_of_ram as u64 + hole_size > top_of_memory {
+        top_of_memory =
+            (align_up(top_of_ram as u64, hole_size) + hole_size).min(1 << guest_phys_addr_size);
+    }
+
+    // We'll also be relatively conservative and try to get away with just reserving
+    // 32 GiB for the hole.
+
+    // The hole should be aligned to 1G addresses. With enough bits, that should be
+    // vacuously true, but just in case let's ensure that the top_of_memory is a
+    // multiple of the hole size.
+    let top_of_memory = align_down(top_of_memory, MMIO64_HOLE_SIZE as u64) as usize;
+
+    // Let's start by sticking it at the very end of the address space.
+    let mut mmio64_hole = top_of_memory - MMIO64_HOLE_SIZE..top_of_memory;
+
+    // Keep scaling down until we find a hole or run out of memory.
+    // In theory we could scale down by 1G chunks until we get to the 4G boundary,
+    // but there should be enough address space available to use bigger, hole-sized
+    // chunks.
+    while !zero_page.check_e820_gap(mmio64_hole.clone()) && mmio64_hole.start >= MMIO64_HOLE_SIZE {
+        mmio64_hole.start -= MMIO64_HOLE_SIZE;
+        mmio64_hole.end -= MMIO64_HOLE_SIZE;
+    }
+
+    if mmio64_hole.start < MMIO64_HOLE_SIZE {
+        Err("could not find memory region for 64-bit PCI MMIO hole")
+    } else {
+        Ok(mmio64_hole.start as u64..mmio64_hole.end as u64)
     }
 }
 
+/// Anything below 1 MiB in the `_CRS` allowlist is either I/O ports or legacy
+/// regions such as the VGA window, neither of which is a useful MMIO hole.
+const CRS_ALLOWLIST_MMIO_MIN_ADDRESS: u32 = 0x10_0000;
+
+/// Derives the 32-bit PCI MMIO hole from the `etc/pci-crs-whitelist` fw_cfg
+/// file, if the VMM provides one.
+///
+/// The allowlist lists the ranges the VMM advertises in the `_CRS` of the PCI
+/// root bridge. We pick the largest memory range from it that isn't backed by
+/// RAM. The entries only have 32-bit addresses, so this can't tell us anything
+/// about the 64-bit hole.
+fn mmio32_hole_from_crs_allowlist(
+    firmware: &mut dyn Firmware,
+    zero_page: &ZeroPage,
+) -> Result<Option<Range<u32>>, &'static str> {
+    let Some(allowlist) = read_pci_crs_allowlist(firmware)? else {
+        return Ok(None);
+    };
+
+    let hole = allowlist
+        .iter()
+        .filter(|entry| entry.length > 0 && entry.address >= CRS_ALLOWLIST_MMIO_MIN_ADDRESS)
+        .filter_map(|entry| {
+            let end = entry.address.checked_add(entry.length);
+            if end.is_none() {
+                log::warn!("ignoring PCI CRS allowlist entry past 4 GiB: {:?}", entry);
+            }
+            end.map(|end| entry.address..end)
+        })
+        .filter(|hole| {
+            let backed_by_ram = !zero_page.check_e820_gap(hole.start as usize..hole.end as usize);
+            if backed_by_ram {
+                log::warn!("ignoring PCI CRS allowlist entry overlapping RAM: {:x?}", hole);
+            }
+            !backed_by_ram
+        })
+        .max_by_key(|hole| hole.len());
+
+    if hole.is_none() {
+        log::warn!("no usable MMIO range in the PCI CRS allowlist; guessing the 32-bit hole");
+    }
+    Ok(hole)
+}
+
 pub struct Q35 {}
 
 impl Machine for Q35 {
@@ -217,9 +282,15 @@ impl Machine for Q35 {
     const PCI_DEVICE_ID: u16 = 0x29C0;
 
     fn mmio32_hole(
-        _firmware: &mut dyn Firmware,
-        _zero_page: &ZeroPage,
+        firmware: &mut dyn Firmware,
+        zero_page: &ZeroPage,
     ) -> Result<Range<u32>, &'static str> {
+        // Newer VMMs tell us which ranges they will expose in the root bridge's
+        // `_CRS`; if that's the case, use those instead of guessing.
+        if let Some(hole) = mmio32_hole_from_crs_allowlist(firmware, zero_page)? {
+            return Ok(hole);
+        }
+
         // SeaBIOS: PCI EXBAR start is hardcoded to 0xB000_0000 and size is 256 MiB:
         // https://github.com/coreboot/seabios/blob/b686f4600792c504f01929f761be473e298de33d/src/fw/dev-q35.h#L11
         // The PCI memory starts just past that (at 0xC000_0000, the 3G mark).
@@ -270,7 +341,11 @@ mod tests {
     use oak_linux_boot_params::{BootE820Entry, E820EntryType};
 
     use super::*;
-    use crate::{fw_cfg::TestFirmware, hal::MockPlatform};
+    use crate::{
+        fw_cfg::TestFirmware,
+        hal::MockPlatform,
+        pci::{PciCrsAllowlistEntry, PCI_CRS_ALLOWLIST_FILE_NAME},
+    };
 
     #[googletest::test]
     fn pc_hole_from_fwcfg() {
@@ -331,6 +406,43 @@ mod tests {
         )
     }
 
+    #[googletest::test]
+    fn q35_hole_from_crs_allowlist() {
+        let mut firmware = TestFirmware::default();
+        let mut zero_page = ZeroPage::new();
+        zero_page.insert_e820_entry(BootE820Entry::new(0x0, 0x8000_0000, E820EntryType::RAM));
+        let allowlist = [
+            // I/O ports, ignored.
+            PciCrsAllowlistEntry { address: 0x6000, length: 0x1000 },
+            // Overlaps RAM, ignored even though it's the largest.
+            PciCrsAllowlistEntry { address: 0x4000_0000, length: 0x8000_0000 },
+            PciCrsAllowlistEntry { address: 0x8000_0000, length: 0x6000_0000 },
+            PciCrsAllowlistEntry { address: 0xF000_0000, length: 0x0800_0000 },
+        ];
+        firmware.files.insert(PCI_CRS_ALLOWLIST_FILE_NAME.to_owned(), allowlist.as_bytes().into());
+
+        assert_that!(
+            Q35::mmio32_hole(&mut firmware, &zero_page),
+            ok(eq(&(0x8000_0000..0xE000_0000)))
+        );
+    }
+
+    #[googletest::test]
+    fn q35_hole_falls_back_without_usable_crs_allowlist() {
+        let mut firmware = TestFirmware::default();
+        let zero_page = ZeroPage::new();
+        let expected = Q35::mmio32_hole(&mut firmware, &zero_page);
+
+        // Only I/O ports and a range that would wrap around 4 GiB.
+        let allowlist = [
+            PciCrsAllowlistEntry { address: 0xC000, length: 0x4000 },
+            PciCrsAllowlistEntry { address: 0xC000_0000, length: 0x4000_0000 },
+        ];
+        firmware.files.insert(PCI_CRS_ALLOWLIST_FILE_NAME.to_owned(), allowlist.as_bytes().into());
+
+        assert_that!(Q35::mmio32_hole(&mut firmware, &zero_page), eq(&expected));
+    }
+
     #[googletest::test]
     fn mmio64_hole() {
         let gpa_bits = 40;
@@ -405,4 +517,42 @@ mod tests {
             ))
         );
     }
+
+    #[googletest::test]
+    fn mmio64_hole_above_high_top_of_ram() {
+        let mut zero_page = ZeroPage::new();
+        zero_page.insert_e820_entry(BootE820Entry::new(0, 0xC000_0000, E820EntryType::RAM));
+        // 2 TiB of RAM above the 4 GiB mark; that no longer fits under 40 bits.
+        let top_of_ram = 0x200_0000_0000;
+        zero_page.insert_e820_entry(BootE820Entry::new(
+            0x1_0000_0000,
+            top_of_ram - 0x1_0000_0000,
+            E820EntryType::RAM,
+        ));
+
+        assert_that!(
+            place_mmio64_hole(&zero_page, 48),
+            ok(eq(&(top_of_ram as u64..(top_of_ram + MMIO64_HOLE_SIZE) as u64)))
+        );
+
+        // If the address space can't fit the hole above the top of RAM, we give up.
+        assert_that!(place_mmio64_hole(&zero_page, 41), err(anything()));
+    }
+
+    #[googletest::test]
+    fn mmio64_hole_stays_below_default_limit() {
+        let mut zero_page = ZeroPage::new();
+        zero_page.insert_e820_entry(BootE820Entry::new(
+            0x1_0000_0000,
+            0x10_0000_0000,
+            E820EntryType::RAM,
+        ));
+
+        // With plenty of room under 40 bits, the reported address size is not used.
+        assert_that!(
+            place_mmio64_hole(&zero_page, 48),
+            ok(eq(&((1 << DEFAULT_MMIO64_ADDR_SIZE) - MMIO64_HOLE_SIZE as u64
+                ..1 << DEFAULT_MMIO64_ADDR_SIZE)))
+        );
+    }
 }
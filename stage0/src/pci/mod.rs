@@ -18,7 +18,7 @@ use alloc::{boxed::Box, rc::Rc};
 use core::{ffi::CStr, fmt::Display, ops::Range};
 
 use spinning_top::Spinlock;
-use zerocopy::{FromBytes, FromZeros, IntoBytes};
+use zerocopy::{FromBytes, FromZeros, Immutable, IntoBytes};
 
 use crate::{
     fw_cfg::Firmware,
@@ -36,10 +36,10 @@ mod resource_allocator;
 
 use device::Bdf;
 use machine::{I440fx, Machine, Q35};
-use resource_allocator::ResourceAllocator;
+use resource_allocator::{ResourceAllocator, ResourceUsage};
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, FromBytes, IntoBytes)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, FromBytes, Immutable, IntoBytes)]
 pub struct PciCrsAllowlistEntry {
     pub address: u32,
     pub length: u32,
@@ -51,6 +51,11 @@ pub const PCI_CRS_ALLOWLIST_MAX_ENTRY_COUNT: usize = 11;
 const PCI_CRS_ALLOWLIST_FILE_NAME: &CStr = c"etc/pci-crs-whitelist";
 const EXTRA_ROOTS_FILE_NAME: &CStr = c"etc/extra-pci-roots";
 
+/// Whether to assign addresses to (and thus enable) the expansion ROMs of PCI
+/// devices. Option ROMs are not needed by the workloads we boot, so they are
+/// only detected and left unmapped by default.
+const ALLOCATE_EXPANSION_ROMS: bool = false;
+
 /// PCI class codes.
 ///
 /// We use a struct instead of enum because Rust enums are closed, but we will
@@ -58,9 +63,9 @@ const EXTRA_ROOTS_FILE_NAME: &CStr = c"etc/extra-pci-roots";
 ///
 /// See the PCI Code and ID Assignment Specification on pcisig.com for the
 /// authoritative source.
-#[derive(PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[repr(transparent)]
-struct PciClass(pub u8);
+pub struct PciClass(pub u8);
 
 impl PciClass {
     pub const BRIDGE: PciClass = PciClass(0x06);
@@ -77,9 +82,9 @@ impl Display for PciClass {
 ///
 /// See the PCI Code and ID Assignment Specification on pcisig.com for the
 /// authoritative source.
-#[derive(PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[repr(transparent)]
-struct PciSubclass(pub u8);
+pub struct PciSubclass(pub u8);
 impl PciSubclass {
     #[allow(dead_code)]
     pub const HOST_BRIDGE: PciSubclass = PciSubclass(0x00);
@@ -102,11 +107,23 @@ struct PciBridgeBusRegister {
     pub primary_bus_number: u8,
 }
 
+/// A PCI function found while enumerating a bus.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PciDevice {
+    pub address: Bdf,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub class: PciClass,
+    pub subclass: PciSubclass,
+}
+
 struct BarIter {
     device: Bdf,
     // Bridges have up to 2 BARs, normal devices 6.
     max_bars: u8,
     index: Option<u8>,
+    // Register of the expansion ROM BAR, until it has been probed.
+    expansion_rom_register: Option<u8>,
     access: Rc<Spinlock<Box<dyn ConfigAccess>>>,
 }
 
@@ -117,7 +134,17 @@ impl Iterator for BarIter {
         // Try to find a next BAR.
         loop {
             self.index = self.index.filter(|&index| index < self.max_bars);
-            let index = self.index?;
+            let Some(index) = self.index else {
+                // All the regular BARs have been probed; the expansion ROM comes last.
+                let register = self.expansion_rom_register.take()?;
+                return PciBar::new_expansion_rom(
+                    self.device,
+                    register,
+                    self.access.lock().as_mut(),
+                )
+                .ok()
+                .flatten();
+            };
 
             let bar = PciBar::new(self.device, index, self.access.lock().as_mut()).ok()?;
             // We've consumed at least one entry.
@@ -127,7 +154,9 @@ impl Iterator for BarIter {
                     // Unimplemented BAR.
                     continue;
                 }
-                Some(PciBar::Io { .. }) | Some(PciBar::Memory32 { .. }) => {
+                Some(PciBar::Io { .. })
+                | Some(PciBar::Memory32 { .. })
+                | Some(PciBar::ExpansionRom { .. }) => {
                     return bar;
                 }
                 Some(PciBar::Memory64 { .. }) => {
@@ -193,12 +222,19 @@ impl PciAddress {
         access: Rc<Spinlock<Box<dyn ConfigAccess>>>,
     ) -> Result<BarIter, &'static str> {
         let (class, subclass) = self.class_code(access.lock().as_mut())?;
-        let max_bars = if class == PciClass::BRIDGE && subclass == PciSubclass::PCI_TO_PCI_BRIDGE {
-            2
-        } else {
-            6
-        };
-        Ok(BarIter { device: self.0, max_bars, index: Some(0), access })
+        let (max_bars, expansion_rom_register) =
+            if class == PciClass::BRIDGE && subclass == PciSubclass::PCI_TO_PCI_BRIDGE {
+                (2, PciBar::BRIDGE_EXPANSION_ROM_REGISTER)
+            } else {
+                (6, PciBar::EXPANSION_ROM_REGISTER)
+            };
+        Ok(BarIter {
+            device: self.0,
+            max_bars,
+            index: Some(0),
+            expansion_rom_register: Some(expansion_rom_register),
+            access,
+        })
     }
     /// Checks if the device exists at all.
     fn exists(&self, access: &mut dyn ConfigAccess) -> Result<bool, &'static str> {
@@ -282,6 +318,46 @@ impl Iterator for BusDeviceIterator {
     }
 }
 
+/// Formats a size in bytes using the largest binary unit it is a multiple of.
+struct ByteSize(u64);
+
+impl Display for ByteSize {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for (unit, shift) in [("GiB", 30), ("MiB", 20), ("KiB", 10)] {
+            if self.0 >= 1 << shift && self.0.is_multiple_of(1 << shift) {
+                return write!(f, "{}{}", self.0 >> shift, unit);
+            }
+        }
+        write!(f, "{}B", self.0)
+    }
+}
+
+/// Resources assigned to device BARs by [`PciBus::init`], per window.
+#[derive(Debug, PartialEq, Eq)]
+struct PciResourceUsage {
+    io: ResourceUsage<u16>,
+    mem32: ResourceUsage<u32>,
+    mem64: ResourceUsage<u64>,
+}
+
+impl Display for PciResourceUsage {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "io: {}/{} ports used across {} BARs; mem32: {}/{} used across {} BARs; mem64: {}/{} used across {} BARs",
+            self.io.allocated,
+            self.io.window,
+            self.io.allocations,
+            ByteSize(self.mem32.allocated.into()),
+            ByteSize(self.mem32.window.into()),
+            self.mem32.allocations,
+            ByteSize(self.mem64.allocated),
+            ByteSize(self.mem64.window),
+            self.mem64.allocations,
+        )
+    }
+}
+
 struct PciBus {
     pub root: PciAddress,
 }
@@ -296,15 +372,37 @@ impl PciBus {
         }
     }
 
+    /// Enumerates the devices on the bus and assigns resources to their BARs.
+    ///
+    /// `visitor` is invoked for every device found, before its BARs are
+    /// configured. Expansion ROMs are only assigned an address if
+    /// `allocate_expansion_roms` is set. No memory BAR is ever placed within
+    /// one of the `reserved_mmio` ranges.
+    ///
+    /// Returns how much of each window was assigned to the BARs.
     fn init(
         &mut self,
         windows: &PciWindows,
+        reserved_mmio: &[Range<u64>],
         config_access: Rc<Spinlock<Box<dyn ConfigAccess>>>,
-    ) -> Result<(), &'static str> {
+        visitor: &mut dyn FnMut(&PciDevice),
+        allocate_expansion_roms: bool,
+    ) -> Result<PciResourceUsage, &'static str> {
         // Prepare the allocators for all the resources.
         let mut io_allocator = ResourceAllocator::new(windows.pci_window_16.clone());
         let mut mem32_allocator = ResourceAllocator::new(windows.pci_window_32.clone());
         let mut mem64_allocator = ResourceAllocator::new(windows.pci_window_64.clone());
+        for range in reserved_mmio {
+            // Clamp the reservation to the 32-bit window, as its end may be at or
+            // above 4 GiB.
+            let end = range.end.min(windows.pci_window_32.end.into());
+            if let (Ok(start), Ok(end)) = (u32::try_from(range.start), u32::try_from(end)) {
+                if start < end {
+                    mem32_allocator.reserve(start..end);
+                }
+            }
+            mem64_allocator.reserve(range.clone());
+        }
 
         for function in self.iter_devices(config_access.clone()) {
             let (vendor_id, device_id) =
@@ -320,6 +418,8 @@ impl PciBus {
                 subclass
             );
 
+            visitor(&PciDevice { address: function.0, vendor_id, device_id, class, subclass });
+
             if class == PciClass::BRIDGE && subclass == PciSubclass::PCI_TO_PCI_BRIDGE {
                 let bridge_bus_numbers =
                     function.bridge_bus_numbers(config_access.lock().as_mut())?;
@@ -362,7 +462,15 @@ impl PciBus {
                         let bar_size = bar_size.try_into().unwrap();
                         let allocation = io_allocator
                             .allocate(bar_size)
-                            .ok_or("out of memory for 64-bit memory BAR")?
+                            .ok_or_else(|| {
+                                log::error!(
+                                    "Out of I/O space assigning {} ports to BAR{} of {}",
+                                    bar_size,
+                                    offset,
+                                    function
+                                );
+                                "out of IO space for I/O BAR"
+                            })?
                             .start;
                         log::debug!(
                             "    assigning [0x{:04x}-0x{:04x})",
@@ -371,10 +479,31 @@ impl PciBus {
                         );
                         bar.set_address(allocation.into(), config_access.lock().as_mut())?;
                     }
+                    PciBar::ExpansionRom { bar_size, .. } => {
+                        log::debug!("  ROM: size {}", bar_size);
+                        if !allocate_expansion_roms {
+                            log::debug!("    leaving unassigned");
+                            continue;
+                        }
+                        let allocation = mem32_allocator
+                            .allocate(bar_size)
+                            .ok_or("out of memory for expansion ROM BAR")?
+                            .start;
+                        log::debug!(
+                            "    assigning [0x{:08x}-0x{:08x})",
+                            allocation,
+                            allocation + bar_size
+                        );
+                        bar.set_address(allocation.into(), config_access.lock().as_mut())?;
+                    }
                 }
             }
         }
-        Ok(())
+        Ok(PciResourceUsage {
+            io: io_allocator.usage(),
+            mem32: mem32_allocator.usage(),
+            mem64: mem64_allocator.usage(),
+        })
     }
 
     fn iter_devices(&self, access: Rc<Spinlock<Box<dyn ConfigAccess>>>) -> BusDeviceIterator {
@@ -391,23 +520,56 @@ pub struct PciWindows {
     pub pci_window_64: Range<u64>,
 }
 
+impl PciWindows {
+    /// Creates the PCI windows, checking that the two memory windows don't
+    /// overlap; otherwise the same memory could be handed out to two BARs.
+    ///
+    /// The I/O port window lives in a separate address space, so it can't
+    /// conflict with either of them.
+    pub fn new(
+        pci_window_16: Range<u16>,
+        pci_window_32: Range<u32>,
+        pci_window_64: Range<u64>,
+    ) -> Result<Self, &'static str> {
+        let mem32 = u64::from(pci_window_32.start)..u64::from(pci_window_32.end);
+        if mem32.start < pci_window_64.end && pci_window_64.start < mem32.end {
+            log::error!(
+                "PCI: 32-bit window {:#x?} overlaps 64-bit window {:#x?}",
+                pci_window_32,
+                pci_window_64
+            );
+            return Err("overlapping 32-bit and 64-bit PCI windows");
+        }
+        Ok(Self { pci_window_16, pci_window_32, pci_window_64 })
+    }
+}
+
 fn init_machine<P: Platform, M: Machine>(
     mut root_bus: PciBus,
     firmware: &mut dyn Firmware,
     zero_page: &mut ZeroPage,
+    reserved_mmio: &[Range<u64>],
     config_access: Rc<Spinlock<Box<dyn ConfigAccess>>>,
+    visitor: &mut dyn FnMut(&PciDevice),
 ) -> Result<Option<PciWindows>, &'static str> {
     // Determine the PCI holes. How this is done is unfortunately extremely clunky
     // and machine-specific.
-    let pci_windows = PciWindows {
-        pci_window_16: M::io_port_range(firmware, zero_page)?,
-        pci_window_32: M::mmio32_hole(firmware, zero_page)?,
-        pci_window_64: M::mmio64_hole::<P>(firmware, zero_page)?,
-    };
+    let pci_windows = PciWindows::new(
+        M::io_port_range(firmware, zero_page)?,
+        M::mmio32_hole(firmware, zero_page)?,
+        M::mmio64_hole::<P>(firmware, zero_page)?,
+    )?;
 
     log::info!("PCI: using windows {:?}", pci_windows);
 
-    root_bus.init(&pci_windows, config_access)?;
+    let usage = root_bus.init(
+        &pci_windows,
+        reserved_mmio,
+        config_access,
+        visitor,
+        ALLOCATE_EXPANSION_ROMS,
+    )?;
+    log::info!("PCI: {}", usage);
 
     // Find out if there are any extra roots.
     let extra_roots = read_extra_roots(firmware)?;
@@ -417,9 +579,19 @@ fn init_machine<P: Platform, M: Machine>(
     Ok(Some(pci_windows))
 }
 
+/// Initializes the PCI root bus, if there is one.
+///
+/// `visitor` is invoked for every PCI device found during enumeration, allowing
+/// the caller to collect the devices it is interested in.
+///
+/// `reserved_mmio` lists memory ranges (e.g. a framebuffer or other platform
+/// device) that must be kept out of the PCI windows; no BAR will be placed
+/// within them.
 pub fn init<P: Platform>(
     firmware: &mut dyn Firmware,
     zero_page: &mut ZeroPage,
+    reserved_mmio: &[Range<u64>],
+    visitor: &mut dyn FnMut(&PciDevice),
 ) -> Result<Option<PciWindows>, &'static str> {
     // At this point we know nothing about the platform we're on, so we have to
     // rely on the legacy CAM to get the device ID of the first PCI root to
@@ -441,12 +613,22 @@ pub fn init<P: Platform>(
     let root_bridge_device_id =
         root_bus.root.vendor_device_id(config_access.clone().lock().as_mut())?;
     match root_bridge_device_id {
-        (I440fx::PCI_VENDOR_ID, I440fx::PCI_DEVICE_ID) => {
-            init_machine::<P, I440fx>(root_bus, firmware, zero_page, config_access)
-        }
-        (Q35::PCI_VENDOR_ID, Q35::PCI_DEVICE_ID) => {
-            init_machine::<P, Q35>(root_bus, firmware, zero_page, config_access)
-        }
+        (I440fx::PCI_VENDOR_ID, I440fx::PCI_DEVICE_ID) => init_machine::<P, I440fx>(
+            root_bus,
+            firmware,
+            zero_page,
+            reserved_mmio,
+            config_access,
+            visitor,
+        ),
+        (Q35::PCI_VENDOR_ID, Q35::PCI_DEVICE_ID) => init_machine::<P, Q35>(
+            root_bus,
+            firmware,
+            zero_page,
+            reserved_mmio,
+            config_access,
+            visitor,
+        ),
         (vendor_id, device_id) => {
             log::error!(
                 "Unknown PCI root device: {:04x}:{:04x} -- will not initialize PCI bus",
@@ -493,10 +675,12 @@ pub fn read_pci_crs_allowlist(
 
 #[cfg(test)]
 mod tests {
+    use std::sync::{Arc, Mutex};
+
     use googletest::prelude::*;
 
     use super::*;
-    use crate::fw_cfg::TestFirmware;
+    use crate::{fw_cfg::TestFirmware, pci::config_access::MockConfigAccess};
 
     #[googletest::test]
     fn test_allowlist() {
@@ -544,4 +728,281 @@ mod tests {
 
         assert_that!(read_pci_crs_allowlist(&mut firmware), err(anything()));
     }
+
+    #[googletest::test]
+    fn test_windows_overlap() {
+        assert_that!(
+            PciWindows::new(0xC000..0xFFFF, 0xB000_0000..0xE000_0000, 0xD000_0000..0x1_D000_0000),
+            err(anything())
+        );
+        // The 64-bit window is entirely inside the 32-bit one.
+        assert_that!(
+            PciWindows::new(0xC000..0xFFFF, 0xB000_0000..0xE000_0000, 0xC000_0000..0xC800_0000),
+            err(anything())
+        );
+    }
+
+    #[googletest::test]
+    fn test_windows_adjacent() {
+        assert_that!(
+            PciWindows::new(0xC000..0xFFFF, 0xB000_0000..0xE000_0000, 0xE000_0000..0x1_E000_0000),
+            ok(anything())
+        );
+        // No 64-bit window at all.
+        assert_that!(
+            PciWindows::new(0xC000..0xFFFF, 0xB000_0000..0xE000_0000, 0..0),
+            ok(anything())
+        );
+    }
+
+    #[googletest::test]
+    fn test_resource_usage_summary() {
+        let usage = PciResourceUsage {
+            io: ResourceUsage { allocated: 0x100, window: 0x3FFF, allocations: 2 },
+            mem32: ResourceUsage { allocated: 0x300_4000, window: 0x3000_0000, allocations: 6 },
+            mem64: ResourceUsage { allocated: 0, window: 0x8_0000_0000, allocations: 0 },
+        };
+
+        assert_that!(
+            usage.to_string(),
+            eq("io: 256/16383 ports used across 2 BARs; \
+                mem32: 49168KiB/768MiB used across 6 BARs; \
+                mem64: 0B/32GiB used across 0 BARs")
+        );
+    }
+
+    #[googletest::test]
+    fn test_init_collects_bridges() {
+        let mut access = MockConfigAccess::new();
+        access.expect_read().returning(|address, offset| {
+            // (vendor ID, device ID, class, subclass)
+            let device = match (address.bus(), address.device(), address.function()) {
+                (0, 0, 0) => Some((0x8086, 0x29C0, 0x06, 0x00)), // Q35 host bridge
+                (0, 1, 0) => Some((0x1AF4, 0x1000, 0x02, 0x00)), // virtio-net
+                (0, 2, 0) => Some((0x1B36, 0x0001, 0x06, 0x04)), // PCI-to-PCI bridge
+                (0, 31, 0) => Some((0x8086, 0x2918, 0x06, 0x01)), // ISA bridge
+                _ => None,
+            };
+            Ok(match (device, offset) {
+                (None, _) => 0xFFFF_FFFF,
+                (Some((vendor_id, device_id, _, _)), 0x00) => (device_id << 16) | vendor_id,
+                (Some((_, _, class, subclass)), 0x02) => (class << 24) | (subclass << 16),
+                // Single-function devices with no BARs.
+                (Some(_), _) => 0,
+            })
+        });
+        access.expect_write().returning(|_, _, _| Ok(()));
+        let config_access: Rc<Spinlock<Box<dyn ConfigAccess>>> =
+            Rc::new(Spinlock::new(Box::new(access)));
+        let mut bus = PciBus::new(0, config_access.lock().as_mut()).unwrap().unwrap();
+        let windows = PciWindows {
+            pci_window_16: 0xC000..0xFFFF,
+            pci_window_32: 0xB000_0000..0xE000_0000,
+            pci_window_64: 0x80_0000_0000..0x88_0000_0000,
+        };
+
+        let mut bridges = Vec::new();
+        let result = bus.init(
+            &windows,
+            &[],
+            config_access,
+            &mut |device| {
+                if device.class == PciClass::BRIDGE {
+                    bridges.push(*device);
+                }
+            },
+            false,
+        );
+
+        assert_that!(result, ok(anything()));
+        assert_that!(
+            bridges,
+            elements_are![
+                eq(&PciDevice {
+                    address: Bdf::new(0, 0, 0).unwrap(),
+                    vendor_id: 0x8086,
+                    device_id: 0x29C0,
+                    class: PciClass::BRIDGE,
+                    subclass: PciSubclass::HOST_BRIDGE,
+                }),
+                eq(&PciDevice {
+                    address: Bdf::new(0, 2, 0).unwrap(),
+                    vendor_id: 0x1B36,
+                    device_id: 0x0001,
+                    class: PciClass::BRIDGE,
+                    subclass: PciSubclass::PCI_TO_PCI_BRIDGE,
+                }),
+                eq(&PciDevice {
+                    address: Bdf::new(0, 31, 0).unwrap(),
+                    vendor_id: 0x8086,
+                    device_id: 0x2918,
+                    class: PciClass::BRIDGE,
+                    subclass: PciSubclass(0x01),
+                }),
+            ]
+        );
+    }
+
+    /// Values written to the config space, with the register they went to.
+    type ConfigWrites = Arc<Mutex<Vec<(u8, u32)>>>;
+
+    /// Sets up a bus whose only device is a Q35 host bridge at the root,
+    /// recording every config space write. `register` supplies the value of
+    /// the bridge's registers beyond its IDs and class.
+    fn root_device_bus(
+        register: fn(u8) -> u32,
+    ) -> (PciBus, Rc<Spinlock<Box<dyn ConfigAccess>>>, ConfigWrites) {
+        let writes = Arc::new(Mutex::new(Vec::new()));
+        let mut access = MockConfigAccess::new();
+        access.expect_read().returning(move |address, offset| {
+            Ok(match (address == Bdf::root(), offset) {
+                (false, _) => 0xFFFF_FFFF,
+                (true, 0x00) => 0x29C0_8086,
+                (true, 0x02) => 0x0600_0000,
+                (true, offset) => register(offset),
+            })
+        });
+        access.expect_write().returning({
+            let writes = writes.clone();
+            move |_, offset, value| {
+                writes.lock().unwrap().push((offset, value));
+                Ok(())
+            }
+        });
+        let config_access: Rc<Spinlock<Box<dyn ConfigAccess>>> =
+            Rc::new(Spinlock::new(Box::new(access)));
+        let bus = PciBus::new(0, config_access.lock().as_mut()).unwrap().unwrap();
+        (bus, config_access, writes)
+    }
+
+    /// Returns the values written to `register`, in order.
+    fn writes_to(writes: &ConfigWrites, register: u8) -> Vec<u32> {
+        writes
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(offset, _)| *offset == register)
+            .map(|(_, value)| *value)
+            .collect()
+    }
+
+    /// Sets up a bus with a single device exposing a 64 KiB expansion ROM.
+    fn expansion_rom_bus() -> (PciBus, Rc<Spinlock<Box<dyn ConfigAccess>>>, ConfigWrites) {
+        root_device_bus(|offset| match offset {
+            PciBar::EXPANSION_ROM_REGISTER => 0xFFFF_0000,
+            _ => 0,
+        })
+    }
+
+    const TEST_WINDOWS: PciWindows = PciWindows {
+        pci_window_16: 0xC000..0xFFFF,
+        pci_window_32: 0xB000_0000..0xE000_0000,
+        pci_window_64: 0x80_0000_0000..0x88_0000_0000,
+    };
+
+    #[googletest::test]
+    fn test_expansion_rom_reported() {
+        let (_, config_access, _) = expansion_rom_bus();
+
+        let bars: Vec<PciBar> = PciAddress(Bdf::root()).iter_bars(config_access).unwrap().collect();
+
+        assert_that!(
+            bars,
+            elements_are![matches_pattern!(PciBar::ExpansionRom {
+                bdf: eq(&Bdf::root()),
+                register: eq(&PciBar::EXPANSION_ROM_REGISTER),
+                bar_size: eq(&0x1_0000)
+            })]
+        );
+    }
+
+    #[googletest::test]
+    fn test_expansion_rom_not_assigned() {
+        let (mut bus, config_access, writes) = expansion_rom_bus();
+
+        let result = bus.init(&TEST_WINDOWS, &[], config_access, &mut |_| {}, false);
+
+        assert_that!(result, ok(anything()));
+        // Only the probe, which leaves the ROM disabled.
+        assert_that!(
+            writes_to(&writes, PciBar::EXPANSION_ROM_REGISTER),
+            elements_are![eq(&0xFFFF_F800)]
+        );
+    }
+
+    #[googletest::test]
+    fn test_expansion_rom_assigned() {
+        let (mut bus, config_access, writes) = expansion_rom_bus();
+
+        let result = bus.init(&TEST_WINDOWS, &[], config_access, &mut |_| {}, true);
+
+        // The ROM is the only thing using any of the windows.
+        assert_that!(
+            result,
+            ok(eq(&PciResourceUsage {
+                io: ResourceUsage { allocated: 0, window: 0x3FFF, allocations: 0 },
+                mem32: ResourceUsage { allocated: 0x1_0000, window: 0x3000_0000, allocations: 1 },
+                mem64: ResourceUsage { allocated: 0, window: 0x8_0000_0000, allocations: 0 },
+            }))
+        );
+        // The ROM is placed at the start of the 32-bit window and enabled.
+        assert_that!(
+            writes_to(&writes, PciBar::EXPANSION_ROM_REGISTER),
+            elements_are![eq(&0xFFFF_F800), eq(&0xB000_0001)]
+        );
+    }
+
+    #[googletest::test]
+    fn test_io_bar_out_of_io_space() {
+        // BAR0: I/O BAR of size 256.
+        let (mut bus, config_access, _) = root_device_bus(|offset| match offset {
+            0x04 => 0xFFFF_FF01,
+            _ => 0,
+        });
+        let windows = PciWindows { pci_window_16: 0xC000..0xC010, ..TEST_WINDOWS };
+
+        let result = bus.init(&windows, &[], config_access, &mut |_| {}, false);
+
+        assert_that!(result, err(eq(&"out of IO space for I/O BAR")));
+    }
+
+    #[googletest::test]
+    fn test_memory_bar_skips_reserved_range() {
+        // BAR0: 32-bit memory BAR of size 1 MiB.
+        let (mut bus, config_access, writes) = root_device_bus(|offset| match offset {
+            0x04 => 0xFFF0_0000,
+            _ => 0,
+        });
+        // Keep the first 16 MiB of the 32-bit window, and some 64-bit memory, free.
+        let reserved = [0xB000_0000..0xB100_0000, 0x80_0000_0000..0x81_0000_0000];
+
+        let result = bus.init(&TEST_WINDOWS, &reserved, config_access, &mut |_| {}, false);
+
+        // The memory abandoned in front of the reserved range isn't counted.
+        assert_that!(
+            result,
+            ok(field!(
+                &PciResourceUsage.mem32,
+                eq(ResourceUsage { allocated: 0x10_0000, window: 0x3000_0000, allocations: 1 })
+            ))
+        );
+        // The BAR is placed right after the reserved range.
+        assert_that!(writes_to(&writes, 0x04).last(), some(eq(&0xB100_0000)));
+    }
+
+    #[googletest::test]
+    fn test_memory_bar_reserved_range_above_4gib() {
+        // BAR0: 32-bit memory BAR of size 512 MiB.
+        let (mut bus, config_access, _) = root_device_bus(|offset| match offset {
+            0x04 => 0xE000_0000,
+            _ => 0,
+        });
+        let windows = PciWindows { pci_window_32: 0xC000_0000..0xF000_0000, ..TEST_WINDOWS };
+        // Covers the whole 32-bit window and continues past 4 GiB.
+        let reserved = 0xC000_0000..0x1_2000_0000;
+
+        let result = bus.init(&windows, &[reserved], config_access, &mut |_| {}, false);
+
+        assert_that!(result, err(eq(&"out of memory for 32-bit memory BAR")));
+    }
 }
@@ -14,27 +14,43 @@
 // limitations under the License.
 //
 
-use core::ops::{Add, Range};
+use alloc::vec::Vec;
+use core::ops::{Add, Range, Sub};
 
-pub trait ResourceAllocatorIdx: Add<Output = Self> + PartialOrd + Sized + Clone + Copy {
-    fn next_multiple_of(self, rhs: Self) -> Self;
+pub trait ResourceAllocatorIdx:
+    Add<Output = Self> + Sub<Output = Self> + Default + PartialOrd + Sized + Clone + Copy
+{
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_next_multiple_of(self, rhs: Self) -> Option<Self>;
 }
 
 impl ResourceAllocatorIdx for u16 {
-    fn next_multiple_of(self, rhs: Self) -> Self {
-        self.next_multiple_of(rhs)
+    fn checked_add(self, rhs: Self) -> Option<Self> {
+        self.checked_add(rhs)
+    }
+
+    fn checked_next_multiple_of(self, rhs: Self) -> Option<Self> {
+        self.checked_next_multiple_of(rhs)
     }
 }
 
 impl ResourceAllocatorIdx for u32 {
-    fn next_multiple_of(self, rhs: Self) -> Self {
-        self.next_multiple_of(rhs)
+    fn checked_add(self, rhs: Self) -> Option<Self> {
+        self.checked_add(rhs)
+    }
+
+    fn checked_next_multiple_of(self, rhs: Self) -> Option<Self> {
+        self.checked_next_multiple_of(rhs)
     }
 }
 
 impl ResourceAllocatorIdx for u64 {
-    fn next_multiple_of(self, rhs: Self) -> Self {
-        self.next_multiple_of(rhs)
+    fn checked_add(self, rhs: Self) -> Option<Self> {
+        self.checked_add(rhs)
+    }
+
+    fn checked_next_multiple_of(self, rhs: Self) -> Option<Self> {
+        self.checked_next_multiple_of(rhs)
     }
 }
 
@@ -48,15 +64,39 @@ impl ResourceAllocatorIdx for u64 {
 /// 2. The algorithm is a basic bump allocator, which means the allocations only
 ///    grow up. Alignment requirements may force chunks of resources to be
 ///    abandoned, even if some future allocation request would fit.
+/// 3. Allocations skip over reserved ranges entirely, so the resources between
+///    the previous allocation and a reserved range may be abandoned as well.
 pub struct ResourceAllocator<Idx: ResourceAllocatorIdx> {
     range: Range<Idx>,
     index: Idx,
+    reserved: Vec<Range<Idx>>,
+    allocated: Idx,
+    allocations: usize,
+}
+
+/// How much of the window of a [`ResourceAllocator`] has been handed out.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ResourceUsage<Idx> {
+    /// Sum of the sizes of all allocations. Resources abandoned due to
+    /// alignment or reserved ranges are not counted.
+    pub allocated: Idx,
+    /// Size of the whole window.
+    pub window: Idx,
+    /// Number of successful allocations.
+    pub allocations: usize,
 }
 
 impl<Idx: ResourceAllocatorIdx> ResourceAllocator<Idx> {
     pub fn new(range: Range<Idx>) -> Self {
         let index = range.start;
-        Self { range, index }
+        Self { range, index, reserved: Vec::new(), allocated: Idx::default(), allocations: 0 }
+    }
+
+    /// Reserves `range` so that it will never be handed out by this allocator.
+    ///
+    /// Parts of `range` outside of the allocator's window are ignored.
+    pub fn reserve(&mut self, range: Range<Idx>) {
+        self.reserved.push(range);
     }
 
     /// Allocate resources from this allocator.
@@ -65,16 +105,35 @@ impl<Idx: ResourceAllocatorIdx> ResourceAllocator<Idx> {
     /// (a) be `size`-aligned, and
     /// (b) be `size` in size.
     ///
-    /// If the request cannot be satisfied, returns `None`.
+    /// If the request cannot be satisfied, including when the allocation would
+    /// run past the end of the `Idx` range, returns `None`.
     pub fn allocate(&mut self, size: Idx) -> Option<Range<Idx>> {
-        // Ensure alignment with `size`.
-        let index = self.index.next_multiple_of(size);
-        if index + size > self.range.end {
+        // Ensure alignment with `size`, and move past any reserved ranges the
+        // allocation would overlap with.
+        let mut index = self.index.checked_next_multiple_of(size)?;
+        let mut end = index.checked_add(size)?;
+        while let Some(reserved) =
+            self.reserved.iter().find(|reserved| reserved.start < end && index < reserved.end)
+        {
+            index = reserved.end.checked_next_multiple_of(size)?;
+            end = index.checked_add(size)?;
+        }
+        if end > self.range.end {
             None
         } else {
-            let result = index..index + size;
-            self.index = index + size;
-            Some(result)
+            self.index = end;
+            self.allocated = self.allocated + size;
+            self.allocations += 1;
+            Some(index..end)
+        }
+    }
+
+    /// Returns how much of the window has been allocated so far.
+    pub fn usage(&self) -> ResourceUsage<Idx> {
+        ResourceUsage {
+            allocated: self.allocated,
+            window: self.range.end - self.range.start,
+            allocations: self.allocations,
         }
     }
 }
@@ -92,4 +151,47 @@ mod tests {
         assert_that!(allocator.allocate(64), some(eq(&(64..128))));
         assert_that!(allocator.allocate(16), none());
     }
+
+    #[googletest::test]
+    fn test_resource_allocator_skips_reserved() {
+        let mut allocator = ResourceAllocator::new(16u32..256u32);
+        allocator.reserve(24..40);
+        allocator.reserve(0..8);
+        assert_that!(allocator.allocate(16), some(eq(&(48..64))));
+        allocator.reserve(64..128);
+        assert_that!(allocator.allocate(64), some(eq(&(128..192))));
+        allocator.reserve(192..256);
+        assert_that!(allocator.allocate(16), none());
+    }
+
+    #[googletest::test]
+    fn test_resource_allocator_reserved_at_top_of_range() {
+        let mut allocator = ResourceAllocator::new(0xC000_0000u32..u32::MAX);
+        allocator.reserve(0xF000_0000..u32::MAX);
+        assert_that!(allocator.allocate(0x1000_0000), some(eq(&(0xC000_0000..0xD000_0000))));
+        assert_that!(allocator.allocate(0x2000_0000), none());
+        assert_that!(allocator.allocate(0x1000_0000), some(eq(&(0xD000_0000..0xE000_0000))));
+        assert_that!(allocator.allocate(0x1000_0000), some(eq(&(0xE000_0000..0xF000_0000))));
+        // Moving past the reservation would overflow the index.
+        assert_that!(allocator.allocate(0x1000_0000), none());
+    }
+
+    #[googletest::test]
+    fn test_resource_allocator_usage() {
+        let mut allocator = ResourceAllocator::new(16u32..256u32);
+        assert_that!(
+            allocator.usage(),
+            eq(ResourceUsage { allocated: 0, window: 240, allocations: 0 })
+        );
+
+        allocator.allocate(16);
+        // Aligning this one abandons 32..64, which isn't counted as allocated.
+        allocator.allocate(64);
+        // Failed allocations aren't counted either.
+        allocator.allocate(256);
+        assert_that!(
+            allocator.usage(),
+            eq(ResourceUsage { allocated: 80, window: 240, allocations: 2 })
+        );
+    }
 }